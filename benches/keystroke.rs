@@ -0,0 +1,121 @@
+//! Benchmarks of the work done for each keystroke on a large input. The target is to stay
+//! under 1ms per keystroke for a 10k-char buffer. Over a slow PTY, the time goes into the
+//! bytes written, which only the changed cells of a frame should take.
+//!
+//! Run with `cargo bench --features internals`.
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rusty_prompt::internals::format_suggestions;
+use rusty_prompt::{Buffer, ColumnLayout, Completer, CompletionManager, Document, Renderer, Suggestion};
+
+/// Size of the large input, in chars.
+const INPUT_LEN: usize = 10_000;
+
+/// Lines of about 80 chars, so that line lookups have something to index.
+fn large_input() -> String {
+    let line = "SELECT id, name, email FROM users WHERE created_at > now() - interval '1 day';\n";
+    line.repeat(INPUT_LEN / line.len() + 1).chars().take(INPUT_LEN).collect()
+}
+
+struct Tables(Vec<Suggestion>);
+
+impl Completer for Tables {
+    fn complete(&self, _: &Document) -> Vec<Suggestion> {
+        self.0.clone()
+    }
+}
+
+fn tables() -> Vec<Suggestion> {
+    (0..100)
+        .map(|i| Suggestion::new(format!("table_{}", i), format!("Rows of the {}th table, with a long description", i)))
+        .collect()
+}
+
+fn bench_document(c: &mut Criterion) {
+    let text = large_input();
+    let middle = INPUT_LEN / 2;
+    c.bench_function("document line lookups", |b| {
+        b.iter(|| {
+            let d = Document::with_text(black_box(text.clone()), middle);
+            black_box((d.cursor_position_row(), d.current_line().len(), d.line_count(), d.display_cursor_position()))
+        })
+    });
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let mut buffer = Buffer::new();
+    buffer.insert_text(&large_input(), false, true);
+    buffer.set_cursor_position(INPUT_LEN / 2);
+    c.bench_function("insert a char and query the document", |b| {
+        b.iter(|| {
+            buffer.insert_text("x", false, true);
+            black_box(buffer.document().get_word_before_cursor());
+            buffer.delete_before_cursor(1);
+        })
+    });
+}
+
+fn bench_format_suggestions(c: &mut Criterion) {
+    let suggestions = tables();
+    c.bench_function("format 100 suggestions", |b| b.iter(|| format_suggestions(black_box(&suggestions), 80, ColumnLayout::default())));
+}
+
+fn bench_render(c: &mut Criterion) {
+    let document = Document::with_text(large_input(), INPUT_LEN / 2);
+    let mut completion = CompletionManager::new(Tables(tables()), 6);
+    completion.update_suggestions(&document);
+    let mut renderer = Renderer::new(io::sink(), "> ");
+    renderer.update_win_size(120, 200);
+    c.bench_function("render a frame", |b| {
+        b.iter(|| {
+            renderer.render(&document, &completion).unwrap();
+            renderer.break_line(&Document::new()).unwrap();
+        })
+    });
+}
+
+/// A terminal behind a serial line, e.g. ssh over a slow link: each write takes as long as
+/// its bytes take to go through at `baud`, ten bits each.
+struct SlowPty {
+    baud: u32,
+}
+
+impl Write for SlowPty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        thread::sleep(Duration::from_secs_f64(buf.len() as f64 * 10.0 / self.baud as f64));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn bench_slow_pty(c: &mut Criterion) {
+    let typed = [
+        Document::with_text("SELECT * FROM table_1".to_string(), 21),
+        Document::with_text("SELECT * FROM table_12".to_string(), 22),
+    ];
+    let mut completion = CompletionManager::new(Tables(tables()), 6);
+    completion.update_suggestions(&typed[0]);
+    let mut renderer = Renderer::new(SlowPty { baud: 115_200 }, "> ");
+    renderer.update_win_size(80, 24);
+    renderer.set_toolbar("table_1: rows of the 1th table");
+    let mut group = c.benchmark_group("slow pty");
+    group.sample_size(10);
+    group.bench_function("render a keystroke at 115200 baud", |b| {
+        b.iter(|| {
+            for document in &typed {
+                renderer.render(document, &completion).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_document, bench_insert, bench_format_suggestions, bench_render, bench_slow_pty);
+criterion_main!(benches);
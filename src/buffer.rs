@@ -0,0 +1,1055 @@
+use std::cell::OnceCell;
+use std::ops::Range;
+
+use crossterm::event::KeyCode;
+use ropey::Rope;
+
+use crate::document::{Document, WordModel};
+use crate::key_bind::KeyBindFunc;
+
+/// A change that [Buffer::repeat_last_change] can make again.
+#[derive(Debug, Clone)]
+enum Change {
+    /// Text typed in a row.
+    Insert(String),
+    /// A key binding function that changed the text, see [Buffer::apply].
+    Func(KeyBindFunc),
+}
+
+/// A change of the text of a [Buffer]: the chars in `range` of the text before the change
+/// were replaced with `text`. An insertion has an empty range and a deletion an empty text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChange {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextChange {
+    /// Returns the change turning `old` into `new`, from the first char they differ at to
+    /// the last one.
+    pub(crate) fn between(old: &Rope, new: &str) -> Self {
+        let prefix = old.chars().zip(new.chars()).take_while(|(a, b)| a == b).count();
+        let new_len = new.chars().count();
+        let max_suffix = old.len_chars().min(new_len) - prefix;
+        let suffix = old.chars_at(old.len_chars()).reversed()
+            .zip(new.chars().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let text = new.chars().skip(prefix).take(new_len - prefix - suffix).collect();
+        Self { range: prefix..old.len_chars() - suffix, text }
+    }
+}
+
+/// Buffer emulates the console buffer.
+///
+/// The text is kept in a rope, so that edits and line lookups stay cheap for large
+/// multi-line inputs. Only `\n` is treated as a line ending, like in [Document].
+#[derive(Debug, Default)]
+pub struct Buffer {
+    text: Rope,
+    cursor_position: usize,
+    /// Remember the original column for the next up/down movement.
+    preferred_column: Option<usize>,
+    last_key_stroke: Option<KeyCode>,
+    word_model: WordModel,
+    /// Number of leading characters that can't be edited or entered by the cursor.
+    protected: usize,
+    /// Document of the current text and cursor position, built on first use after a
+    /// change.
+    document: OnceCell<Document>,
+    /// Text and cursor position before each change, see [Buffer::undo].
+    undo_stack: Vec<(Rope, usize)>,
+    /// A change is going on, the text before it is on top of `undo_stack`.
+    in_change: bool,
+    /// Text typed so far, if the change going on is typing.
+    typed: Option<String>,
+    last_change: Option<Change>,
+    /// The last change was just repeated, so [apply](Buffer::apply) doesn't record the
+    /// repetition as a change of its own.
+    repeated: bool,
+    /// Changes of the text not taken yet, if they are tracked, see
+    /// [Buffer::take_changes].
+    changes: Option<Vec<TextChange>>,
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn word_model(&self) -> &WordModel {
+        &self.word_model
+    }
+
+    /// Sets how words are delimited for word-wise movement and deletion.
+    pub fn set_word_model(&mut self, word_model: WordModel) {
+        self.word_model = word_model;
+    }
+
+    /// Returns string of the current line.
+    pub fn text(&self) -> String {
+        self.text.to_string()
+    }
+
+    /// Returns the number of chars in the text.
+    pub fn len_chars(&self) -> usize {
+        self.text.len_chars()
+    }
+
+    /// Returns the number of lines. A trailing `\n` starts a new, empty line.
+    pub fn line_count(&self) -> usize {
+        self.text.len_lines()
+    }
+
+    /// Returns the line at `row` without its line ending.
+    pub fn line(&self, row: usize) -> Option<String> {
+        if row >= self.text.len_lines() {
+            return None;
+        }
+        let line = self.text.line(row).to_string();
+        Some(line.strip_suffix('\n').map(str::to_string).unwrap_or(line))
+    }
+
+    /// Returns the char index of the first character of the line at `row`.
+    pub fn line_start(&self, row: usize) -> Option<usize> {
+        if row >= self.text.len_lines() {
+            return None;
+        }
+        Some(self.text.line_to_char(row))
+    }
+
+    /// Returns the row the cursor is on. (0-based.)
+    pub fn cursor_row(&self) -> usize {
+        self.text.char_to_line(self.cursor_position)
+    }
+
+    pub fn protected(&self) -> usize {
+        self.protected
+    }
+
+    /// Makes the first `len` characters read-only, e.g. a command name inserted for the
+    /// user: they can't be deleted, and the cursor stays after them.
+    pub fn set_protected(&mut self, len: usize) {
+        self.protected = len.min(self.text.len_chars());
+        self.clamp_cursor();
+    }
+
+    /// Keeps the cursor out of the protected characters.
+    fn clamp_cursor(&mut self) {
+        self.changed();
+        self.cursor_position = self.cursor_position.max(self.protected);
+    }
+
+    /// Starts or stops keeping the changes of the text for [take_changes](Buffer::take_changes).
+    /// They are not kept by default.
+    pub fn set_track_changes(&mut self, track: bool) {
+        self.changes = track.then(Vec::new);
+    }
+
+    /// Returns the changes of the text since the last call, oldest first, each relative to
+    /// the text left by the one before, so that e.g. a highlighter can work on the changed
+    /// lines only. Empty unless [tracked](Buffer::set_track_changes).
+    pub fn take_changes(&mut self) -> Vec<TextChange> {
+        self.changes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record(&mut self, range: Range<usize>, text: &str) {
+        if let Some(changes) = &mut self.changes {
+            if !range.is_empty() || !text.is_empty() {
+                changes.push(TextChange { range, text: text.to_string() });
+            }
+        }
+    }
+
+    /// Drops the document, to be called before changing the text, the cursor position or
+    /// the last key stroke.
+    fn changed(&mut self) {
+        self.document.take();
+    }
+
+    /// To be called before changing the text. Saves the text for [undo](Buffer::undo)
+    /// unless a change is already going on. Typing going on ends.
+    fn edit(&mut self) {
+        self.changed();
+        if !self.in_change || self.typed.is_some() {
+            self.save_undo();
+        }
+        self.typed = None;
+    }
+
+    fn save_undo(&mut self) {
+        self.undo_stack.push((self.text.clone(), self.cursor_position));
+        self.in_change = true;
+    }
+
+    /// Ends the change going on, so that the next edits are undone separately. Every
+    /// edit until then, e.g. by a key binding editing the text in several steps or by
+    /// typing a word, is one change for [undo](Buffer::undo) and
+    /// [repeat_last_change](Buffer::repeat_last_change). The prompt ends the change on
+    /// every key but typed characters.
+    pub fn end_change(&mut self) {
+        self.in_change = false;
+        self.typed = None;
+    }
+
+    /// Restores the text and the cursor position from before the last change. Returns
+    /// false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.end_change();
+        let Some((text, cursor)) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.changed();
+        if self.changes.is_some() {
+            let change = TextChange::between(&self.text, &text.to_string());
+            self.record(change.range, &change.text);
+        }
+        self.text = text;
+        self.cursor_position = cursor.min(self.text.len_chars());
+        self.protected = self.protected.min(self.text.len_chars());
+        self.preferred_column = None;
+        self.clamp_cursor();
+        true
+    }
+
+    /// Forgets the changes to undo, e.g. once the input is accepted.
+    pub fn clear_undo(&mut self) {
+        self.end_change();
+        self.undo_stack.clear();
+    }
+
+    /// Inserts text typed by the user at the cursor. Text typed in a row, until the
+    /// [change ends](Buffer::end_change), is undone at once and repeated as a whole, like
+    /// an insert session in vi.
+    pub fn insert_typed(&mut self, v: &str) {
+        let mut typed = match self.typed.take() {
+            Some(typed) => typed,
+            None => {
+                self.save_undo();
+                String::new()
+            }
+        };
+        self.insert(v, false, true);
+        typed.push_str(v);
+        self.last_change = Some(Change::Insert(typed.clone()));
+        self.typed = Some(typed);
+    }
+
+    /// Runs a key binding function, recording it as the last change if it changed the
+    /// text, see [repeat_last_change](Buffer::repeat_last_change).
+    pub fn apply(&mut self, func: KeyBindFunc) {
+        let changes = self.undo_stack.len();
+        self.repeated = false;
+        func(self);
+        if self.undo_stack.len() > changes && !std::mem::take(&mut self.repeated) {
+            self.last_change = Some(Change::Func(func));
+        }
+    }
+
+    /// Makes the last change again at the cursor, as vi's `.` does: types the same text
+    /// again, or runs the same key binding function. Returns false if nothing changed the
+    /// text yet.
+    pub fn repeat_last_change(&mut self) -> bool {
+        let Some(change) = self.last_change.clone() else {
+            return false;
+        };
+        self.end_change();
+        match &change {
+            Change::Insert(text) => self.insert_text(text, false, true),
+            Change::Func(func) => func(self),
+        }
+        self.end_change();
+        self.last_change = Some(change);
+        self.repeated = true;
+        true
+    }
+
+    pub fn cursor_position(&self) -> usize {
+        self.cursor_position
+    }
+
+    pub fn last_key_stroke(&self) -> Option<KeyCode> {
+        self.last_key_stroke
+    }
+
+    pub fn set_last_key_stroke(&mut self, key: Option<KeyCode>) {
+        self.changed();
+        self.last_key_stroke = key;
+    }
+
+    /// Returns a [Document] of the current text and cursor position. It is built once
+    /// per change, so the renderer, the completer and key bindings can all query the same
+    /// document without copying the text again.
+    pub fn document(&self) -> &Document {
+        self.document.get_or_init(|| {
+            let mut document = Document::with_text(self.text(), self.cursor_position);
+            document.last_key = self.last_key_stroke;
+            document
+        })
+    }
+
+    /// Returns the cursor position on rendered text on terminal emulators.
+    /// So if Document is "日本(cursor)語", DisplayedCursorPosition returns 4 because '日' and '本'
+    /// are double width characters.
+    pub fn display_cursor_position(&self) -> usize {
+        self.document().display_cursor_position()
+    }
+
+    /// Inserts string into the buffer at the cursor position.
+    pub fn insert_text(&mut self, v: &str, overwrite: bool, move_cursor: bool) {
+        self.edit();
+        self.insert(v, overwrite, move_cursor);
+    }
+
+    fn insert(&mut self, v: &str, overwrite: bool, move_cursor: bool) {
+        self.clamp_cursor();
+        let oc = self.cursor_position;
+
+        if overwrite {
+            let overwritten = self.text.chars_at(oc)
+                .take(v.chars().count())
+                .take_while(|&c| c != '\n')
+                .count();
+            self.text.remove(oc..oc + overwritten);
+            self.record(oc..oc + overwritten, v);
+        } else {
+            self.record(oc..oc, v);
+        }
+        self.text.insert(oc, v);
+        if !v.is_empty() {
+            self.preferred_column = None;
+        }
+
+        if move_cursor {
+            self.cursor_position += v.chars().count();
+        }
+    }
+
+    /// Inserts a tab at the cursor. With `expand_to` set, inserts spaces up to the next
+    /// multiple of that width instead, counted from the start of the current line.
+    pub fn insert_tab(&mut self, expand_to: Option<usize>) {
+        match expand_to {
+            None => self.insert_text("\t", false, true),
+            Some(tab_width) => {
+                let tab_width = tab_width.max(1);
+                let cursor = self.cursor_position;
+                let line_start = self.text.line_to_char(self.text.char_to_line(cursor));
+                let before = self.text.slice(line_start..cursor).to_string();
+                let column = Document::with_text(before, usize::MAX)
+                    .display_cursor_position_with_tab_width(tab_width);
+                let spaces = " ".repeat(tab_width - column % tab_width);
+                self.insert_text(&spaces, false, true);
+            }
+        }
+    }
+
+    /// Replaces the whole text. The cursor is moved back if it would point past the end.
+    /// The protected characters stay protected if the new text starts with them.
+    pub fn set_text(&mut self, v: String) {
+        self.changed();
+        self.cursor_position = self.cursor_position.min(v.chars().count());
+        if self.text != v {
+            self.edit();
+            let protected = self.text.slice(..self.protected);
+            if !v.starts_with(&protected.to_string()) {
+                self.protected = 0;
+            }
+            self.preferred_column = None;
+            if self.changes.is_some() {
+                let change = TextChange::between(&self.text, &v);
+                self.record(change.range, &change.text);
+            }
+            self.text = Rope::from(v);
+        }
+        self.clamp_cursor();
+    }
+
+    pub fn set_cursor_position(&mut self, p: usize) {
+        self.changed();
+        self.cursor_position = p.clamp(self.protected, self.text.len_chars());
+    }
+
+    /// Is [set_cursor_position](Buffer::set_cursor_position) for code written when the
+    /// cursor was an `i32`. Negative positions move to the start.
+    #[deprecated(note = "the cursor position is a usize, use `set_cursor_position`")]
+    pub fn set_cursor_position_i32(&mut self, p: i32) {
+        self.set_cursor_position(usize::try_from(p).unwrap_or(0));
+    }
+
+    /// Move to left on the current line.
+    pub fn cursor_left(&mut self, count: usize) {
+        let l = self.document().get_cursor_left_position(count);
+        self.changed();
+        self.cursor_position -= l;
+        self.clamp_cursor();
+        self.preferred_column = None;
+    }
+
+    /// Move to right on the current line.
+    pub fn cursor_right(&mut self, count: usize) {
+        let l = self.document().get_cursor_right_position(count);
+        self.changed();
+        self.cursor_position += l;
+        self.clamp_cursor();
+        self.preferred_column = None;
+    }
+
+    /// Move to the start of the previous word.
+    pub fn cursor_word_left(&mut self) {
+        let start = self.document().find_start_of_previous_word_with_model(&self.word_model);
+        self.changed();
+        self.cursor_position = start;
+        self.clamp_cursor();
+        self.preferred_column = None;
+    }
+
+    /// Move to the end of the current word.
+    pub fn cursor_word_right(&mut self) {
+        let end = self.document().find_end_of_current_word_with_model(&self.word_model);
+        self.changed();
+        self.cursor_position += end;
+        self.clamp_cursor();
+        self.preferred_column = None;
+    }
+
+    /// Move to the blank line before the current paragraph.
+    pub fn cursor_paragraph_backward(&mut self) {
+        let start = self.document().find_previous_paragraph_start();
+        self.changed();
+        self.cursor_position = start;
+        self.clamp_cursor();
+        self.preferred_column = None;
+    }
+
+    /// Move to the blank line after the current paragraph.
+    pub fn cursor_paragraph_forward(&mut self) {
+        let end = self.document().find_next_paragraph_end();
+        self.changed();
+        self.cursor_position += end;
+        self.clamp_cursor();
+        self.preferred_column = None;
+    }
+
+    /// Move cursor to the previous line.
+    /// (for multi-line edit).
+    pub fn cursor_up(&mut self, count: usize) {
+        let document = self.document();
+        let orig = self.preferred_column.unwrap_or_else(|| document.cursor_position_col());
+        let up = document.get_cursor_up_position(count, Some(orig));
+        self.changed();
+        self.cursor_position = self.cursor_position.saturating_add_signed(up);
+        self.clamp_cursor();
+        // Remember the original column for the next up/down movement.
+        self.preferred_column = Some(orig);
+    }
+
+    /// Move cursor to the next line.
+    /// (for multi-line edit).
+    pub fn cursor_down(&mut self, count: usize) {
+        let document = self.document();
+        let orig = self.preferred_column.unwrap_or_else(|| document.cursor_position_col());
+        let down = document.get_cursor_down_position(count, Some(orig));
+        self.changed();
+        self.cursor_position = self.cursor_position.saturating_add_signed(down);
+        self.clamp_cursor();
+        // Remember the original column for the next up/down movement.
+        self.preferred_column = Some(orig);
+    }
+
+    /// Delete specified number of characters before cursor and return the deleted text.
+    pub fn delete_before_cursor(&mut self, count: usize) -> String {
+        let cursor = self.cursor_position;
+        self.delete_range(cursor.saturating_sub(count)..cursor)
+    }
+
+    /// Deletes the characters in `range`, given in char indices, and returns them.
+    /// The range is clamped to the text without the protected characters, and the cursor
+    /// keeps pointing at the same character, or at the start of the range if it was
+    /// inside it.
+    pub fn delete_range(&mut self, range: Range<usize>) -> String {
+        let end = range.end.min(self.text.len_chars());
+        let start = range.start.max(self.protected).min(end);
+        if start == end {
+            return String::new();
+        }
+        self.edit();
+        let deleted = self.text.slice(start..end).to_string();
+        let cursor = self.cursor_position;
+        self.cursor_position = if cursor >= end {
+            cursor - (end - start)
+        } else {
+            cursor.min(start)
+        };
+        self.text.remove(start..end);
+        self.record(start..end, "");
+        self.preferred_column = None;
+        deleted
+    }
+
+    /// Replaces the characters in `range`, given in char indices, with `text` and returns
+    /// the replaced characters. The range is clamped to the text without the protected
+    /// characters. A cursor after the range keeps pointing at the same character, and a
+    /// cursor inside it moves to the end of the inserted text.
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) -> String {
+        let end = range.end.max(self.protected).min(self.text.len_chars());
+        let start = range.start.max(self.protected).min(end);
+        self.edit();
+        let cursor = self.cursor_position;
+        let replaced = self.text.slice(start..end).to_string();
+        self.text.remove(start..end);
+        self.text.insert(start, text);
+        self.record(start..end, text);
+        let inserted = text.chars().count();
+        self.cursor_position = if cursor >= end {
+            cursor - (end - start) + inserted
+        } else if cursor > start {
+            start + inserted
+        } else {
+            cursor
+        };
+        self.preferred_column = None;
+        replaced
+    }
+
+    /// Deletes from the cursor to the end of the line and returns the deleted text. At the
+    /// end of a line, the line ending is deleted instead, joining the next line.
+    pub fn kill_to_end_of_line(&mut self) -> String {
+        let count = self.document().get_end_of_line_position();
+        if count == 0 {
+            return self.delete(1);
+        }
+        self.delete(count)
+    }
+
+    /// Deletes from the start of the line to the cursor and returns the deleted text.
+    pub fn kill_to_start_of_line(&mut self) -> String {
+        let count = self.document().current_line_before_cursor().chars().count();
+        self.delete_before_cursor(count)
+    }
+
+    /// Deletes from the cursor to the end of the current word, including the spaces
+    /// in front of it, and returns the deleted text. Words follow the [WordModel].
+    pub fn kill_word(&mut self) -> String {
+        let count = self.document().find_end_of_current_word_with_model(&self.word_model);
+        self.delete(count)
+    }
+
+    /// Deletes from the start of the word before the cursor, including the spaces
+    /// after it, to the cursor and returns the deleted text. Words follow the [WordModel].
+    pub fn backward_kill_word(&mut self) -> String {
+        let start = self.document().find_start_of_previous_word_with_model(&self.word_model);
+        self.delete_before_cursor(self.cursor_position.saturating_sub(start))
+    }
+
+    /// Insert a line ending at the current position.
+    pub fn new_line(&mut self, copy_margin: bool) {
+        if copy_margin {
+            let margin = self.document().leading_whitespace_in_current_line();
+            self.insert_text(&("\n".to_string() + &margin), false, true);
+        } else {
+            self.insert_text("\n", false, true);
+        }
+    }
+
+    /// Delete specified number of characters and Return the deleted text.
+    pub fn delete(&mut self, count: usize) -> String {
+        let cursor = self.cursor_position;
+        self.delete_range(cursor..cursor.saturating_add(count))
+    }
+
+    /// Joins the next line to the current one by deleting the line ending after the current line.
+    pub fn join_next_line(&mut self, separator: &str) {
+        if !self.document().on_last_line() {
+            let end = self.document().get_end_of_line_position();
+            self.changed();
+            self.cursor_position += end;
+            self.delete(1);
+            // Remove spaces
+            let document = self.document();
+            let text = format!(
+                "{}{}{}",
+                document.text_before_cursor_str(),
+                separator,
+                document.text_after_cursor_str().trim_start_matches(' ')
+            );
+            self.set_text(text);
+        }
+    }
+
+    /// Swaps the last two characters before the cursor.
+    pub fn swap_characters_before_cursor(&mut self) {
+        if self.cursor_position >= 2 + self.protected {
+            self.edit();
+            let cursor = self.cursor_position;
+            let x = self.text.char(cursor - 2);
+            self.text.remove(cursor - 2..cursor - 1);
+            self.text.insert_char(cursor - 1, x);
+            let swapped = self.text.slice(cursor - 2..cursor).to_string();
+            self.record(cursor - 2..cursor, &swapped);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_cache() {
+        let mut b = Buffer::new();
+        b.insert_text("hello", false, true);
+        let first = b.document() as *const Document;
+        assert_eq!(first, b.document() as *const Document);
+        assert_eq!(5, b.document().cursor_position());
+
+        b.cursor_left(2);
+        assert_eq!(3, b.document().cursor_position());
+        b.set_last_key_stroke(Some(KeyCode::Left));
+        assert_eq!(Some(KeyCode::Left), b.document().last_key_stroke());
+        b.delete_before_cursor(1);
+        assert_eq!("helo", b.document().text);
+        b.swap_characters_before_cursor();
+        assert_eq!("ehlo", b.document().text);
+        b.set_text("hi".to_string());
+        assert_eq!(("hi", 2), (b.document().text.as_str(), b.document().cursor_position()));
+    }
+
+    #[test]
+    fn test_new_buffer() {
+        let b = Buffer::new();
+        assert_eq!("", b.text());
+        assert_eq!(0, b.cursor_position());
+    }
+
+    #[test]
+    fn test_insert_text() {
+        let mut b = Buffer::new();
+        b.insert_text("some_text", false, true);
+        assert_eq!("some_text", b.text());
+        assert_eq!("some_text".len(), b.cursor_position());
+    }
+
+    #[test]
+    fn test_line_lookups() {
+        let mut b = Buffer::new();
+        b.insert_text("select *\r\nfrom t\n", false, true);
+        assert_eq!(3, b.line_count());
+        assert_eq!(Some("select *\r".to_string()), b.line(0));
+        assert_eq!(Some("from t".to_string()), b.line(1));
+        assert_eq!(Some("".to_string()), b.line(2));
+        assert_eq!(None, b.line(3));
+        assert_eq!(Some("select *\r\n".chars().count()), b.line_start(1));
+        assert_eq!(2, b.cursor_row());
+
+        let mut b = Buffer::new();
+        for i in 0..5000 {
+            b.insert_text(&format!("line {}\n", i), false, true);
+        }
+        b.set_cursor_position(b.line_start(2500).unwrap());
+        assert_eq!(2500, b.cursor_row());
+        assert_eq!(Some("line 2500".to_string()), b.line(2500));
+        assert_eq!(5001, b.line_count());
+    }
+
+    #[test]
+    fn test_insert_text_overwrite() {
+        let mut b = Buffer::new();
+        b.insert_text("ABC", false, true);
+        b.cursor_left(2);
+        b.insert_text("ba", true, true);
+        assert_eq!("Aba", b.text());
+        b.insert_text("cd", true, true);
+        assert_eq!("Abacd", b.text());
+        // line endings are never overwritten
+        b.set_text("ab\ncd".to_string());
+        b.set_cursor_position(1);
+        b.insert_text("xyz", true, true);
+        assert_eq!("axyz\ncd", b.text());
+    }
+
+    #[test]
+    fn test_cursor_movement() {
+        let mut b = Buffer::new();
+        b.insert_text("some_text", false, true);
+
+        b.cursor_left(1);
+        b.cursor_left(2);
+        b.cursor_right(1);
+        b.insert_text("A", false, true);
+        assert_eq!("some_teAxt", b.text());
+        assert_eq!("some_teA".len(), b.cursor_position());
+
+        // Moving over left character counts.
+        b.cursor_left(100);
+        b.insert_text("A", false, true);
+        assert_eq!("Asome_teAxt", b.text());
+        assert_eq!("A".len(), b.cursor_position());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_i32_cursor_shim() {
+        let mut b = Buffer::new();
+        b.insert_text("some_text", false, true);
+        b.set_cursor_position_i32(-5);
+        assert_eq!(0, b.document().cursor_position_i32());
+        b.set_cursor_position_i32(4);
+        assert_eq!(4, b.document().cursor_position_i32());
+    }
+
+    #[test]
+    fn test_cursor_movement_with_multi_byte() {
+        let mut b = Buffer::new();
+        b.insert_text("あいうえお", false, true);
+        b.cursor_left(1);
+        assert_eq!("お", b.document().text_after_cursor());
+    }
+
+    #[test]
+    fn test_cursor_up() {
+        let mut b = Buffer::new();
+        b.insert_text("long line1\nline2", false, true);
+        b.cursor_up(1);
+        assert_eq!(5, b.document().cursor_position());
+
+        // Going up when already at the top.
+        b.cursor_up(1);
+        assert_eq!(5, b.document().cursor_position());
+
+        // Going up to a line that's shorter.
+        b.set_text(String::new());
+        b.insert_text("line1\nlong line2", false, true);
+        b.cursor_up(1);
+        assert_eq!(5, b.document().cursor_position());
+    }
+
+    #[test]
+    fn test_cursor_down() {
+        let mut b = Buffer::new();
+        b.insert_text("line1\nline2", false, true);
+        b.set_cursor_position(3);
+
+        // Normally going down
+        b.cursor_down(1);
+        assert_eq!("line1\nlin".len(), b.document().cursor_position());
+
+        // Going down to a line that's shorter.
+        let mut b = Buffer::new();
+        b.insert_text("long line1\na\nb", false, true);
+        b.set_cursor_position(3);
+        b.cursor_down(1);
+        assert_eq!("long line1\na".len(), b.document().cursor_position());
+    }
+
+    #[test]
+    fn test_delete_before_cursor() {
+        let mut b = Buffer::new();
+        b.insert_text("some_text", false, true);
+        b.cursor_left(2);
+        let deleted = b.delete_before_cursor(1);
+        assert_eq!("some_txt", b.text());
+        assert_eq!("e", deleted);
+        assert_eq!("some_t".len(), b.cursor_position());
+
+        // Delete over the characters length before cursor.
+        let deleted = b.delete_before_cursor(100);
+        assert_eq!("some_t", deleted);
+        assert_eq!("xt", b.text());
+
+        // If cursor position is a beginning of line, it has no effect.
+        let deleted = b.delete_before_cursor(1);
+        assert_eq!("", deleted);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut b = Buffer::new();
+        b.insert_text("日本語です", false, true);
+        b.set_cursor_position(1);
+        assert_eq!("本語", b.delete(2));
+        assert_eq!("日です", b.text());
+        assert_eq!("です", b.delete(100));
+        assert_eq!("", b.delete(1));
+    }
+
+    #[test]
+    fn test_delete_range() {
+        let mut b = Buffer::new();
+        b.insert_text("日本語 text", false, true);
+        b.set_cursor_position(6);
+        assert_eq!("本語", b.delete_range(1..3));
+        assert_eq!("日 text", b.text());
+        assert_eq!(4, b.cursor_position());
+
+        // the cursor inside the range moves to its start
+        assert_eq!(" te", b.delete_range(1..4));
+        assert_eq!("日xt", b.text());
+        assert_eq!(1, b.cursor_position());
+
+        // the range is clamped to the text
+        assert_eq!("t", b.delete_range(2..100));
+        assert_eq!("", b.delete_range(5..10));
+        assert_eq!("日x", b.text());
+        assert_eq!(1, b.cursor_position());
+    }
+
+    #[test]
+    fn test_kill_line() {
+        let mut b = Buffer::new();
+        b.insert_text("こんにちは\nДобрый день", false, true);
+        b.set_cursor_position(2);
+        assert_eq!("にちは", b.kill_to_end_of_line());
+        assert_eq!("こん\nДобрый день", b.text());
+        // at the end of the line, the line ending is killed
+        assert_eq!("\n", b.kill_to_end_of_line());
+        assert_eq!("こんДобрый день", b.text());
+        assert_eq!("こん", b.kill_to_start_of_line());
+        assert_eq!("Добрый день", b.text());
+        assert_eq!(0, b.cursor_position());
+        assert_eq!("", b.kill_to_start_of_line());
+    }
+
+    #[test]
+    fn test_kill_word() {
+        let mut b = Buffer::new();
+        b.insert_text("apple  りんご день", false, true);
+        b.set_cursor_position(5);
+        assert_eq!("  りんご", b.kill_word());
+        assert_eq!("apple день", b.text());
+        b.set_cursor_position(10);
+        assert_eq!("день", b.backward_kill_word());
+        assert_eq!("apple ", b.text());
+        assert_eq!("apple ", b.backward_kill_word());
+        assert_eq!("", b.text());
+    }
+
+    #[test]
+    fn test_word_model() {
+        let mut b = Buffer::new();
+        b.insert_text("foo.bar(baz)", false, true);
+        b.cursor_word_left();
+        assert_eq!(0, b.cursor_position());
+        b.cursor_word_right();
+        assert_eq!(12, b.cursor_position());
+
+        b.set_word_model(WordModel::CharClass);
+        b.cursor_word_left();
+        assert_eq!(11, b.cursor_position());
+        b.cursor_word_left();
+        assert_eq!(8, b.cursor_position());
+        assert_eq!("(", b.backward_kill_word());
+        assert_eq!("baz", b.kill_word());
+        assert_eq!("foo.bar)", b.text());
+        b.set_cursor_position(0);
+        b.cursor_word_right();
+        assert_eq!(3, b.cursor_position());
+    }
+
+    #[test]
+    fn test_replace_range() {
+        let mut b = Buffer::new();
+        b.insert_text("git chek main", false, true);
+        b.set_cursor_position(8);
+        assert_eq!("chek", b.replace_range(4..8, "checkout"));
+        assert_eq!("git checkout main", b.text());
+        assert_eq!(12, b.cursor_position());
+
+        // cursor after the range keeps its character
+        b.set_cursor_position(14);
+        b.replace_range(0..3, "ジット");
+        assert_eq!("ジット checkout main", b.text());
+        assert_eq!(14, b.cursor_position());
+
+        // cursor before the range does not move, the range is clamped
+        b.set_cursor_position(1);
+        assert_eq!("main", b.replace_range(13..100, "dev"));
+        assert_eq!("ジット checkout dev", b.text());
+        assert_eq!(1, b.cursor_position());
+        assert_eq!("", b.replace_range(5..5, "X"));
+        assert_eq!("ジット cXheckout dev", b.text());
+    }
+
+    #[test]
+    fn test_new_line() {
+        let mut b = Buffer::new();
+        b.insert_text("  hello", false, true);
+        b.new_line(false);
+        assert_eq!("  hello\n", b.text());
+        b.set_text("  hello".to_string());
+        b.set_cursor_position(7);
+        b.new_line(true);
+        assert_eq!("  hello\n  ", b.text());
+    }
+
+    #[test]
+    fn test_join_next_line() {
+        let mut b = Buffer::new();
+        b.insert_text("line1\nline2\nline3", false, true);
+        b.cursor_up(1);
+        b.join_next_line(" ");
+        assert_eq!("line1\nline2 line3", b.text());
+
+        // Test when there is no '\n' in the text
+        let mut b = Buffer::new();
+        b.insert_text("line1", false, true);
+        b.set_cursor_position(0);
+        b.join_next_line(" ");
+        assert_eq!("line1", b.text());
+    }
+
+    #[test]
+    fn test_swap_characters_before_cursor() {
+        let mut b = Buffer::new();
+        b.insert_text("hello world", false, true);
+        b.cursor_left(2);
+        b.swap_characters_before_cursor();
+        assert_eq!("hello wrold", b.text());
+    }
+
+    #[test]
+    fn test_insert_tab() {
+        let mut b = Buffer::new();
+        b.insert_text("ab", false, true);
+        b.insert_tab(None);
+        assert_eq!("ab\t", b.text());
+
+        let mut b = Buffer::new();
+        b.insert_text("ab", false, true);
+        b.insert_tab(Some(4));
+        assert_eq!("ab  ", b.text());
+        b.insert_tab(Some(4));
+        assert_eq!("ab      ", b.text());
+
+        // columns are counted from the start of the current line
+        b.insert_text("\n日", false, true);
+        b.insert_tab(Some(4));
+        assert_eq!("ab      \n日  ", b.text());
+    }
+
+    #[test]
+    fn test_protected() {
+        let mut b = Buffer::new();
+        b.insert_text("git commit", false, true);
+        b.set_protected(4);
+        b.cursor_left(20);
+        assert_eq!(4, b.cursor_position());
+        b.cursor_word_left();
+        b.set_cursor_position(0);
+        assert_eq!(4, b.cursor_position());
+        b.delete_before_cursor(3);
+        b.kill_to_start_of_line();
+        b.swap_characters_before_cursor();
+        assert_eq!("git commit", b.text());
+        b.insert_text("-C ", false, true);
+        assert_eq!("git -C commit", b.text());
+
+        b.set_cursor_position(13);
+        b.kill_to_start_of_line();
+        assert_eq!("git ", b.text());
+        b.replace_range(0..4, "x");
+        assert_eq!("git x", b.text());
+        b.set_text("git status".to_string());
+        assert_eq!(4, b.protected());
+        b.set_text("ls".to_string());
+        assert_eq!(0, b.protected());
+        b.set_cursor_position(0);
+        assert_eq!(0, b.cursor_position());
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut b = Buffer::new();
+        assert!(!b.undo());
+        b.insert_typed("he");
+        b.insert_typed("llo");
+        b.end_change();
+        b.insert_typed(" world");
+        // a change while typing ends the typing
+        b.delete_before_cursor(1);
+        assert_eq!("hello worl", b.text());
+        assert!(b.undo());
+        assert_eq!("hello world", b.text());
+        assert!(b.undo());
+        assert_eq!("hello", b.text());
+        assert_eq!(5, b.cursor_position());
+        assert!(b.undo());
+        assert_eq!("", b.text());
+        assert!(!b.undo());
+
+        // edits until the change ends are undone at once
+        b.insert_text("a b", false, true);
+        b.end_change();
+        b.delete_before_cursor(1);
+        b.insert_text("c", false, true);
+        b.end_change();
+        assert_eq!("a c", b.text());
+        assert!(b.undo());
+        assert_eq!("a b", b.text());
+        b.clear_undo();
+        assert!(!b.undo());
+    }
+
+    #[test]
+    fn test_repeat_last_change() {
+        let mut b = Buffer::new();
+        assert!(!b.repeat_last_change());
+        b.insert_typed("ab");
+        b.end_change();
+        assert!(b.repeat_last_change());
+        assert_eq!("abab", b.text());
+        assert!(b.undo());
+        assert_eq!("ab", b.text());
+
+        // moving isn't a change, so the last change stays the typing
+        b.apply(crate::key_bind_func::go_line_beginning);
+        b.apply(crate::key_bind_func::delete_char);
+        assert_eq!("b", b.text());
+        b.end_change();
+        b.apply(crate::key_bind_func::repeat_last_change);
+        assert_eq!("", b.text());
+        b.insert_typed("xy");
+        b.end_change();
+        b.apply(crate::key_bind_func::go_line_beginning);
+        b.apply(crate::key_bind_func::repeat_last_change);
+        assert_eq!("xyxy", b.text());
+        assert_eq!(2, b.cursor_position());
+    }
+
+    #[test]
+    fn test_text_changes() {
+        let change = |range: Range<usize>, text: &str| TextChange { range, text: text.to_string() };
+        let mut b = Buffer::new();
+        b.insert_text("hello", false, true);
+        assert!(b.take_changes().is_empty());
+
+        b.set_track_changes(true);
+        b.insert_text(" world", false, true);
+        b.insert_text("HE", true, false);
+        b.delete_before_cursor(1);
+        b.set_cursor_position(3);
+        b.swap_characters_before_cursor();
+        assert_eq!("hlelo worlHE", b.text());
+        assert_eq!(vec![
+            change(5..5, " world"),
+            change(11..11, "HE"),
+            change(10..11, ""),
+            change(1..3, "le"),
+        ], b.take_changes());
+        assert!(b.take_changes().is_empty());
+
+        // a whole new text is reduced to what differs
+        b.end_change();
+        b.set_text("hlelo HE".to_string());
+        assert_eq!(vec![change(6..10, "")], b.take_changes());
+        b.end_change();
+        assert!(b.undo());
+        assert_eq!(vec![change(6..6, "worl")], b.take_changes());
+        b.set_text("hlelo worlHE".to_string());
+        assert!(b.take_changes().is_empty());
+
+        b.set_track_changes(false);
+        b.insert_text("!", false, true);
+        assert!(b.take_changes().is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossterm::style::Color;
+#[cfg(unix)]
+use signal_hook::consts::SIGINT;
+
+use crate::output::Output;
+use crate::style::{SpanStyle, StyledText};
+
+/// How often the listener checks whether Ctrl-C was pressed or the command is done.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Tells the executor that Ctrl-C was pressed while it ran, see
+/// `Prompt::with_cancel_token`. A long running executor checks
+/// [is_cancelled](CancelToken::is_cancelled) now and then and returns early once it is.
+///
+/// Clones share the same state, so the executor keeps one to check while the prompt
+/// holds another. It is reset before each command.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Cancels the command, as Ctrl-C does.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Cancels the token when Ctrl-C is pressed while the executor runs on the prompt's
+/// thread, and prints a notice to `output`. The terminal stays in cooked mode and stdin
+/// is left to the executor, so Ctrl-C is caught as SIGINT, which child processes in the
+/// foreground get too. Without the listener, SIGINT terminates the process as usual.
+///
+/// Only Unix has SIGINT; elsewhere the token is only cancelled with
+/// [cancel](CancelToken::cancel).
+pub(crate) struct CtrlCListener {
+    #[cfg(unix)]
+    id: signal_hook::SigId,
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Set when SIGINT is to run its default action, i.e. when no listener is running.
+#[cfg(unix)]
+fn default_action() -> io::Result<&'static Arc<AtomicBool>> {
+    static DEFAULT_ACTION: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    if let Some(default_action) = DEFAULT_ACTION.get() {
+        return Ok(default_action);
+    }
+    let default_action = Arc::new(AtomicBool::new(true));
+    signal_hook::flag::register_conditional_default(SIGINT, Arc::clone(&default_action))?;
+    Ok(DEFAULT_ACTION.get_or_init(|| default_action))
+}
+
+impl CtrlCListener {
+    pub(crate) fn start(token: CancelToken, output: Output) -> io::Result<Self> {
+        token.reset();
+        let interrupted = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        let id = {
+            // installed first, so that nothing can fail once the listener is registered
+            let default_action = default_action()?;
+            let id = signal_hook::flag::register(SIGINT, Arc::clone(&interrupted))?;
+            default_action.store(false, Ordering::SeqCst);
+            id
+        };
+        let done = Arc::new(AtomicBool::new(false));
+        let listening = Arc::clone(&done);
+        let thread = thread::spawn(move || {
+            while !listening.load(Ordering::SeqCst) {
+                if interrupted.swap(false, Ordering::SeqCst) && !token.is_cancelled() {
+                    token.cancel();
+                    output.print_styled(&StyledText::new().span("^C cancelling…", SpanStyle::default().fg(Color::DarkGrey)));
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Ok(Self {
+            #[cfg(unix)]
+            id,
+            done,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for CtrlCListener {
+    /// Stops listening once the command is done, giving SIGINT its default action back.
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        #[cfg(unix)]
+        {
+            signal_hook::low_level::unregister(self.id);
+            if let Ok(default_action) = default_action() {
+                default_action.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::new();
+        assert!(!token.is_cancelled());
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        token.reset();
+        assert!(!token.is_cancelled());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ctrl_c_listener() {
+        let token = CancelToken::new();
+        let listener = CtrlCListener::start(token.clone(), Output::with_writer(io::sink())).unwrap();
+        signal_hook::low_level::raise(SIGINT).unwrap();
+        for _ in 0..500 {
+            if token.is_cancelled() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        assert!(token.is_cancelled());
+        drop(listener);
+        assert!(default_action().unwrap().load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use crate::key_bind::KeyBindFunc;
+use crate::key_bind_func::*;
+
+/// What a [Command] is about, to group the key bindings in the help.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CommandCategory {
+    Movement,
+    Editing,
+    Completion,
+    History,
+    Prompt,
+}
+
+impl fmt::Display for CommandCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CommandCategory::Movement => "Movement",
+            CommandCategory::Editing => "Editing",
+            CommandCategory::Completion => "Completion",
+            CommandCategory::History => "History",
+            CommandCategory::Prompt => "Prompt",
+        })
+    }
+}
+
+/// A named function of the prompt that keys are bound to, see `Prompt::key_map`.
+#[derive(Debug)]
+pub struct Command {
+    /// The readline name of the command, e.g. `beginning-of-line`, as used in an inputrc.
+    pub name: &'static str,
+    /// What the command does in a word or two, e.g. `line start`, for the key hints and
+    /// the help.
+    pub label: &'static str,
+    pub category: CommandCategory,
+    /// The function run on the buffer, or None for commands the prompt carries out
+    /// itself, e.g. completion.
+    pub func: Option<KeyBindFunc>,
+}
+
+const fn command(name: &'static str, label: &'static str, category: CommandCategory, func: Option<KeyBindFunc>) -> Command {
+    Command { name, label, category, func }
+}
+
+/// The named commands, aliases after the command they stand for.
+static COMMANDS: &[Command] = {
+    use CommandCategory::*;
+    &[
+        command("beginning-of-line", "line start", Movement, Some(go_line_beginning)),
+        command("end-of-line", "line end", Movement, Some(go_line_end)),
+        command("forward-char", "char right", Movement, Some(go_right_char)),
+        command("backward-char", "char left", Movement, Some(go_left_char)),
+        command("forward-word", "word right", Movement, Some(go_right_word)),
+        command("backward-word", "word left", Movement, Some(go_left_word)),
+        command("forward-paragraph", "next paragraph", Movement, Some(go_next_paragraph)),
+        command("backward-paragraph", "previous paragraph", Movement, Some(go_previous_paragraph)),
+        command("delete-char", "delete char", Editing, Some(delete_char)),
+        command("backward-delete-char", "delete char before", Editing, Some(delete_before_char)),
+        command("kill-word", "cut word", Editing, Some(delete_word_after)),
+        command("backward-kill-word", "cut word before", Editing, Some(delete_word)),
+        command("unix-word-rubout", "cut word before", Editing, Some(delete_word)),
+        command("kill-line", "cut to line end", Editing, Some(kill_line)),
+        command("backward-kill-line", "cut to line start", Editing, Some(kill_line_before)),
+        command("unix-line-discard", "cut to line start", Editing, Some(kill_line_before)),
+        command("transpose-chars", "swap chars", Editing, Some(transpose_chars)),
+        command("undo", "undo", Editing, Some(undo)),
+        command("vi-redo", "repeat change", Editing, Some(repeat_last_change)),
+        command("quoted-insert", "insert literally", Editing, None),
+        command("insert-digraph", "digraph", Editing, None),
+        command("complete", "complete", Completion, None),
+        command("menu-complete", "next", Completion, None),
+        command("menu-complete-backward", "previous", Completion, None),
+        command("menu-page-down", "page down", Completion, None),
+        command("menu-page-up", "page up", Completion, None),
+        command("fuzzy-complete", "find", Completion, None),
+        command("accept-suggestion", "accept", Completion, None),
+        command("dismiss-completion", "dismiss", Completion, None),
+        command("accept-suggestion-1", "accept 1", Completion, None),
+        command("accept-suggestion-2", "accept 2", Completion, None),
+        command("accept-suggestion-3", "accept 3", Completion, None),
+        command("accept-suggestion-4", "accept 4", Completion, None),
+        command("accept-suggestion-5", "accept 5", Completion, None),
+        command("accept-suggestion-6", "accept 6", Completion, None),
+        command("accept-suggestion-7", "accept 7", Completion, None),
+        command("accept-suggestion-8", "accept 8", Completion, None),
+        command("accept-suggestion-9", "accept 9", Completion, None),
+        command("previous-history", "previous entry", History, None),
+        command("next-history", "next entry", History, None),
+        command("reverse-search-history", "history", History, None),
+        command("toggle-history-scope", "all scopes", History, None),
+        command("rerun-last", "run last again", History, None),
+        command("accept-line", "run", Prompt, None),
+        command("insert-comment", "comment out", Prompt, None),
+        command("push-line", "stash input", Prompt, None),
+        command("abort", "cancel", Prompt, None),
+        command("end-of-file", "exit", Prompt, None),
+        command("previous-field", "back", Prompt, None),
+        command("help", "help", Prompt, None),
+        command("command-palette", "commands", Prompt, None),
+    ]
+};
+
+impl Command {
+    /// Returns the command called `name`.
+    pub fn named(name: &str) -> Option<&'static Command> {
+        COMMANDS.iter().find(|c| c.name == name)
+    }
+
+    /// Returns the command running `func`, if it is one of the built-in functions.
+    pub fn of(func: KeyBindFunc) -> Option<&'static Command> {
+        COMMANDS.iter().find(|c| c.func.is_some_and(|f| std::ptr::fn_addr_eq(f, func)))
+    }
+
+    /// Returns every named command.
+    pub fn all() -> &'static [Command] {
+        COMMANDS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let command = Command::named("unix-line-discard").unwrap();
+        assert_eq!("cut to line start", command.label);
+        assert_eq!(CommandCategory::Editing, command.category);
+        // aliases are found by their function under the name they stand for
+        assert_eq!("backward-kill-line", Command::of(kill_line_before).unwrap().name);
+        assert!(Command::named("complete").unwrap().func.is_none());
+        assert!(Command::named("vi-editing-mode").is_none());
+        assert!(Command::of(|b| b.set_text(String::new())).is_none());
+    }
+}
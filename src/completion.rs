@@ -1,16 +1,36 @@
-use std::process::id;
-use std::thread::spawn;
+use crate::document::Document;
+use crate::frecency::Frecency;
+use crate::width::{display_width, truncate_to_width};
 
 const SHORTEN_SUFFIX: &str = "...";
 const LEFT_PREFIX: &str = " ";
 const LEFT_SUFFIX: &str = " ";
 const RIGHT_PREFIX: &str = " ";
 const RIGHT_SUFFIX: &str = " ";
+const HINT_PREFIX: &str = " ";
+const HINT_SUFFIX: &str = " ";
+/// Space between the end of a scrolling description and its start coming around again.
+const MARQUEE_GAP: &str = "   ";
+/// Steps a scrolling description stays at its start before it starts moving.
+const MARQUEE_PAUSE: usize = 4;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Suggestion {
     text: String,
+    #[cfg_attr(feature = "serde", serde(default))]
     description: String,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    id: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "std::ops::Not::not"))]
+    correction: bool,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    url: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    hint: Option<String>,
+    /// Why the suggestion can't be selected, if it can't.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    disabled: Option<String>,
 }
 
 impl Suggestion {
@@ -19,6 +39,11 @@ impl Suggestion {
         Self {
             text,
             description,
+            id: None,
+            correction: false,
+            url: None,
+            hint: None,
+            disabled: None,
         }
     }
 
@@ -26,9 +51,74 @@ impl Suggestion {
         Self {
             text,
             description: "".to_string(),
+            id: None,
+            correction: false,
+            url: None,
+            hint: None,
+            disabled: None,
         }
     }
 
+    /// Sets an identifier passed along when the suggestion is accepted, to tell apart
+    /// suggestions with the same text, see `Prompt::with_on_accepted`.
+    pub fn with_id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Marks the suggestion as a correction of what was typed rather than a completion,
+    /// so that it is shown with the theme's correction style.
+    pub fn mark_correction(mut self) -> Self {
+        self.correction = true;
+        self
+    }
+
+    pub fn is_correction(&self) -> bool {
+        self.correction
+    }
+
+    /// Links the description to `url`, e.g. the documentation of a flag, in terminals
+    /// that support hyperlinks.
+    pub fn with_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Shows `hint` right-aligned in a column of its own after the description, e.g. the
+    /// alias `k` of `kubectl` or the key inserting the suggestion.
+    pub fn with_hint<S: Into<String>>(mut self, hint: S) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    /// Shows the suggestion dimmed, with `reason` in place of its description, and keeps
+    /// it from being selected, e.g. a flag conflicting with one already typed.
+    pub fn disable<S: Into<String>>(mut self, reason: S) -> Self {
+        self.disabled = Some(reason.into());
+        self
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.is_some()
+    }
+
+    /// Returns why the suggestion can't be selected, see [disable](Suggestion::disable).
+    pub fn disabled_reason(&self) -> Option<&str> {
+        self.disabled.as_deref()
+    }
+
     pub fn text(&self) -> &str {
         &self.text
     }
@@ -38,95 +128,502 @@ impl Suggestion {
     }
 }
 
-trait Completer {
+/// Completer returns the suggestions for the document being edited.
+pub trait Completer {
     // TODO: maybe better to do `&mut self`
-    fn complete(&self, input: &str) -> Vec<Suggestion>;
+    fn complete(&self, document: &Document) -> Vec<Suggestion>;
+
+    /// Returns true while suggestions are being computed in the background, e.g. by
+    /// [ThreadedCompleter](crate::ThreadedCompleter). The prompt then shows a spinner
+    /// and asks again until it returns false.
+    fn pending(&self) -> bool {
+        false
+    }
+}
+
+/// Is like [Completer], but also receives the context of the prompt, see
+/// `Prompt::with_context`. Every [Completer] is a ContextCompleter ignoring the context.
+pub trait ContextCompleter<Ctx> {
+    fn complete_with_context(&self, document: &Document, ctx: &Ctx) -> Vec<Suggestion>;
+
+    /// See [Completer::pending].
+    fn pending(&self) -> bool {
+        false
+    }
+}
+
+impl<Ctx, T: Completer> ContextCompleter<Ctx> for T {
+    fn complete_with_context(&self, document: &Document, _: &Ctx) -> Vec<Suggestion> {
+        self.complete(document)
+    }
+
+    fn pending(&self) -> bool {
+        Completer::pending(self)
+    }
+}
+
+/// When the suggestions are asked for as the input changes, see
+/// [CompletionManager::set_trigger].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompletionTrigger {
+    /// On every change of the input, once the word before the cursor is
+    /// [long enough](CompletionManager::set_min_prefix_len).
+    #[default]
+    WhileTyping,
+    /// Only when Tab is pressed. Typing updates the suggestions already shown.
+    Tab,
+}
+
+/// Breakpoints deciding which columns of the completion menu are left out when the
+/// terminal is too narrow for all of them, see [format_suggestions]. The text column has
+/// priority over the description column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnLayout {
+    /// Width, without padding, the description column needs to be shown at all. It is left
+    /// out rather than truncated to a few chars, unless the descriptions are this short.
+    pub min_description_width: usize,
+    /// Chars of the texts that must be left before a `...` when they are truncated. With
+    /// less room, the texts are cut short to a single column without padding or `...`.
+    pub min_text_width: usize,
 }
 
-#[derive(Default)]
-struct CompletionManager<'a, C: Completer + Default> {
-    selected: i32,
+impl Default for ColumnLayout {
+    fn default() -> Self {
+        Self { min_description_width: 10, min_text_width: 3 }
+    }
+}
+
+pub struct CompletionManager<'a, C> {
+    selected: Option<usize>,
     tmp: Vec<Suggestion>,
     max: usize,
     completer: C,
-    vertical_scroll: isize,
+    vertical_scroll: usize,
     word_separator: &'a str,
     show_at_start: bool,
+    scroll_off: usize,
+    wrap_around: bool,
+    available_height: usize,
+    filter_while_typing: bool,
+    keep_selection: bool,
+    ignore_case: bool,
+    column_layout: ColumnLayout,
+    trigger: CompletionTrigger,
+    min_prefix_len: usize,
+    /// Start of the word the suggestions were dismissed in, see
+    /// [dismiss](CompletionManager::dismiss).
+    dismissed_at: Option<usize>,
+    loading: bool,
+    frecency: Option<Frecency>,
 }
 
-impl<'a, C: Completer + Default> CompletionManager<'a, C> {
-    fn new(completer: C, max: usize) -> Self {
+impl<'a, C> CompletionManager<'a, C> {
+    pub fn new(completer: C, max: usize) -> Self {
         Self {
-            completer,
-            selected: -1,
+            selected: None,
+            tmp: Vec::new(),
             max,
+            completer,
             vertical_scroll: 0,
-            ..Default::default()
+            word_separator: "",
+            show_at_start: false,
+            scroll_off: 0,
+            wrap_around: true,
+            available_height: usize::MAX,
+            filter_while_typing: false,
+            keep_selection: true,
+            ignore_case: true,
+            column_layout: ColumnLayout::default(),
+            trigger: CompletionTrigger::default(),
+            min_prefix_len: 0,
+            dismissed_at: None,
+            loading: false,
+            frecency: None,
         }
     }
 
-    fn get_suggestions(&self) -> &[Suggestion] {
+    pub fn get_suggestions(&self) -> &[Suggestion] {
         &self.tmp
     }
 
-    fn update_suggestions(&mut self, input: &str) {
-        self.tmp = self.completer.complete(input);
+    /// Returns the suggestions to change or reorder them, e.g. with
+    /// [Frecency::rank](crate::Frecency::rank).
+    pub fn get_suggestions_mut(&mut self) -> &mut [Suggestion] {
+        &mut self.tmp
+    }
+
+    /// Returns the currently selected suggestion, if any.
+    pub fn get_selected_suggestion(&self) -> Option<&Suggestion> {
+        self.selected.and_then(|i| self.tmp.get(i))
+    }
+
+    /// Returns the index of the selected suggestion, or `None` if none is selected.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Selects the suggestion at `index`, scrolling to show it. `None`, or an index past
+    /// the suggestions, clears the selection.
+    pub fn set_selected(&mut self, index: Option<usize>) {
+        match index.filter(|&i| self.selectable(i)) {
+            Some(i) => {
+                self.selected = Some(i);
+                self.scroll_to_selected();
+            }
+            None => self.reset_selection(),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Limits the number of suggestions shown at once below [max](CompletionManager::max),
+    /// e.g. to the rows left in the terminal, see `Renderer::completion_height`.
+    pub fn set_available_height(&mut self, available_height: usize) {
+        self.available_height = available_height;
+        self.scroll_to_selected();
+    }
+
+    /// Returns the number of suggestions shown at once, or 1 for the loading row.
+    pub fn window_height(&self) -> usize {
+        let rows = if self.tmp.is_empty() && self.loading { 1 } else { self.tmp.len() };
+        self.max.min(self.available_height).min(rows)
+    }
+
+    /// Returns true if the completer was still computing the suggestions when they were
+    /// last updated, see [Completer::pending].
+    pub fn loading(&self) -> bool {
+        self.loading
+    }
+
+    pub fn vertical_scroll(&self) -> usize {
+        self.vertical_scroll
+    }
+
+    pub fn word_separator(&self) -> &str {
+        self.word_separator
+    }
+
+    /// Sets the characters delimiting the word replaced by the selected suggestion.
+    /// Spaces are used if it is empty.
+    pub fn set_word_separator(&mut self, word_separator: &'a str) {
+        self.word_separator = word_separator;
+    }
+
+    pub fn show_at_start(&self) -> bool {
+        self.show_at_start
+    }
+
+    /// Sets whether the suggestions for the empty input are shown as soon as the prompt
+    /// is, before any key is pressed, and again for each new line.
+    pub fn set_show_at_start(&mut self, show_at_start: bool) {
+        self.show_at_start = show_at_start;
+    }
+
+    pub fn scroll_off(&self) -> usize {
+        self.scroll_off
+    }
+
+    /// Sets the number of suggestions kept visible above and below the selected one when
+    /// scrolling, as far as the list and the window allow.
+    pub fn set_scroll_off(&mut self, scroll_off: usize) {
+        self.scroll_off = scroll_off;
+        self.scroll_to_selected();
+    }
+
+    pub fn wrap_around(&self) -> bool {
+        self.wrap_around
+    }
+
+    /// Sets whether moving past the last or the first suggestion clears the selection,
+    /// so that the next move starts over from the other end, which is the default. The
+    /// selection stops at the ends otherwise.
+    pub fn set_wrap_around(&mut self, wrap_around: bool) {
+        self.wrap_around = wrap_around;
+    }
+
+    pub fn filter_while_typing(&self) -> bool {
+        self.filter_while_typing
+    }
+
+    /// Sets whether typing while suggestions are shown narrows them down with
+    /// [filter](CompletionManager::filter) before the completer is asked again, instead
+    /// of inserting the selected suggestion.
+    pub fn set_filter_while_typing(&mut self, filter_while_typing: bool) {
+        self.filter_while_typing = filter_while_typing;
+    }
+
+    pub fn keep_selection(&self) -> bool {
+        self.keep_selection
+    }
+
+    /// Sets whether the selected suggestion stays selected when the suggestions are
+    /// updated, wherever it moves in the list, which is the default. Suggestions are
+    /// matched by text and id. Otherwise the selection stays at the same position.
+    pub fn set_keep_selection(&mut self, keep_selection: bool) {
+        self.keep_selection = keep_selection;
+    }
+
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// Sets whether [filter](CompletionManager::filter) ignores case, which is the
+    /// default.
+    pub fn set_ignore_case(&mut self, ignore_case: bool) {
+        self.ignore_case = ignore_case;
+    }
+
+    pub fn column_layout(&self) -> ColumnLayout {
+        self.column_layout
+    }
+
+    /// Sets when the columns of the menu are left out on narrow terminals.
+    pub fn set_column_layout(&mut self, column_layout: ColumnLayout) {
+        self.column_layout = column_layout;
+    }
+
+    pub fn trigger(&self) -> CompletionTrigger {
+        self.trigger
+    }
+
+    /// Sets whether the suggestions open as you type or only on Tab, e.g. for a slow
+    /// completer.
+    pub fn set_trigger(&mut self, trigger: CompletionTrigger) {
+        self.trigger = trigger;
+    }
+
+    pub fn min_prefix_len(&self) -> usize {
+        self.min_prefix_len
+    }
+
+    /// Sets how many chars the word before the cursor needs before the suggestions open
+    /// while typing. Tab opens them for shorter words too.
+    pub fn set_min_prefix_len(&mut self, min_prefix_len: usize) {
+        self.min_prefix_len = min_prefix_len;
+    }
+
+    pub fn frecency(&self) -> Option<&Frecency> {
+        self.frecency.as_ref()
+    }
+
+    pub fn frecency_mut(&mut self) -> Option<&mut Frecency> {
+        self.frecency.as_mut()
+    }
+
+    /// Ranks the suggestions of the completer with `frecency`, before the selection is
+    /// restored so that it stays on the same suggestion.
+    pub fn set_frecency(&mut self, frecency: Option<Frecency>) {
+        self.frecency = frecency;
+    }
+
+    /// Returns whether the input changing to `document` updates the suggestions without
+    /// Tab being pressed: when they are already shown, or when typing opens them.
+    pub fn triggered_by(&self, document: &Document) -> bool {
+        if !self.tmp.is_empty() || self.loading {
+            return true;
+        }
+        if self.dismissed(document) {
+            return false;
+        }
+        match self.trigger {
+            CompletionTrigger::WhileTyping => document.cursor_position().saturating_sub(self.word_start(document)) >= self.min_prefix_len,
+            CompletionTrigger::Tab => false,
+        }
+    }
+
+    /// Closes the suggestions and keeps typing from opening them again until a new word
+    /// is started or they are updated otherwise, e.g. by Tab.
+    pub fn dismiss(&mut self, document: &Document) {
+        self.reset();
+        self.dismissed_at = Some(self.word_start(document));
+    }
+
+    /// Returns true if the suggestions were [dismissed](CompletionManager::dismiss) in
+    /// the word before the cursor of `document`.
+    pub fn dismissed(&self, document: &Document) -> bool {
+        self.dismissed_at == Some(self.word_start(document))
+    }
+
+    fn word_start(&self, document: &Document) -> usize {
+        document.find_start_of_previous_word_until_separator(self.word_separator)
+    }
+
+    /// Keeps the suggestions starting with `word`, ignoring case unless
+    /// [set_ignore_case](CompletionManager::set_ignore_case) says otherwise, without
+    /// asking the completer. The selected suggestion stays selected if it is kept.
+    pub fn filter(&mut self, word: &str) {
+        let selected = self.get_selected_suggestion().cloned();
+        if self.ignore_case {
+            let word = word.to_lowercase();
+            self.tmp.retain(|s| s.text.to_lowercase().starts_with(&word));
+        } else {
+            self.tmp.retain(|s| s.text.starts_with(word));
+        }
+        self.select(selected);
+    }
+
+    /// Returns whether there is a suggestion at `index` that isn't
+    /// [disabled](Suggestion::disable).
+    fn selectable(&self, index: usize) -> bool {
+        self.tmp.get(index).is_some_and(|s| !s.is_disabled())
+    }
+
+    /// Returns the selectable suggestion nearest to `index`, looking down from it first if
+    /// `down`, up otherwise.
+    fn selectable_near(&self, index: usize, down: bool) -> Option<usize> {
+        let below = (index..self.tmp.len()).find(|&i| self.selectable(i));
+        let above = (0..=index).rev().find(|&i| self.selectable(i));
+        if down { below.or(above) } else { above.or(below) }
+    }
+
+    /// Selects `suggestion` again after the suggestions changed, or clears the selection
+    /// if it is gone. See [set_keep_selection](CompletionManager::set_keep_selection).
+    fn select(&mut self, suggestion: Option<Suggestion>) {
+        let position = if self.keep_selection {
+            suggestion.and_then(|s| self.tmp.iter().position(|t| t.text == s.text && t.id == s.id))
+        } else {
+            self.selected
+        };
+        self.set_selected(position);
     }
 
-    fn update(&mut self) {
-        let max = self.max.min(self.tmp.len());
+    pub fn update_suggestions(&mut self, document: &Document)
+    where
+        C: Completer,
+    {
+        self.update_suggestions_with_context(document, &());
+    }
 
-        if self.selected > -1 && self.selected as usize >= self.tmp.len() {
-            self.reset()
-        } else if self.selected < -1 {
-            self.selected = self.tmp.len() as i32 - 1;
-            self.vertical_scroll = (self.tmp.len() - max) as isize;
+    /// Is the same as [update_suggestions](CompletionManager::update_suggestions), but
+    /// passes `ctx` to the completer.
+    pub fn update_suggestions_with_context<Ctx>(&mut self, document: &Document, ctx: &Ctx)
+    where
+        C: ContextCompleter<Ctx>,
+    {
+        let selected = self.get_selected_suggestion().cloned();
+        self.dismissed_at = None;
+        self.tmp = self.completer.complete_with_context(document, ctx);
+        if let Some(frecency) = &self.frecency {
+            frecency.rank(&mut self.tmp);
         }
+        self.loading = self.completer.pending();
+        self.select(selected);
+    }
+
+    /// Replaces the suggestions with `suggestions` instead of asking the completer, e.g.
+    /// for suggestions that don't come from it. The selected suggestion stays selected if
+    /// it is still there.
+    pub fn set_suggestions(&mut self, suggestions: Vec<Suggestion>) {
+        let selected = self.get_selected_suggestion().cloned();
+        self.dismissed_at = None;
+        self.tmp = suggestions;
+        self.loading = false;
+        self.select(selected);
+    }
+
+    /// Scrolls the least needed to show the selected suggestion and the
+    /// [scroll_off](CompletionManager::set_scroll_off) ones around it.
+    fn scroll_to_selected(&mut self) {
+        let height = self.window_height();
+        let (Some(selected), true) = (self.selected, height > 0) else {
+            self.vertical_scroll = 0;
+            return;
+        };
+        let off = self.scroll_off.min((height - 1) / 2);
+        if selected.saturating_sub(off) < self.vertical_scroll {
+            self.vertical_scroll = selected.saturating_sub(off);
+        } else if selected + off >= self.vertical_scroll + height {
+            self.vertical_scroll = selected + off + 1 - height;
+        }
+        self.vertical_scroll = self.vertical_scroll.min(self.tmp.len().saturating_sub(height));
+    }
+
+    /// Clears the selection and the suggestions.
+    pub fn reset(&mut self) {
+        self.reset_selection();
+        self.tmp.clear();
+        self.loading = false;
     }
 
-    fn reset(&mut self) {
-        self.selected = -1;
+    fn reset_selection(&mut self) {
+        self.selected = None;
         self.vertical_scroll = 0;
-        self.update_suggestions("");
     }
 
-    fn previous(&mut self) {
-        if self.vertical_scroll == self.selected as isize && self.selected > 0 {
-            self.vertical_scroll -= 1;
+    /// Selects the previous suggestion, or the last one if none is selected. Disabled
+    /// suggestions are skipped.
+    pub fn previous(&mut self) {
+        let before = self.selected.unwrap_or(self.tmp.len());
+        self.selected = match (0..before).rev().find(|&i| self.selectable(i)) {
+            Some(previous) => Some(previous),
+            None if self.wrap_around => None,
+            None => self.selected,
+        };
+        self.scroll_to_selected();
+    }
+
+    /// Selects the next suggestion, or the first one if none is selected. Disabled
+    /// suggestions are skipped.
+    pub fn next(&mut self) {
+        let after = self.selected.map_or(0, |selected| selected + 1);
+        self.selected = match (after..self.tmp.len()).find(|&i| self.selectable(i)) {
+            Some(next) => Some(next),
+            None if self.wrap_around => None,
+            None => self.selected,
+        };
+        self.scroll_to_selected();
+    }
+
+    /// Moves the selection up by a window of suggestions, stopping at the first one.
+    /// Selects the top of the last window if none is selected.
+    pub fn page_up(&mut self) {
+        let len = self.tmp.len();
+        if len == 0 {
+            return;
         }
-        self.selected -= 1;
-        self.update();
+        let page = self.window_height().max(1);
+        let selected = self.selected.unwrap_or_else(|| {
+            self.vertical_scroll = len.saturating_sub(page);
+            len
+        });
+        self.selected = self.selectable_near(selected.saturating_sub(page), false);
+        self.scroll_to_selected();
     }
 
-    fn next(&mut self) {
-        if self.vertical_scroll + self.max as isize - 1 == self.selected as isize {
-            self.vertical_scroll += 1;
+    /// Moves the selection down by a window of suggestions, stopping at the last one.
+    /// Selects the bottom of the first window if none is selected.
+    pub fn page_down(&mut self) {
+        let len = self.tmp.len();
+        if len == 0 {
+            return;
         }
-        self.selected += 1;
-        self.update();
+        let page = self.window_height().max(1);
+        self.selected = self.selectable_near(self.selected.map_or(page - 1, |selected| selected + page).min(len - 1), true);
+        self.scroll_to_selected();
     }
 
-    fn completing(&self) -> bool {
-        self.selected != -1
+    pub fn completing(&self) -> bool {
+        self.selected.is_some()
     }
 }
 
 fn delete_break_line_characters(s: &str) -> String {
-    let s = s.replace("\n", "");
-    let s = s.replace("\r", "");
-    s
+    let s = s.replace('\n', "");
+    s.replace('\r', "")
 }
 
 fn format_texts(o: &[&str], max: usize, prefix: &str, suffix: &str) -> (Vec<String>, usize) {
     let mut n = vec!["".to_string(); o.len()];
 
-    let len_prefix = prefix.len();
-    let len_suffix = suffix.len();
-    let len_shorten = SHORTEN_SUFFIX.len();
+    let len_prefix = display_width(prefix);
+    let len_suffix = display_width(suffix);
+    let len_shorten = display_width(SHORTEN_SUFFIX);
     let min = len_prefix + len_suffix + len_shorten;
 
-    let mut width = o.iter()
-        .map(|s| delete_break_line_characters(s).len())
+    let width = o.iter()
+        .map(|s| display_width(&delete_break_line_characters(s)))
         .max()
         .unwrap_or(0);
 
@@ -134,55 +631,108 @@ fn format_texts(o: &[&str], max: usize, prefix: &str, suffix: &str) -> (Vec<Stri
         return (n, width);
     }
 
-    if min >= max {
+    if len_prefix + width + len_suffix > max && min >= max {
         return (n, 0);
     }
 
     let width = if len_prefix + width + len_suffix > max {
-        max - len_prefix - len_suffix
+        max.saturating_sub(len_prefix + len_suffix)
     } else {
         width
     };
 
     for (idx, &i) in o.iter().enumerate() {
-        let x = i.len();
+        let x = display_width(i);
         if x <= width {
             let spaces = " ".repeat(width - x);
-            n[idx] = (prefix.to_string() + i + &spaces + suffix);
-        } else if x > width {
-            let mut i = i.clone();
-            let mut i = i.to_string();
-            i.truncate(width - SHORTEN_SUFFIX.len());
-            let mut x = i + SHORTEN_SUFFIX;
-            if x.len() < width {
-                x = format!("{:count$}", x, count = width - x.len());
+            n[idx] = prefix.to_string() + i + &spaces + suffix;
+        } else {
+            let mut x = truncate_to_width(i, width.saturating_sub(len_shorten)) + SHORTEN_SUFFIX;
+            // a wide character that didn't fit leaves a gap to fill
+            let x_width = display_width(&x);
+            if x_width < width {
+                x += &" ".repeat(width - x_width);
             }
-            n[idx] = (prefix.to_string() + &x + suffix);
+            n[idx] = prefix.to_string() + &x + suffix;
         }
     }
 
-    return (n, len_prefix + width + len_suffix);
+    (n, len_prefix + width + len_suffix)
+}
+
+/// Returns the description column of a formatted suggestion with `description` scrolled
+/// by `step` chars in it, or None if `description` fits in the column and doesn't need to
+/// scroll. `formatted` is the description as [format_suggestions] padded and truncated it.
+pub(crate) fn marquee(description: &str, formatted: &str, step: usize) -> Option<String> {
+    let description = delete_break_line_characters(description);
+    let padding = display_width(RIGHT_PREFIX) + display_width(RIGHT_SUFFIX);
+    let width = display_width(formatted).checked_sub(padding).filter(|&w| w > 0)?;
+    if display_width(&description) <= width {
+        return None;
+    }
+    let looped = description.chars().chain(MARQUEE_GAP.chars()).collect::<Vec<_>>();
+    let start = step.saturating_sub(MARQUEE_PAUSE) % looped.len();
+    let rotated = looped[start..].iter().chain(&looped).collect::<String>();
+    let mut window = truncate_to_width(&rotated, width);
+    // a wide character that didn't fit leaves a gap to fill
+    window += &" ".repeat(width - display_width(&window));
+    Some(format!("{}{}{}", RIGHT_PREFIX, window, RIGHT_SUFFIX))
 }
 
+/// Cuts the texts short to `max` without padding or `...`, for a column too narrow for
+/// [format_texts]. Returns them with the width of the column.
+fn cut_texts(o: &[&str], max: usize) -> (Vec<String>, usize) {
+    let width = o.iter()
+        .map(|s| display_width(&delete_break_line_characters(s)))
+        .max()
+        .unwrap_or(0)
+        .min(max);
+    let n = o.iter()
+        .map(|s| {
+            let x = truncate_to_width(&delete_break_line_characters(s), width);
+            // a wide character that didn't fit leaves a gap to fill
+            let x_width = display_width(&x);
+            x + &" ".repeat(width - x_width)
+        })
+        .collect();
+    (n, width)
+}
+
+/// Returns the widest of `o`, without line breaks.
+fn max_width(o: &[&str]) -> usize {
+    o.iter().map(|s| display_width(&delete_break_line_characters(s))).max().unwrap_or(0)
+}
+
+/// Pads and truncates the texts, descriptions and hints of `suggestions` into the columns
+/// of the completion menu, at most `max` wide. Returns them with the width of the menu.
+///
+/// Columns too narrow to be of use are left out as `layout` says: first the description
+/// column, then the padding and `...` of the texts. The hints are never cut: their
+/// column comes out of the room left beside the texts, or is left out if it doesn't fit.
 // TODO: convert this to return Result<(Vec<Suggestion>, usize)>. Use eyre?
-fn format_suggestions(suggestions: &[Suggestion], max: usize) -> (Vec<Suggestion>, usize) {
+pub fn format_suggestions(suggestions: &[Suggestion], max: usize, layout: ColumnLayout) -> (Vec<Suggestion>, usize) {
     let left = suggestions.iter()
         .map(|s| s.text.as_str())
         .collect::<Vec<&str>>();
     let right = suggestions.iter()
-        .map(|s| s.description.as_str())
+        .map(|s| s.disabled_reason().unwrap_or(&s.description))
         .collect::<Vec<&str>>();
 
-    let (left, left_width) = format_texts(
-        &left,
-        max,
-        LEFT_PREFIX,
-        LEFT_SUFFIX,
-    );
+    let left_padding = display_width(LEFT_PREFIX) + display_width(LEFT_SUFFIX);
+    let text_width = max_width(&left).min(layout.min_text_width + display_width(SHORTEN_SUFFIX));
+    let (left, left_width) = if left_padding + text_width <= max {
+        format_texts(&left, max, LEFT_PREFIX, LEFT_SUFFIX)
+    } else {
+        cut_texts(&left, max)
+    };
     if left_width == 0 {
         return (vec![], 0);
     }
-    let (right, right_width) = if max > left_width {
+    let (hints, hint_width) = format_hints(suggestions, max.saturating_sub(left_width));
+    let max = max - hint_width;
+    let right_padding = display_width(RIGHT_PREFIX) + display_width(RIGHT_SUFFIX);
+    let description_width = max_width(&right).min(layout.min_description_width);
+    let (right, right_width) = if left_width + right_padding + description_width <= max {
         format_texts(
             &right,
             max - left_width,
@@ -195,16 +745,36 @@ fn format_suggestions(suggestions: &[Suggestion], max: usize) -> (Vec<Suggestion
 
     let new_suggestions = left.into_iter()
         .zip(right)
-        .map(|(text, desc)| Suggestion::new(text, desc))
+        .zip(hints)
+        .map(|((text, desc), hint)| Suggestion { hint, ..Suggestion::new(text, desc) })
         .collect::<Vec<Suggestion>>();
 
-    return (new_suggestions, left_width + right_width);
+    (new_suggestions, left_width + right_width + hint_width)
+}
+
+/// Right-aligns the [hints](Suggestion::with_hint) of `suggestions` into a column at most
+/// `max` wide. Returns them with the width of the column, which is 0 if no suggestion has
+/// a hint or they don't fit.
+fn format_hints(suggestions: &[Suggestion], max: usize) -> (Vec<Option<String>>, usize) {
+    let hints = suggestions.iter()
+        .map(|s| delete_break_line_characters(s.hint().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    let width = hints.iter().map(|h| display_width(h)).max().unwrap_or(0);
+    let column = display_width(HINT_PREFIX) + width + display_width(HINT_SUFFIX);
+    if width == 0 || column > max {
+        return (vec![None; suggestions.len()], 0);
+    }
+    let hints = hints.into_iter()
+        .map(|h| Some(format!("{}{}{}{}", HINT_PREFIX, " ".repeat(width - display_width(&h)), h, HINT_SUFFIX)))
+        .collect();
+    (hints, column)
 }
 
 #[cfg(test)]
 mod tests {
     use std::ops::Add;
     use super::*;
+    use crate::tests::TestCompleter;
 
     fn compare_format_suggestions(
         suggestions: Vec<Suggestion>,
@@ -227,6 +797,19 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_marquee() {
+        let description = "Show the commit log";
+        assert_eq!(None, marquee(description, " Show the commit log ", 3));
+        assert_eq!(Some(" Show the  ".to_string()), marquee(description, " Show t... ", 0));
+        assert_eq!(Some(" Show the  ".to_string()), marquee(description, " Show t... ", MARQUEE_PAUSE));
+        assert_eq!(Some(" how the c ".to_string()), marquee(description, " Show t... ", MARQUEE_PAUSE + 1));
+        // comes around after the gap
+        let step = MARQUEE_PAUSE + description.len() - 2;
+        assert_eq!(Some(" og   Show ".to_string()), marquee(description, " Show t... ", step));
+        assert_eq!(Some(" 日本  ".to_string()), marquee("日本語の説明", " 日... ", MARQUEE_PAUSE));
+    }
+
     #[test]
     fn test_format_suggestions_title() {
         let input = vec![
@@ -241,7 +824,7 @@ mod tests {
         ];
         let max = 100;
         let ex_wdith = 6;
-        let (suggestions, width) = format_suggestions(&input, max);
+        let (suggestions, width) = format_suggestions(&input, max, ColumnLayout::default());
         compare_format_suggestions(suggestions, width, expected, ex_wdith);
     }
 
@@ -259,7 +842,7 @@ mod tests {
         ];
         let max = 100;
         let ex_wdith = " apple   ".to_string().add(" This is apple.   ").len();
-        let (suggestions, width) = format_suggestions(&input, max);
+        let (suggestions, width) = format_suggestions(&input, max, ColumnLayout::default());
         compare_format_suggestions(suggestions, width, expected, ex_wdith);
     }
 
@@ -277,7 +860,7 @@ mod tests {
         ];
         let max = 8;
         let ex_wdith = 8;
-        let (suggestions, width) = format_suggestions(&input, max);
+        let (suggestions, width) = format_suggestions(&input, max, ColumnLayout::default());
         compare_format_suggestions(suggestions, width, expected, ex_wdith);
     }
 
@@ -288,11 +871,84 @@ mod tests {
             Suggestion::with_title("This is banana.".to_string()),
             Suggestion::with_title("This is coconut.".to_string()),
         ];
-        let expected = Vec::new();
+        let expected = vec![
+            Suggestion::with_title("Thi".to_string()),
+            Suggestion::with_title("Thi".to_string()),
+            Suggestion::with_title("Thi".to_string()),
+        ];
         let max = 3;
-        let ex_wdith = 0;
-        let (suggestions, width) = format_suggestions(&input, max);
+        let ex_wdith = 3;
+        let (suggestions, width) = format_suggestions(&input, max, ColumnLayout::default());
         compare_format_suggestions(suggestions, width, expected, ex_wdith);
+
+        let (suggestions, width) = format_suggestions(&input, 0, ColumnLayout::default());
+        compare_format_suggestions(suggestions, width, Vec::new(), 0);
+    }
+
+    #[test]
+    fn test_format_suggestions_column_layout() {
+        let input = vec![
+            Suggestion::new("apple".to_string(), "This is apple.".to_string()),
+            Suggestion::new("banana".to_string(), "Yellow".to_string()),
+        ];
+        // only 3 columns would be left for the descriptions, so they are left out
+        let expected = vec![
+            Suggestion::new(" apple  ".to_string(), "".to_string()),
+            Suggestion::new(" banana ".to_string(), "".to_string()),
+        ];
+        let (suggestions, width) = format_suggestions(&input, 13, ColumnLayout::default());
+        compare_format_suggestions(suggestions, width, expected, 8);
+
+        let layout = ColumnLayout { min_description_width: 3, min_text_width: 3 };
+        let expected = vec![
+            Suggestion::new(" apple  ".to_string(), " T... ".to_string()),
+            Suggestion::new(" banana ".to_string(), " Y... ".to_string()),
+        ];
+        let (suggestions, width) = format_suggestions(&input, 14, layout);
+        compare_format_suggestions(suggestions, width, expected, 14);
+
+        // short descriptions are shown as long as they fit whole
+        let input = vec![Suggestion::new("ls".to_string(), "list".to_string())];
+        let expected = vec![Suggestion::new(" ls ".to_string(), " list ".to_string())];
+        let (suggestions, width) = format_suggestions(&input, 10, ColumnLayout::default());
+        compare_format_suggestions(suggestions, width, expected, 10);
+
+        // the texts are cut short without padding rather than showing a lone "..."
+        let input = vec![Suggestion::new("coconut".to_string(), "This is coconut.".to_string())];
+        let expected = vec![Suggestion::new("cocon".to_string(), "".to_string())];
+        let (suggestions, width) = format_suggestions(&input, 5, ColumnLayout::default());
+        compare_format_suggestions(suggestions, width, expected, 5);
+        let layout = ColumnLayout { min_description_width: 10, min_text_width: 1 };
+        let expected = vec![Suggestion::new(" c... ".to_string(), "".to_string())];
+        let (suggestions, width) = format_suggestions(&input, 6, layout);
+        compare_format_suggestions(suggestions, width, expected, 6);
+    }
+
+    #[test]
+    fn test_format_suggestions_with_hints() {
+        let input = vec![
+            Suggestion::new("kubectl".to_string(), "Kubernetes CLI".to_string()).with_hint("k"),
+            Suggestion::new("terraform".to_string(), "Provisioning".to_string()).with_hint("tf"),
+            Suggestion::new("ls".to_string(), "List files".to_string()),
+        ];
+        let (suggestions, width) = format_suggestions(&input, 40, ColumnLayout::default());
+        assert_eq!(11 + 16 + 4, width);
+        let rows = suggestions.iter()
+            .map(|s| format!("{}{}{}", s.text(), s.description(), s.hint().unwrap_or_default()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![
+            " kubectl    Kubernetes CLI   k ",
+            " terraform  Provisioning    tf ",
+            " ls         List files         ",
+        ], rows);
+
+        // the hints keep their width, the description gives way
+        let (suggestions, width) = format_suggestions(&input, 30, ColumnLayout::default());
+        assert_eq!(30, width);
+        assert_eq!((" Kubernetes... ", Some("  k ")), (suggestions[0].description(), suggestions[0].hint()));
+        // and a column too narrow for them leaves them out
+        let (suggestions, width) = format_suggestions(&input, 12, ColumnLayout::default());
+        assert_eq!((11, None), (width, suggestions[1].hint()));
     }
 
     #[test]
@@ -316,7 +972,7 @@ mod tests {
         let max = 50;
         let ex_wdith = expected.last().unwrap().text.len() +
             expected.last().unwrap().description.len();
-        let (suggestions, width) = format_suggestions(&input, max);
+        let (suggestions, width) = format_suggestions(&input, max, ColumnLayout::default());
         compare_format_suggestions(suggestions, width, expected, ex_wdith);
     }
 
@@ -341,7 +997,7 @@ mod tests {
         let max = 500;
         let ex_wdith = expected.last().unwrap().text.len() +
             expected.last().unwrap().description.len();
-        let (suggestions, width) = format_suggestions(&input, max);
+        let (suggestions, width) = format_suggestions(&input, max, ColumnLayout::default());
         compare_format_suggestions(suggestions, width, expected, ex_wdith);
     }
 
@@ -399,6 +1055,24 @@ mod tests {
         compare_format_text(actual, width, expected, ex_width);
     }
 
+    #[test]
+    fn test_format_text_wide_and_ansi() {
+        let input = vec!["日本語", "\x1b[31mred\x1b[0m"];
+        let expected = vec![" 日本語 ", " \x1b[31mred\x1b[0m    "];
+        let max = 100;
+        let ex_width = 8;
+        let (actual, width) = format_texts(&input, max, " ", " ");
+        compare_format_text(actual, width, expected, ex_width);
+
+        // "日本" is 4 columns wide, so only "日" fits before the "..." and a space fills the gap
+        let input = vec!["日本語です"];
+        let expected = vec![" 日...  "];
+        let max = 8;
+        let ex_width = 8;
+        let (actual, width) = format_texts(&input, max, " ", " ");
+        compare_format_text(actual, width, expected, ex_width);
+    }
+
     #[test]
     fn test_format_text_shorten() {
         let input = vec!["apple", "banana", "coconut"];
@@ -408,4 +1082,256 @@ mod tests {
         let (actual, width) = format_texts(&input, max, " ", " ");
         compare_format_text(actual, width, expected, ex_width);
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_suggestion_serde() {
+        let s = Suggestion::new("users".to_string(), "Store the user information".to_string());
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(r#"{"text":"users","description":"Store the user information"}"#, json);
+        assert_eq!(s, serde_json::from_str(&json).unwrap());
+
+        let s = Suggestion::with_title("orders".to_string()).with_id("table:2");
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(r#"{"text":"orders","description":"","id":"table:2"}"#, json);
+        assert_eq!(s, serde_json::from_str(&json).unwrap());
+    }
+
+    fn manager(len: usize, max: usize) -> CompletionManager<'static, TestCompleter> {
+        let mut m = CompletionManager::new(TestCompleter::new(0..len), max);
+        m.update_suggestions(&Document::new());
+        m
+    }
+
+    /// Returns (selected, vertical_scroll) after each call of `step`.
+    fn walk<'a, C>(
+        m: &mut CompletionManager<'a, C>,
+        times: usize,
+        step: fn(&mut CompletionManager<'a, C>),
+    ) -> Vec<(Option<usize>, usize)> {
+        (0..times).map(|_| {
+            step(m);
+            (m.selected(), m.vertical_scroll())
+        }).collect()
+    }
+
+    #[test]
+    fn test_next_and_previous_wrap_around() {
+        let mut m = manager(5, 3);
+        assert_eq!(
+            vec![(Some(0), 0), (Some(1), 0), (Some(2), 0), (Some(3), 1), (Some(4), 2), (None, 0), (Some(0), 0)],
+            walk(&mut m, 7, CompletionManager::next),
+        );
+        assert_eq!(
+            vec![(None, 0), (Some(4), 2), (Some(3), 2), (Some(2), 2), (Some(1), 1), (Some(0), 0), (None, 0)],
+            walk(&mut m, 7, CompletionManager::previous),
+        );
+
+        let mut m = manager(0, 3);
+        m.next();
+        m.previous();
+        assert!(!m.completing());
+    }
+
+    #[test]
+    fn test_next_and_previous_without_wrap_around() {
+        let mut m = manager(4, 3);
+        m.set_wrap_around(false);
+        assert_eq!(
+            vec![(Some(0), 0), (Some(1), 0), (Some(2), 0), (Some(3), 1), (Some(3), 1)],
+            walk(&mut m, 5, CompletionManager::next),
+        );
+        assert_eq!(
+            vec![(Some(2), 1), (Some(1), 1), (Some(0), 0), (Some(0), 0)],
+            walk(&mut m, 4, CompletionManager::previous),
+        );
+    }
+
+    #[test]
+    fn test_scroll_off() {
+        let mut m = manager(10, 5);
+        m.set_scroll_off(1);
+        assert_eq!(
+            vec![(Some(0), 0), (Some(1), 0), (Some(2), 0), (Some(3), 0), (Some(4), 1), (Some(5), 2)],
+            walk(&mut m, 6, CompletionManager::next),
+        );
+        assert_eq!(
+            vec![(Some(4), 2), (Some(3), 2), (Some(2), 1), (Some(1), 0), (Some(0), 0)],
+            walk(&mut m, 5, CompletionManager::previous),
+        );
+        // the window only scrolls as far as the list goes
+        assert_eq!(
+            vec![(Some(9), 5), (Some(8), 5), (Some(7), 5), (Some(6), 5), (Some(5), 4)],
+            walk(&mut m, 6, CompletionManager::previous)[1..].to_vec(),
+        );
+
+        // a margin larger than half the window keeps the selection in the middle
+        let mut m = manager(10, 3);
+        m.set_scroll_off(5);
+        assert_eq!(
+            vec![(Some(0), 0), (Some(1), 0), (Some(2), 1), (Some(3), 2)],
+            walk(&mut m, 4, CompletionManager::next),
+        );
+    }
+
+    #[test]
+    fn test_page_up_and_page_down() {
+        let mut m = manager(10, 4);
+        assert_eq!(
+            vec![(Some(3), 0), (Some(7), 4), (Some(9), 6), (Some(9), 6)],
+            walk(&mut m, 4, CompletionManager::page_down),
+        );
+        assert_eq!(
+            vec![(Some(5), 5), (Some(1), 1), (Some(0), 0)],
+            walk(&mut m, 3, CompletionManager::page_up),
+        );
+
+        let mut m = manager(10, 4);
+        m.page_up();
+        assert_eq!((Some(6), 6), (m.selected(), m.vertical_scroll()));
+
+        let mut m = manager(0, 4);
+        m.page_down();
+        assert!(!m.completing());
+    }
+
+    #[test]
+    fn test_update_keeps_selection_visible() {
+        let mut m = manager(10, 3);
+        walk(&mut m, 8, CompletionManager::next);
+        assert_eq!((Some(7), 5), (m.selected(), m.vertical_scroll()));
+        m.completer = TestCompleter::new(0..8);
+        m.update_suggestions(&Document::new());
+        assert_eq!((Some(7), 5), (m.selected(), m.vertical_scroll()));
+        m.completer = TestCompleter::new(0..5);
+        m.update_suggestions(&Document::new());
+        assert_eq!((None, 0), (m.selected(), m.vertical_scroll()));
+    }
+
+    #[test]
+    fn test_available_height() {
+        let mut m = manager(10, 5);
+        assert_eq!(5, m.window_height());
+        walk(&mut m, 5, CompletionManager::next);
+        assert_eq!((Some(4), 0), (m.selected(), m.vertical_scroll()));
+        m.set_available_height(2);
+        assert_eq!(2, m.window_height());
+        assert_eq!((Some(4), 3), (m.selected(), m.vertical_scroll()));
+        m.page_down();
+        assert_eq!((Some(6), 5), (m.selected(), m.vertical_scroll()));
+        m.set_available_height(0);
+        assert_eq!(0, m.window_height());
+        assert_eq!(0, m.vertical_scroll());
+        assert_eq!(2, manager(2, 5).window_height());
+    }
+
+    #[test]
+    fn test_filter_keeps_selection() {
+        let mut m = CompletionManager::new(TestCompleter::default(), 3);
+        m.tmp = ["Apple", "apricot", "banana", "avocado"].iter().map(|s| Suggestion::with_title(s.to_string())).collect();
+        m.next();
+        m.next();
+        m.filter("ap");
+        assert_eq!(vec!["Apple", "apricot"], m.tmp.iter().map(|s| s.text()).collect::<Vec<_>>());
+        assert_eq!("apricot", m.get_selected_suggestion().unwrap().text());
+        m.filter("apple");
+        assert_eq!(1, m.tmp.len());
+        assert!(!m.completing());
+    }
+
+    fn flags() -> TestCompleter {
+        TestCompleter::new(["--all", "--namespace", "--output", "--watch"])
+    }
+
+    #[test]
+    fn test_disabled_suggestions_are_skipped() {
+        let mut m = CompletionManager::new(TestCompleter::default(), 2);
+        m.tmp = ["--all", "--none", "--some", "--quiet"].iter()
+            .map(|s| Suggestion::with_title(s.to_string()))
+            .enumerate()
+            .map(|(i, s)| if i % 2 == 1 { s.disable("conflicts with --all") } else { s })
+            .collect();
+        assert_eq!(
+            vec![(Some(0), 0), (Some(2), 1), (None, 0), (Some(0), 0)],
+            walk(&mut m, 4, CompletionManager::next),
+        );
+        assert_eq!(
+            vec![(None, 0), (Some(2), 1), (Some(0), 0)],
+            walk(&mut m, 3, CompletionManager::previous),
+        );
+        m.page_down();
+        assert_eq!(Some(2), m.selected());
+        m.set_selected(Some(3));
+        assert!(!m.completing());
+
+        let (formatted, _) = format_suggestions(&m.tmp, 40, ColumnLayout::default());
+        assert_eq!(" conflicts with --all ", formatted[1].description());
+    }
+
+    #[test]
+    fn test_set_selected() {
+        let mut m = manager(10, 3);
+        m.set_selected(Some(8));
+        assert_eq!((Some(8), 6), (m.selected(), m.vertical_scroll()));
+        assert_eq!("8", m.get_selected_suggestion().unwrap().text());
+        m.set_selected(Some(10));
+        assert_eq!((None, 0), (m.selected(), m.vertical_scroll()));
+        m.set_selected(Some(2));
+        m.set_selected(None);
+        assert!(!m.completing());
+    }
+
+    #[test]
+    fn test_keep_selection() {
+        // the flags left once `t` is typed, described by the input
+        let typed_t = || TestCompleter::from(vec![
+            Suggestion::new("--output".to_string(), "t".to_string()),
+            Suggestion::new("--watch".to_string(), "t".to_string()),
+        ]);
+        let mut m = CompletionManager::new(flags(), 4);
+        m.update_suggestions(&Document::new());
+        for _ in 0..4 {
+            m.next();
+        }
+        assert_eq!("--watch", m.get_selected_suggestion().unwrap().text());
+        // matched by text, even though the description changed
+        m.completer = typed_t();
+        m.update_suggestions(&Document::new());
+        assert_eq!(Some(1), m.selected());
+        assert_eq!("--watch", m.get_selected_suggestion().unwrap().text());
+        m.completer = TestCompleter::new(["--all"]);
+        m.update_suggestions(&Document::new());
+        assert!(!m.completing());
+
+        m.set_keep_selection(false);
+        m.completer = flags();
+        m.update_suggestions(&Document::new());
+        m.next();
+        m.next();
+        m.completer = typed_t();
+        m.update_suggestions(&Document::new());
+        assert_eq!(Some(1), m.selected());
+        assert_eq!("--watch", m.get_selected_suggestion().unwrap().text());
+        m.completer = TestCompleter::new(["--all"]);
+        m.update_suggestions(&Document::new());
+        assert!(!m.completing());
+    }
+
+    #[test]
+    fn test_frecency_keeps_selection() {
+        let mut frecency = Frecency::new();
+        frecency.record(&Suggestion::with_title("--watch".to_string()));
+        let mut m = CompletionManager::new(flags(), 4);
+        m.set_frecency(Some(frecency));
+        m.update_suggestions(&Document::new());
+        assert_eq!("--watch", m.get_suggestions()[0].text());
+        m.next();
+        m.next();
+        assert_eq!("--all", m.get_selected_suggestion().unwrap().text());
+        // ranked before the selection is restored
+        m.completer = TestCompleter::new(["--all", "--namespace", "--watch"]);
+        m.update_suggestions(&Document::new());
+        assert_eq!(Some(1), m.selected());
+        assert_eq!("--all", m.get_selected_suggestion().unwrap().text());
+    }
+}
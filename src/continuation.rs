@@ -0,0 +1,172 @@
+/// Tells whether shell-like input is complete, for `Prompt::with_input_complete`: it
+/// isn't while a quote or a bracket is open, or after a trailing escape character.
+///
+/// ```
+/// use rusty_prompt::Continuation;
+///
+/// let continuation = Continuation::new();
+/// assert!(!continuation.is_input_complete("echo \"hello"));
+/// assert!(!continuation.is_input_complete("if (a"));
+/// assert!(!continuation.is_input_complete("ls \\"));
+/// assert!(continuation.is_input_complete("echo 'it''s' # (done"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Continuation {
+    /// Quote characters, each with whether the escape character works between them.
+    quotes: Vec<(char, bool)>,
+    /// Opening brackets, each with its closing one.
+    brackets: Vec<(char, char)>,
+    escape: Option<char>,
+    /// Starts a comment running to the end of the line, at the start of a word.
+    comment: Option<char>,
+}
+
+impl Default for Continuation {
+    /// The rules of a POSIX shell: `'` quotes literally, `"` and `` ` `` allow escapes,
+    /// the brackets are `()`, `[]` and `{}`, `\` escapes and `#` starts a comment.
+    fn default() -> Self {
+        Self {
+            quotes: vec![('\'', false), ('"', true), ('`', true)],
+            brackets: vec![('(', ')'), ('[', ']'), ('{', '}')],
+            escape: Some('\\'),
+            comment: Some('#'),
+        }
+    }
+}
+
+impl Continuation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the quote characters, each with whether the escape character works between
+    /// them.
+    pub fn with_quotes(mut self, quotes: Vec<(char, bool)>) -> Self {
+        self.quotes = quotes;
+        self
+    }
+
+    /// Sets the pairs of opening and closing brackets.
+    pub fn with_brackets(mut self, brackets: Vec<(char, char)>) -> Self {
+        self.brackets = brackets;
+        self
+    }
+
+    /// Sets the character escaping the next one, None for none.
+    pub fn with_escape(mut self, escape: Option<char>) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Sets the character starting a comment at the start of a word, None for none.
+    pub fn with_comment(mut self, comment: Option<char>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Returns false if another line is needed: a quote or a bracket is open, or the
+    /// input ends with the escape character. A closing bracket not matching the open one
+    /// can't be fixed by more lines, so the input is complete, for the application to
+    /// report the error.
+    pub fn is_input_complete(&self, input: &str) -> bool {
+        let scan = self.scan(input);
+        let mut closing = Vec::new();
+        for (_, c) in scan.brackets {
+            if let Some(close) = self.closing(c) {
+                closing.push(close);
+            } else if closing.pop() != Some(c) {
+                return true;
+            }
+        }
+        !scan.escaped_end && !scan.open_quote && closing.is_empty()
+    }
+
+    /// Returns the bracket closing `c`, None if `c` doesn't open one.
+    pub(crate) fn closing(&self, c: char) -> Option<char> {
+        self.brackets.iter().find(|(open, _)| *open == c).map(|&(_, close)| close)
+    }
+
+    /// Finds the brackets of `input` outside quotes, comments and escapes.
+    pub(crate) fn scan(&self, input: &str) -> Scan {
+        let mut scan = Scan::default();
+        let mut quote: Option<(char, bool)> = None;
+        let mut word_start = true;
+        let mut chars = input.chars().enumerate();
+        while let Some((i, c)) = chars.next() {
+            let escapes = quote.is_none_or(|(_, escapes)| escapes);
+            if escapes && Some(c) == self.escape {
+                match chars.next() {
+                    None => scan.escaped_end = true,
+                    // an escaped line break continues the line
+                    Some((_, '\n')) if chars.clone().next().is_none() => scan.escaped_end = true,
+                    Some(_) => {}
+                }
+                word_start = false;
+                continue;
+            }
+            match quote {
+                Some((q, _)) if c == q => quote = None,
+                Some(_) => {}
+                None if word_start && Some(c) == self.comment => {
+                    // the next line starts a word
+                    chars.by_ref().find(|&(_, c)| c == '\n');
+                    continue;
+                }
+                None => {
+                    if let Some(&q) = self.quotes.iter().find(|(q, _)| *q == c) {
+                        quote = Some(q);
+                    } else if self.brackets.iter().any(|&(open, close)| c == open || c == close) {
+                        scan.brackets.push((i, c));
+                    }
+                }
+            }
+            word_start = c.is_whitespace();
+        }
+        scan.open_quote = quote.is_some();
+        scan
+    }
+}
+
+/// What [Continuation::scan] finds in the input.
+#[derive(Debug, Default)]
+pub(crate) struct Scan {
+    /// The char index of each bracket outside quotes, comments and escapes, with the bracket.
+    pub brackets: Vec<(usize, char)>,
+    pub open_quote: bool,
+    /// The input ends with the escape character, or with an escaped line break.
+    pub escaped_end: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_input_complete() {
+        let c = Continuation::new();
+        assert!(c.is_input_complete(""));
+        assert!(c.is_input_complete("ls -la"));
+        assert!(!c.is_input_complete("echo 'a"));
+        assert!(c.is_input_complete("echo 'a\\'"));
+        assert!(!c.is_input_complete("echo \"a\\\""));
+        assert!(c.is_input_complete("echo \"(\""));
+        assert!(!c.is_input_complete("f() {\n  echo [a"));
+        assert!(c.is_input_complete("f() {\n  echo [a]\n}"));
+        assert!(!c.is_input_complete("ls \\"));
+        assert!(!c.is_input_complete("ls \\\n"));
+        assert!(c.is_input_complete("ls \\\n-a"));
+        assert!(c.is_input_complete("echo a#(b)"));
+        assert!(!c.is_input_complete("echo a#(b"));
+        assert!(c.is_input_complete("echo a # (b"));
+        assert!(!c.is_input_complete("echo ( # )"));
+        assert!(c.is_input_complete("echo # (\n#("));
+        // a wrong closing bracket is left to the application
+        assert!(c.is_input_complete("echo (]"));
+
+        let c = Continuation::new().with_quotes(vec![('"', true)]).with_brackets(vec![('<', '>')]).with_comment(None);
+        assert!(c.is_input_complete("it's"));
+        assert!(c.is_input_complete("<a # >"));
+        assert!(!c.is_input_complete("<a"));
+        assert!(c.is_input_complete("(a"));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::completion::{Completer, Suggestion};
+use crate::document::Document;
+
+/// Returns the number of single character insertions, deletions and substitutions
+/// needed to turn `a` into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    edit_distance(a, b, false)
+}
+
+/// Is like [levenshtein], but also counts swapping two adjacent characters as a single
+/// edit (optimal string alignment), which is the most common typo.
+pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    edit_distance(a, b, true)
+}
+
+fn edit_distance(a: &str, b: &str, transpositions: bool) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // rows i - 2, i - 1 and i of the distance matrix
+    let mut before = vec![0; b.len() + 1];
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if transpositions && i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Returns the candidates within `max_distance` edits of `word`, closest first and
+/// marked as corrections. A candidate also matches if only its start is close, since
+/// `word` may not be fully typed yet.
+pub fn corrections(word: &str, candidates: Vec<Suggestion>, max_distance: usize) -> Vec<Suggestion> {
+    let len = word.chars().count();
+    let mut found = candidates.into_iter()
+        .filter_map(|candidate| {
+            let text = candidate.text();
+            let start = text.char_indices().nth(len).map_or(text, |(i, _)| &text[..i]);
+            let distance = damerau_levenshtein(word, text).min(damerau_levenshtein(word, start));
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect::<Vec<_>>();
+    found.sort_by_key(|(distance, _)| *distance);
+    found.into_iter().map(|(_, s)| s.mark_correction()).collect()
+}
+
+/// Completer offering "did you mean" corrections when its inner completer has no
+/// suggestions, e.g. for `--namepsace`.
+///
+/// The corrections are taken from the suggestions of `candidates`, which should return
+/// every valid word at the cursor without filtering them by the word being typed.
+pub struct DidYouMean<C, U> {
+    completer: C,
+    candidates: U,
+    max_distance: usize,
+}
+
+impl<C, U> DidYouMean<C, U> {
+    pub fn new(completer: C, candidates: U) -> Self {
+        Self {
+            completer,
+            candidates,
+            max_distance: 2,
+        }
+    }
+
+    /// Sets the maximum number of edits between the typed word and a correction.
+    /// The default is 2.
+    pub fn with_max_distance(mut self, max_distance: usize) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+}
+
+impl<C: Completer, U: Completer> Completer for DidYouMean<C, U> {
+    fn complete(&self, document: &Document) -> Vec<Suggestion> {
+        let suggestions = self.completer.complete(document);
+        if !suggestions.is_empty() {
+            return suggestions;
+        }
+        let word = document.get_word_before_cursor();
+        if word.is_empty() {
+            return suggestions;
+        }
+        corrections(&word, self.candidates.complete(document), self.max_distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCompleter;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, levenshtein("", ""));
+        assert_eq!(3, levenshtein("", "abc"));
+        assert_eq!(3, levenshtein("kitten", "sitting"));
+        assert_eq!(2, levenshtein("ab", "ba"));
+        assert_eq!(1, damerau_levenshtein("ab", "ba"));
+        assert_eq!(1, damerau_levenshtein("--namepsace", "--namespace"));
+        // a transposed pair is not edited again
+        assert_eq!(3, damerau_levenshtein("ca", "abc"));
+        assert_eq!(1, damerau_levenshtein("日本", "本日"));
+    }
+
+    const FLAGS: [&str; 4] = ["--all", "--namespace", "--name", "--output"];
+
+    fn complete(text: &str) -> Vec<(String, bool)> {
+        let d = Document::with_text(text.to_string(), text.chars().count());
+        DidYouMean::new(TestCompleter::new(FLAGS).prefixed(), TestCompleter::new(FLAGS)).complete(&d).iter()
+            .map(|s| (s.text().to_string(), s.is_correction()))
+            .collect()
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        assert_eq!(vec![("--all".to_string(), false)], complete("get --a"));
+        assert_eq!(vec![("--namespace".to_string(), true)], complete("get --namepsace"));
+        // only the typed part has to be close
+        assert_eq!(vec![("--namespace".to_string(), true), ("--name".to_string(), true)], complete("get --nmaes"));
+        assert!(complete("get --verbose").is_empty());
+        assert_eq!(4, complete("get ").len());
+    }
+}
@@ -0,0 +1,205 @@
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+
+use crossterm::style::Color;
+
+use crate::buffer::TextChange;
+use crate::style::SpanStyle;
+use crate::threaded_completer::panic_message;
+
+/// How bad a [Diagnostic] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Returns the style of the text the diagnostic is about, only the foreground color
+    /// and the underline being set so that it goes on top of the highlighting.
+    pub fn style(self) -> SpanStyle {
+        match self {
+            Severity::Warning => SpanStyle::default().fg(Color::Yellow).underline(),
+            Severity::Error => SpanStyle::default().fg(Color::Red).underline(),
+        }
+    }
+}
+
+/// A problem found in the input by the linter, see `Prompt::with_linter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Char range of the text the problem is about.
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning<S: Into<String>>(range: Range<usize>, message: S) -> Self {
+        Self { range, severity: Severity::Warning, message: message.into() }
+    }
+
+    pub fn error<S: Into<String>>(range: Range<usize>, message: S) -> Self {
+        Self { range, severity: Severity::Error, message: message.into() }
+    }
+
+    /// Moves the range along with the text after `change`. Returns false if the change
+    /// touches the range, the diagnostic being outdated then.
+    pub(crate) fn follow(&mut self, change: &TextChange) -> bool {
+        if change.range.start <= self.range.end && change.range.end >= self.range.start {
+            return false;
+        }
+        if change.range.end < self.range.start {
+            let inserted = change.text.chars().count();
+            self.range.start = self.range.start + inserted - change.range.len();
+            self.range.end = self.range.end + inserted - change.range.len();
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// The text waiting for the worker.
+    queued: Option<String>,
+    /// The text the worker is linting.
+    running: Option<String>,
+    done: Option<(String, Vec<Diagnostic>)>,
+    /// The message the linter panicked with, not reported yet.
+    error: Option<String>,
+    closed: bool,
+}
+
+/// Runs the linter on a background thread, so that a slow one, e.g. one asking a
+/// database whether tables exist, never blocks typing. Texts queued while another one is
+/// linted replace each other, so only the latest input is linted.
+pub(crate) struct LintWorker {
+    shared: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl LintWorker {
+    pub(crate) fn new<F: Fn(&str) -> Vec<Diagnostic> + Send + 'static>(linter: F) -> Self {
+        let shared = Arc::new((Mutex::new(State::default()), Condvar::new()));
+        let worker = Arc::clone(&shared);
+        thread::spawn(move || {
+            let (state, ready) = &*worker;
+            loop {
+                let text = {
+                    let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+                    let text = loop {
+                        if state.closed {
+                            return;
+                        }
+                        if let Some(text) = state.queued.take() {
+                            break text;
+                        }
+                        state = ready.wait(state).unwrap_or_else(PoisonError::into_inner);
+                    };
+                    state.running = Some(text.clone());
+                    text
+                };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| linter(&text)));
+                let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+                state.running = None;
+                let diagnostics = result.unwrap_or_else(|payload| {
+                    state.error = Some(panic_message(payload.as_ref()));
+                    Vec::new()
+                });
+                state.done = Some((text, diagnostics));
+            }
+        });
+        Self { shared }
+    }
+
+    /// Returns the diagnostics of `text` if they are ready, else has it linted.
+    pub(crate) fn lint(&self, text: &str) -> Option<Vec<Diagnostic>> {
+        let (state, ready) = &*self.shared;
+        let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, diagnostics)) = state.done.as_ref().filter(|(done, _)| done == text) {
+            return Some(diagnostics.clone());
+        }
+        if state.running.as_deref() != Some(text) && state.queued.as_deref() != Some(text) {
+            state.queued = Some(text.to_string());
+            ready.notify_one();
+        }
+        None
+    }
+
+    pub(crate) fn pending(&self) -> bool {
+        let state = self.shared.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.queued.is_some() || state.running.is_some()
+    }
+
+    /// Returns the message the linter last panicked with, once.
+    pub(crate) fn take_error(&self) -> Option<String> {
+        self.shared.0.lock().unwrap_or_else(PoisonError::into_inner).error.take()
+    }
+}
+
+impl Drop for LintWorker {
+    /// Stops the worker once it is done with the current text, without waiting for it.
+    fn drop(&mut self) {
+        let (state, ready) = &*self.shared;
+        state.lock().unwrap_or_else(PoisonError::into_inner).closed = true;
+        ready.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_follow() {
+        let change = |range: Range<usize>, text: &str| TextChange { range, text: text.to_string() };
+        let mut d = Diagnostic::error(4..9, "unknown table");
+        assert!(d.follow(&change(10..12, "")));
+        assert_eq!(4..9, d.range);
+        assert!(d.follow(&change(0..1, "abc")));
+        assert_eq!(6..11, d.range);
+        assert!(!d.follow(&change(11..11, "s")));
+        assert!(!d.follow(&change(3..7, "")));
+    }
+
+    #[test]
+    fn test_lint_worker() {
+        let w = LintWorker::new(|text: &str| match text.find("bad") {
+            Some(i) => vec![Diagnostic::warning(i..i + 3, "bad word")],
+            None => Vec::new(),
+        });
+        let mut diagnostics = None;
+        for _ in 0..500 {
+            diagnostics = w.lint("a bad b");
+            if diagnostics.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        assert_eq!(Some(vec![Diagnostic::warning(2..5, "bad word")]), diagnostics);
+        assert!(!w.pending());
+    }
+
+    #[test]
+    fn test_lint_worker_panics() {
+        let w = LintWorker::new(|text: &str| match text {
+            "boom" => panic!("linter failed"),
+            _ => Vec::new(),
+        });
+        let mut diagnostics = None;
+        for _ in 0..500 {
+            diagnostics = w.lint("boom");
+            if diagnostics.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        assert_eq!(Some(Vec::new()), diagnostics);
+        assert!(!w.pending());
+        assert_eq!(Some("linter failed".to_string()), w.take_error());
+        assert_eq!(None, w.take_error());
+    }
+}
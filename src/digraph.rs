@@ -0,0 +1,157 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::key_bind::Key;
+
+/// Digraphs of RFC 1345 as in vim, the two characters followed by the one they stand for.
+static DIGRAPHS: &[(char, char, char)] = &[
+    // latin letters
+    ('a', ':', 'ä'), ('e', ':', 'ë'), ('i', ':', 'ï'), ('o', ':', 'ö'), ('u', ':', 'ü'), ('y', ':', 'ÿ'),
+    ('A', ':', 'Ä'), ('E', ':', 'Ë'), ('I', ':', 'Ï'), ('O', ':', 'Ö'), ('U', ':', 'Ü'),
+    ('a', '\'', 'á'), ('e', '\'', 'é'), ('i', '\'', 'í'), ('o', '\'', 'ó'), ('u', '\'', 'ú'),
+    ('A', '\'', 'Á'), ('E', '\'', 'É'), ('I', '\'', 'Í'), ('O', '\'', 'Ó'), ('U', '\'', 'Ú'),
+    ('a', '!', 'à'), ('e', '!', 'è'), ('i', '!', 'ì'), ('o', '!', 'ò'), ('u', '!', 'ù'),
+    ('A', '!', 'À'), ('E', '!', 'È'), ('I', '!', 'Ì'), ('O', '!', 'Ò'), ('U', '!', 'Ù'),
+    ('a', '>', 'â'), ('e', '>', 'ê'), ('i', '>', 'î'), ('o', '>', 'ô'), ('u', '>', 'û'),
+    ('A', '>', 'Â'), ('E', '>', 'Ê'), ('I', '>', 'Î'), ('O', '>', 'Ô'), ('U', '>', 'Û'),
+    ('a', '?', 'ã'), ('o', '?', 'õ'), ('n', '?', 'ñ'), ('A', '?', 'Ã'), ('O', '?', 'Õ'), ('N', '?', 'Ñ'),
+    ('a', 'a', 'å'), ('A', 'A', 'Å'), ('a', 'e', 'æ'), ('A', 'E', 'Æ'), ('o', '/', 'ø'), ('O', '/', 'Ø'),
+    ('c', ',', 'ç'), ('C', ',', 'Ç'), ('s', 's', 'ß'), ('o', 'e', 'œ'), ('O', 'E', 'Œ'),
+    // greek letters
+    ('a', '*', 'α'), ('b', '*', 'β'), ('g', '*', 'γ'), ('d', '*', 'δ'), ('e', '*', 'ε'), ('l', '*', 'λ'),
+    ('m', '*', 'μ'), ('p', '*', 'π'), ('s', '*', 'σ'), ('W', '*', 'Ω'), ('D', '*', 'Δ'),
+    // symbols
+    ('E', 'u', '€'), ('P', 'd', '£'), ('Y', 'e', '¥'), ('C', 't', '¢'), ('S', 'E', '§'), ('P', 'I', '¶'),
+    ('C', 'o', '©'), ('R', 'g', '®'), ('T', 'M', '™'), ('D', 'G', '°'), ('+', '-', '±'), ('*', 'X', '×'),
+    ('-', ':', '÷'), ('!', '=', '≠'), ('=', '<', '≤'), ('>', '=', '≥'), ('1', '2', '½'), ('1', '4', '¼'),
+    ('3', '4', '¾'), ('<', '<', '«'), ('>', '>', '»'), ('!', 'I', '¡'), ('?', 'I', '¿'), ('.', 'M', '·'),
+    ('-', '>', '→'), ('<', '-', '←'), ('-', '!', '↑'), ('-', 'v', '↓'), ('O', 'K', '✓'), ('X', 'X', '✗'),
+    ('-', 'N', '–'), ('-', 'M', '—'), ('N', 'S', '\u{a0}'),
+    // hiragana, two columns wide
+    ('a', '5', 'あ'), ('i', '5', 'い'), ('u', '5', 'う'), ('e', '5', 'え'), ('o', '5', 'お'),
+];
+
+/// Returns the character the digraph of `first` and `second` stands for, e.g. `é` for `e`
+/// and `'`. The characters can be typed in either order, as in vim.
+pub fn digraph(first: char, second: char) -> Option<char> {
+    DIGRAPHS.iter()
+        .find(|&&(a, b, _)| (a, b) == (first, second))
+        .or_else(|| DIGRAPHS.iter().find(|&&(a, b, _)| (a, b) == (second, first)))
+        .map(|&(_, _, c)| c)
+}
+
+/// A character being entered over several keys, see `Prompt::with_digraph_key` and
+/// `Prompt::with_literal_key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SpecialInsert {
+    /// Waiting for the two characters of a digraph, the first one if it was typed.
+    Digraph(Option<char>),
+    /// Waiting for a key to insert literally, or for `u` or `U` to enter a code point.
+    Literal,
+    /// Waiting for the hex digits of a code point, at most `max` of them.
+    CodePoint { digits: String, max: usize },
+}
+
+/// What a key does to a [SpecialInsert].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Step {
+    Wait(SpecialInsert),
+    Insert(char),
+    /// The code point ended with a key that isn't a digit: the character, unless no
+    /// digits or a wrong code point was entered, and then the key, to be handled as usual.
+    InsertThen(Option<char>, Key),
+    Cancel,
+}
+
+impl SpecialInsert {
+    pub(crate) fn feed(self, key: Key) -> Step {
+        if key.code == KeyCode::Esc || key == Key::ctrl('c') || key == Key::ctrl('g') {
+            return Step::Cancel;
+        }
+        let typed = match key.code {
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => Some(c),
+            _ => None,
+        };
+        match self {
+            SpecialInsert::Digraph(first) => match (first, typed) {
+                (None, Some(c)) => Step::Wait(SpecialInsert::Digraph(Some(c))),
+                // vim inserts the second character of an unknown digraph
+                (Some(first), Some(c)) => Step::Insert(digraph(first, c).unwrap_or(c)),
+                (_, None) => Step::Cancel,
+            },
+            SpecialInsert::Literal => match (typed, key.code) {
+                (Some('u'), _) => Step::Wait(SpecialInsert::CodePoint { digits: String::new(), max: 4 }),
+                (Some('U'), _) => Step::Wait(SpecialInsert::CodePoint { digits: String::new(), max: 8 }),
+                (Some(c), _) => Step::Insert(c),
+                (None, KeyCode::Tab) => Step::Insert('\t'),
+                (None, KeyCode::Enter) => Step::Insert('\n'),
+                _ => Step::Cancel,
+            },
+            SpecialInsert::CodePoint { mut digits, max } => match typed {
+                // u+XXXX, as code points are usually written
+                Some('+') if digits.is_empty() => Step::Wait(SpecialInsert::CodePoint { digits, max }),
+                Some(c) if c.is_ascii_hexdigit() => {
+                    digits.push(c);
+                    match digits.len() < max {
+                        true => Step::Wait(SpecialInsert::CodePoint { digits, max }),
+                        false => code_point(&digits).map_or(Step::Cancel, Step::Insert),
+                    }
+                }
+                _ => Step::InsertThen(code_point(&digits), key),
+            },
+        }
+    }
+}
+
+fn code_point(digits: &str) -> Option<char> {
+    u32::from_str_radix(digits, 16).ok().and_then(char::from_u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::width::display_width;
+
+    fn feed(keys: &str) -> Step {
+        let mut step = Step::Wait(SpecialInsert::Literal);
+        for c in keys.chars() {
+            step = match step {
+                Step::Wait(insert) => insert.feed(Key::plain(KeyCode::Char(c))),
+                step => return step,
+            };
+        }
+        step
+    }
+
+    #[test]
+    fn test_digraph() {
+        assert_eq!(Some('é'), digraph('e', '\''));
+        assert_eq!(Some('é'), digraph('\'', 'e'));
+        assert_eq!(Some('€'), digraph('E', 'u'));
+        assert_eq!(None, digraph('q', 'q'));
+
+        let step = SpecialInsert::Digraph(None).feed(Key::plain(KeyCode::Char('a')));
+        assert_eq!(Step::Wait(SpecialInsert::Digraph(Some('a'))), step);
+        assert_eq!(Step::Insert('ä'), SpecialInsert::Digraph(Some('a')).feed(Key::plain(KeyCode::Char(':'))));
+        assert_eq!(Step::Insert('z'), SpecialInsert::Digraph(Some('q')).feed(Key::plain(KeyCode::Char('z'))));
+        assert_eq!(Step::Cancel, SpecialInsert::Digraph(Some('a')).feed(Key::plain(KeyCode::Esc)));
+
+        assert_eq!(1, display_width(&digraph('a', ':').unwrap().to_string()));
+        assert_eq!(2, display_width(&digraph('a', '5').unwrap().to_string()));
+    }
+
+    #[test]
+    fn test_code_point() {
+        assert_eq!(Step::Insert('x'), feed("x"));
+        assert_eq!(Step::Insert('é'), feed("u00e9"));
+        assert_eq!(Step::Insert('→'), feed("u+2192"));
+        assert_eq!(Step::Insert('😀'), feed("U0001F600"));
+        let step = feed("u301 ");
+        assert_eq!(Step::InsertThen(Some('\u{301}'), Key::plain(KeyCode::Char(' '))), step);
+        assert_eq!(Step::Cancel, feed("ud800"));
+        assert_eq!(Step::Insert('\t'), SpecialInsert::Literal.feed(Key::plain(KeyCode::Tab)));
+
+        // a combining accent takes no column of its own, a wide character two
+        assert_eq!(1, display_width("e\u{301}"));
+        assert_eq!(2, display_width("😀"));
+    }
+}
@@ -1,15 +1,68 @@
+use std::cell::OnceCell;
+use std::ops::Range;
+
 use crossterm::event::KeyCode;
 use unicode_width::UnicodeWidthChar;
 
+use crate::links::{find_links, Link};
+
+/// Number of columns between tab stops unless configured otherwise.
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Character classes of the [WordModel::CharClass] word model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Whitespace,
+    Punctuation,
+    /// Alphanumeric characters and `_`.
+    Word,
+}
+
+impl CharClass {
+    pub fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            CharClass::Whitespace
+        } else if c.is_alphanumeric() || c == '_' {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+/// Decides where words start and end for word-wise cursor movement and deletion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordModel {
+    /// Words are delimited by any of the given characters, or by spaces if empty.
+    Separator(String),
+    /// Words are runs of chars of the same [CharClass], so "foo.bar(baz)" is made of
+    /// the words "foo", ".", "bar", "(", "baz" and ")".
+    CharClass,
+}
+
+impl Default for WordModel {
+    fn default() -> Self {
+        WordModel::Separator(String::new())
+    }
+}
+
+/// Document is an immutable snapshot of the text and the cursor position.
+///
+/// `cursor_position` and every other index taken or returned by Document are char
+/// indices unless documented otherwise. Use [char_to_byte](Document::char_to_byte) and
+/// [byte_to_char](Document::byte_to_char) to convert from and to byte offsets in `text`.
 #[derive(Debug, Default)]
-struct Document {
-    pub text: String,
-    cursor_position: i32,
-    last_key: Option<KeyCode>,
+pub struct Document {
+    pub(crate) text: String,
+    pub(crate) cursor_position: usize,
+    pub(crate) last_key: Option<KeyCode>,
+    /// Byte offset of every char, built on first use. None when the text is ASCII,
+    /// since both units are then the same.
+    char_offsets: OnceCell<Option<Vec<usize>>>,
 }
 
 impl Document {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             text: String::new(),
             cursor_position: 0,
@@ -17,10 +70,67 @@ impl Document {
         }
     }
 
-    pub fn cursor_position(&self) -> i32 {
+    /// Returns a document with the given text and cursor position, clamped to the text.
+    pub fn with_text(text: String, cursor_position: usize) -> Self {
+        let cursor_position = cursor_position.min(text.chars().count());
+        Self {
+            text,
+            cursor_position,
+            ..Default::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor_position(&self) -> usize {
         self.cursor_position
     }
 
+    /// Is [cursor_position](Document::cursor_position) for code written when the cursor
+    /// was an `i32`, saturating at `i32::MAX`.
+    #[deprecated(note = "the cursor position is a usize, use `cursor_position`")]
+    pub fn cursor_position_i32(&self) -> i32 {
+        i32::try_from(self.cursor_position).unwrap_or(i32::MAX)
+    }
+
+    fn char_offsets(&self) -> Option<&[usize]> {
+        self.char_offsets
+            .get_or_init(|| {
+                if self.text.is_ascii() {
+                    None
+                } else {
+                    Some(self.text.char_indices().map(|(i, _)| i).collect())
+                }
+            })
+            .as_deref()
+    }
+
+    /// Returns the number of chars in the text.
+    pub fn char_count(&self) -> usize {
+        self.char_offsets().map_or(self.text.len(), |o| o.len())
+    }
+
+    /// Converts a char index to the byte offset of that char in `text`.
+    /// Indices past the end are clamped to the length of the text.
+    pub fn char_to_byte(&self, char_index: usize) -> usize {
+        match self.char_offsets() {
+            None => char_index.min(self.text.len()),
+            Some(offsets) => offsets.get(char_index).copied().unwrap_or(self.text.len()),
+        }
+    }
+
+    /// Converts a byte offset in `text` to the index of the char containing that byte.
+    /// Offsets past the end are clamped to the number of chars.
+    pub fn byte_to_char(&self, byte_index: usize) -> usize {
+        match self.char_offsets() {
+            None => byte_index.min(self.text.len()),
+            Some(offsets) if byte_index >= self.text.len() => offsets.len(),
+            Some(offsets) => bisect::right(offsets, byte_index) - 1,
+        }
+    }
+
     pub fn last_key_stroke(&self) -> Option<KeyCode> {
         self.last_key
     }
@@ -28,407 +138,514 @@ impl Document {
     /// Returns the cursor position on rendered text on terminal emulators.
     /// So if Document is "日本(cursor)語", DisplayedCursorPosition returns 4 because '日' and '本'
     /// are double width characters.
-    fn display_cursor_position(&self) -> usize {
-        self.text.chars()
-            .take(self.cursor_position as usize)
-            .map(|c| UnicodeWidthChar::width(c).unwrap_or(0))
-            .sum()
+    pub fn display_cursor_position(&self) -> usize {
+        self.display_cursor_position_with_tab_width(DEFAULT_TAB_WIDTH)
+    }
+
+    /// Is the same as [display_cursor_position](Document::display_cursor_position), but tabs
+    /// advance to the next multiple of `tab_width`, counted from the start of their line.
+    pub fn display_cursor_position_with_tab_width(&self, tab_width: usize) -> usize {
+        let tab_width = tab_width.max(1);
+        let mut column = 0;
+        let mut position = 0;
+        for c in self.text.chars().take(self.cursor_position) {
+            let width = match c {
+                '\t' => tab_width - column % tab_width,
+                '\n' => {
+                    column = 0;
+                    continue;
+                }
+                c => UnicodeWidthChar::width(c).unwrap_or(0),
+            };
+            column += width;
+            position += width;
+        }
+        position
     }
 
     /// Return character relative to cursor position, or empty string
     // TODO: return type should be option, since it is possible for the string to empty
-    // TODO: offset should be a unsigned num data type
-    fn get_char_relative_to_cursor(&self, offset: i32) -> char {
-        let mut s = self.text.clone();
-        let mut count = 0;
-        while !s.is_empty() {
-            count += 1;
-            let temp = s.chars().take(1)
-                .map(|c| (c, c.len_utf8()))
-                .collect::<Vec<_>>();
-            let (c, size) = temp.first().unwrap();
-            if count == self.cursor_position + offset {
-                return *c;
-            }
-            s = s.split_at(*size).1.to_string();
-        }
-        char::default()
+    pub fn get_char_relative_to_cursor(&self, offset: isize) -> char {
+        let Some(index) = self.cursor_position.checked_add_signed(offset).and_then(|i| i.checked_sub(1)) else {
+            return char::default();
+        };
+        self.text[self.char_to_byte(index)..]
+            .chars()
+            .next()
+            .unwrap_or_default()
     }
 
     /// Returns the text before the cursor
-    fn text_before_cursor(&self) -> String {
-        self.text.chars()
-            .take(self.cursor_position as usize)
-            .collect::<String>()
+    pub fn text_before_cursor(&self) -> String {
+        self.text_before_cursor_str().to_string()
     }
 
     /// Returns the text after the cursor
-    fn text_after_cursor(&self) -> String {
-        self.text.chars()
-            .skip(self.cursor_position as usize)
-            .collect::<String>()
+    pub fn text_after_cursor(&self) -> String {
+        self.text_after_cursor_str().to_string()
+    }
+
+    /// Is the same as [text_before_cursor](Document::text_before_cursor), without copying.
+    pub fn text_before_cursor_str(&self) -> &str {
+        &self.text[..self.cursor_byte()]
+    }
+
+    /// Is the same as [text_after_cursor](Document::text_after_cursor), without copying.
+    pub fn text_after_cursor_str(&self) -> &str {
+        &self.text[self.cursor_byte()..]
+    }
+
+    fn cursor_byte(&self) -> usize {
+        self.char_to_byte(self.cursor_position)
     }
 
-    /// Returns an index relative to the cursor position
-    /// pointing to the start of the previous word. Return 0 if nothing was found.
-    // TODO: replace return type with Option<i32>
-    // TODO: consider returning unsigned num data type
-    fn find_start_of_previous_word(&self) -> i32 {
-        self.text_before_cursor()
-            .rfind(' ')
-            .map(|c| c + 1)
-            .unwrap_or(0) as i32
+    /// Returns the char index of the start of the previous word. Return 0 if nothing was
+    /// found.
+    pub fn find_start_of_previous_word(&self) -> usize {
+        self.find_start_before_cursor(|c| c == ' ', false)
     }
 
     /// Is almost the same as [find_start_of_previous_word].
     /// The only difference is to ignore contiguous spaces.
-    // TODO: replace return type with Option<i32>
-    // TODO: consider returning unsigned num data type
-    fn find_start_of_previous_word_with_space(&self) -> i32 {
-        let end = self.text_before_cursor()
-            .rfind(|c| c != ' ');
-        if end.is_none() {
-            return 0;
-        }
-        let start = self.text_before_cursor()
-            .split_at(end.unwrap())
-            .0
-            .rfind(' ');
-        match start {
-            None => 0,
-            Some(start) => (start + 1) as i32
-        }
+    pub fn find_start_of_previous_word_with_space(&self) -> usize {
+        self.find_start_before_cursor(|c| c == ' ', true)
     }
 
     /// Is almost the same as [find_start_of_previous_word](Document::find_start_of_previous_word).
     /// But this can specify Separator. Return 0 if nothing was found.
-    // TODO: replace return type with Option<i32>
-    // TODO: consider returning unsigned num data type
-    fn find_start_of_previous_word_until_separator<S: AsRef<str>>(&self, sep: S) -> i32 {
+    pub fn find_start_of_previous_word_until_separator<S: AsRef<str>>(&self, sep: S) -> usize {
         let sep = sep.as_ref();
         if sep.is_empty() {
             return self.find_start_of_previous_word();
         }
-
-        self.text_before_cursor()
-            .rfind(|c| sep.contains(c))
-            .map(|c| c + 1)
-            .unwrap_or(0) as i32
+        self.find_start_before_cursor(|c| sep.contains(c), false)
     }
 
     /// Is almost the same as find_start_of_previous_word_with_space.
     /// But this can specify Separator. Return 0 if nothing was found.
-    fn find_start_of_previous_word_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> i32 {
+    pub fn find_start_of_previous_word_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> usize {
         let sep = sep.as_ref();
         if sep.is_empty() {
             return self.find_start_of_previous_word_with_space();
         }
-        let end = self.text_before_cursor()
-            .rfind(|c| !sep.contains(c));
-        match end {
-            None => 0,
-            Some(end) => {
-                let start = self.text_before_cursor()
-                    .split_at(end)
-                    .0
-                    .rfind(|c| sep.contains(c));
-                match start {
-                    None => 0,
-                    Some(start) => (start + 1) as i32
-                }
+        self.find_start_before_cursor(|c| sep.contains(c), true)
+    }
+
+    /// Char index in the text before the cursor just after the last separator, or 0.
+    /// With `skip_separators`, separators right before the cursor are skipped first.
+    fn find_start_before_cursor<F: Fn(char) -> bool>(&self, is_sep: F, skip_separators: bool) -> usize {
+        let before = self.text_before_cursor_str();
+        let end = if skip_separators {
+            match before.rfind(|c| !is_sep(c)) {
+                None => return 0,
+                Some(end) => end,
             }
-        }
+        } else {
+            before.len()
+        };
+        before[..end].char_indices()
+            .rfind(|&(_, c)| is_sep(c))
+            .map_or(0, |(i, c)| self.byte_to_char(i + c.len_utf8()))
     }
 
     /// Returns an index relative to the cursor position.
     /// pointing to the end of the current word. Return 0 if nothing was found.
     // TODO: ported code, but doc comment seems outdated? https://github.com/c-bata/go-prompt/blob/82a912274504477990ecf7c852eebb7c85291772/document.go#L191
-    fn find_end_of_current_word(&self) -> i32 {
-        self.text_after_cursor()
-            .find(' ')
-            .unwrap_or_else(|| self.text_after_cursor().len()) as i32
+    pub fn find_end_of_current_word(&self) -> usize {
+        self.find_end_after_cursor(|c| c == ' ', false)
     }
 
     /// Is almost the same as [find_end_of_current_word].
     /// The only difference is to ignore contiguous spaces.
-    fn find_end_of_current_word_with_space(&self) -> i32 {
-        let start = self.text_after_cursor()
-            .find(|c| c != ' ');
-        match start {
-            None => self.text_after_cursor().len() as i32,
-            Some(start) => {
-                let end = self.text_after_cursor()
-                    .split_at(start).1
-                    .find(' ');
-                match end {
-                    None => self.text_after_cursor().len() as i32,
-                    Some(end) => (start + end) as i32
-                }
-            }
-        }
+    pub fn find_end_of_current_word_with_space(&self) -> usize {
+        self.find_end_after_cursor(|c| c == ' ', true)
     }
 
     /// Is almost the same as [find_end_of_current_word].
     /// But this can specify Separator. Return 0 if nothing was found.
-    fn find_end_of_current_word_until_separator<S: AsRef<str>>(&self, sep: S) -> i32 {
+    pub fn find_end_of_current_word_until_separator<S: AsRef<str>>(&self, sep: S) -> usize {
         let sep = sep.as_ref();
         if sep.is_empty() {
-            self.find_end_of_current_word()
-        } else {
-            self.text_after_cursor()
-                .find(|c| sep.contains(c))
-                .unwrap_or_else(|| self.text_after_cursor().len()) as i32
+            return self.find_end_of_current_word();
         }
+        self.find_end_after_cursor(|c| sep.contains(c), false)
     }
 
     /// Is almost the same as [find_end_of_current_word_with_space].
     /// But this can specify Separator. Return 0 if nothing was found.
-    fn find_end_of_current_word_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> i32 {
+    pub fn find_end_of_current_word_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> usize {
         let sep = sep.as_ref();
         if sep.is_empty() {
-            self.find_end_of_current_word_with_space()
+            return self.find_end_of_current_word_with_space();
+        }
+        self.find_end_after_cursor(|c| sep.contains(c), true)
+    }
+
+    /// Char index in the text after the cursor of the first separator, or its length.
+    /// With `skip_separators`, separators right after the cursor are skipped first.
+    fn find_end_after_cursor<F: Fn(char) -> bool>(&self, is_sep: F, skip_separators: bool) -> usize {
+        let cursor = self.cursor_byte();
+        let after = &self.text[cursor..];
+        let start = if skip_separators {
+            after.find(|c| !is_sep(c)).unwrap_or(after.len())
         } else {
-            let start = self.text_after_cursor()
-                .find(|c| !sep.contains(c));
-            match start {
-                None => self.text_after_cursor().len() as i32,
-                Some(start) => {
-                    let end = self.text_after_cursor()
-                        .split_at(start).1
-                        .find(|c| sep.contains(c));
-                    match end {
-                        None => self.text_after_cursor().len() as i32,
-                        Some(end) => (start + end) as i32
-                    }
-                }
+            0
+        };
+        let end = after[start..].find(&is_sep).map_or(after.len(), |end| start + end);
+        self.byte_to_char(cursor + end) - self.cursor_position
+    }
+
+    /// Is like [find_start_of_previous_word_with_space], but words are runs of chars of
+    /// the same [CharClass], as with readline or vi's `b`. Whitespace before the cursor
+    /// is skipped first. Returns a char index into the text before the cursor.
+    pub fn find_start_of_previous_word_by_class(&self) -> usize {
+        let before = self.text_before_cursor_str().trim_end_matches(char::is_whitespace);
+        let class = match before.chars().next_back() {
+            None => return 0,
+            Some(c) => CharClass::of(c),
+        };
+        let start = before.trim_end_matches(|c| CharClass::of(c) == class).len();
+        self.byte_to_char(start)
+    }
+
+    /// Is like [find_end_of_current_word_with_space], but words are runs of chars of the
+    /// same [CharClass]. Whitespace after the cursor is skipped first.
+    pub fn find_end_of_current_word_by_class(&self) -> usize {
+        let cursor = self.cursor_byte();
+        let after = &self.text[cursor..];
+        let word = after.trim_start_matches(char::is_whitespace);
+        let rest = match word.chars().next() {
+            None => word,
+            Some(c) => {
+                let class = CharClass::of(c);
+                word.trim_start_matches(|c| CharClass::of(c) == class)
             }
+        };
+        self.byte_to_char(cursor + after.len() - rest.len()) - self.cursor_position
+    }
+
+    /// Returns the start of the previous word according to `model`, as a char index into
+    /// the text before the cursor.
+    pub fn find_start_of_previous_word_with_model(&self, model: &WordModel) -> usize {
+        match model {
+            WordModel::Separator(sep) => self.find_start_of_previous_word_until_separator_ignore_next_to_cursor(sep),
+            WordModel::CharClass => self.find_start_of_previous_word_by_class(),
+        }
+    }
+
+    /// Returns the end of the current word according to `model`, relative to the cursor.
+    pub fn find_end_of_current_word_with_model(&self, model: &WordModel) -> usize {
+        match model {
+            WordModel::Separator(sep) => self.find_end_of_current_word_until_separator_ignore_next_to_cursor(sep),
+            WordModel::CharClass => self.find_end_of_current_word_by_class(),
         }
     }
 
     ///Returns the word before the cursor.
     /// If we have whitespace before the cursor this returns an empty string.
-    fn get_word_before_cursor(&self) -> String {
-        self.text_before_cursor()
-            .split_at(self.find_start_of_previous_word() as usize).1
-            .to_string()
+    pub fn get_word_before_cursor(&self) -> String {
+        self.text_before_cursor_from(self.find_start_of_previous_word())
     }
 
     /// Returns the word after the cursor.
     /// If we have whitespace after the cursor this returns an empty string.
-    fn get_word_after_cursor(&self) -> String {
-        self.text_after_cursor()
-            .split_at(self.find_end_of_current_word() as usize).0
-            .to_string()
+    pub fn get_word_after_cursor(&self) -> String {
+        self.text_after_cursor_until(self.find_end_of_current_word())
     }
 
     /// Returns the word before the cursor.
     /// Unlike [get_word_before_cursor], it returns string containing space
-    fn get_word_before_cursor_with_space(&self) -> String {
-        self.text_before_cursor()
-            .split_at(self.find_start_of_previous_word_with_space() as usize).1
-            .to_string()
+    pub fn get_word_before_cursor_with_space(&self) -> String {
+        self.text_before_cursor_from(self.find_start_of_previous_word_with_space())
     }
 
     /// Returns the word after the cursor.
     /// Unlike [get_word_after_cursor], it returns string containing space
-    fn get_word_after_cursor_with_space(&self) -> String {
-        self.text_after_cursor()
-            .split_at(self.find_end_of_current_word_with_space() as usize).0
-            .to_string()
+    pub fn get_word_after_cursor_with_space(&self) -> String {
+        self.text_after_cursor_until(self.find_end_of_current_word_with_space())
     }
 
     /// Returns the text before the cursor until next separator.
-    fn get_word_before_cursor_until_separator<S: AsRef<str>>(&self, sep: S) -> String {
-        self.text_before_cursor().split_at(self.find_start_of_previous_word_until_separator(sep) as usize).1
-            .to_string()
+    pub fn get_word_before_cursor_until_separator<S: AsRef<str>>(&self, sep: S) -> String {
+        self.text_before_cursor_from(self.find_start_of_previous_word_until_separator(sep))
     }
 
     /// Returns the text after the cursor until next separator.
-    fn get_word_after_cursor_until_separator<S: AsRef<str>>(&self, sep: S) -> String {
-        self.text_after_cursor().split_at(self.find_end_of_current_word_until_separator(sep) as usize).0
-            .to_string()
+    pub fn get_word_after_cursor_until_separator<S: AsRef<str>>(&self, sep: S) -> String {
+        self.text_after_cursor_until(self.find_end_of_current_word_until_separator(sep))
     }
 
     /// Returns the word before the cursor.
     /// Unlike [get_word_before_cursor], it returns string containing space
-    fn get_word_before_cursor_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> String {
-        self.text_before_cursor().split_at(self.find_start_of_previous_word_until_separator_ignore_next_to_cursor(sep) as usize).1.to_string()
+    pub fn get_word_before_cursor_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> String {
+        self.text_before_cursor_from(self.find_start_of_previous_word_until_separator_ignore_next_to_cursor(sep))
     }
 
     /// Returns the word after the cursor.
     /// Unlike [get_word_after_cursor], it returns string containing space
-    fn get_word_after_cursor_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> String {
-        self.text_after_cursor().split_at(self.find_end_of_current_word_until_separator_ignore_next_to_cursor(sep) as usize).0.to_string()
+    pub fn get_word_after_cursor_until_separator_ignore_next_to_cursor<S: AsRef<str>>(&self, sep: S) -> String {
+        self.text_after_cursor_until(self.find_end_of_current_word_until_separator_ignore_next_to_cursor(sep))
+    }
+
+    /// Returns the char range of the word the cursor is in or touches, from the start of
+    /// the word before the cursor to the end of the word after it. When the cursor sits
+    /// between two spaces the range is empty and starts at the cursor.
+    pub fn word_range_under_cursor(&self) -> Range<usize> {
+        self.word_range_under_cursor_until_separator("")
+    }
+
+    /// Is the same as [word_range_under_cursor](Document::word_range_under_cursor), but words
+    /// are delimited by any char of `sep`, or by spaces if it is empty.
+    pub fn word_range_under_cursor_until_separator<S: AsRef<str>>(&self, sep: S) -> Range<usize> {
+        let sep = sep.as_ref();
+        let start = self.find_start_of_previous_word_until_separator(sep);
+        let end = self.cursor_position + self.find_end_of_current_word_until_separator(sep);
+        start..end
+    }
+
+    /// Returns the word the cursor is in or touches. See
+    /// [word_range_under_cursor](Document::word_range_under_cursor).
+    pub fn get_word_under_cursor(&self) -> &str {
+        self.get_word_under_cursor_until_separator("")
+    }
+
+    /// Is the same as [get_word_under_cursor](Document::get_word_under_cursor), but words
+    /// are delimited by any char of `sep`, or by spaces if it is empty.
+    pub fn get_word_under_cursor_until_separator<S: AsRef<str>>(&self, sep: S) -> &str {
+        self.slice(self.word_range_under_cursor_until_separator(sep))
+    }
+
+    /// Returns the URL or the file path the cursor is in or touches, see [find_links], e.g.
+    /// for a key binding opening it.
+    pub fn link_under_cursor(&self) -> Option<Link> {
+        let cursor = self.cursor_position;
+        find_links(&self.text).into_iter().find(|link| link.range.start <= cursor && cursor <= link.range.end)
+    }
+
+    /// Returns the text in `range`, given in char indices and clamped to the text.
+    pub fn slice(&self, range: Range<usize>) -> &str {
+        let end = self.char_to_byte(range.end);
+        &self.text[self.char_to_byte(range.start).min(end)..end]
+    }
+
+    /// Returns the text between the char index `start` and the cursor.
+    fn text_before_cursor_from(&self, start: usize) -> String {
+        self.slice(start..self.cursor_position).to_string()
+    }
+
+    /// Returns the first `len` chars after the cursor.
+    fn text_after_cursor_until(&self, len: usize) -> String {
+        let start = self.cursor_position;
+        self.slice(start..start.saturating_add(len)).to_string()
     }
 
     /// Returns the text from the start of the line until the cursor.
-    fn current_line_before_cursor(&self) -> String {
-        self.text_before_cursor().split('\n')
-            .last()
-            .expect("expected at least one substring")
+    pub fn current_line_before_cursor(&self) -> String {
+        self.text_before_cursor_str().rsplit('\n')
+            .next()
+            .unwrap_or_default()
             .to_string()
     }
 
     /// Returns the text from the cursor until the end of the line.
-    fn current_line_after_cursor(&self) -> String {
-        self.text_after_cursor().split('\n').take(1).collect::<String>()
+    pub fn current_line_after_cursor(&self) -> String {
+        self.text_after_cursor_str()
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .to_string()
     }
 
     /// Return the text on the line where the cursor is. (when the input
     /// consists of just one line, it equals `text`.
-    fn current_line(&self) -> String {
-        self.current_line_before_cursor() + self.current_line_after_cursor().as_str()
+    pub fn current_line(&self) -> &str {
+        let cursor = self.cursor_byte();
+        let start = self.text[..cursor].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.text[cursor..].find('\n').map_or(self.text.len(), |i| cursor + i);
+        &self.text[start..end]
+    }
+
+    /// Returns an iterator over all the lines, without their line breaks.
+    pub fn lines(&self) -> impl Iterator<Item = &str> + '_ {
+        self.text.split('\n')
     }
 
-    /// Returns a Vec of all the lines.
-    // TODO: do we have to map to String?
-    // TODO: we can optimize to not create a Vec every time
-    fn lines(&self) -> Vec<String> {
-        self.text.split('\n').map(|s| s.to_string()).collect::<Vec<String>>()
+    /// Returns the line at `row` (0-based), or None if there is no such line.
+    pub fn line(&self, row: usize) -> Option<&str> {
+        self.lines().nth(row)
     }
 
     /// Return the number of lines in this document. If the document ends
     /// with a trailing \n, that counts as the beginning of a new line.
-    fn line_count(&self) -> usize {
-        self.lines().len()
+    pub fn line_count(&self) -> usize {
+        self.text.bytes().filter(|&b| b == b'\n').count() + 1
     }
 
     /// Array pointing to the start indexes of all the lines.
-    fn line_start_indexes(&self) -> Vec<usize> {
+    pub fn line_start_indexes(&self) -> Vec<usize> {
         // TODO: Cache, because this is often reused.
         // (If it is used, it's often used many times.
         // And this has to be fast for editing big documents!)
-        let lc = self.line_count();
-        let lengths = self.lines()
-            .into_iter()
-            .map(|l| l.len())
-            .collect::<Vec<_>>();
-
-        let mut indexes = Vec::with_capacity(lc + 1);
-        indexes.push(0); // https://github.com/jonathanslenders/python-prompt-toolkit/blob/master/prompt_toolkit/document.py#L189
+        // https://github.com/jonathanslenders/python-prompt-toolkit/blob/master/prompt_toolkit/document.py#L189
+        let mut indexes = Vec::with_capacity(self.line_count());
         let mut pos = 0;
-        for l in lengths {
-            pos += l + 1;
+        for line in self.lines() {
             indexes.push(pos);
-        }
-        if lc > 1 {
-            // Pop the last item. (This is not a new line.)
-            indexes.pop().expect("expected to be able to pop last index");
+            pos += line.chars().count() + 1;
         }
         indexes
     }
 
     /// For the index of a character at a certain line, calculate the index of
     /// the first character on that line.
-    fn find_line_start_index(&self, index: usize) -> (usize, usize) {
+    pub fn find_line_start_index(&self, index: usize) -> (usize, usize) {
         let indexes = self.line_start_indexes();
-        let pos = bisect::right(&indexes, index) - 1;
+        // the first line starts at 0, so there is always one starting at or before index
+        let pos = bisect::right(&indexes, index).saturating_sub(1);
         (pos, indexes[pos])
     }
 
     /// Returns the current row. (0-based.)
-    fn cursor_position_row(&self) -> usize {
-        self.find_line_start_index(self.cursor_position as usize).0
+    pub fn cursor_position_row(&self) -> usize {
+        self.find_line_start_index(self.cursor_position).0
     }
 
     /// Returns the current column. (0-based.)
-    fn cursor_position_col(&self) -> usize {
-        self.cursor_position as usize - self.find_line_start_index(self.cursor_position as usize).1
+    pub fn cursor_position_col(&self) -> usize {
+        self.cursor_position - self.find_line_start_index(self.cursor_position).1
     }
 
-    /// returns the relative position for cursor left.
-    fn get_cursor_left_position(&self, count: i32) -> i32 {
-        if count < 0 {
-            return self.get_cursor_right_position(-count);
-        }
-        if self.cursor_position_col() > count as usize {
-            return -count;
-        }
-        -(self.cursor_position_col() as i32)
+    /// Returns how many chars the cursor moves back when moving `count` chars left,
+    /// stopping at the start of the line.
+    pub fn get_cursor_left_position(&self, count: usize) -> usize {
+        self.cursor_position_col().min(count)
     }
 
-    /// returns relative position for cursor right.
-    fn get_cursor_right_position(&self, count: i32) -> i32 {
-        if count < 0 {
-            return self.get_cursor_left_position(-count);
-        }
-        if self.current_line_after_cursor().len() > count as usize {
-            return count;
-        }
-        self.current_line_after_cursor().len() as i32
+    /// Returns how many chars the cursor moves forward when moving `count` chars right,
+    /// stopping at the end of the line.
+    pub fn get_cursor_right_position(&self, count: usize) -> usize {
+        self.get_end_of_line_position().min(count)
     }
 
     /// return the relative cursor position (character index) where we would be
     /// if the user pressed the arrow-up button.
-    fn get_cursor_up_position(&self, count: i32, preferred_column: Option<usize>) -> i32 {
-        let col = if let Some(n) = preferred_column {
-            n
-        } else {
-            self.cursor_position_col()
-        };
-
-        let row = (self.cursor_position_row() as i32 - count)
-            .max(0) as usize;
-        self.translate_row_col_to_index(row, col) as i32 - self.cursor_position
+    pub fn get_cursor_up_position(&self, count: usize, preferred_column: Option<usize>) -> isize {
+        let col = preferred_column.unwrap_or_else(|| self.cursor_position_col());
+        let row = self.cursor_position_row().saturating_sub(count);
+        self.delta_to(self.translate_row_col_to_index(row, col))
     }
 
     /// return the relative cursor position (character index) where we would be if the
     /// user pressed the arrow-down button.
-    fn get_cursor_down_position(&self, count: i32, preferred_column: Option<usize>) -> i32 {
-        let col = if let Some(n) = preferred_column {
-            n
+    pub fn get_cursor_down_position(&self, count: usize, preferred_column: Option<usize>) -> isize {
+        let col = preferred_column.unwrap_or_else(|| self.cursor_position_col());
+        let row = self.cursor_position_row().saturating_add(count);
+        self.delta_to(self.translate_row_col_to_index(row, col))
+    }
+
+    /// Returns the signed distance from the cursor to the char index `index`.
+    fn delta_to(&self, index: usize) -> isize {
+        index as isize - self.cursor_position as isize
+    }
+
+    /// Returns the index of the start of the blank line before the current paragraph, or 0
+    /// if there is none, like vi's `{`. Blank lines right before the cursor are skipped.
+    pub fn find_previous_paragraph_start(&self) -> usize {
+        let lines = self.lines().collect::<Vec<_>>();
+        let blank = |row: usize| lines[row].trim().is_empty();
+        let mut row = self.cursor_position_row();
+        while row > 0 && blank(row) {
+            row -= 1;
+        }
+        while row > 0 && !blank(row) {
+            row -= 1;
+        }
+        self.translate_row_col_to_index(row, 0)
+    }
+
+    /// Returns the position of the blank line after the current paragraph relative to the
+    /// cursor, or of the end of the text if there is none, like vi's `}`. Blank lines right
+    /// after the cursor are skipped.
+    pub fn find_next_paragraph_end(&self) -> usize {
+        let lines = self.lines().collect::<Vec<_>>();
+        let blank = |row: usize| lines[row].trim().is_empty();
+        let last = lines.len() - 1;
+        let mut row = self.cursor_position_row();
+        while row < last && blank(row) {
+            row += 1;
+        }
+        while row < last && !blank(row) {
+            row += 1;
+        }
+        let target = if blank(row) {
+            self.translate_row_col_to_index(row, 0)
         } else {
-            self.cursor_position_col()
+            self.char_count()
         };
-
-        let row = self.cursor_position_row() as i32 + count;
-        self.translate_row_col_to_index(row as usize, col) as i32 - self.cursor_position
+        target.saturating_sub(self.cursor_position)
     }
 
     /// Given a (row, col), return the corresponding index.
     /// (Row and col params are 0-based.)
-    fn translate_row_col_to_index(&self, row: usize, column: usize) -> usize {
-        let indexes = self.line_start_indexes();
-        let row = row.clamp(0, indexes.len() - 1);
-        let line = {
-            let lines = self.lines();
-            lines.get(row)
-                .unwrap_or_else(|| panic!("line row {} does not exist", row))
-                .clone()
-        };
-
-        if column > 0 || !line.is_empty() {
-            if column > line.len() {
-                indexes[row] + line.len()
-            } else {
-                indexes[row] + column
-            }
-        } else {
-            indexes[row]
-        }.clamp(0, self.text.len())
+    pub fn translate_row_col_to_index(&self, row: usize, column: usize) -> usize {
+        let row = row.min(self.line_count().saturating_sub(1));
+        let mut start = 0;
+        let mut lines = self.lines();
+        for line in lines.by_ref().take(row) {
+            start += line.chars().count() + 1;
+        }
+        let len = lines.next().map_or(0, |line| line.chars().count());
+        (start + column.min(len)).min(self.char_count())
     }
 
     /// Given an index for the text, return the corresponding (row, col) tuple.
     /// (0-based. Returns (0, 0) for index=0.)
-    fn translate_index_to_position(&self, index: usize) -> (usize, usize) {
+    pub fn translate_index_to_position(&self, index: usize) -> (usize, usize) {
         let (row, row_index) = self.find_line_start_index(index);
-        (row, index - row_index)
+        (row, index.saturating_sub(row_index))
     }
 
     /// Returns true when we are at the last line.
-    fn on_last_line(&self) -> bool {
+    pub fn on_last_line(&self) -> bool {
         self.cursor_position_row() == self.line_count() - 1
     }
 
     /// Returns relative position for the end of this line.
-    fn get_end_of_line_position(&self) -> usize {
+    pub fn get_end_of_line_position(&self) -> usize {
         self.current_line_after_cursor().chars().count()
     }
 
-    fn leading_whitespace_in_current_line(&self) -> String {
-        let trimmed = self.current_line();
-        let idx = self.current_line().len() - trimmed.trim().len();
-        self.current_line()[..idx].to_string()
+    pub fn leading_whitespace_in_current_line(&self) -> String {
+        let line = self.current_line();
+        let idx = line.len() - line.trim_start().len();
+        line[..idx].to_string()
+    }
+}
+
+/// A document is serialized as a snapshot of its text and cursor position.
+#[cfg(feature = "serde")]
+mod snapshot {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Document;
+
+    #[derive(Serialize, Deserialize)]
+    struct Snapshot<T> {
+        text: T,
+        cursor_position: usize,
+    }
+
+    impl Serialize for Document {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Snapshot { text: &self.text, cursor_position: self.cursor_position }.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Document {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let snapshot = Snapshot::<String>::deserialize(deserializer)?;
+            Ok(Document::with_text(snapshot.text, snapshot.cursor_position))
+        }
     }
 }
 
@@ -496,11 +713,36 @@ mod tests {
         }.display_cursor_position());
     }
 
+    #[test]
+    fn test_display_cursor_position_with_tabs() {
+        assert_eq!(9, Document {
+            text: "a\tb".to_string(),
+            cursor_position: 3,
+            ..Default::default()
+        }.display_cursor_position());
+        assert_eq!(8, Document {
+            text: "abcdefg\tb".to_string(),
+            cursor_position: 8,
+            ..Default::default()
+        }.display_cursor_position());
+        assert_eq!(4, Document {
+            text: "日\tb".to_string(),
+            cursor_position: 2,
+            ..Default::default()
+        }.display_cursor_position_with_tab_width(4));
+        // tab stops restart on every line
+        assert_eq!(3 + 4, Document {
+            text: "abc\n\tx".to_string(),
+            cursor_position: 5,
+            ..Default::default()
+        }.display_cursor_position_with_tab_width(4));
+    }
+
     #[test]
     fn test_get_char_relative_to_cursor() {
         assert_eq!('e', Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: ("line 1\n".to_string() + "lin").chars().count() as i32,
+            cursor_position: ("line 1\n".to_string() + "lin").chars().count(),
             ..Default::default()
         }.get_char_relative_to_cursor(1));
         assert_eq!('く', Document {
@@ -519,7 +761,7 @@ mod tests {
     fn test_text_before_cursor() {
         assert_eq!("line 1\nlin", Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: ("line 1\n".to_string() + "lin").chars().count() as i32,
+            cursor_position: ("line 1\n".to_string() + "lin").chars().count(),
             ..Default::default()
         }.text_before_cursor());
         assert_eq!("あいうえお\nかき", Document {
@@ -538,7 +780,7 @@ mod tests {
     fn test_text_after_cursor() {
         assert_eq!("e 2\nline 3\nline 4\n", Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: ("line 1\n".to_string() + "lin").chars().count() as i32,
+            cursor_position: ("line 1\n".to_string() + "lin").chars().count(),
             ..Default::default()
         }.text_after_cursor());
         assert_eq!("くけこ\nさしすせそ\nたちつてと\n", Document {
@@ -556,57 +798,57 @@ mod tests {
     // TODO: consider using macros for testcases
     #[test]
     fn test_find_start_of_previous_word() {
-        assert_eq!("apple ".len() as i32, Document {
+        assert_eq!("apple ".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.find_start_of_previous_word());
-        assert_eq!("apple ".len() as i32, Document {
+        assert_eq!("apple ".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator(""));
 
-        assert_eq!("apply -f ./file/".len() as i32, Document {
+        assert_eq!("apply -f ./file/".chars().count(), Document {
             text: "apply -f ./file/foo.json".to_string(),
-            cursor_position: "apply -f ./file/foo.json".len() as i32,
+            cursor_position: "apply -f ./file/foo.json".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator(" /"));
 
-        assert_eq!("apple ".len() as i32, Document {
+        assert_eq!("apple ".chars().count(), Document {
             text: "apple ".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_start_of_previous_word());
-        assert_eq!("apple ".len() as i32, Document {
+        assert_eq!("apple ".chars().count(), Document {
             text: "apple ".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator(""));
 
-        assert_eq!("apply -f ./".len() as i32, Document {
+        assert_eq!("apply -f ./".chars().count(), Document {
             text: "apply -f ./file/foo.json".to_string(),
-            cursor_position: "apply -f ./".len() as i32,
+            cursor_position: "apply -f ./".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator(" /"));
 
-        assert_eq!("あいうえお ".len() as i32, Document {
+        assert_eq!("あいうえお ".chars().count(), Document {
             text: "あいうえお かきくけこ さしすせそ".to_string(),
             cursor_position: 8,
             ..Default::default()
         }.find_start_of_previous_word());
-        assert_eq!("あいうえお ".len() as i32, Document {
+        assert_eq!("あいうえお ".chars().count(), Document {
             text: "あいうえお かきくけこ さしすせそ".to_string(),
             cursor_position: 8,
             ..Default::default()
         }.find_start_of_previous_word_until_separator(""));
 
-        assert_eq!("Добрый ".len() as i32, Document {
+        assert_eq!("Добрый ".chars().count(), Document {
             text: "Добрый день Добрый день".to_string(),
             cursor_position: 9,
             ..Default::default()
         }.find_start_of_previous_word());
-        assert_eq!("Добрый ".len() as i32, Document {
+        assert_eq!("Добрый ".chars().count(), Document {
             text: "Добрый день Добрый день".to_string(),
             cursor_position: 9,
             ..Default::default()
@@ -615,57 +857,57 @@ mod tests {
 
     #[test]
     fn test_find_start_of_previous_word_with_space() {
-        assert_eq!("apple ".len() as i32, Document {
+        assert_eq!("apple ".chars().count(), Document {
             text: "apple bana ".to_string(),
-            cursor_position: "apple bana ".len() as i32,
+            cursor_position: "apple bana ".len(),
             ..Default::default()
         }.find_start_of_previous_word_with_space());
-        assert_eq!("apple ".len() as i32, Document {
+        assert_eq!("apple ".chars().count(), Document {
             text: "apple bana ".to_string(),
-            cursor_position: "apple bana ".len() as i32,
+            cursor_position: "apple bana ".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!("apply -f /file/".len() as i32, Document {
+        assert_eq!("apply -f /file/".chars().count(), Document {
             text: "apply -f /file/foo/".to_string(),
-            cursor_position: "apply -f /file/foo/".len() as i32,
+            cursor_position: "apply -f /file/foo/".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator_ignore_next_to_cursor(" /"));
 
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple ".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_start_of_previous_word_with_space());
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple ".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "file/".to_string(),
-            cursor_position: "file/".len() as i32,
+            cursor_position: "file/".len(),
             ..Default::default()
         }.find_start_of_previous_word_until_separator_ignore_next_to_cursor(" /"));
 
-        assert_eq!("あいうえお ".len() as i32, Document {
+        assert_eq!("あいうえお ".chars().count(), Document {
             text: "あいうえお かきくけこ ".to_string(),
             cursor_position: 12,
             ..Default::default()
         }.find_start_of_previous_word_with_space());
-        assert_eq!("あいうえお ".len() as i32, Document {
+        assert_eq!("あいうえお ".chars().count(), Document {
             text: "あいうえお かきくけこ ".to_string(),
             cursor_position: 12,
             ..Default::default()
         }.find_start_of_previous_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!("Добрый ".len() as i32, Document {
+        assert_eq!("Добрый ".chars().count(), Document {
             text: "Добрый день ".to_string(),
             cursor_position: 12,
             ..Default::default()
         }.find_start_of_previous_word_with_space());
-        assert_eq!("Добрый ".len() as i32, Document {
+        assert_eq!("Добрый ".chars().count(), Document {
             text: "Добрый день ".to_string(),
             cursor_position: 12,
             ..Default::default()
@@ -674,69 +916,69 @@ mod tests {
 
     #[test]
     fn test_find_end_of_current_word() {
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.find_end_of_current_word());
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator(""));
 
-        assert_eq!("bana".len() as i32, Document {
+        assert_eq!("bana".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_end_of_current_word());
-        assert_eq!("bana".len() as i32, Document {
+        assert_eq!("bana".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator(""));
 
-        assert_eq!("file".len() as i32, Document {
+        assert_eq!("file".chars().count(), Document {
             text: "apply -f ./file/foo.json".to_string(),
-            cursor_position: "apply -f ./".len() as i32,
+            cursor_position: "apply -f ./".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator(" /"));
 
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple".len() as i32,
+            cursor_position: "apple".len(),
             ..Default::default()
         }.find_end_of_current_word());
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple".len() as i32,
+            cursor_position: "apple".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator(""));
 
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apply -f ./file/foo.json".to_string(),
-            cursor_position: "apply -f .".len() as i32,
+            cursor_position: "apply -f .".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator(" /"));
 
-        assert_eq!("ple".len() as i32, Document {
+        assert_eq!("ple".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "ap".len() as i32,
+            cursor_position: "ap".len(),
             ..Default::default()
         }.find_end_of_current_word());
-        assert_eq!("ple".len() as i32, Document {
+        assert_eq!("ple".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "ap".len() as i32,
+            cursor_position: "ap".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator(""));
 
         // りん(cursor)ご ばなな
-        assert_eq!("ご".len() as i32, Document {
+        assert_eq!("ご".chars().count(), Document {
             text: "りんご ばなな".to_string(),
             cursor_position: 2,
             ..Default::default()
         }.find_end_of_current_word());
-        assert_eq!("ご".len() as i32, Document {
+        assert_eq!("ご".chars().count(), Document {
             text: "りんご ばなな".to_string(),
             cursor_position: 2,
             ..Default::default()
@@ -754,12 +996,12 @@ mod tests {
         }.find_end_of_current_word_until_separator(""));
 
         // Доб(cursor)рый день
-        assert_eq!("рый".len() as i32, Document {
+        assert_eq!("рый".chars().count(), Document {
             text: "Добрый день".to_string(),
             cursor_position: 3,
             ..Default::default()
         }.find_end_of_current_word());
-        assert_eq!("рый".len() as i32, Document {
+        assert_eq!("рый".chars().count(), Document {
             text: "Добрый день".to_string(),
             cursor_position: 3,
             ..Default::default()
@@ -768,90 +1010,90 @@ mod tests {
 
     #[test]
     fn test_find_end_of_current_word_with_space() {
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.find_end_of_current_word_with_space());
-        assert_eq!("".len() as i32, Document {
+        assert_eq!("".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!("bana".len() as i32, Document {
+        assert_eq!("bana".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_end_of_current_word_with_space());
-        assert_eq!("bana".len() as i32, Document {
+        assert_eq!("bana".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!("file".len() as i32, Document {
+        assert_eq!("file".chars().count(), Document {
             text: "apply -f /file/foo.json".to_string(),
-            cursor_position: "apply -f /".len() as i32,
+            cursor_position: "apply -f /".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(" /"));
 
-        assert_eq!(" bana".len() as i32, Document {
+        assert_eq!(" bana".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple".len() as i32,
+            cursor_position: "apple".len(),
             ..Default::default()
         }.find_end_of_current_word_with_space());
-        assert_eq!(" bana".len() as i32, Document {
+        assert_eq!(" bana".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple".len() as i32,
+            cursor_position: "apple".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!("/to".len() as i32, Document {
+        assert_eq!("/to".chars().count(), Document {
             text: "apply -f /path/to".to_string(),
-            cursor_position: "apply -f /path".len() as i32,
+            cursor_position: "apply -f /path".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(" /"));
 
-        assert_eq!("ple".len() as i32, Document {
+        assert_eq!("ple".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "ap".len() as i32,
+            cursor_position: "ap".len(),
             ..Default::default()
         }.find_end_of_current_word_with_space());
-        assert_eq!("ple".len() as i32, Document {
+        assert_eq!("ple".chars().count(), Document {
             text: "apple bana".to_string(),
-            cursor_position: "ap".len() as i32,
+            cursor_position: "ap".len(),
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!("かきくけこ".len() as i32, Document {
+        assert_eq!("かきくけこ".chars().count(), Document {
             text: "あいうえお かきくけこ".to_string(),
             cursor_position: 6,
             ..Default::default()
         }.find_end_of_current_word_with_space());
-        assert_eq!("かきくけこ".len() as i32, Document {
+        assert_eq!("かきくけこ".chars().count(), Document {
             text: "あいうえお かきくけこ".to_string(),
             cursor_position: 6,
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!(" かきくけこ".len() as i32, Document {
+        assert_eq!(" かきくけこ".chars().count(), Document {
             text: "あいうえお かきくけこ".to_string(),
             cursor_position: 5,
             ..Default::default()
         }.find_end_of_current_word_with_space());
-        assert_eq!(" かきくけこ".len() as i32, Document {
+        assert_eq!(" かきくけこ".chars().count(), Document {
             text: "あいうえお かきくけこ".to_string(),
             cursor_position: 5,
             ..Default::default()
         }.find_end_of_current_word_until_separator_ignore_next_to_cursor(""));
 
-        assert_eq!(" день".len() as i32, Document {
+        assert_eq!(" день".chars().count(), Document {
             text: "Добрый день".to_string(),
             cursor_position: 6,
             ..Default::default()
         }.find_end_of_current_word_with_space());
-        assert_eq!(" день".len() as i32, Document {
+        assert_eq!(" день".chars().count(), Document {
             text: "Добрый день".to_string(),
             cursor_position: 6,
             ..Default::default()
@@ -862,57 +1104,57 @@ mod tests {
     fn test_get_word_after_cursor() {
         assert_eq!("", Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.get_word_after_cursor());
         assert_eq!("", Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple bana".len() as i32,
+            cursor_position: "apple bana".len(),
             ..Default::default()
         }.get_word_after_cursor_until_separator(""));
 
         assert_eq!("le", Document {
             text: "apply -f ./file/foo.json".to_string(),
-            cursor_position: "apply -f ./fi".len() as i32,
+            cursor_position: "apply -f ./fi".len(),
             ..Default::default()
         }.get_word_after_cursor_until_separator("/"));
 
         assert_eq!("bana", Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.get_word_after_cursor());
         assert_eq!("bana", Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple ".len() as i32,
+            cursor_position: "apple ".len(),
             ..Default::default()
         }.get_word_after_cursor_until_separator(""));
 
         assert_eq!("", Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple".len() as i32,
+            cursor_position: "apple".len(),
             ..Default::default()
         }.get_word_after_cursor());
         assert_eq!("", Document {
             text: "apple bana".to_string(),
-            cursor_position: "apple".len() as i32,
+            cursor_position: "apple".len(),
             ..Default::default()
         }.get_word_after_cursor_until_separator(""));
 
         assert_eq!("", Document {
             text: "apply -f ./file/foo.json".to_string(),
-            cursor_position: "apply -f .".len() as i32,
+            cursor_position: "apply -f .".len(),
             ..Default::default()
         }.get_word_after_cursor_until_separator(" /"));
 
         assert_eq!("ple", Document {
             text: "apple bana".to_string(),
-            cursor_position: "ap".len() as i32,
+            cursor_position: "ap".len(),
             ..Default::default()
         }.get_word_after_cursor());
         assert_eq!("ple", Document {
             text: "apple bana".to_string(),
-            cursor_position: "ap".len() as i32,
+            cursor_position: "ap".len(),
             ..Default::default()
         }.get_word_after_cursor_until_separator(""));
 
@@ -943,7 +1185,7 @@ mod tests {
     fn test_current_line_before_cursor() {
         assert_eq!("lin", Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nlin".len() as i32,
+            cursor_position: "line 1\nlin".len(),
             ..Default::default()
         }.current_line_before_cursor());
     }
@@ -952,7 +1194,7 @@ mod tests {
     fn test_current_line_after_cursor() {
         assert_eq!("e 2", Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nlin".len() as i32,
+            cursor_position: "line 1\nlin".len(),
             ..Default::default()
         }.current_line_after_cursor());
     }
@@ -961,7 +1203,7 @@ mod tests {
     fn test_current_line() {
         assert_eq!("line 2", Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nlin".len() as i32,
+            cursor_position: "line 1\nlin".len(),
             ..Default::default()
         }.current_line());
     }
@@ -970,12 +1212,12 @@ mod tests {
     fn test_cursor_position_row_and_col() {
         assert_eq!(1, Document {
             text: "line 1\nline 2\nline 3\n".to_string(),
-            cursor_position: "line 1\nlin".len() as i32,
+            cursor_position: "line 1\nlin".len(),
             ..Default::default()
         }.cursor_position_row());
         assert_eq!(3, Document {
             text: "line 1\nline 2\nline 3\n".to_string(),
-            cursor_position: "line 1\nlin".len() as i32,
+            cursor_position: "line 1\nlin".len(),
             ..Default::default()
         }.cursor_position_col());
 
@@ -995,18 +1237,18 @@ mod tests {
     fn test_get_cursor_left_position() {
         let d = Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nline 2\nlin".len() as i32,
+            cursor_position: "line 1\nline 2\nlin".len(),
             ..Default::default()
         };
-        assert_eq!(-2, d.get_cursor_left_position(2));
-        assert_eq!(-3, d.get_cursor_left_position(10));
+        assert_eq!(2, d.get_cursor_left_position(2));
+        assert_eq!(3, d.get_cursor_left_position(10));
     }
 
     #[test]
     fn test_get_cursor_right_position() {
         let d = Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nline 2\nlin".len() as i32,
+            cursor_position: "line 1\nline 2\nlin".len(),
             ..Default::default()
         };
         assert_eq!(2, d.get_cursor_right_position(2));
@@ -1017,12 +1259,12 @@ mod tests {
     fn test_get_cursor_up_position() {
         let d = Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nline 2\nlin".len() as i32,
+            cursor_position: "line 1\nline 2\nlin".len(),
             ..Default::default()
         };
-        assert_eq!("lin".len() as i32 - "line 1\nline 2\nlin".len() as i32,
+        assert_eq!("lin".len() as isize - "line 1\nline 2\nlin".len() as isize,
                    d.get_cursor_up_position(2, None));
-        assert_eq!("lin".len() as i32 - "line 1\nline 2\nlin".len() as i32,
+        assert_eq!("lin".len() as isize - "line 1\nline 2\nlin".len() as isize,
                    d.get_cursor_up_position(100, None));
     }
 
@@ -1030,12 +1272,12 @@ mod tests {
     fn test_get_cursor_down_position() {
         let d = Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "lin".len() as i32,
+            cursor_position: "lin".len(),
             ..Default::default()
         };
-        assert_eq!("line 1\nline 2\nlin".len() as i32 - "lin".len() as i32,
+        assert_eq!("line 1\nline 2\nlin".len() as isize - "lin".len() as isize,
                    d.get_cursor_down_position(2, None));
-        assert_eq!("line 1\nline 2\nline 3\nline 4\n".len() as i32 - "lin".len() as i32,
+        assert_eq!("line 1\nline 2\nline 3\nline 4\n".len() as isize - "lin".len() as isize,
                    d.get_cursor_down_position(100, None));
     }
 
@@ -1043,7 +1285,7 @@ mod tests {
     fn test_translate_row_col_to_index() {
         let d = Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nlin".len() as i32,
+            cursor_position: "line 1\nlin".len(),
             ..Default::default()
         };
         assert_eq!("line 1\nline 2\nlin".len(),
@@ -1055,7 +1297,7 @@ mod tests {
     fn test_translate_index_to_position() {
         let d = Document {
             text: "line 1\nline 2\nline 3\nline 4\n".to_string(),
-            cursor_position: "line 1\nlin".len() as i32,
+            cursor_position: "line 1\nlin".len(),
             ..Default::default()
         };
         assert_eq!((2, 3),
@@ -1069,12 +1311,12 @@ mod tests {
     fn test_on_last_line() {
         let d = Document {
             text: "line 1\nline 2\nline 3".to_string(),
-            cursor_position: "line 1\nline".len() as i32,
+            cursor_position: "line 1\nline".len(),
             ..Default::default()
         };
         assert!(!d.on_last_line());
         let d = Document {
-            cursor_position: "line 1\nline 2\nline".len() as i32,
+            cursor_position: "line 1\nline 2\nline".len(),
             ..d
         };
         assert!(d.on_last_line());
@@ -1084,9 +1326,232 @@ mod tests {
     fn test_get_end_of_line_position() {
         let d = Document {
             text: "line 1\nline 2\nline 3".to_string(),
-            cursor_position: "line 1\nli".len() as i32,
+            cursor_position: "line 1\nli".len(),
             ..Default::default()
         };
         assert_eq!("ne 2".len(), d.get_end_of_line_position());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_char_to_byte_and_byte_to_char() {
+        let d = Document::with_text("aé日b".to_string(), 0);
+        assert_eq!(4, d.char_count());
+        assert_eq!(vec![0, 1, 3, 6, 7, 7], (0..6).map(|i| d.char_to_byte(i)).collect::<Vec<_>>());
+        assert_eq!(vec![0, 1, 1, 2, 2, 2, 3, 4, 4], (0..9).map(|i| d.byte_to_char(i)).collect::<Vec<_>>());
+
+        let d = Document::with_text("abc".to_string(), 0);
+        assert_eq!(2, d.char_to_byte(2));
+        assert_eq!(3, d.byte_to_char(10));
+    }
+
+    #[test]
+    fn test_multibyte_lines() {
+        // あい\nうえ(cursor)お
+        let d = Document::with_text("あい\nうえお".to_string(), 5);
+        assert_eq!(vec![0, 3], d.line_start_indexes());
+        assert_eq!((1, 2), (d.cursor_position_row(), d.cursor_position_col()));
+        assert_eq!(1, d.get_cursor_right_position(5));
+        assert_eq!(2, d.get_cursor_left_position(5));
+        assert_eq!(2, d.translate_row_col_to_index(0, 10));
+        assert_eq!(-3, d.get_cursor_up_position(1, None));
+        assert_eq!('え', d.get_char_relative_to_cursor(0));
+
+        // separators that are not ASCII
+        let d = Document::with_text("りんご、ばなな".to_string(), 6);
+        assert_eq!(4, d.find_start_of_previous_word_until_separator("、"));
+        assert_eq!("ばな", d.get_word_before_cursor_until_separator("、"));
+    }
+
+    #[test]
+    fn test_leading_whitespace_in_current_line() {
+        let d = Document::with_text("  ab  ".to_string(), 3);
+        assert_eq!("  ", d.leading_whitespace_in_current_line());
+    }
+
+    #[test]
+    fn test_lines() {
+        let d = Document::with_text("line 1\nline 2\n".to_string(), 9);
+        assert_eq!(vec!["line 1", "line 2", ""], d.lines().collect::<Vec<_>>());
+        assert_eq!(Some("line 2"), d.line(1));
+        assert_eq!(None, d.line(3));
+        assert_eq!(3, d.line_count());
+        assert_eq!("line 2", d.current_line());
+
+        let d = Document::with_text("single".to_string(), 2);
+        assert_eq!(vec![0], d.line_start_indexes());
+        assert_eq!(0, d.get_cursor_down_position(1, None));
+    }
+
+    #[test]
+    fn test_text_around_cursor_str() {
+        let d = Document::with_text("あいう\nえお".to_string(), 2);
+        assert_eq!("あい", d.text_before_cursor_str());
+        assert_eq!("う\nえお", d.text_after_cursor_str());
+        assert_eq!(d.text_before_cursor(), d.text_before_cursor_str());
+        assert_eq!(d.text_after_cursor(), d.text_after_cursor_str());
+    }
+
+    #[test]
+    fn test_word_by_class() {
+        let d = Document::with_text("foo.bar(baz)".to_string(), 0);
+        assert_eq!(3, d.find_end_of_current_word_by_class());
+        let d = Document::with_text("foo.bar(baz)".to_string(), 3);
+        assert_eq!(1, d.find_end_of_current_word_by_class());
+        assert_eq!(0, d.find_start_of_previous_word_by_class());
+        let d = Document::with_text("foo.bar(baz)".to_string(), 7);
+        assert_eq!(4, d.find_start_of_previous_word_by_class());
+        assert_eq!(1, d.find_end_of_current_word_by_class());
+
+        // whitespace next to the cursor is skipped
+        let d = Document::with_text("echo  héllo-wörld  ".to_string(), 6);
+        assert_eq!(5, d.find_end_of_current_word_by_class());
+        assert_eq!(0, d.find_start_of_previous_word_by_class());
+        let d = Document::with_text("echo  héllo-wörld  ".to_string(), 19);
+        assert_eq!(12, d.find_start_of_previous_word_by_class());
+        assert_eq!(0, d.find_end_of_current_word_by_class());
+        assert_eq!(0, Document::with_text("   ".to_string(), 2).find_start_of_previous_word_by_class());
+
+        let model = WordModel::default();
+        assert_eq!(6, d.find_start_of_previous_word_with_model(&model));
+        assert_eq!(12, d.find_start_of_previous_word_with_model(&WordModel::CharClass));
+    }
+
+    #[test]
+    fn test_word_under_cursor() {
+        // りん(cursor)ご ばなな
+        let d = Document::with_text("りんご ばなな".to_string(), 2);
+        assert_eq!(0..3, d.word_range_under_cursor());
+        assert_eq!("りんご", d.get_word_under_cursor());
+
+        // on the boundaries of a word
+        let d = Document::with_text("apple banana".to_string(), 5);
+        assert_eq!("apple", d.get_word_under_cursor());
+        let d = Document::with_text("apple banana".to_string(), 6);
+        assert_eq!(6..12, d.word_range_under_cursor());
+        assert_eq!("banana", d.get_word_under_cursor());
+
+        // between spaces
+        let d = Document::with_text("apple  banana".to_string(), 6);
+        assert_eq!(6..6, d.word_range_under_cursor());
+        assert_eq!("", d.get_word_under_cursor());
+        assert_eq!("", Document::new().get_word_under_cursor());
+
+        let d = Document::with_text("cat ./dir/fi.txt".to_string(), 12);
+        assert_eq!("./dir/fi.txt", d.get_word_under_cursor());
+        assert_eq!(10..16, d.word_range_under_cursor_until_separator(" /"));
+        assert_eq!("fi.txt", d.get_word_under_cursor_until_separator(" /"));
+    }
+
+    #[test]
+    fn test_paragraph_navigation() {
+        let text = "first\nparagraph\n\n  \nsecond\nparagraph";
+        // in the middle of the second paragraph
+        let d = Document::with_text(text.to_string(), 24);
+        assert_eq!("first\nparagraph\n\n".chars().count(), d.find_previous_paragraph_start());
+        assert_eq!(text.chars().count() - 24, d.find_next_paragraph_end());
+
+        // in the first paragraph
+        let d = Document::with_text(text.to_string(), 3);
+        assert_eq!(0, d.find_previous_paragraph_start());
+        assert_eq!("first\nparagraph\n".chars().count() - 3, d.find_next_paragraph_end());
+
+        // on a blank line, the blank lines are skipped
+        let d = Document::with_text(text.to_string(), "first\nparagraph\n".chars().count());
+        assert_eq!(0, d.find_previous_paragraph_start());
+        assert_eq!(text.chars().count() - 16, d.find_next_paragraph_end());
+
+        assert_eq!(0, Document::new().find_previous_paragraph_start());
+        assert_eq!(0, Document::new().find_next_paragraph_end());
+    }
+
+    #[test]
+    fn test_slice() {
+        let d = Document::with_text("あいうえお".to_string(), 0);
+        assert_eq!("いう", d.slice(1..3));
+        assert_eq!("えお", d.slice(3..10));
+        assert_eq!("", d.slice(Range { start: 4, end: 2 }));
+        assert_eq!("", d.slice(7..9));
+    }
+
+    #[test]
+    fn test_link_under_cursor() {
+        let d = Document::with_text("open https://x.org now".to_string(), 5);
+        assert_eq!(Some(5..18), d.link_under_cursor().map(|link| link.range));
+        let d = Document::with_text("open https://x.org now".to_string(), 18);
+        assert_eq!("https://x.org", d.link_under_cursor().unwrap().url);
+        assert_eq!(None, Document::with_text("open https://x.org now".to_string(), 20).link_under_cursor());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_snapshot() {
+        let d = Document::with_text("こんにちは".to_string(), 2);
+        let json = serde_json::to_string(&d).unwrap();
+        assert_eq!(r#"{"text":"こんにちは","cursor_position":2}"#, json);
+        let d: Document = serde_json::from_str(&json).unwrap();
+        assert_eq!(("こんにちは", 2), (d.text(), d.cursor_position()));
+
+        // the cursor is clamped to the text
+        let d: Document = serde_json::from_str(r#"{"text":"ab","cursor_position":9}"#).unwrap();
+        assert_eq!(2, d.cursor_position());
+    }
+
+    #[test]
+    fn test_extreme_counts() {
+        let d = Document::with_text("ab\ncd".to_string(), 4);
+        assert_eq!(1, d.get_cursor_left_position(usize::MAX));
+        assert_eq!(1, d.get_cursor_right_position(usize::MAX));
+        assert_eq!(-3, d.get_cursor_up_position(usize::MAX, None));
+        assert_eq!(0, d.get_cursor_down_position(usize::MAX, None));
+        for offset in [isize::MIN, isize::MAX] {
+            assert_eq!(char::default(), d.get_char_relative_to_cursor(offset));
+        }
+    }
+
+    /// Texts mixing ASCII, multi-byte and wide chars, tabs and line breaks.
+    fn any_text() -> impl proptest::strategy::Strategy<Value = String> {
+        "[a-z ./_\\-é日👍\t\n\r\u{301}]{0,40}"
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_index_position_roundtrip(text in any_text()) {
+            let d = Document::with_text(text, 0);
+            for index in 0..=d.char_count() {
+                let (row, col) = d.translate_index_to_position(index);
+                proptest::prop_assert_eq!(index, d.translate_row_col_to_index(row, col));
+            }
+        }
+
+        #[test]
+        fn prop_cursor_moves_stay_in_text(text in any_text(), cursor in 0usize..50, count in proptest::prop_oneof![0usize..50, proptest::num::usize::ANY]) {
+            let d = Document::with_text(text, cursor);
+            let len = d.char_count();
+            let cursor = d.cursor_position();
+            proptest::prop_assert_eq!(d.text(), format!("{}{}", d.text_before_cursor_str(), d.text_after_cursor_str()));
+            let moves = [
+                cursor.checked_sub(d.get_cursor_left_position(count)),
+                cursor.checked_add(d.get_cursor_right_position(count)),
+                cursor.checked_add_signed(d.get_cursor_up_position(count, None)),
+                cursor.checked_add_signed(d.get_cursor_down_position(count, Some(count))),
+                Some(d.find_start_of_previous_word()),
+                cursor.checked_add(d.find_end_of_current_word()),
+                Some(d.find_start_of_previous_word_by_class()),
+                cursor.checked_add(d.find_end_of_current_word_by_class()),
+                cursor.checked_add(d.find_next_paragraph_end()),
+            ];
+            for to in moves {
+                let to = to.unwrap_or(usize::MAX);
+                proptest::prop_assert!((0..=len).contains(&to), "{} out of 0..={}", to, len);
+                let moved = Document::with_text(d.text().to_string(), to);
+                proptest::prop_assert!(d.text().is_char_boundary(moved.text_before_cursor_str().len()));
+            }
+            proptest::prop_assert!(d.find_previous_paragraph_start() <= d.char_count());
+            let range = d.word_range_under_cursor();
+            proptest::prop_assert!(range.start <= range.end && range.end <= d.char_count());
+            d.get_char_relative_to_cursor(count as isize);
+            d.display_cursor_position();
+            d.leading_whitespace_in_current_line();
+        }
+    }
+}
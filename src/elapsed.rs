@@ -0,0 +1,116 @@
+use std::fmt;
+use std::time::Duration;
+
+use crossterm::style::Color;
+
+use crate::style::{SpanStyle, StyledText};
+
+/// Formats the elapsed time of a command, see [ElapsedTime::with_format].
+type Format = Box<dyn Fn(Duration) -> String>;
+
+/// Where [ElapsedTime] shows how long a command took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElapsedPlacement {
+    /// On a line of its own after the output of the command.
+    Line,
+    /// In the prefix segment with this name, see `Renderer::set_prefix_segments`. The
+    /// segment is emptied, and so hidden, after a command faster than the threshold.
+    Segment(String),
+}
+
+/// Shows how long the executor took to run each command, see `Prompt::with_elapsed_time`.
+pub struct ElapsedTime {
+    threshold: Duration,
+    placement: ElapsedPlacement,
+    style: SpanStyle,
+    format: Format,
+}
+
+impl fmt::Debug for ElapsedTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElapsedTime")
+            .field("threshold", &self.threshold)
+            .field("placement", &self.placement)
+            .field("style", &self.style)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ElapsedTime {
+    /// Shows the time taken by commands running for at least `threshold` on a line after
+    /// their output, e.g. `took 2.5s`.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            placement: ElapsedPlacement::Line,
+            style: SpanStyle::default().fg(Color::DarkGrey),
+            format: Box::new(|elapsed| format!("took {}", format_duration(elapsed))),
+        }
+    }
+
+    /// Shows the time in the prefix segment called `name` instead, e.g. `2.5s`.
+    pub fn in_segment<N: Into<String>>(mut self, name: N) -> Self {
+        self.placement = ElapsedPlacement::Segment(name.into());
+        self.format = Box::new(format_duration);
+        self
+    }
+
+    /// Sets the style of the line, segments have their own style.
+    pub fn with_style(mut self, style: SpanStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_format<F: Fn(Duration) -> String + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+
+    pub fn placement(&self) -> &ElapsedPlacement {
+        &self.placement
+    }
+
+    /// Returns the text shown for a command that took `elapsed`, or None if it was faster
+    /// than the threshold.
+    pub fn text(&self, elapsed: Duration) -> Option<StyledText> {
+        (elapsed >= self.threshold).then(|| StyledText::new().span((self.format)(elapsed), self.style))
+    }
+}
+
+/// Formats `duration` the way shells show the time taken by a command: `850ms`, `2.5s`,
+/// `1m 5s` or `2h 3m`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0 => format!("{}ms", duration.as_millis()),
+        1..=59 => format!("{:.1}s", duration.as_secs_f64()),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!("850ms", format_duration(Duration::from_millis(850)));
+        assert_eq!("2.5s", format_duration(Duration::from_millis(2500)));
+        assert_eq!("1m 5s", format_duration(Duration::from_secs(65)));
+        assert_eq!("2h 3m", format_duration(Duration::from_secs(2 * 3600 + 3 * 60 + 7)));
+    }
+
+    #[test]
+    fn test_elapsed_time() {
+        let elapsed = ElapsedTime::new(Duration::from_secs(2));
+        assert_eq!(None, elapsed.text(Duration::from_millis(1999)));
+        assert_eq!("took 3.0s", elapsed.text(Duration::from_secs(3)).unwrap().text());
+        assert_eq!(&ElapsedPlacement::Line, elapsed.placement());
+
+        let elapsed = elapsed.in_segment("took");
+        assert_eq!("3.0s", elapsed.text(Duration::from_secs(3)).unwrap().text());
+        let elapsed = elapsed.with_format(|d| format!("{}s", d.as_secs()));
+        assert_eq!("3s", elapsed.text(Duration::from_secs(3)).unwrap().text());
+    }
+}
@@ -0,0 +1,52 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::key_bind::{Key, KeyBind};
+use crate::key_bind_func::*;
+
+/*
+========
+Emacs-like keyboard shortcuts
+========
+
+| Key         | Effect                                         |
+|-------------|------------------------------------------------|
+| Ctrl + A    | Go to the beginning of the line (Home)         |
+| Ctrl + E    | Go to the end of the line (End)                |
+| Ctrl + F    | Forward one character                          |
+| Ctrl + B    | Backward one character                         |
+| Ctrl + D    | Delete character under the cursor              |
+| Ctrl + H    | Delete character before the cursor (Backspace) |
+| Ctrl + W    | Cut the word before the cursor                 |
+| Ctrl + K    | Cut the line after the cursor                  |
+| Ctrl + U    | Cut the line before the cursor                 |
+| Ctrl + T    | Swap the last two characters before the cursor |
+| Ctrl + _    | Undo the last change                           |
+| Alt + F     | Forward one word                               |
+| Alt + B     | Backward one word                              |
+| Alt + D     | Cut the word after the cursor                  |
+| Alt + Bksp  | Cut the word before the cursor                 |
+| Alt + {     | Backward one paragraph                         |
+| Alt + }     | Forward one paragraph                          |
+*/
+
+pub(crate) static EMACS_KEY_BINDINGS: &[KeyBind] = &[
+    KeyBind { key: Key::ctrl('e'), func: go_line_end },
+    KeyBind { key: Key::ctrl('a'), func: go_line_beginning },
+    KeyBind { key: Key::ctrl('k'), func: kill_line },
+    KeyBind { key: Key::ctrl('u'), func: kill_line_before },
+    KeyBind { key: Key::ctrl('d'), func: delete_char },
+    KeyBind { key: Key::ctrl('h'), func: delete_before_char },
+    KeyBind { key: Key::ctrl('f'), func: go_right_char },
+    KeyBind { key: Key::ctrl('b'), func: go_left_char },
+    KeyBind { key: Key::ctrl('w'), func: delete_word },
+    KeyBind { key: Key::ctrl('t'), func: transpose_chars },
+    // terminals send Ctrl-_ as the byte of Ctrl-7
+    KeyBind { key: Key::ctrl('7'), func: undo },
+    KeyBind { key: Key::ctrl('_'), func: undo },
+    KeyBind { key: Key::alt('f'), func: go_right_word },
+    KeyBind { key: Key::alt('b'), func: go_left_word },
+    KeyBind { key: Key::alt('d'), func: delete_word_after },
+    KeyBind { key: Key::new(KeyCode::Backspace, KeyModifiers::ALT), func: delete_word },
+    KeyBind { key: Key::alt('{'), func: go_previous_paragraph },
+    KeyBind { key: Key::alt('}'), func: go_next_paragraph },
+];
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+use crate::history::HistoryEntry;
+use crate::history_store::{format_entry, parse_entry, replace_file, HistoryStore};
+
+/// Size of the nonce written before each encrypted entry.
+const NONCE_LEN: usize = 12;
+
+/// History store like [FileHistory](crate::FileHistory), but encrypting each entry with
+/// ChaCha20-Poly1305, so that the command lines are not written to disk in plain text.
+///
+/// The 256-bit key is supplied by the application, e.g. from the system keyring, and the
+/// history can't be read back without it. Each line of the file holds the hex encoded
+/// nonce and ciphertext of one entry, so that new entries are appended without rewriting
+/// the file. Lines that don't decrypt with the key, because they were written with
+/// another key or tampered with, make [load](HistoryStore::load) fail with
+/// [InvalidData](io::ErrorKind::InvalidData).
+pub struct EncryptedFileHistory {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+}
+
+impl EncryptedFileHistory {
+    pub fn new<P: Into<PathBuf>>(path: P, key: [u8; 32]) -> Self {
+        Self { path: path.into(), cipher: ChaCha20Poly1305::new(&key.into()) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn encrypt(&self, entry: &HistoryEntry) -> io::Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher
+            .encrypt(&nonce, format_entry(entry).as_bytes())
+            .map_err(|_| io::Error::other("failed to encrypt a history entry"))?;
+        Ok(hex(nonce.iter().chain(&ciphertext)))
+    }
+
+    fn decrypt(&self, line: &str) -> io::Result<HistoryEntry> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid encrypted history entry");
+        let bytes = unhex(line).filter(|b| b.len() > NONCE_LEN).ok_or_else(invalid)?;
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| invalid())?;
+        String::from_utf8(plaintext).ok().and_then(|text| parse_entry(&text)).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Debug for EncryptedFileHistory {
+    // leaves the key out
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedFileHistory").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+impl HistoryStore for EncryptedFileHistory {
+    fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        text.lines().filter(|l| !l.is_empty()).map(|line| self.decrypt(line)).collect()
+    }
+
+    fn append(&mut self, entry: &HistoryEntry) -> io::Result<()> {
+        let line = self.encrypt(entry)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+
+    fn prune(&mut self, max: usize) -> io::Result<()> {
+        let entries = self.load()?;
+        if entries.len() <= max {
+            return Ok(());
+        }
+        replace_file(&self.path, |out| {
+            for entry in &entries[entries.len() - max..] {
+                writeln!(out, "{}", self.encrypt(entry)?)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+fn hex<'b, I: Iterator<Item = &'b u8>>(bytes: I) -> String {
+    bytes.map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_file_history() {
+        let path = std::env::temp_dir().join(format!("rusty-prompt-encrypted-history-{}", std::process::id()));
+        let mut store = EncryptedFileHistory::new(&path, [7; 32]);
+        assert!(store.load().unwrap().is_empty());
+        let entries = [
+            HistoryEntry::new("psql -h db-prod-42").with_scope("ops"),
+            HistoryEntry::new("for f in *\ndo cat $f\ndone"),
+            HistoryEntry::new("ls"),
+        ];
+        for entry in &entries {
+            store.append(entry).unwrap();
+        }
+        assert_eq!(entries.to_vec(), store.load().unwrap());
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(3, text.lines().count());
+        assert!(!text.contains("db-prod"));
+
+        store.prune(1).unwrap();
+        assert_eq!(vec![HistoryEntry::new("ls")], store.load().unwrap());
+
+        let other = EncryptedFileHistory::new(&path, [8; 32]);
+        assert_eq!(io::ErrorKind::InvalidData, other.load().unwrap_err().kind());
+        fs::write(&path, "zz\n").unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, store.load().unwrap_err().kind());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!("00ff10", hex([0u8, 255, 16].iter()));
+        assert_eq!(Some(vec![0, 255, 16]), unhex("00ff10"));
+        assert_eq!(None, unhex("0"));
+        assert_eq!(None, unhex("gg"));
+    }
+}
@@ -0,0 +1,154 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::key_bind::Key;
+
+/// What the keys read after an Esc within the esc timeout stand for, see
+/// `Prompt::with_esc_timeout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Sequence {
+    /// The keys may be the start of a longer escape sequence.
+    Incomplete,
+    /// The Esc and the keys are the escape sequence of this key, or Alt and a key for
+    /// terminals sending Alt as an Esc prefix.
+    Key(Key),
+    /// Esc and the keys were pressed one after the other.
+    Keys(Vec<Key>),
+}
+
+/// Parses the keys read after an Esc, which crossterm reports separately when the
+/// terminal sends an escape sequence over several reads.
+pub(crate) fn parse(keys: &[Key]) -> Sequence {
+    let chars = keys.iter()
+        .map(|key| match key.code {
+            KeyCode::Char(c) if key.modifiers.is_empty() => Some(c),
+            _ => None,
+        })
+        .collect::<Option<String>>();
+    match (keys, chars.as_deref()) {
+        ([], _) => Sequence::Incomplete,
+        (_, Some(chars)) if chars.starts_with('[') => csi(&chars[1..]),
+        (_, Some(chars)) if chars.starts_with('O') => ss3(&chars[1..]),
+        ([key], _) if key.code != KeyCode::Esc => Sequence::Key(Key::new(key.code, key.modifiers | KeyModifiers::ALT)),
+        _ => Sequence::Keys(esc_then(keys)),
+    }
+}
+
+/// Returns the keys read after an Esc once the esc timeout is over: Alt and the key if a
+/// single one was read, else Esc and the keys.
+pub(crate) fn finish(keys: &[Key]) -> Vec<Key> {
+    match keys {
+        [key] if key.code != KeyCode::Esc => vec![Key::new(key.code, key.modifiers | KeyModifiers::ALT)],
+        _ => esc_then(keys),
+    }
+}
+
+fn esc_then(keys: &[Key]) -> Vec<Key> {
+    [Key::plain(KeyCode::Esc)].into_iter().chain(keys.iter().copied()).collect()
+}
+
+fn keys_of(rest: &str) -> Vec<Key> {
+    rest.chars().map(|c| Key::plain(KeyCode::Char(c))).collect()
+}
+
+/// Parses what follows `ESC [`: parameters, then a final character.
+fn csi(rest: &str) -> Sequence {
+    let Some(last) = rest.chars().last() else {
+        return Sequence::Incomplete;
+    };
+    if last.is_ascii_digit() || last == ';' {
+        return Sequence::Incomplete;
+    }
+    let params = &rest[..rest.len() - last.len_utf8()];
+    let mut params = params.split(';');
+    let first = params.next().unwrap_or_default();
+    let modifiers = params.next().and_then(|m| m.parse::<u8>().ok()).map_or(KeyModifiers::NONE, modifiers);
+    let code = match (last, first) {
+        ('A', _) => KeyCode::Up,
+        ('B', _) => KeyCode::Down,
+        ('C', _) => KeyCode::Right,
+        ('D', _) => KeyCode::Left,
+        ('H', _) | ('~', "1" | "7") => KeyCode::Home,
+        ('F', _) | ('~', "4" | "8") => KeyCode::End,
+        ('Z', _) => KeyCode::BackTab,
+        ('~', "2") => KeyCode::Insert,
+        ('~', "3") => KeyCode::Delete,
+        ('~', "5") => KeyCode::PageUp,
+        ('~', "6") => KeyCode::PageDown,
+        _ => {
+            let mut keys = keys_of("[");
+            keys.extend(keys_of(rest));
+            return Sequence::Keys(esc_then(&keys));
+        }
+    };
+    Sequence::Key(Key::new(code, modifiers))
+}
+
+/// Parses what follows `ESC O`, sent by terminals in application mode.
+fn ss3(rest: &str) -> Sequence {
+    let code = match rest {
+        "" => return Sequence::Incomplete,
+        "A" => KeyCode::Up,
+        "B" => KeyCode::Down,
+        "C" => KeyCode::Right,
+        "D" => KeyCode::Left,
+        "H" => KeyCode::Home,
+        "F" => KeyCode::End,
+        "P" => KeyCode::F(1),
+        "Q" => KeyCode::F(2),
+        "R" => KeyCode::F(3),
+        "S" => KeyCode::F(4),
+        _ => return Sequence::Keys(esc_then(&keys_of(&format!("O{}", rest)))),
+    };
+    Sequence::Key(Key::plain(code))
+}
+
+/// Returns the modifiers of the xterm parameter, one more than a bit mask of them.
+fn modifiers(param: u8) -> KeyModifiers {
+    let mask = param.saturating_sub(1);
+    let mut modifiers = KeyModifiers::NONE;
+    if mask & 1 != 0 {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+    if mask & 2 != 0 {
+        modifiers |= KeyModifiers::ALT;
+    }
+    if mask & 4 != 0 {
+        modifiers |= KeyModifiers::CONTROL;
+    }
+    modifiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(s: &str) -> Sequence {
+        parse(&keys_of(s))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Sequence::Incomplete, parse_str(""));
+        assert_eq!(Sequence::Incomplete, parse_str("["));
+        assert_eq!(Sequence::Incomplete, parse_str("[1;5"));
+        assert_eq!(Sequence::Key(Key::plain(KeyCode::Up)), parse_str("[A"));
+        assert_eq!(Sequence::Key(Key::plain(KeyCode::Delete)), parse_str("[3~"));
+        assert_eq!(Sequence::Key(Key::new(KeyCode::Right, KeyModifiers::CONTROL)), parse_str("[1;5C"));
+        assert_eq!(Sequence::Key(Key::plain(KeyCode::F(1))), parse_str("OP"));
+        assert_eq!(Sequence::Key(Key::alt('f')), parse_str("f"));
+        assert_eq!(Sequence::Key(Key::new(KeyCode::Up, KeyModifiers::ALT)), parse(&[Key::plain(KeyCode::Up)]));
+
+        let esc = Key::plain(KeyCode::Esc);
+        assert_eq!(Sequence::Keys(vec![esc, esc]), parse(&[esc]));
+        let keys = vec![esc, Key::plain(KeyCode::Char('[')), Key::plain(KeyCode::Char('x'))];
+        assert_eq!(Sequence::Keys(keys), parse_str("[x"));
+    }
+
+    #[test]
+    fn test_finish() {
+        let esc = Key::plain(KeyCode::Esc);
+        assert_eq!(vec![esc], finish(&[]));
+        assert_eq!(vec![Key::alt('[')], finish(&keys_of("[")));
+        assert_eq!(vec![esc, Key::plain(KeyCode::Char('[')), Key::plain(KeyCode::Char('1'))], finish(&keys_of("[1")));
+    }
+}
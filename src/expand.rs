@@ -0,0 +1,203 @@
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crossterm::style::Color;
+
+use crate::document::Document;
+use crate::style::{SpanStyle, StyledText};
+
+/// Expands a leading `~` or `~/` to the home directory and `$VAR` or `${VAR}` to the value
+/// of the environment variable, like a shell would. Unset variables expand to nothing.
+pub fn expand_vars(word: &str) -> Cow<'_, str> {
+    if !word.starts_with('~') && !word.contains('$') {
+        return Cow::Borrowed(word);
+    }
+
+    let mut out = String::with_capacity(word.len());
+    let mut rest = word;
+    if rest == "~" || rest.starts_with("~/") {
+        match env::var("HOME") {
+            Ok(home) => {
+                out.push_str(&home);
+                rest = &rest[1..];
+            }
+            Err(_) => return Cow::Borrowed(word),
+        }
+    }
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        if name.is_empty() {
+            out.push('$');
+        } else {
+            out.push_str(&env::var(name).unwrap_or_default());
+        }
+        rest = &after[len..];
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Returns true if `word` contains `*` or `?`.
+pub fn is_glob(word: &str) -> bool {
+    word.contains(['*', '?'])
+}
+
+/// Returns the paths matching `pattern`, sorted, where `*` matches any run of characters
+/// and `?` any single character within a path component. As in shells, wildcards only
+/// match names starting with a dot if the pattern component does too.
+pub fn glob(pattern: &str) -> Vec<PathBuf> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec![PathBuf::from("/")], rest),
+        None => (vec![PathBuf::new()], pattern),
+    };
+    for component in rest.split('/').filter(|c| !c.is_empty()) {
+        paths = if is_glob(component) {
+            paths.iter().flat_map(|dir| matching_entries(dir, component)).collect()
+        } else {
+            paths.into_iter()
+                .map(|dir| dir.join(component))
+                .filter(|path| path.symlink_metadata().is_ok())
+                .collect()
+        };
+    }
+    if paths.len() == 1 && paths[0].as_os_str().is_empty() {
+        return Vec::new();
+    }
+    paths.sort();
+    paths
+}
+
+fn matching_entries(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let read_from = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let entries = match fs::read_dir(read_from) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if name.starts_with('.') && !pattern.starts_with('.') {
+                return None;
+            }
+            wildcard_match(pattern, &name).then(|| dir.join(name))
+        })
+        .collect()
+}
+
+/// Matches `name` against `pattern` made of literal characters, `*` and `?`.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` and of the name when it was reached, to backtrack to
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Describes what the word under the cursor expands to: the number of paths matching a
+/// glob, or the path resulting from `~` and `$VAR` expansion. Returns None if the word
+/// does not expand. Meant to be shown in the toolbar, see `Prompt::with_preview`.
+pub fn expansion_preview(document: &Document) -> Option<StyledText> {
+    let word = document.get_word_under_cursor();
+    let expanded = expand_vars(word);
+    let dim = SpanStyle::default().fg(Color::DarkGrey);
+    if is_glob(&expanded) {
+        let matches = glob(&expanded);
+        let text = match matches.as_slice() {
+            [] => StyledText::new().span("no matches", SpanStyle::default().fg(Color::Red)),
+            [path] => StyledText::new().span("→ ", dim).span(path.display().to_string(), SpanStyle::default()),
+            _ => StyledText::new().span(format!("{} matches", matches.len()), SpanStyle::default()),
+        };
+        return Some(text);
+    }
+    match expanded {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(path) => Some(StyledText::new().span("→ ", dim).span(path, SpanStyle::default())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_vars() {
+        env::set_var("RUSTY_PROMPT_TEST_DIR", "/tmp/rp");
+        assert_eq!("/tmp/rp/a", expand_vars("$RUSTY_PROMPT_TEST_DIR/a"));
+        assert_eq!("/tmp/rp_x", expand_vars("${RUSTY_PROMPT_TEST_DIR}_x"));
+        assert_eq!("/a", expand_vars("$RUSTY_PROMPT_TEST_UNSET/a"));
+        assert_eq!("cost: $", expand_vars("cost: $"));
+        assert_eq!(format!("{}/x", env::var("HOME").unwrap()), expand_vars("~/x"));
+        assert_eq!("~user", expand_vars("~user"));
+        assert!(matches!(expand_vars("plain/path"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.rs", "lib.rs"));
+        assert!(wildcard_match("l?b.*", "lib.rs"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(!wildcard_match("*.rs", "lib.rsx"));
+        assert!(!wildcard_match("?", ""));
+    }
+
+    #[test]
+    fn test_glob() {
+        let dir = env::temp_dir().join(format!("rusty-prompt-glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a.rs", "b.rs", "c.txt", ".hidden.rs", "sub/d.rs"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let base = dir.to_str().unwrap();
+
+        assert_eq!(vec![dir.join("a.rs"), dir.join("b.rs")], glob(&format!("{}/*.rs", base)));
+        assert_eq!(vec![dir.join(".hidden.rs")], glob(&format!("{}/.*.rs", base)));
+        assert_eq!(vec![dir.join("sub/d.rs")], glob(&format!("{}/s*/?.rs", base)));
+        assert!(glob(&format!("{}/*.md", base)).is_empty());
+
+        let d = Document::with_text(format!("rm {}/*.rs", base), 5);
+        assert_eq!("2 matches", expansion_preview(&d).unwrap().text());
+        let d = Document::with_text(format!("rm {}/*.md", base), 5);
+        assert_eq!("no matches", expansion_preview(&d).unwrap().text());
+        fs::remove_dir_all(dir).unwrap();
+
+        let d = Document::with_text("cd ~/x".to_string(), 4);
+        assert_eq!(format!("→ {}/x", env::var("HOME").unwrap()), expansion_preview(&d).unwrap().text());
+        assert_eq!(None, expansion_preview(&Document::with_text("ls -la".to_string(), 4)));
+    }
+}
@@ -0,0 +1,247 @@
+use std::cell::{Cell, RefCell};
+use std::ffi::OsString;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::completion::{Completer, Suggestion};
+use crate::document::Document;
+
+/// Completer that delegates to a long-lived subprocess, so that completions can be
+/// written in any language.
+///
+/// The process is spawned on the first completion and is sent one JSON-RPC 2.0 request
+/// per line on its stdin:
+///
+/// ```text
+/// {"jsonrpc":"2.0","id":1,"method":"complete","params":{"text":"git ch","cursor_position":6,"word":"ch"}}
+/// ```
+///
+/// and must answer each one with a single line on its stdout, whose result is a list of
+/// suggestions. `description`, `id` and `url` may be omitted.
+///
+/// ```text
+/// {"jsonrpc":"2.0","id":1,"result":[{"text":"checkout","description":"Switch branches"}]}
+/// ```
+///
+/// If the process fails, exits, answers with an error or doesn't answer within the
+/// [timeout](ExternalCompleter::with_timeout), there are no suggestions for that request,
+/// the error is kept in [last_error](ExternalCompleter::last_error) and the process is
+/// spawned again for the next request.
+pub struct ExternalCompleter {
+    program: OsString,
+    args: Vec<OsString>,
+    timeout: Duration,
+    process: RefCell<Option<Process>>,
+    next_id: Cell<u64>,
+    last_error: RefCell<Option<String>>,
+}
+
+/// Default time to wait for the answer to a request.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines of stdout, read on a thread of their own so that they can be waited for
+    /// with a deadline.
+    lines: Receiver<io::Result<String>>,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'static str,
+    params: Params<'a>,
+}
+
+#[derive(Serialize)]
+struct Params<'a> {
+    text: &'a str,
+    cursor_position: usize,
+    word: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: Option<u64>,
+    result: Option<Vec<Suggestion>>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+impl ExternalCompleter {
+    pub fn new<P, I, A>(program: P, args: I) -> Self
+    where
+        P: Into<OsString>,
+        I: IntoIterator<Item = A>,
+        A: Into<OsString>,
+    {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            timeout: DEFAULT_TIMEOUT,
+            process: RefCell::new(None),
+            next_id: Cell::new(1),
+            last_error: RefCell::new(None),
+        }
+    }
+
+    /// Sets how long to wait for the answer to a request before giving up on it and
+    /// killing the process, 2 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the error of the last request, if it failed.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.borrow().clone()
+    }
+
+    fn spawn(&self) -> io::Result<Process> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let stdout = BufReader::new(child.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?);
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Process { child, stdin, lines })
+    }
+
+    fn request(&self, document: &Document) -> io::Result<Vec<Suggestion>> {
+        let mut process = self.process.borrow_mut();
+        let spawned = match process.take() {
+            Some(spawned) => spawned,
+            None => self.spawn()?,
+        };
+        let process = process.insert(spawned);
+
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let word = document.get_word_before_cursor();
+        let request = Request {
+            jsonrpc: "2.0",
+            id,
+            method: "complete",
+            params: Params {
+                text: document.text(),
+                cursor_position: document.cursor_position(),
+                word: &word,
+            },
+        };
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        process.stdin.write_all(line.as_bytes())?;
+        process.stdin.flush()?;
+
+        let line = match process.lines.recv_timeout(self.timeout) {
+            Ok(line) => line?,
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "completer did not answer in time"));
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "completer exited"));
+            }
+        };
+        let response: Response = serde_json::from_str(&line)?;
+        if response.id != Some(id) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "response id does not match the request"));
+        }
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(io::Error::other(error.message)),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(io::Error::new(io::ErrorKind::InvalidData, "response has no result")),
+        }
+    }
+
+    fn kill(&self) {
+        if let Some(mut process) = self.process.borrow_mut().take() {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
+
+impl Completer for ExternalCompleter {
+    fn complete(&self, document: &Document) -> Vec<Suggestion> {
+        match self.request(document) {
+            Ok(suggestions) => {
+                *self.last_error.borrow_mut() = None;
+                suggestions
+            }
+            Err(error) => {
+                *self.last_error.borrow_mut() = Some(error.to_string());
+                // The answer to this request may still come, so start over.
+                self.kill();
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl Drop for ExternalCompleter {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Answers every request with the word it was sent.
+    const ECHO_WORD: &str = r#"sed -u 's/.*"id":\([0-9]*\).*"word":"\([^"]*\)".*/{"jsonrpc":"2.0","id":\1,"result":[{"text":"\2!"}]}/'"#;
+
+    #[test]
+    fn test_external_completer() {
+        let completer = ExternalCompleter::new("sh", ["-c", ECHO_WORD]);
+        let d = Document::with_text("git che".to_string(), 7);
+        assert_eq!(vec![Suggestion::with_title("che!".to_string())], completer.complete(&d));
+        let d = Document::with_text("git st".to_string(), 6);
+        assert_eq!(vec![Suggestion::with_title("st!".to_string())], completer.complete(&d));
+        assert_eq!(None, completer.last_error());
+    }
+
+    #[test]
+    fn test_external_completer_errors() {
+        let completer = ExternalCompleter::new("sh", ["-c", r#"read line; echo '{"jsonrpc":"2.0","id":1,"error":{"code":1,"message":"boom"}}'"#]);
+        assert!(completer.complete(&Document::new()).is_empty());
+        assert_eq!(Some("boom".to_string()), completer.last_error());
+
+        // a process that exits without answering
+        let completer = ExternalCompleter::new("sh", ["-c", "exit 0"]);
+        assert!(completer.complete(&Document::new()).is_empty());
+        assert!(completer.last_error().is_some());
+
+        // a process that never answers is killed once the deadline passes
+        let completer = ExternalCompleter::new("sh", ["-c", "read line; exec sleep 10"])
+            .with_timeout(Duration::from_millis(100));
+        assert!(completer.complete(&Document::new()).is_empty());
+        assert_eq!(Some("completer did not answer in time".to_string()), completer.last_error());
+        assert!(completer.process.borrow().is_none());
+
+        let completer = ExternalCompleter::new("/nonexistent/completer", Vec::<String>::new());
+        assert!(completer.complete(&Document::new()).is_empty());
+        assert!(completer.last_error().is_some());
+    }
+}
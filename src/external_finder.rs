@@ -0,0 +1,92 @@
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crossterm::terminal;
+
+use crate::picker::one_line;
+
+/// Fuzzy finder program such as fzf or skim, run to pick a history entry or a suggestion
+/// instead of the built-in picker, see `Prompt::with_external_finder`.
+///
+/// The candidates are written one per line to the program's stdin, with the lines of
+/// multi-line ones joined by `↵`, and the program prints the chosen line on its stdout.
+/// It draws its interface on the terminal itself, as fzf and skim do through `/dev/tty`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalFinder {
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+impl ExternalFinder {
+    /// A finder running `program` with `args`, e.g. `ExternalFinder::new("fzf",
+    /// ["--height", "40%", "--reverse"])`.
+    pub fn new<P, I, A>(program: P, args: I) -> Self
+    where
+        P: Into<OsString>,
+        I: IntoIterator<Item = A>,
+        A: Into<OsString>,
+    {
+        Self { program: program.into(), args: args.into_iter().map(Into::into).collect() }
+    }
+
+    /// Runs the finder over `candidates` and returns the index of the chosen one, or
+    /// None if the finder is cancelled, which fzf and skim tell with a non-zero exit
+    /// status, or prints a line that is not a candidate.
+    ///
+    /// Raw mode is turned off while the finder runs, if it is on, and turned back on
+    /// afterwards.
+    pub fn pick<S: AsRef<str>>(&self, candidates: &[S]) -> io::Result<Option<usize>> {
+        let raw = terminal::is_raw_mode_enabled()?;
+        if raw {
+            terminal::disable_raw_mode()?;
+        }
+        let result = self.run(candidates);
+        if raw {
+            terminal::enable_raw_mode()?;
+        }
+        result
+    }
+
+    fn run<S: AsRef<str>>(&self, candidates: &[S]) -> io::Result<Option<usize>> {
+        let lines = candidates.iter().map(|c| one_line(c.as_ref())).collect::<Vec<_>>();
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        match stdin.write_all(lines.join("\n").as_bytes()).and_then(|_| stdin.write_all(b"\n")) {
+            // the finder was quit before reading everything
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
+        drop(stdin);
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let chosen = String::from_utf8_lossy(&output.stdout);
+        let chosen = chosen.lines().next().unwrap_or_default();
+        Ok(lines.iter().position(|line| line == chosen))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_finder() {
+        let candidates = ["ls", "for f in *\ndo wc -l $f\ndone", "pwd"];
+        let finder = ExternalFinder::new("sh", ["-c", "grep wc"]);
+        assert_eq!(Some(1), finder.run(&candidates).unwrap());
+        let finder = ExternalFinder::new("sh", ["-c", "tail -n 1"]);
+        assert_eq!(Some(2), finder.run(&candidates).unwrap());
+        // cancelled
+        let finder = ExternalFinder::new("sh", ["-c", "exit 130"]);
+        assert_eq!(None, finder.run(&candidates).unwrap());
+        let finder = ExternalFinder::new("sh", ["-c", "echo other"]);
+        assert_eq!(None, finder.run(&candidates).unwrap());
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::{self, DirEntry};
+use std::path::PathBuf;
+
+use crate::completion::{Completer, Suggestion};
+use crate::document::Document;
+use crate::expand::expand_vars;
+use crate::path_rules::PathRules;
+
+/// FilePathCompleter is a completer for your local file system. `~` and environment
+/// variables in the word before the cursor are expanded before listing the directory.
+///
+/// Only the last path component is completed, so use it with the path separators as the
+/// completion word separator, see [PathRules::word_separator] and
+/// `Prompt::with_completion_word_separator`.
+#[derive(Debug, Default, Clone)]
+pub struct FilePathCompleter {
+    /// Only entries for which the filter returns true are suggested.
+    pub filter: Option<fn(&DirEntry) -> bool>,
+    pub ignore_case: bool,
+    /// Path separators and home directory, those of the platform by default.
+    pub rules: PathRules,
+}
+
+/// Splits `path` into the directory to list and the prefix of the entries to suggest.
+fn clean_file_path(path: &str, rules: &PathRules) -> (PathBuf, String) {
+    let path = rules.expand_tilde(path);
+    let path = expand_vars(&path);
+    let (dir, base) = rules.split(&path);
+    let dir = if dir.is_empty() { "." } else { dir };
+    (PathBuf::from(dir), base.to_string())
+}
+
+impl Completer for FilePathCompleter {
+    fn complete(&self, document: &Document) -> Vec<Suggestion> {
+        let (dir, base) = clean_file_path(&document.get_word_before_cursor(), &self.rules);
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        let base = if self.ignore_case { base.to_lowercase() } else { base };
+        let mut names = entries
+            .filter_map(Result::ok)
+            .filter(|entry| self.filter.is_none_or(|filter| filter(entry)))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| {
+                if self.ignore_case {
+                    name.to_lowercase().starts_with(&base)
+                } else {
+                    name.starts_with(&base)
+                }
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        names.into_iter().map(Suggestion::with_title).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_clean_file_path() {
+        let rules = PathRules::unix();
+        assert_eq!((PathBuf::from("."), String::new()), clean_file_path("", &rules));
+        assert_eq!((PathBuf::from("."), "foo".to_string()), clean_file_path("foo", &rules));
+        assert_eq!((PathBuf::from("/usr"), "lo".to_string()), clean_file_path("/usr/lo", &rules));
+        assert_eq!((PathBuf::from("/usr/"), String::new()), clean_file_path("/usr/", &rules));
+        assert_eq!((PathBuf::from("/"), "usr".to_string()), clean_file_path("/usr", &rules));
+
+        let rules = PathRules::windows().with_home(Some(PathBuf::from("C:\\Users\\me")));
+        assert_eq!((PathBuf::from("C:\\Users\\me\\"), "Do".to_string()), clean_file_path("~\\Do", &rules));
+        assert_eq!((PathBuf::from("src/"), "ma".to_string()), clean_file_path("src/ma", &rules));
+    }
+
+    #[test]
+    fn test_file_path_completer() {
+        let dir = env::temp_dir().join(format!("rusty-prompt-files-{}", std::process::id()));
+        fs::create_dir_all(dir.join("Docs")).unwrap();
+        for name in ["data.csv", "deploy.sh"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let titles = |c: &FilePathCompleter, text: String| {
+            let d = Document::with_text(text, usize::MAX);
+            c.complete(&d).iter().map(|s| s.text().to_string()).collect::<Vec<_>>()
+        };
+
+        let mut c = FilePathCompleter::default();
+        assert_eq!(vec!["data.csv", "deploy.sh"], titles(&c, format!("cat {}/d", dir.display())));
+        assert_eq!(vec!["Docs", "data.csv", "deploy.sh"], titles(&c, format!("cat {}/", dir.display())));
+        c.ignore_case = true;
+        assert_eq!(vec!["Docs", "data.csv", "deploy.sh"], titles(&c, format!("cat {}/D", dir.display())));
+        c.filter = Some(|entry| entry.path().is_dir());
+        assert_eq!(vec!["Docs"], titles(&c, format!("cat {}/d", dir.display())));
+        assert!(titles(&c, format!("cat {}/missing/", dir.display())).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::io;
+
+use crossterm::event::KeyCode;
+
+use crate::completion::{Completer, Suggestion};
+use crate::document::Document;
+use crate::key_bind::Key;
+use crate::prompt::{Ending, Prompt};
+
+/// Returns why an answer can't be accepted, see [Field::with_validator].
+type Validator<'a> = Box<dyn Fn(&str) -> Result<(), String> + 'a>;
+
+/// Key going back to the previous field.
+const BACK_KEY: Key = Key::plain(KeyCode::Esc);
+
+/// Asks a series of questions, one prompt per [Field], e.g. in a setup wizard.
+///
+/// Esc goes back to the previous field, with its answer ready to be edited, and Ctrl-D
+/// on an empty answer cancels the whole form.
+#[derive(Default)]
+pub struct Form<'a> {
+    fields: Vec<Field<'a>>,
+}
+
+/// A question of a [Form].
+pub struct Field<'a> {
+    name: String,
+    message: String,
+    default: Option<String>,
+    completer: Option<Box<dyn Completer + 'a>>,
+    validators: Vec<Validator<'a>>,
+    secret: bool,
+}
+
+/// What was answered to a field.
+#[derive(Debug, PartialEq, Eq)]
+enum Answer {
+    Line(String),
+    Back,
+    Cancel,
+}
+
+impl<'a> Form<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a field. Answers are returned by field name, so names should be unique.
+    pub fn field(mut self, field: Field<'a>) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Asks every field in order. Returns the answers by field name, or None if the form
+    /// is cancelled.
+    pub fn run(&self) -> io::Result<Option<HashMap<String, String>>> {
+        self.run_with(|field, initial| field.ask(initial))
+    }
+
+    /// Runs the form with `ask` reading the answer to a field, given the text to start
+    /// with.
+    fn run_with<F>(&self, mut ask: F) -> io::Result<Option<HashMap<String, String>>>
+    where
+        F: FnMut(&Field<'a>, Option<&str>) -> io::Result<Answer>,
+    {
+        let mut answers: Vec<Option<String>> = vec![None; self.fields.len()];
+        let mut i = 0;
+        while i < self.fields.len() {
+            let field = &self.fields[i];
+            let initial = answers[i].as_deref().or(field.default.as_deref());
+            match ask(field, initial)? {
+                Answer::Line(answer) => {
+                    answers[i] = Some(answer);
+                    i += 1;
+                }
+                Answer::Back => i = i.saturating_sub(1),
+                Answer::Cancel => return Ok(None),
+            }
+        }
+        let answers = self.fields.iter().zip(answers).map(|(field, answer)| (field.name.clone(), answer.unwrap_or_default()));
+        Ok(Some(answers.collect()))
+    }
+}
+
+/// Completes through a field's completer, if it has one.
+struct FieldCompleter<'a, 'b>(Option<&'b (dyn Completer + 'a)>);
+
+impl Completer for FieldCompleter<'_, '_> {
+    fn complete(&self, document: &Document) -> Vec<Suggestion> {
+        self.0.map(|c| c.complete(document)).unwrap_or_default()
+    }
+
+    fn pending(&self) -> bool {
+        self.0.is_some_and(|c| c.pending())
+    }
+}
+
+impl<'a> Field<'a> {
+    /// A field whose answer is returned under `name`, asked with `message`.
+    pub fn new<N: Into<String>, M: Into<String>>(name: N, message: M) -> Self {
+        Self {
+            name: name.into(),
+            message: message.into(),
+            default: None,
+            completer: None,
+            validators: Vec::new(),
+            secret: false,
+        }
+    }
+
+    /// Starts the answer with `default`, which can be accepted as is or edited.
+    pub fn with_default<S: Into<String>>(mut self, default: S) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    pub fn with_completer<C: Completer + 'a>(mut self, completer: C) -> Self {
+        self.completer = Some(Box::new(completer));
+        self
+    }
+
+    /// Rejects answers for which `validator` returns an error, see
+    /// `Prompt::with_validator`.
+    pub fn with_validator<F: Fn(&str) -> Result<(), String> + 'a>(mut self, validator: F) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    /// Marks the answer as a secret, see `Prompt::with_secret`.
+    pub fn secret(mut self) -> Self {
+        self.secret = true;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn ask(&self, initial: Option<&str>) -> io::Result<Answer> {
+        let mut prompt = Prompt::new(|_| {}, FieldCompleter(self.completer.as_deref()))
+            .with_prefix(format!("{} ", self.message));
+        if self.secret {
+            prompt = prompt.with_secret();
+        }
+        for validator in &self.validators {
+            prompt = prompt.with_validator(move |input| validator(input));
+        }
+        prompt.set_back_key(BACK_KEY);
+        prompt.set_input(initial.unwrap_or_default().to_string());
+        Ok(match prompt.read()? {
+            Ending::Accept(answer) => Answer::Line(answer),
+            Ending::Back => Answer::Back,
+            Ending::Exit => Answer::Cancel,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCompleter;
+
+    fn form() -> Form<'static> {
+        Form::new()
+            .field(Field::new("name", "Project name:").with_default("demo"))
+            .field(Field::new("license", "License:"))
+            .field(Field::new("private", "Private?").with_default("no"))
+    }
+
+    #[test]
+    fn test_form_back_and_defaults() {
+        let mut script = vec![
+            Answer::Line("app".to_string()),
+            Answer::Line("MIT".to_string()),
+            Answer::Back,
+            Answer::Back,
+            Answer::Line("api".to_string()),
+            Answer::Line("MIT".to_string()),
+            Answer::Line("yes".to_string()),
+        ]
+        .into_iter();
+        let mut asked = Vec::new();
+        let answers = form()
+            .run_with(|field, initial| {
+                asked.push((field.name().to_string(), initial.map(str::to_string)));
+                Ok(script.next().unwrap())
+            })
+            .unwrap()
+            .unwrap();
+
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            vec![
+                ("name".to_string(), some("demo")),
+                ("license".to_string(), None),
+                ("private".to_string(), some("no")),
+                // going back shows the previous answers
+                ("license".to_string(), some("MIT")),
+                ("name".to_string(), some("app")),
+                ("license".to_string(), some("MIT")),
+                ("private".to_string(), some("no")),
+            ],
+            asked
+        );
+        assert_eq!(3, answers.len());
+        assert_eq!("api", answers["name"]);
+        assert_eq!("yes", answers["private"]);
+    }
+
+    #[test]
+    fn test_form_cancel() {
+        let mut script = vec![Answer::Back, Answer::Line("app".to_string()), Answer::Cancel].into_iter();
+        assert_eq!(None, form().run_with(|_, _| Ok(script.next().unwrap())).unwrap());
+    }
+
+    #[test]
+    fn test_field_completer() {
+        let field = Field::new("license", "License:").with_completer(TestCompleter::new(["MIT"]));
+        assert_eq!(1, FieldCompleter(field.completer.as_deref()).complete(&Document::new()).len());
+        assert!(FieldCompleter(None).complete(&Document::new()).is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+/// Default time between two frames, about 60 per second.
+pub(crate) const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// Coalesces render requests, e.g. from a burst of repeated keys or pasted text, into
+/// at most one frame per interval.
+#[derive(Debug)]
+pub(crate) struct Frames {
+    interval: Duration,
+    /// Something changed since the last frame.
+    dirty: bool,
+    last_frame: Option<Instant>,
+}
+
+impl Frames {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self { interval, dirty: false, last_frame: None }
+    }
+
+    pub(crate) fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Asks for a frame, which is rendered once it is [due](Frames::due).
+    pub(crate) fn request(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Returns when the requested frame is to be rendered, or None if no frame is
+    /// requested. A frame is due right away if the last one is at least an interval old.
+    pub(crate) fn due(&self) -> Option<Instant> {
+        if !self.dirty {
+            return None;
+        }
+        Some(self.last_frame.map_or_else(Instant::now, |last| last + self.interval))
+    }
+
+    /// Returns whether the requested frame is due at `now`.
+    pub(crate) fn is_due(&self, now: Instant) -> bool {
+        self.dirty && self.last_frame.is_none_or(|last| last + self.interval <= now)
+    }
+
+    /// Records that a frame was rendered at `now`.
+    pub(crate) fn rendered(&mut self, now: Instant) {
+        self.dirty = false;
+        self.last_frame = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let mut frames = Frames::new(Duration::from_millis(50));
+        assert_eq!(None, frames.due());
+        frames.request();
+        assert!(frames.is_due(Instant::now()));
+
+        let now = Instant::now();
+        frames.rendered(now);
+        assert!(!frames.is_due(now));
+        // more requests before the next frame are coalesced into it
+        frames.request();
+        frames.request();
+        assert!(!frames.is_due(now + Duration::from_millis(10)));
+        assert_eq!(Some(now + Duration::from_millis(50)), frames.due());
+        assert!(frames.is_due(now + Duration::from_millis(50)));
+        frames.rendered(now + Duration::from_millis(50));
+        assert_eq!(None, frames.due());
+    }
+}
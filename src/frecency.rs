@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::completion::Suggestion;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Counts how often and how recently suggestions are accepted, to rank the frequently
+/// used ones first ("frecency"), see `Prompt::with_frecency`.
+///
+/// Suggestions are told apart by their id, or by their text if they have none.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Frecency {
+    entries: HashMap<String, Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    count: u32,
+    /// Seconds since the Unix epoch.
+    last_used: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn key(suggestion: &Suggestion) -> &str {
+    suggestion.id().unwrap_or(suggestion.text())
+}
+
+impl Frecency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the counts saved with [save](Frecency::save). A missing file is the same
+    /// as an empty one.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e),
+        };
+        let mut frecency = Self::new();
+        for line in text.lines().filter(|l| !l.is_empty()) {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(count), Some(last_used), Some(key)) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid frecency entry: {}", line)));
+            };
+            let invalid = |_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid frecency entry: {}", line));
+            let entry = Entry {
+                count: count.parse().map_err(invalid)?,
+                last_used: last_used.parse().map_err(invalid)?,
+            };
+            frecency.entries.insert(key.to_string(), entry);
+        }
+        Ok(frecency)
+    }
+
+    /// Writes the counts to `path`, one `count<TAB>last used<TAB>key` line per entry.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        for (key, entry) in entries {
+            writeln!(out, "{}\t{}\t{}", entry.count, entry.last_used, key)?;
+        }
+        out.flush()
+    }
+
+    /// Records that `suggestion` was accepted now.
+    pub fn record(&mut self, suggestion: &Suggestion) {
+        self.record_at(suggestion, now());
+    }
+
+    /// Records that `suggestion` was accepted at `time`, in seconds since the Unix epoch.
+    pub fn record_at(&mut self, suggestion: &Suggestion, time: u64) {
+        let key = key(suggestion);
+        // keeps the file format line based
+        if key.contains('\n') {
+            return;
+        }
+        let entry = self.entries.entry(key.to_string()).or_insert(Entry { count: 0, last_used: time });
+        entry.count = entry.count.saturating_add(1);
+        entry.last_used = entry.last_used.max(time);
+    }
+
+    /// Returns the number of times `suggestion` was accepted.
+    pub fn count(&self, suggestion: &Suggestion) -> u32 {
+        self.entries.get(key(suggestion)).map_or(0, |e| e.count)
+    }
+
+    /// Returns the score of `suggestion` at `time`: the number of times it was accepted,
+    /// weighted by how long ago it was last accepted.
+    pub fn score_at(&self, suggestion: &Suggestion, time: u64) -> f64 {
+        let Some(entry) = self.entries.get(key(suggestion)) else {
+            return 0.0;
+        };
+        let weight = match time.saturating_sub(entry.last_used) {
+            age if age < HOUR => 4.0,
+            age if age < DAY => 2.0,
+            age if age < WEEK => 1.0,
+            _ => 0.5,
+        };
+        entry.count as f64 * weight
+    }
+
+    /// Sorts `suggestions` by decreasing score, keeping the completer's order for equal
+    /// scores.
+    pub fn rank(&self, suggestions: &mut [Suggestion]) {
+        self.rank_at(suggestions, now());
+    }
+
+    /// Is the same as [rank](Frecency::rank), with the scores at `time`.
+    pub fn rank_at(&self, suggestions: &mut [Suggestion], time: u64) {
+        if self.entries.is_empty() {
+            return;
+        }
+        suggestions.sort_by(|a, b| self.score_at(b, time).total_cmp(&self.score_at(a, time)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn titles(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.text()).collect()
+    }
+
+    #[test]
+    fn test_frecency_rank() {
+        let [select, insert, update] = ["select", "insert", "update"].map(|s| Suggestion::with_title(s.to_string()));
+        let mut f = Frecency::new();
+        let t = 10_000_000;
+        f.record_at(&update, t - 2 * WEEK);
+        f.record_at(&update, t - 2 * WEEK);
+        f.record_at(&update, t - 2 * WEEK);
+        f.record_at(&insert, t - 10);
+        assert_eq!(3, f.count(&update));
+        assert_eq!(1.5, f.score_at(&update, t));
+        assert_eq!(4.0, f.score_at(&insert, t));
+        assert_eq!(0.0, f.score_at(&select, t));
+
+        let mut suggestions = vec![select.clone(), insert.clone(), update.clone()];
+        f.rank_at(&mut suggestions, t);
+        assert_eq!(vec!["insert", "update", "select"], titles(&suggestions));
+        // a week later, insert was used less often than update
+        f.rank_at(&mut suggestions, t + WEEK);
+        assert_eq!(vec!["update", "insert", "select"], titles(&suggestions));
+    }
+
+    #[test]
+    fn test_frecency_uses_id() {
+        let mut f = Frecency::new();
+        f.record_at(&Suggestion::with_title("users".to_string()).with_id("db1.users"), 0);
+        assert_eq!(1, f.count(&Suggestion::with_title("users".to_string()).with_id("db1.users")));
+        assert_eq!(0, f.count(&Suggestion::with_title("users".to_string()).with_id("db2.users")));
+        assert_eq!(0, f.count(&Suggestion::with_title("users".to_string())));
+    }
+
+    #[test]
+    fn test_frecency_save_and_load() {
+        let path = env::temp_dir().join(format!("rusty-prompt-frecency-{}", std::process::id()));
+        let mut f = Frecency::new();
+        f.record_at(&Suggestion::with_title("git\tcommit".to_string()), 42);
+        f.record_at(&Suggestion::with_title("ls".to_string()), 7);
+        f.record_at(&Suggestion::with_title("a\nb".to_string()), 7);
+        f.save(&path).unwrap();
+        assert_eq!("1\t42\tgit\tcommit\n1\t7\tls\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(f, Frecency::load(&path).unwrap());
+
+        fs::write(&path, "x\t1\tls\n").unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, Frecency::load(&path).unwrap_err().kind());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Frecency::new(), Frecency::load(&path).unwrap());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::buffer::Buffer;
+
+/// Texts the edits insert, mixing ASCII, multi-byte, wide and combining chars, tabs and
+/// line breaks.
+const PIECES: [&str; 10] = ["a", " ", "\n", "\t", "é", "日本", "👍", "e\u{301}", "\r\n", "x/y-z"];
+
+/// Applies the edits encoded in `data` to a buffer, checking after each one that the
+/// cursor stays in the text and on a char boundary and that the row and column of every
+/// index map back to it. Panics when they don't, for a fuzzer to report, see
+/// `fuzz/fuzz_targets/document_edits.rs`.
+///
+/// Each edit takes two bytes: what to do, and how much or what to insert.
+pub fn document_edits(data: &[u8]) {
+    let mut b = Buffer::new();
+    for edit in data.chunks_exact(2) {
+        let (op, arg) = (edit[0], edit[1]);
+        let count = usize::from(arg % 16);
+        match op % 14 {
+            0 => b.insert_text(PIECES[usize::from(arg) % PIECES.len()], false, true),
+            1 => b.insert_text(PIECES[usize::from(arg) % PIECES.len()], true, true),
+            2 => drop(b.delete_before_cursor(count)),
+            3 => drop(b.delete(count)),
+            4 => b.cursor_left(count),
+            5 => b.cursor_right(count),
+            6 => b.cursor_up(count),
+            7 => b.cursor_down(count),
+            8 => b.cursor_word_left(),
+            9 => b.cursor_word_right(),
+            10 => b.set_cursor_position(usize::from(arg)),
+            11 => b.new_line(arg % 2 == 0),
+            12 => b.swap_characters_before_cursor(),
+            _ => {
+                b.undo();
+            }
+        }
+        check(&b);
+    }
+}
+
+fn check(b: &Buffer) {
+    let d = b.document();
+    let cursor = d.cursor_position();
+    assert!(cursor <= d.char_count(), "cursor {} out of {:?}", cursor, d.text());
+    assert_eq!(d.text(), format!("{}{}", d.text_before_cursor_str(), d.text_after_cursor_str()));
+    for index in 0..=d.char_count() {
+        let (row, col) = d.translate_index_to_position(index);
+        assert_eq!(index, d.translate_row_col_to_index(row, col), "in {:?}", d.text());
+    }
+    d.display_cursor_position();
+    d.get_word_before_cursor();
+    d.get_word_under_cursor();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest::proptest! {
+        #[test]
+        fn prop_document_edits(data in proptest::collection::vec(proptest::num::u8::ANY, 0..200)) {
+            document_edits(&data);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+/// Bonus for a character matched right after the previous one.
+const CONSECUTIVE_BONUS: u32 = 4;
+/// Bonus for a character matched at the start of a word.
+const WORD_START_BONUS: u32 = 3;
+/// Penalty for each character skipped between two matched ones, capped per gap.
+const MAX_GAP_PENALTY: u32 = 3;
+
+/// Scores how well `text` matches `pattern` as a fuzzy subsequence, in the style of fzf:
+/// the characters of `pattern` have to appear in `text` in order, and matches that are
+/// consecutive or start words score higher. Returns None if `text` does not match.
+///
+/// The match ignores case unless `pattern` has an uppercase character. An empty pattern
+/// matches everything with a score of zero.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<u32> {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let fold = |c: char| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+    let pattern = pattern.chars().map(fold).collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let Some(&first) = pattern.first() else {
+        return Some(0);
+    };
+    // tries every start of the first character, since the leftmost one is not always
+    // the best, e.g. "cl" in "cargo clippy"
+    (0..text.len())
+        .filter(|&start| fold(text[start]) == first)
+        .filter_map(|start| score_from(&pattern, &text, start, fold))
+        .max()
+}
+
+/// Scores the match of `pattern` in `text` starting at `start`, taking each following
+/// character of the pattern at its first occurrence.
+fn score_from<F: Fn(char) -> char>(pattern: &[char], text: &[char], start: usize, fold: F) -> Option<u32> {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let mut i = start;
+    for &p in pattern {
+        while fold(*text.get(i)?) != p {
+            i += 1;
+        }
+        score += 1;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        match previous {
+            Some(previous) if previous + 1 == i => score += CONSECUTIVE_BONUS,
+            Some(previous) => score = score.saturating_sub(((i - previous - 1) as u32).min(MAX_GAP_PENALTY)),
+            None => {}
+        }
+        previous = Some(i);
+        i += 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(Some(0), fuzzy_score("", "anything"));
+        assert_eq!(None, fuzzy_score("gx", "git commit"));
+        assert_eq!(None, fuzzy_score("tig", "git"));
+        assert!(fuzzy_score("gc", "git commit").is_some());
+        assert!(fuzzy_score("GIT", "git").is_none());
+        assert_eq!(fuzzy_score("git", "GIT"), fuzzy_score("git", "git"));
+
+        // consecutive and word start matches rank first
+        assert!(fuzzy_score("cl", "cargo clippy") > fuzzy_score("cl", "cancel"));
+        assert!(fuzzy_score("ls", "ls -la") > fuzzy_score("ls", "lost"));
+        assert!(fuzzy_score("gch", "git checkout") > fuzzy_score("gch", "grep each"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::io::{self, Write};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+
+use crate::commands::Command;
+use crate::key_bind::Key;
+use crate::width::{display_width, truncate_to_width};
+
+/// Rows above the bindings: the title.
+const HEADER_HEIGHT: u16 = 1;
+
+/// Full-screen list of the key bindings, see `Prompt::with_help_keys`: the bindings of
+/// each category under its name, scrolled with the arrows and closed with Esc.
+struct Help {
+    lines: Vec<String>,
+    /// Index of the first line shown.
+    scroll: usize,
+}
+
+impl Help {
+    fn new(key_map: &[(Key, &Command)]) -> Self {
+        Self { lines: lines(key_map), scroll: 0 }
+    }
+
+    /// Scrolls the `height` rows shown at once. Returns true if the help is closed.
+    fn feed(&mut self, key: Key, height: usize) -> bool {
+        let last = self.lines.len().saturating_sub(height);
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::F(1) | KeyCode::Char('q') => return true,
+            _ if key == Key::ctrl('c') || key == Key::ctrl('g') => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll += height.max(1),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(height.max(1)),
+            KeyCode::Home => self.scroll = 0,
+            KeyCode::End => self.scroll = last,
+            _ => {}
+        }
+        self.scroll = self.scroll.min(last);
+        false
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W, col: u16, row: u16) -> io::Result<()> {
+        let width = col as usize;
+        let height = row.saturating_sub(HEADER_HEIGHT) as usize;
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(height));
+        queue!(out, cursor::Hide, cursor::MoveTo(0, 0), terminal::Clear(ClearType::All))?;
+        let title = "Key bindings  (↑/↓ to scroll, Esc to close)";
+        queue!(out, SetAttribute(Attribute::Bold), Print(truncate_to_width(title, width)))?;
+        queue!(out, SetAttribute(Attribute::Reset))?;
+        for (y, line) in self.lines.iter().skip(self.scroll).take(height).enumerate() {
+            queue!(out, cursor::MoveTo(0, HEADER_HEIGHT + y as u16))?;
+            if !line.starts_with(' ') {
+                queue!(out, SetAttribute(Attribute::Underlined))?;
+            }
+            queue!(out, Print(truncate_to_width(line, width)), SetAttribute(Attribute::Reset))?;
+        }
+        out.flush()
+    }
+}
+
+/// Returns the lines of the help: the name of each category followed by its bindings,
+/// keys first, in the order of the key map.
+fn lines(key_map: &[(Key, &Command)]) -> Vec<String> {
+    let mut bindings: Vec<(Key, &Command)> = Vec::new();
+    for &(key, command) in key_map {
+        if !bindings.iter().any(|&(k, c)| k == key && c.name == command.name) {
+            bindings.push((key, command));
+        }
+    }
+    bindings.sort_by_key(|(_, command)| command.category);
+    let key_width = bindings.iter().map(|(key, _)| display_width(&key.to_string())).max().unwrap_or(0);
+
+    let mut lines = Vec::new();
+    let mut category = None;
+    for (key, command) in bindings {
+        if category != Some(command.category) {
+            if category.is_some() {
+                lines.push(String::new());
+            }
+            lines.push(command.category.to_string());
+            category = Some(command.category);
+        }
+        let key = key.to_string();
+        lines.push(format!("  {}{}  {}", key, " ".repeat(key_width - display_width(&key)), command.label));
+    }
+    lines
+}
+
+/// Shows the bindings of `key_map` until the help is closed. The help is shown in the
+/// alternate screen, so that the prompt is left as it was, unless the terminal is already
+/// showing it, in which case the caller has to redraw it.
+pub(crate) fn show(key_map: &[(Key, &Command)], alternate: bool) -> io::Result<()> {
+    let mut out = io::stdout();
+    if alternate {
+        queue!(out, terminal::EnterAlternateScreen)?;
+    }
+    let result = run(&mut out, &mut Help::new(key_map));
+    if alternate {
+        queue!(out, terminal::LeaveAlternateScreen)?;
+    }
+    queue!(out, cursor::Show)?;
+    out.flush()?;
+    result
+}
+
+fn run<W: Write>(out: &mut W, help: &mut Help) -> io::Result<()> {
+    let (mut col, mut row) = terminal::size()?;
+    loop {
+        help.draw(out, col, row)?;
+        match event::read()? {
+            Event::Key(event) if event.kind != KeyEventKind::Release => {
+                let height = row.saturating_sub(HEADER_HEIGHT) as usize;
+                if help.feed(event.into(), height) {
+                    return Ok(());
+                }
+            }
+            Event::Resize(c, r) => (col, row) = (c, r),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::width::strip_ansi;
+
+    fn key_map() -> Vec<(Key, &'static Command)> {
+        let command = |name| Command::named(name).unwrap();
+        vec![
+            (Key::plain(KeyCode::Tab), command("complete")),
+            (Key::plain(KeyCode::Enter), command("accept-line")),
+            (Key::ctrl('a'), command("beginning-of-line")),
+            (Key::plain(KeyCode::Home), command("beginning-of-line")),
+            (Key::ctrl('a'), command("beginning-of-line")),
+            (Key::new(KeyCode::Backspace, crossterm::event::KeyModifiers::ALT), command("backward-kill-word")),
+        ]
+    }
+
+    #[test]
+    fn test_help_lines() {
+        let expected = vec![
+            "Movement",
+            "  Ctrl-A         line start",
+            "  Home           line start",
+            "",
+            "Editing",
+            "  Alt-Backspace  cut word before",
+            "",
+            "Completion",
+            "  Tab            complete",
+            "",
+            "Prompt",
+            "  Enter          run",
+        ];
+        assert_eq!(expected, lines(&key_map()));
+    }
+
+    #[test]
+    fn test_help_scroll() {
+        let mut help = Help::new(&key_map());
+        assert!(!help.feed(Key::plain(KeyCode::Down), 4));
+        assert!(!help.feed(Key::plain(KeyCode::PageDown), 4));
+        assert_eq!(5, help.scroll);
+        help.feed(Key::plain(KeyCode::End), 4);
+        assert_eq!(8, help.scroll);
+        help.feed(Key::plain(KeyCode::PageDown), 4);
+        assert_eq!(8, help.scroll);
+
+        let mut out = Vec::new();
+        help.draw(&mut out, 40, 5).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let out = strip_ansi(&out);
+        assert!(out.contains("Key bindings"));
+        assert!(out.contains("Enter          run"));
+        assert!(!out.contains("Ctrl-A"));
+        assert!(help.feed(Key::plain(KeyCode::Esc), 4));
+    }
+}
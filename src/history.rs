@@ -0,0 +1,419 @@
+use std::io;
+use std::path::Path;
+
+use crate::buffer::Buffer;
+use crate::history_store::{read_entries, write_entries};
+
+/// An entry of the [History], with the scope it was entered in, see
+/// [set_scope](History::set_scope).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryEntry {
+    pub text: String,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub scope: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Self { text: text.into(), scope: None }
+    }
+
+    pub fn with_scope<S: Into<String>>(mut self, scope: S) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+}
+
+/// History stores the texts that are entered.
+///
+/// Entries can be partitioned by a scope, e.g. the working directory or the connected
+/// database: once a scope is set, only the entries entered in it are browsed, unless
+/// [all scopes](History::set_all_scopes) are shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<SavedEntry>", into = "Vec<HistoryEntry>")
+)]
+pub struct History {
+    histories: Vec<String>,
+    /// Scope of each entry of `histories`.
+    scopes: Vec<Option<String>>,
+    scope: Option<String>,
+    all_scopes: bool,
+    /// The histories in scope plus the text being edited, as changed while browsing.
+    tmp: Vec<String>,
+    /// Where the cursor was in each entry of `tmp` when it was left, so that coming back
+    /// to the line being typed puts the cursor back too.
+    cursors: Vec<Option<usize>>,
+    selected: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Vec::<HistoryEntry>::new().into()
+    }
+
+    /// Reads the entries saved with [save](History::save). A missing file is the same as
+    /// an empty one.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        read_entries(path).map(Into::into)
+    }
+
+    /// Writes the entries to `path`, one per line. Multi-line entries stay single
+    /// entries, with their line breaks written as `\n` and backslashes as `\\`. The scope
+    /// of an entry, if any, comes first and is followed by a tab.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write_entries(path, &self.entries())
+    }
+
+    /// Returns the texts of all the entries, in every scope.
+    pub fn histories(&self) -> &[String] {
+        &self.histories
+    }
+
+    /// Returns all the entries with their scopes, oldest first.
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.histories.iter()
+            .zip(&self.scopes)
+            .map(|(text, scope)| HistoryEntry { text: text.clone(), scope: scope.clone() })
+            .collect()
+    }
+
+    /// Returns the texts of the entries browsed in the current scope, oldest first.
+    pub fn in_scope(&self) -> Vec<&str> {
+        self.histories.iter()
+            .zip(&self.scopes)
+            .filter(|(_, scope)| self.all_scopes || self.scope.is_none() || **scope == self.scope)
+            .map(|(text, _)| text.as_str())
+            .collect()
+    }
+
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+
+    /// Sets the scope given to new entries. Only the entries of the same scope are
+    /// browsed, or all of them if `scope` is None. Stops browsing.
+    pub fn set_scope(&mut self, scope: Option<String>) {
+        self.scope = scope;
+        self.clear();
+    }
+
+    pub fn all_scopes(&self) -> bool {
+        self.all_scopes
+    }
+
+    /// Browses the entries of every scope instead of only those of the current one.
+    /// Stops browsing.
+    pub fn set_all_scopes(&mut self, all_scopes: bool) {
+        self.all_scopes = all_scopes;
+        self.clear();
+    }
+
+    /// Saves a new entry in the current scope and stops browsing.
+    pub fn add(&mut self, input: String) {
+        self.histories.push(input);
+        self.scopes.push(self.scope.clone());
+        self.clear();
+    }
+
+    /// Drops the edits made while browsing and goes back to the newest position.
+    pub fn clear(&mut self) {
+        self.tmp = self.in_scope().into_iter().map(str::to_string).collect();
+        self.tmp.push(String::new());
+        self.cursors = vec![None; self.tmp.len()];
+        self.selected = self.tmp.len() - 1;
+    }
+
+    /// Replaces the text of `buf` with the previous entry, keeping the current text so
+    /// that it comes back on [newer](History::newer). Returns false if there is none.
+    pub fn older(&mut self, buf: &mut Buffer) -> bool {
+        if self.selected == 0 {
+            return false;
+        }
+        self.leave(buf);
+        self.selected -= 1;
+        self.select(buf);
+        true
+    }
+
+    /// Replaces the text of `buf` with the next entry. Returns false if there is none.
+    pub fn newer(&mut self, buf: &mut Buffer) -> bool {
+        if self.selected + 1 >= self.tmp.len() {
+            return false;
+        }
+        self.leave(buf);
+        self.selected += 1;
+        self.select(buf);
+        true
+    }
+
+    /// Expands the history designators of `input` like a shell: `!!` is the last entry,
+    /// `!n` the n-th entry, `!-n` the n-th last one and `!text` the last one starting
+    /// with `text`, counted among the entries in scope. A `!` followed by a space or `=`,
+    /// at the end, or within single quotes is left as is. Returns an error naming the
+    /// designator that matches no entry.
+    pub fn expand(&self, input: &str) -> Result<String, String> {
+        let entries = self.in_scope();
+        let mut expanded = String::with_capacity(input.len());
+        let mut quoted = false;
+        let mut rest = input;
+        while let Some(c) = rest.chars().next() {
+            let after = &rest[c.len_utf8()..];
+            let designator = match after.chars().next() {
+                Some(next) if c == '!' && !quoted && !next.is_whitespace() && next != '=' => designator(after),
+                _ => None,
+            };
+            let Some(designator) = designator else {
+                quoted ^= c == '\'';
+                expanded.push(c);
+                rest = after;
+                continue;
+            };
+            let entry = match designator {
+                "!" => entries.last(),
+                _ => match designator.parse::<isize>() {
+                    Ok(n) if n > 0 => entries.get(n as usize - 1),
+                    Ok(n) if n < 0 => entries.len().checked_sub(n.unsigned_abs()).map(|i| &entries[i]),
+                    Ok(_) => None,
+                    Err(_) => entries.iter().rev().find(|e| e.starts_with(designator)),
+                },
+            };
+            let entry = entry.ok_or_else(|| format!("!{}: event not found", designator))?;
+            expanded.push_str(entry);
+            rest = &after[designator.len()..];
+        }
+        Ok(expanded)
+    }
+
+    /// Keeps the text and the cursor of the selected entry.
+    fn leave(&mut self, buf: &Buffer) {
+        self.tmp[self.selected] = buf.text();
+        self.cursors[self.selected] = Some(buf.cursor_position());
+    }
+
+    /// Shows the selected entry, with the cursor where it was left or at the end.
+    fn select(&self, buf: &mut Buffer) {
+        buf.set_text(self.tmp[self.selected].clone());
+        buf.set_cursor_position(self.cursors[self.selected].unwrap_or(buf.len_chars()));
+    }
+}
+
+/// Returns the designator at the start of `text`, which follows a `!`.
+fn designator(text: &str) -> Option<&str> {
+    if text.starts_with('!') {
+        return Some("!");
+    }
+    // `!12abc` is entry 12 followed by `abc`
+    let sign = usize::from(text.starts_with('-'));
+    let digits = text[sign..].chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        return Some(&text[..sign + digits]);
+    }
+    let end = text.find(|c: char| c.is_whitespace() || ";|&".contains(c)).unwrap_or(text.len());
+    Some(&text[..end]).filter(|w| !w.is_empty())
+}
+
+impl From<Vec<String>> for History {
+    fn from(histories: Vec<String>) -> Self {
+        histories.into_iter().map(HistoryEntry::new).collect::<Vec<_>>().into()
+    }
+}
+
+impl From<Vec<HistoryEntry>> for History {
+    fn from(entries: Vec<HistoryEntry>) -> Self {
+        let (histories, scopes) = entries.into_iter().map(|e| (e.text, e.scope)).unzip();
+        let mut history = Self {
+            histories,
+            scopes,
+            scope: None,
+            all_scopes: false,
+            tmp: Vec::new(),
+            cursors: Vec::new(),
+            selected: 0,
+        };
+        history.clear();
+        history
+    }
+}
+
+impl From<History> for Vec<String> {
+    fn from(history: History) -> Self {
+        history.histories
+    }
+}
+
+impl From<History> for Vec<HistoryEntry> {
+    fn from(history: History) -> Self {
+        history.entries()
+    }
+}
+
+/// An entry of a serialized [History], which used to be saved as plain texts.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum SavedEntry {
+    Text(String),
+    Entry(HistoryEntry),
+}
+
+#[cfg(feature = "serde")]
+impl From<Vec<SavedEntry>> for History {
+    fn from(entries: Vec<SavedEntry>) -> Self {
+        let entries = entries.into_iter().map(|entry| match entry {
+            SavedEntry::Text(text) => HistoryEntry::new(text),
+            SavedEntry::Entry(entry) => entry,
+        });
+        entries.collect::<Vec<_>>().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_history_clear() {
+        let mut h = History::new();
+        h.add("foo".to_string());
+        h.clear();
+        assert_eq!(vec!["foo".to_string(), "".to_string()], h.tmp);
+        assert_eq!(1, h.selected);
+    }
+
+    #[test]
+    fn test_history_add() {
+        let mut h = History::new();
+        h.add("echo 1".to_string());
+        assert_eq!(vec!["echo 1".to_string()], h.histories);
+        assert_eq!(vec!["echo 1".to_string(), "".to_string()], h.tmp);
+        assert_eq!(1, h.selected);
+    }
+
+    #[test]
+    fn test_history_older_and_newer() {
+        let mut h = History::from(vec!["echo 1".to_string(), "echo 2".to_string()]);
+        let mut buf = Buffer::new();
+        buf.insert_text("echo 3", false, true);
+
+        assert!(h.older(&mut buf));
+        assert_eq!("echo 2", buf.text());
+        assert_eq!(6, buf.cursor_position());
+        assert!(h.older(&mut buf));
+        assert_eq!("echo 1", buf.text());
+        assert!(!h.older(&mut buf));
+
+        // edits are kept while browsing
+        buf.insert_text("0", false, true);
+        assert!(h.newer(&mut buf));
+        assert!(h.older(&mut buf));
+        assert_eq!("echo 10", buf.text());
+        assert!(h.newer(&mut buf));
+        assert!(h.newer(&mut buf));
+        assert_eq!("echo 3", buf.text());
+        assert!(!h.newer(&mut buf));
+
+        // the line being typed comes back with its cursor
+        buf.set_cursor_position(2);
+        assert!(h.older(&mut buf));
+        assert_eq!(6, buf.cursor_position());
+        assert!(h.newer(&mut buf));
+        assert_eq!("echo 3", buf.text());
+        assert_eq!(2, buf.cursor_position());
+
+        let mut empty = History::new();
+        assert!(!empty.older(&mut buf));
+        assert!(!empty.newer(&mut buf));
+    }
+
+    #[test]
+    fn test_history_multi_line() {
+        let entries = vec!["for i in 1 2\ndo echo $i\ndone".to_string(), r"echo a\nb\\".to_string()];
+        let mut h = History::from(entries.clone());
+        let mut buf = Buffer::new();
+        assert!(h.older(&mut buf));
+        assert!(h.older(&mut buf));
+        assert_eq!(entries[0], buf.text());
+        assert_eq!(3, buf.line_count());
+
+        let path = std::env::temp_dir().join(format!("rusty-prompt-history-{}", std::process::id()));
+        h.save(&path).unwrap();
+        assert_eq!("for i in 1 2\\ndo echo $i\\ndone\necho a\\\\nb\\\\\\\\\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(entries, History::load(&path).unwrap().histories());
+        fs::write(&path, "ok\nbad \\x\n").unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, History::load(&path).unwrap_err().kind());
+        fs::remove_file(&path).unwrap();
+        assert!(History::load(&path).unwrap().histories().is_empty());
+    }
+
+    #[test]
+    fn test_history_scope() {
+        let mut h = History::from(vec![
+            HistoryEntry::new("ls"),
+            HistoryEntry::new("select 1").with_scope("prod"),
+            HistoryEntry::new("select 2").with_scope("dev"),
+        ]);
+        assert_eq!(vec!["ls", "select 1", "select 2"], h.in_scope());
+        h.set_scope(Some("prod".to_string()));
+        assert_eq!(vec!["select 1"], h.in_scope());
+        h.add("select 3".to_string());
+        assert_eq!(vec!["select 1", "select 3"], h.in_scope());
+
+        let mut buf = Buffer::new();
+        assert!(h.older(&mut buf));
+        assert!(h.older(&mut buf));
+        assert_eq!("select 1", buf.text());
+        assert!(!h.older(&mut buf));
+
+        h.set_all_scopes(true);
+        assert_eq!(4, h.in_scope().len());
+        h.set_all_scopes(false);
+
+        let path = std::env::temp_dir().join(format!("rusty-prompt-history-scope-{}", std::process::id()));
+        h.save(&path).unwrap();
+        assert_eq!("ls\nprod\tselect 1\ndev\tselect 2\nprod\tselect 3\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(h.entries(), History::load(&path).unwrap().entries());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_history_expand() {
+        let h = History::from(vec!["ls -la".to_string(), "git status".to_string(), "make test".to_string()]);
+        assert_eq!(Ok("make test".to_string()), h.expand("!!"));
+        assert_eq!(Ok("sudo make test".to_string()), h.expand("sudo !!"));
+        assert_eq!(Ok("ls -la | wc".to_string()), h.expand("!1 | wc"));
+        assert_eq!(Ok("git status; ls -la".to_string()), h.expand("!-2; !l"));
+        assert_eq!(Ok("git status".to_string()), h.expand("!git"));
+        assert_eq!(Ok("ls -la/".to_string()), h.expand("!1/"));
+        // left as is
+        assert_eq!(Ok("echo hi! != 'a!b' !".to_string()), h.expand("echo hi! != 'a!b' !"));
+        assert_eq!(Err("!4: event not found".to_string()), h.expand("!4"));
+        assert_eq!(Err("!-9: event not found".to_string()), h.expand("!-9"));
+        assert_eq!(Err("!rm: event not found".to_string()), h.expand("!rm"));
+        assert_eq!(Err("!!: event not found".to_string()), History::new().expand("!!"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_history_serde() {
+        let h = History::from(vec![HistoryEntry::new("ls"), HistoryEntry::new("cd /").with_scope("/tmp")]);
+        let json = serde_json::to_string(&h).unwrap();
+        assert_eq!(r#"[{"text":"ls"},{"text":"cd /","scope":"/tmp"}]"#, json);
+        let restored: History = serde_json::from_str(&json).unwrap();
+        assert_eq!(h, restored);
+        assert_eq!(h.entries(), restored.entries());
+        // histories saved before scopes were added
+        let h = History::from(vec!["ls".to_string(), "cd /".to_string()]);
+        assert_eq!(h, serde_json::from_str(r#"["ls","cd /"]"#).unwrap());
+    }
+}
@@ -0,0 +1,235 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::history::HistoryEntry;
+
+/// Keeps the history across sessions, see `Prompt::with_history_store`.
+///
+/// [FileHistory] is the default implementation. Others can back the history with e.g. a
+/// database or a service syncing it between machines.
+pub trait HistoryStore {
+    /// Returns the saved entries, oldest first.
+    fn load(&self) -> io::Result<Vec<HistoryEntry>>;
+
+    /// Saves a new entry after the others.
+    fn append(&mut self, entry: &HistoryEntry) -> io::Result<()>;
+
+    /// Returns the saved entries containing `query`, newest first. With a `scope`, only
+    /// the entries of that scope are returned.
+    fn search(&self, query: &str, scope: Option<&str>) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries = self.load()?;
+        entries.retain(|entry| entry.text.contains(query) && scope.is_none_or(|s| entry.scope.as_deref() == Some(s)));
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Drops all but the `max` newest entries.
+    fn prune(&mut self, max: usize) -> io::Result<()>;
+}
+
+/// Stores the history in a file, one entry per line, in the format of `History::save`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHistory {
+    path: PathBuf,
+}
+
+impl FileHistory {
+    /// A store in the file at `path`, created on the first append. A missing file is the
+    /// same as an empty one.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl HistoryStore for FileHistory {
+    fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        read_entries(&self.path)
+    }
+
+    fn append(&mut self, entry: &HistoryEntry) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", format_entry(entry))
+    }
+
+    fn prune(&mut self, max: usize) -> io::Result<()> {
+        let entries = self.load()?;
+        if entries.len() <= max {
+            return Ok(());
+        }
+        write_entries(&self.path, &entries[entries.len() - max..])
+    }
+}
+
+/// Reads entries written by [write_entries]. A missing file is the same as an empty one.
+pub(crate) fn read_entries<P: AsRef<Path>>(path: P) -> io::Result<Vec<HistoryEntry>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    text.lines()
+        .map(|line| parse_entry(line).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid history entry: {}", line))
+        }))
+        .collect()
+}
+
+/// Writes `entries` to `path`, one per line, see [replace_file].
+pub(crate) fn write_entries<P: AsRef<Path>>(path: P, entries: &[HistoryEntry]) -> io::Result<()> {
+    replace_file(path.as_ref(), |out| {
+        for entry in entries {
+            writeln!(out, "{}", format_entry(entry))?;
+        }
+        Ok(())
+    })
+}
+
+/// Replaces the file at `path` with what `write` writes. It is written to a temporary
+/// file next to it, which is then renamed over it, so that a crash or a full disk leaves
+/// the old file whole.
+pub(crate) fn replace_file<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut io::BufWriter<fs::File>) -> io::Result<()>,
+{
+    let mut name = path.file_name().ok_or(io::ErrorKind::InvalidInput)?.to_os_string();
+    name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(name);
+    let result = write_then_rename(&tmp, path, write);
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn write_then_rename<F>(tmp: &Path, path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut io::BufWriter<fs::File>) -> io::Result<()>,
+{
+    let mut out = io::BufWriter::new(fs::File::create(tmp)?);
+    write(&mut out)?;
+    let file = out.into_inner().map_err(io::IntoInnerError::into_error)?;
+    // keeps e.g. a history readable only by its owner so
+    if let Ok(metadata) = fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.sync_all()?;
+    fs::rename(tmp, path)
+}
+
+/// Returns the line of `entry` in a history file: its escaped text, preceded by its
+/// escaped scope and a tab if it has one.
+pub(crate) fn format_entry(entry: &HistoryEntry) -> String {
+    match &entry.scope {
+        Some(scope) => format!("{}\t{}", escape(scope), escape(&entry.text)),
+        None => escape(&entry.text),
+    }
+}
+
+/// Reverses [format_entry], or returns None if `line` is not a valid entry.
+pub(crate) fn parse_entry(line: &str) -> Option<HistoryEntry> {
+    Some(match line.split_once('\t') {
+        Some((scope, text)) => HistoryEntry::new(unescape(text)?).with_scope(unescape(scope)?),
+        None => HistoryEntry::new(unescape(line)?),
+    })
+}
+
+fn escape(entry: &str) -> String {
+    let mut escaped = String::with_capacity(entry.len());
+    for c in entry.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [escape], or returns None on an unknown escape sequence.
+fn unescape(line: &str) -> Option<String> {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            entry.push(c);
+            continue;
+        }
+        entry.push(match chars.next()? {
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            _ => return None,
+        });
+    }
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_history() {
+        let path = std::env::temp_dir().join(format!("rusty-prompt-file-history-{}", std::process::id()));
+        let mut store = FileHistory::new(&path);
+        assert!(store.load().unwrap().is_empty());
+        let entries = [
+            HistoryEntry::new("ls"),
+            HistoryEntry::new("git status").with_scope("/src/app"),
+            HistoryEntry::new("for f in *\ndo git add $f\ndone").with_scope("/src/lib"),
+            HistoryEntry::new("printf 'a\tb'"),
+        ];
+        for entry in &entries {
+            store.append(entry).unwrap();
+        }
+        assert_eq!(entries.to_vec(), store.load().unwrap());
+        assert_eq!(vec![entries[2].clone(), entries[1].clone()], store.search("git", None).unwrap());
+        assert_eq!(vec![entries[1].clone()], store.search("git", Some("/src/app")).unwrap());
+
+        store.prune(2).unwrap();
+        assert_eq!(entries[2..].to_vec(), store.load().unwrap());
+        store.prune(5).unwrap();
+        assert_eq!(2, store.load().unwrap().len());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let dir = std::env::temp_dir().join(format!("rusty-prompt-replace-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history");
+        fs::write(&path, "ls\npwd\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        // a failed write leaves the file as it was
+        let failed = replace_file(&path, |out| {
+            writeln!(out, "pwd")?;
+            Err(io::Error::other("disk full"))
+        });
+        assert!(failed.is_err());
+        assert_eq!("ls\npwd\n", fs::read_to_string(&path).unwrap());
+
+        FileHistory::new(&path).prune(1).unwrap();
+        assert_eq!("pwd\n", fs::read_to_string(&path).unwrap());
+        // no temporary file is left behind
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(0o600, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use std::env;
+
+/// Returns whether the terminal is known to render OSC 8 hyperlinks.
+///
+/// `FORCE_HYPERLINK=1` or `FORCE_HYPERLINK=0` overrides the detection. Otherwise the
+/// terminal is recognized from the variables it sets, and unknown terminals are assumed
+/// not to support hyperlinks, since some of them print the escape sequence as text.
+pub fn supports_hyperlinks() -> bool {
+    detect(|name| env::var(name).ok())
+}
+
+fn detect<F: Fn(&str) -> Option<String>>(var: F) -> bool {
+    if let Some(force) = var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    if var("CI").is_some() || var("TERM").is_some_and(|t| t == "dumb") {
+        return false;
+    }
+    if var("WT_SESSION").is_some() || var("KONSOLE_VERSION").is_some() || var("DOMTERM").is_some() {
+        return true;
+    }
+    if var("VTE_VERSION").and_then(|v| v.parse::<u32>().ok()).is_some_and(|v| v >= 5000) {
+        return true;
+    }
+    if let Some(program) = var("TERM_PROGRAM") {
+        if ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper", "Tabby"].contains(&program.as_str()) {
+            return true;
+        }
+    }
+    var("TERM").is_some_and(|t| ["kitty", "alacritty", "foot", "ghostty", "wezterm"].iter().any(|n| t.contains(n)))
+}
+
+/// Returns the escape sequence starting a link to `url`.
+pub(crate) fn start(url: &str) -> String {
+    // control characters would end the sequence early
+    let url = url.chars().filter(|c| !c.is_control()).collect::<String>();
+    format!("\x1b]8;;{}\x1b\\", url)
+}
+
+/// Escape sequence ending a link.
+pub(crate) const END: &str = "\x1b]8;;\x1b\\";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect_with(vars: &[(&str, &str)]) -> bool {
+        detect(|name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()))
+    }
+
+    #[test]
+    fn test_detect_hyperlinks() {
+        assert!(!detect_with(&[]));
+        assert!(!detect_with(&[("TERM", "xterm-256color")]));
+        assert!(detect_with(&[("TERM", "xterm-kitty")]));
+        assert!(detect_with(&[("TERM_PROGRAM", "iTerm.app")]));
+        assert!(detect_with(&[("VTE_VERSION", "6003")]));
+        assert!(!detect_with(&[("VTE_VERSION", "4600")]));
+        assert!(!detect_with(&[("TERM_PROGRAM", "WezTerm"), ("CI", "true")]));
+        assert!(detect_with(&[("FORCE_HYPERLINK", "1"), ("TERM", "dumb")]));
+        assert!(!detect_with(&[("FORCE_HYPERLINK", "0"), ("WT_SESSION", "1")]));
+    }
+
+    #[test]
+    fn test_start() {
+        assert_eq!("\x1b]8;;https://example.com/a\x1b\\", start("https://example.com/\x07a"));
+    }
+}
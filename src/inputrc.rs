@@ -0,0 +1,357 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::commands::Command;
+use crate::key_bind::{Key, KeyBind, KeyBindMode};
+
+/// The practical subset of a readline init file, `~/.inputrc`, that applies to the
+/// prompt, see `Prompt::with_inputrc`.
+///
+/// Supported are key bindings to the readline functions the prompt has, in either the
+/// `"\e[1;5C": forward-word` or the `Control-u: unix-line-discard` form, and the
+/// `editing-mode` and `completion-ignore-case` variables. `$if mode=emacs` and
+/// `$if term=…` blocks are read, other `$if` blocks, e.g. for another application, are
+/// not. Everything else is skipped and listed in [skipped](Inputrc::skipped).
+#[derive(Debug, Clone, Default)]
+pub struct Inputrc {
+    pub bindings: Vec<KeyBind>,
+    pub editing_mode: Option<KeyBindMode>,
+    pub completion_ignore_case: Option<bool>,
+    /// Lines that were not applied, with their line number and why.
+    pub skipped: Vec<(usize, String)>,
+}
+
+impl Inputrc {
+    /// Reads the file named by `$INPUTRC`, or else `~/.inputrc`, as readline does. A
+    /// missing file is the same as an empty one.
+    pub fn load_default() -> io::Result<Self> {
+        let path = match env::var_os("INPUTRC") {
+            Some(path) => PathBuf::from(path),
+            None => match env::var_os("HOME") {
+                Some(home) => Path::new(&home).join(".inputrc"),
+                None => return Ok(Self::default()),
+            },
+        };
+        Self::load(path)
+    }
+
+    /// Reads the init file at `path`. A missing file is the same as an empty one.
+    /// `$include` directives are not followed.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut inputrc = Self::default();
+        // whether each enclosing $if block is read
+        let mut conditions: Vec<bool> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let n = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(directive) = line.strip_prefix('$') {
+                let (name, arg) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+                let enclosing = conditions.iter().all(|&c| c);
+                match name {
+                    "if" => conditions.push(condition(arg.trim())),
+                    "else" => match conditions.last_mut() {
+                        Some(c) => *c = !*c,
+                        None => inputrc.skipped.push((n, "$else without $if".to_string())),
+                    },
+                    "endif" if conditions.pop().is_none() => {
+                        inputrc.skipped.push((n, "$endif without $if".to_string()));
+                    }
+                    "endif" => {}
+                    _ if enclosing => inputrc.skipped.push((n, format!("unsupported directive ${}", name))),
+                    _ => {}
+                }
+                continue;
+            }
+            if !conditions.iter().all(|&c| c) {
+                continue;
+            }
+            if let Err(reason) = inputrc.parse_line(line) {
+                inputrc.skipped.push((n, reason));
+            }
+        }
+        inputrc
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        if let Some(rest) = line.strip_prefix("set ") {
+            let (variable, value) = rest.trim().split_once(char::is_whitespace).ok_or("missing value")?;
+            return self.set(variable, value.trim());
+        }
+        let (keys, function) = split_binding(line).ok_or("not a key binding")?;
+        let key = parse_keys(keys).ok_or_else(|| format!("unsupported key sequence {}", keys))?;
+        if function.starts_with(['"', '\'']) {
+            return Err("macros are not supported".to_string());
+        }
+        let func = Command::named(function)
+            .and_then(|command| command.func)
+            .ok_or_else(|| format!("unsupported function {}", function))?;
+        self.bindings.push(KeyBind { key, func });
+        Ok(())
+    }
+
+    fn set(&mut self, variable: &str, value: &str) -> Result<(), String> {
+        match variable.to_lowercase().as_str() {
+            "editing-mode" => match value {
+                "emacs" => self.editing_mode = Some(KeyBindMode::EmacsKeyBind),
+                _ => return Err(format!("unsupported editing mode {}", value)),
+            },
+            // readline takes anything but off as on
+            "completion-ignore-case" => self.completion_ignore_case = Some(!value.eq_ignore_ascii_case("off")),
+            _ => return Err(format!("unsupported variable {}", variable)),
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the block of `$if condition` is read.
+fn condition(condition: &str) -> bool {
+    match condition.split_once('=') {
+        Some(("mode", mode)) => mode.trim() == "emacs",
+        Some(("term", _)) => true,
+        _ => false,
+    }
+}
+
+/// Splits `keys: function` at the colon ending the keys, which may be quoted and contain
+/// colons themselves.
+fn split_binding(line: &str) -> Option<(&str, &str)> {
+    let end = if let Some(quoted) = line.strip_prefix('"') {
+        let mut escaped = false;
+        let close = quoted.char_indices().find(|&(_, c)| {
+            let close = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            close
+        })?;
+        1 + close.0 + 1
+    } else {
+        line.find(':')?
+    };
+    let (keys, rest) = line.split_at(end);
+    Some((keys.trim(), rest.trim_start().strip_prefix(':')?.trim()))
+}
+
+/// Parses the keys of a binding into a single key press, or returns None for sequences
+/// of several keys or keys the terminal can't report.
+fn parse_keys(keys: &str) -> Option<Key> {
+    match keys.strip_prefix('"').and_then(|k| k.strip_suffix('"')) {
+        Some(quoted) => parse_sequence(&unescape(quoted)?),
+        None => parse_key_name(keys),
+    }
+}
+
+/// Parses a key name such as `Control-u`, `M-f` or `Rubout`.
+fn parse_key_name(name: &str) -> Option<Key> {
+    let mut name = name;
+    let mut modifiers = KeyModifiers::NONE;
+    loop {
+        let lower = name.to_lowercase();
+        if let Some(len) = ["control-", "c-"].iter().find(|p| lower.starts_with(**p)).map(|p| p.len()) {
+            modifiers |= KeyModifiers::CONTROL;
+            name = &name[len..];
+        } else if let Some(len) = ["meta-", "m-"].iter().find(|p| lower.starts_with(**p)).map(|p| p.len()) {
+            modifiers |= KeyModifiers::ALT;
+            name = &name[len..];
+        } else {
+            break;
+        }
+    }
+    let code = match name.to_lowercase().as_str() {
+        "rubout" | "del" => KeyCode::Backspace,
+        "escape" | "esc" => KeyCode::Esc,
+        "newline" | "lfd" | "return" | "ret" => KeyCode::Enter,
+        "space" | "spc" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        _ => {
+            let mut chars = name.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            KeyCode::Char(if modifiers.contains(KeyModifiers::CONTROL) { c.to_ascii_lowercase() } else { c })
+        }
+    };
+    Some(Key::new(code, modifiers))
+}
+
+/// Replaces the escapes of a quoted key sequence with the characters they stand for.
+fn unescape(quoted: &str) -> Option<Vec<char>> {
+    let mut sequence = Vec::new();
+    let mut chars = quoted.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            sequence.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'e' => sequence.push('\x1b'),
+            'C' if chars.peek() == Some(&'-') => {
+                chars.next();
+                let c = match chars.next()? {
+                    '\\' => match chars.next()? {
+                        'e' => '\x1b',
+                        c => c,
+                    },
+                    c => c,
+                };
+                sequence.push(if c == '?' { '\x7f' } else { char::from(c.to_ascii_lowercase() as u8 & 0x1f) });
+            }
+            'M' if chars.peek() == Some(&'-') => {
+                chars.next();
+                sequence.push('\x1b');
+            }
+            'a' => sequence.push('\x07'),
+            'd' => sequence.push('\x7f'),
+            'n' => sequence.push('\n'),
+            'r' => sequence.push('\r'),
+            't' => sequence.push('\t'),
+            c @ ('\\' | '"' | '\'') => sequence.push(c),
+            _ => return None,
+        }
+    }
+    Some(sequence)
+}
+
+/// Parses the characters a terminal sends for a key press.
+fn parse_sequence(sequence: &[char]) -> Option<Key> {
+    match sequence {
+        [c] => Some(single_key(*c)),
+        ['\x1b', c] => {
+            let key = single_key(*c);
+            Some(Key::new(key.code, key.modifiers | KeyModifiers::ALT))
+        }
+        ['\x1b', '[' | 'O', rest @ ..] => parse_csi(rest),
+        _ => None,
+    }
+}
+
+/// Returns the key for a single character, e.g. Ctrl-U for `\x15`.
+fn single_key(c: char) -> Key {
+    match c {
+        '\t' => Key::plain(KeyCode::Tab),
+        '\r' | '\n' => Key::plain(KeyCode::Enter),
+        '\x1b' => Key::plain(KeyCode::Esc),
+        '\x7f' => Key::plain(KeyCode::Backspace),
+        '\x01'..='\x1a' => Key::ctrl(char::from(c as u8 - 1 + b'a')),
+        c => Key::plain(KeyCode::Char(c)),
+    }
+}
+
+/// Parses the rest of a CSI or SS3 sequence after `\e[` or `\eO`, e.g. `1;5C` for
+/// Ctrl-Right.
+fn parse_csi(rest: &[char]) -> Option<Key> {
+    let (last, params) = rest.split_last()?;
+    let params = params.iter().collect::<String>();
+    let mut params = params.split(';');
+    let number = params.next().unwrap_or_default();
+    let modifiers = match params.next() {
+        Some(m) => modifiers(m.parse().ok()?)?,
+        None => KeyModifiers::NONE,
+    };
+    let code = match (last, number) {
+        ('A', _) => KeyCode::Up,
+        ('B', _) => KeyCode::Down,
+        ('C', _) => KeyCode::Right,
+        ('D', _) => KeyCode::Left,
+        ('H', _) | ('~', "1" | "7") => KeyCode::Home,
+        ('F', _) | ('~', "4" | "8") => KeyCode::End,
+        ('~', "2") => KeyCode::Insert,
+        ('~', "3") => KeyCode::Delete,
+        ('~', "5") => KeyCode::PageUp,
+        ('~', "6") => KeyCode::PageDown,
+        _ => return None,
+    };
+    Some(Key::new(code, modifiers))
+}
+
+/// Decodes the modifier parameter of a CSI sequence, 1 plus the sum of Shift 1, Alt 2
+/// and Ctrl 4.
+fn modifiers(param: u8) -> Option<KeyModifiers> {
+    let bits = param.checked_sub(1)?;
+    let mut modifiers = KeyModifiers::NONE;
+    for (bit, modifier) in [(1, KeyModifiers::SHIFT), (2, KeyModifiers::ALT), (4, KeyModifiers::CONTROL)] {
+        if bits & bit != 0 {
+            modifiers |= modifier;
+        }
+    }
+    Some(modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(Some(Key::new(KeyCode::Right, KeyModifiers::CONTROL)), parse_keys(r#""\e[1;5C""#));
+        assert_eq!(Some(Key::new(KeyCode::Delete, KeyModifiers::NONE)), parse_keys(r#""\e[3~""#));
+        assert_eq!(Some(Key::plain(KeyCode::Home)), parse_keys(r#""\eOH""#));
+        assert_eq!(Some(Key::ctrl('w')), parse_keys(r#""\C-w""#));
+        assert_eq!(Some(Key::alt('f')), parse_keys(r#""\M-f""#));
+        assert_eq!(Some(Key::alt('b')), parse_keys(r#""\eb""#));
+        assert_eq!(Some(Key::new(KeyCode::Backspace, KeyModifiers::ALT)), parse_keys(r#""\e\C-?""#));
+        assert_eq!(Some(Key::ctrl('u')), parse_keys("Control-u"));
+        assert_eq!(Some(Key::alt('.')), parse_keys("M-."));
+        assert_eq!(Some(Key::plain(KeyCode::Backspace)), parse_keys("Rubout"));
+        // several keys
+        assert_eq!(None, parse_keys(r#""\C-x\C-e""#));
+    }
+
+    #[test]
+    fn test_parse_inputrc() {
+        let inputrc = Inputrc::parse(
+            r#"
+# word motion
+"\e[1;5C": forward-word
+"\e[1;5D":backward-word
+Control-w: backward-kill-word
+"\C-x\C-r": re-read-init-file
+"\ep": "echo macro"
+set editing-mode emacs
+set completion-ignore-case on
+set bell-style none
+$if mode=vi
+"\C-u": kill-line
+$else
+"\C-u": unix-line-discard
+$endif
+$if Bash
+Space: magic-space
+$endif
+"#,
+        );
+        let bindings = inputrc.bindings.iter().map(|b| b.key).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Key::new(KeyCode::Right, KeyModifiers::CONTROL),
+                Key::new(KeyCode::Left, KeyModifiers::CONTROL),
+                Key::ctrl('w'),
+                Key::ctrl('u'),
+            ],
+            bindings
+        );
+        let mut buffer = Buffer::new();
+        buffer.insert_text("ls -l", false, true);
+        (inputrc.bindings[3].func)(&mut buffer);
+        assert_eq!("", buffer.text());
+        assert_eq!(Some(KeyBindMode::EmacsKeyBind), inputrc.editing_mode);
+        assert_eq!(Some(true), inputrc.completion_ignore_case);
+        let skipped = inputrc.skipped.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+        assert_eq!(vec![6, 7, 10], skipped);
+    }
+}
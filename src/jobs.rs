@@ -0,0 +1,143 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::style::StyledText;
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: usize,
+    /// Ids and names of the jobs that are still running.
+    running: Vec<(usize, String)>,
+    /// Completion messages waiting for the next prompt.
+    notices: Vec<StyledText>,
+}
+
+/// Background jobs of the application, e.g. commands run with `&`, whose completion
+/// messages are printed just before the next prompt, as shells tell of finished jobs,
+/// rather than in the middle of the input being typed. See `Prompt::with_jobs`.
+///
+/// Clones share the same jobs, so the application keeps one to register jobs with while
+/// the prompt holds another.
+#[derive(Debug, Clone, Default)]
+pub struct Jobs {
+    state: Arc<Mutex<State>>,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Registers a job called `name`, e.g. the command it runs, numbered from 1 like
+    /// shell jobs. The returned handle is to be sent wherever the job runs, and
+    /// [done](Job::done) once it is.
+    pub fn register<S: Into<String>>(&self, name: S) -> Job {
+        let mut state = self.state();
+        state.next_id += 1;
+        let id = state.next_id;
+        let name = name.into();
+        state.running.push((id, name.clone()));
+        Job { id, name, jobs: self.clone(), finished: false }
+    }
+
+    /// Returns the ids and names of the jobs that are still running.
+    pub fn running(&self) -> Vec<(usize, String)> {
+        self.state().running.clone()
+    }
+
+    /// Takes the completion messages queued since the last call.
+    pub(crate) fn take_notices(&self) -> Vec<StyledText> {
+        std::mem::take(&mut self.state().notices)
+    }
+
+    fn finish(&self, id: usize, notice: StyledText) {
+        let mut state = self.state();
+        state.running.retain(|(running, _)| *running != id);
+        state.notices.push(notice);
+    }
+}
+
+/// A job registered with [Jobs::register]. Dropping it before it is [done](Job::done),
+/// e.g. when the thread running the job panics, finishes it with an `Exited` or a
+/// `Panicked` notice, so that it doesn't stay running forever.
+#[derive(Debug)]
+pub struct Job {
+    id: usize,
+    name: String,
+    jobs: Jobs,
+    finished: bool,
+}
+
+impl Job {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Queues `message` to be printed before the next prompt, after the job id, e.g.
+    /// `[1] Done    make`.
+    pub fn done<T: Into<StyledText>>(mut self, message: T) {
+        self.finish(message.into());
+    }
+
+    fn finish(&mut self, message: StyledText) {
+        let mut notice = StyledText::from(format!("[{}] ", self.id));
+        for span in message.spans() {
+            notice.push(span.text.as_str(), span.style);
+        }
+        self.jobs.finish(self.id, notice);
+        self.finished = true;
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if !self.finished {
+            let status = if std::thread::panicking() { "Panicked" } else { "Exited" };
+            self.finish(StyledText::from(format!("{status}    {}", self.name)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs() {
+        let jobs = Jobs::new();
+        let make = jobs.register("make");
+        let sleep = jobs.clone().register("sleep 10");
+        assert_eq!((1, "make"), (make.id(), make.name()));
+        assert_eq!(vec![(1, "make".to_string()), (2, "sleep 10".to_string())], jobs.running());
+        assert!(jobs.take_notices().is_empty());
+
+        std::thread::spawn(move || sleep.done(format!("Done    {}", "sleep 10"))).join().unwrap();
+        assert_eq!(vec![(1, "make".to_string())], jobs.running());
+        let notices = jobs.take_notices();
+        assert_eq!(vec!["[2] Done    sleep 10"], notices.iter().map(StyledText::text).collect::<Vec<_>>());
+        assert!(jobs.take_notices().is_empty());
+    }
+
+    #[test]
+    fn test_job_dropped() {
+        let jobs = Jobs::new();
+        let make = jobs.register("make");
+        let result = std::thread::spawn(move || {
+            let _make = make;
+            panic!("build failed");
+        })
+        .join();
+        assert!(result.is_err());
+        drop(jobs.register("sleep 10"));
+        assert!(jobs.running().is_empty());
+        let notices = jobs.take_notices();
+        assert_eq!(vec!["[1] Panicked    make", "[2] Exited    sleep 10"], notices.iter().map(StyledText::text).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::buffer::Buffer;
+use crate::completion::CompletionManager;
+use crate::history::History;
+use crate::key_bind_func::*;
+
+/// A key press, made of a key code and the modifiers held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// A key pressed without modifiers.
+    pub const fn plain(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    /// `c` pressed with Control, e.g. `Key::ctrl('a')` for Ctrl-A.
+    pub const fn ctrl(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    /// `c` pressed with Alt (or Meta), e.g. `Key::alt('f')` for Alt-F.
+    pub const fn alt(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::ALT)
+    }
+}
+
+impl fmt::Display for Key {
+    /// Writes the key as it is shown in the key hints and the help, e.g. `Ctrl-R`,
+    /// `Alt-Backspace` or `Shift-Tab`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl-"),
+            (KeyModifiers::ALT, "Alt-"),
+            (KeyModifiers::SHIFT, "Shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::BackTab => f.write_str("Shift-Tab"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    /// Shift is dropped from characters, since it is already part of the character:
+    /// Alt-{ is `Key::alt('{')` whether or not the terminal reports Shift.
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        if let KeyCode::Char(_) = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self::new(event.code, modifiers)
+    }
+}
+
+/// Function run when its key is pressed.
+pub type KeyBindFunc = fn(&mut Buffer);
+
+/// Function run when its key is pressed, with the context of the prompt, see
+/// `Prompt::with_context_key_bind`.
+pub type ContextKeyBindFunc<Ctx> = fn(&mut Buffer, &mut Ctx);
+
+/// Function run when its key is pressed, before the built-in handling of the key, see
+/// `Prompt::with_key_handler`. Returns true if it handled the key, so that the built-in
+/// handling is skipped.
+pub type KeyHandlerFunc<C, Ctx> = fn(&mut KeyContext<'_, '_, C, Ctx>) -> bool;
+
+/// State of the prompt a [KeyHandlerFunc] can read and change.
+pub struct KeyContext<'k, 'a, C, Ctx> {
+    pub buffer: &'k mut Buffer,
+    /// The suggestions, and whether the completion menu is open and what is selected.
+    pub completion: &'k mut CompletionManager<'a, C>,
+    pub history: &'k History,
+    pub context: &'k mut Ctx,
+    pub(crate) accept_selected: bool,
+}
+
+impl<C, Ctx> KeyContext<'_, '_, C, Ctx> {
+    /// Inserts the selected suggestion in place of the word being completed once the
+    /// handler returns, as pressing a key while a suggestion is selected does, and closes
+    /// the menu. Does nothing if no suggestion is selected.
+    pub fn accept_selected(&mut self) {
+        self.accept_selected = true;
+    }
+}
+
+/// Binds a key to a function.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyBind {
+    pub key: Key,
+    pub func: KeyBindFunc,
+}
+
+/// Selects the key bindings used on top of the common ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyBindMode {
+    /// Only the common key bindings.
+    CommonKeyBind,
+    /// The common key bindings and the emacs ones.
+    #[default]
+    EmacsKeyBind,
+}
+
+/// Key bindings that are always active.
+pub(crate) static COMMON_KEY_BINDINGS: &[KeyBind] = &[
+    KeyBind { key: Key::plain(KeyCode::End), func: go_line_end },
+    KeyBind { key: Key::plain(KeyCode::Home), func: go_line_beginning },
+    KeyBind { key: Key::plain(KeyCode::Delete), func: delete_char },
+    KeyBind { key: Key::plain(KeyCode::Backspace), func: delete_before_char },
+    KeyBind { key: Key::plain(KeyCode::Right), func: go_right_char },
+    KeyBind { key: Key::plain(KeyCode::Left), func: go_left_char },
+    KeyBind { key: Key::new(KeyCode::Right, KeyModifiers::CONTROL), func: go_right_word },
+    KeyBind { key: Key::new(KeyCode::Left, KeyModifiers::CONTROL), func: go_left_word },
+];
@@ -0,0 +1,88 @@
+use crate::buffer::Buffer;
+
+/// Go to the end of the line.
+pub fn go_line_end(buf: &mut Buffer) {
+    let count = buf.document().get_end_of_line_position();
+    buf.cursor_right(count);
+}
+
+/// Go to the beginning of the line.
+pub fn go_line_beginning(buf: &mut Buffer) {
+    let count = buf.document().cursor_position_col();
+    buf.cursor_left(count);
+}
+
+/// Delete the character under the cursor.
+pub fn delete_char(buf: &mut Buffer) {
+    buf.delete(1);
+}
+
+/// Delete the word before the cursor.
+pub fn delete_word(buf: &mut Buffer) {
+    buf.backward_kill_word();
+}
+
+/// Delete the word after the cursor.
+pub fn delete_word_after(buf: &mut Buffer) {
+    buf.kill_word();
+}
+
+/// Delete the character before the cursor.
+pub fn delete_before_char(buf: &mut Buffer) {
+    buf.delete_before_cursor(1);
+}
+
+/// Move forward one character.
+pub fn go_right_char(buf: &mut Buffer) {
+    buf.cursor_right(1);
+}
+
+/// Move backward one character.
+pub fn go_left_char(buf: &mut Buffer) {
+    buf.cursor_left(1);
+}
+
+/// Move forward to the end of the current word.
+pub fn go_right_word(buf: &mut Buffer) {
+    buf.cursor_word_right();
+}
+
+/// Move backward to the start of the previous word.
+pub fn go_left_word(buf: &mut Buffer) {
+    buf.cursor_word_left();
+}
+
+/// Move backward to the blank line before the current paragraph.
+pub fn go_previous_paragraph(buf: &mut Buffer) {
+    buf.cursor_paragraph_backward();
+}
+
+/// Move forward to the blank line after the current paragraph.
+pub fn go_next_paragraph(buf: &mut Buffer) {
+    buf.cursor_paragraph_forward();
+}
+
+/// Delete from the cursor to the end of the line.
+pub fn kill_line(buf: &mut Buffer) {
+    buf.kill_to_end_of_line();
+}
+
+/// Delete from the start of the line to the cursor.
+pub fn kill_line_before(buf: &mut Buffer) {
+    buf.kill_to_start_of_line();
+}
+
+/// Swap the two characters before the cursor.
+pub fn transpose_chars(buf: &mut Buffer) {
+    buf.swap_characters_before_cursor();
+}
+
+/// Undo the last change.
+pub fn undo(buf: &mut Buffer) {
+    buf.undo();
+}
+
+/// Make the last change again, like `.` in vi.
+pub fn repeat_last_change(buf: &mut Buffer) {
+    buf.repeat_last_change();
+}
@@ -0,0 +1,199 @@
+use crate::commands::Command;
+use crate::key_bind::{Key, KeyBindFunc};
+
+/// What a key is bound to in a [Keymap].
+#[derive(Debug, Clone, Copy)]
+pub enum Binding {
+    /// A named command, run as from the command palette.
+    Command(&'static Command),
+    Func(KeyBindFunc),
+}
+
+impl Binding {
+    /// Returns the binding to the command called `name`.
+    pub fn named(name: &str) -> Option<Self> {
+        Command::named(name).map(Binding::Command)
+    }
+
+    /// Returns the command the binding runs, if it is a named one.
+    pub fn command(&self) -> Option<&'static Command> {
+        match *self {
+            Binding::Command(command) => Some(command),
+            Binding::Func(func) => Command::of(func),
+        }
+    }
+}
+
+/// When a [Keymap] given to `Prompt::with_keymap` is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeymapCondition {
+    #[default]
+    Always,
+    /// While the completion menu is open.
+    Completing,
+}
+
+/// Named set of key bindings, a layer of a [KeymapStack]. A key bound in a keymap shadows
+/// the bindings of the layers below, unless the keymap falls through, in which case its
+/// bindings run after those below. Keys it doesn't bind always go through to the layers
+/// below.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    name: String,
+    bindings: Vec<(Key, Binding)>,
+    fallthrough: bool,
+    condition: KeymapCondition,
+}
+
+impl Keymap {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), bindings: Vec::new(), fallthrough: false, condition: KeymapCondition::Always }
+    }
+
+    /// Makes the keys bound in the keymap also run the bindings of the layers below,
+    /// first.
+    pub fn with_fallthrough(mut self) -> Self {
+        self.fallthrough = true;
+        self
+    }
+
+    /// Sets when the prompt uses the keymap, always by default.
+    pub fn with_condition(mut self, condition: KeymapCondition) -> Self {
+        self.condition = condition;
+        self
+    }
+
+    /// Binds `key`, after the bindings it already has in the keymap.
+    pub fn with_binding(mut self, key: Key, binding: Binding) -> Self {
+        self.bind(key, binding);
+        self
+    }
+
+    /// Binds `key`, after the bindings it already has in the keymap.
+    pub fn bind(&mut self, key: Key, binding: Binding) {
+        self.bindings.push((key, binding));
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fallthrough(&self) -> bool {
+        self.fallthrough
+    }
+
+    pub fn condition(&self) -> KeymapCondition {
+        self.condition
+    }
+
+    pub fn bindings(&self) -> &[(Key, Binding)] {
+        &self.bindings
+    }
+
+    /// Returns whether the keymap binds `key`.
+    pub fn binds(&self, key: Key) -> bool {
+        self.bindings.iter().any(|(k, _)| *k == key)
+    }
+}
+
+/// Keymaps from the lowest layer to the highest, e.g. the common bindings, then the
+/// bindings of the editing mode, then a transient keymap while the completion menu is
+/// open. The highest keymap binding a key decides what it does.
+#[derive(Debug, Clone, Default)]
+pub struct KeymapStack {
+    keymaps: Vec<Keymap>,
+}
+
+impl KeymapStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts `keymap` on top of the others.
+    pub fn push(&mut self, keymap: Keymap) {
+        self.keymaps.push(keymap);
+    }
+
+    /// Removes the keymap on top.
+    pub fn pop(&mut self) -> Option<Keymap> {
+        self.keymaps.pop()
+    }
+
+    /// Returns the keymaps, from the lowest layer to the highest.
+    pub fn keymaps(&self) -> &[Keymap] {
+        &self.keymaps
+    }
+
+    /// Returns what `key` runs, in order: the bindings of the highest keymap binding it,
+    /// preceded by those of the layers below as long as the keymaps fall through. Empty if
+    /// no keymap binds the key.
+    pub fn resolve(&self, key: Key) -> Vec<Binding> {
+        let mut layers = Vec::new();
+        for keymap in self.keymaps.iter().rev().filter(|keymap| keymap.binds(key)) {
+            layers.push(keymap);
+            if !keymap.fallthrough {
+                break;
+            }
+        }
+        layers.iter().rev()
+            .flat_map(|keymap| keymap.bindings.iter().filter(|(k, _)| *k == key).map(|(_, binding)| *binding))
+            .collect()
+    }
+
+    /// Returns whether a keymap binds `key` without falling through, so that what the key
+    /// does without bindings, e.g. typing its character, doesn't happen.
+    pub fn shadows(&self, key: Key) -> bool {
+        self.keymaps.iter().any(|keymap| !keymap.fallthrough && keymap.binds(key))
+    }
+
+    /// Returns every binding that [resolve](KeymapStack::resolve) can return, from the
+    /// lowest layer to the highest, leaving out those shadowed by a higher keymap.
+    pub fn effective_bindings(&self) -> Vec<(Key, Binding)> {
+        let mut bindings = Vec::new();
+        for (i, keymap) in self.keymaps.iter().enumerate() {
+            let shadowed = |key: Key| self.keymaps[i + 1..].iter().any(|above| !above.fallthrough && above.binds(key));
+            bindings.extend(keymap.bindings.iter().filter(|(key, _)| !shadowed(*key)).copied());
+        }
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_bind_func::*;
+    use crossterm::event::KeyCode;
+
+    fn names(bindings: &[Binding]) -> Vec<&str> {
+        bindings.iter().map(|b| b.command().map_or("?", |c| c.name)).collect()
+    }
+
+    #[test]
+    fn test_keymap_stack() {
+        let j = Key::plain(KeyCode::Char('j'));
+        let mut stack = KeymapStack::new();
+        stack.push(Keymap::new("global")
+            .with_binding(Key::plain(KeyCode::Home), Binding::Func(go_line_beginning))
+            .with_binding(Key::ctrl('a'), Binding::Func(go_line_beginning)));
+        stack.push(Keymap::new("mode").with_binding(Key::ctrl('a'), Binding::Func(kill_line)));
+        stack.push(Keymap::new("user")
+            .with_fallthrough()
+            .with_binding(Key::ctrl('a'), Binding::Func(delete_char))
+            .with_binding(Key::ctrl('a'), Binding::Func(transpose_chars)));
+        assert_eq!(vec!["beginning-of-line"], names(&stack.resolve(Key::plain(KeyCode::Home))));
+        // the mode shadows the global binding, the user bindings run after it
+        assert_eq!(vec!["kill-line", "delete-char", "transpose-chars"], names(&stack.resolve(Key::ctrl('a'))));
+        assert!(stack.resolve(j).is_empty());
+        assert!(!stack.shadows(j));
+
+        stack.push(Keymap::new("menu").with_binding(j, Binding::named("menu-complete").unwrap()));
+        assert_eq!(vec!["menu-complete"], names(&stack.resolve(j)));
+        assert!(stack.shadows(j));
+        let effective = stack.effective_bindings();
+        assert_eq!(5, effective.len());
+        assert!(!effective.iter().any(|(k, b)| *k == Key::ctrl('a') && b.command().unwrap().name == "beginning-of-line"));
+
+        assert_eq!("menu", stack.pop().unwrap().name());
+        assert!(stack.resolve(j).is_empty());
+    }
+}
@@ -0,0 +1,586 @@
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+use crossterm::style::Color;
+
+use crate::completion::{format_suggestions, marquee, CompletionManager};
+use crate::document::Document;
+use crate::style::{SpanStyle, Style, StyledText};
+use crate::theme::{Border, Theme};
+use crate::transform::DisplayText;
+use crate::width::{char_width, display_width};
+
+pub(crate) const SCROLLBAR_WIDTH: usize = 1;
+/// Suggestions numbered for Alt-<digit>, see [Renderer::set_number_badges](crate::Renderer::set_number_badges).
+pub(crate) const NUMBER_BADGES: usize = 9;
+pub(crate) const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+pub(crate) const WINDOW_TOO_SMALL: &str = "Your console window is too small...";
+
+/// What a cell of a [Frame] shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Role {
+    #[default]
+    Blank,
+    Prefix,
+    Input,
+    Placeholder,
+    /// The selected suggestion, shown in place of the word before the cursor.
+    Preview,
+    Badge,
+    Suggestion,
+    SelectedSuggestion,
+    Correction,
+    DisabledSuggestion,
+    Description,
+    SelectedDescription,
+    Hint,
+    Scrollbar,
+    ScrollbarThumb,
+    Border,
+    Loading,
+    PreviewPane,
+    Toolbar,
+    KeyHints,
+    /// The message shown instead of the prompt when the terminal is too small.
+    Warning,
+}
+
+/// One column of a [Frame]. The second column of a wide character has no text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cell {
+    pub text: String,
+    pub style: Style,
+    pub role: Role,
+    /// The URL the cell links to, see [Renderer::set_hyperlinks](crate::Renderer::set_hyperlinks).
+    pub link: Option<String>,
+}
+
+/// The rows the prompt takes on the terminal, as laid out by [Renderer::layout](crate::Renderer::layout),
+/// from the first row of the input down to the last row of the toolbar.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    rows: Vec<Vec<Cell>>,
+    cursor: (usize, usize),
+    marquee_running: bool,
+}
+
+impl Frame {
+    fn new(width: usize) -> Self {
+        Self { width, ..Self::default() }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the cell at column `x` of row `y`.
+    pub fn cell(&self, x: usize, y: usize) -> Option<&Cell> {
+        self.rows.get(y)?.get(x)
+    }
+
+    /// Returns the text of row `y`, padded to the width of the frame.
+    pub fn row_text(&self, y: usize) -> String {
+        self.rows.get(y).map(|row| row.iter().map(|c| c.text.as_str()).collect()).unwrap_or_default()
+    }
+
+    /// Returns the (column, row) the cursor is left at.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub(crate) fn row(&self, y: usize) -> Option<&[Cell]> {
+        self.rows.get(y).map(Vec::as_slice)
+    }
+
+    /// Returns whether the description of the selected suggestion is scrolled.
+    pub(crate) fn marquee_running(&self) -> bool {
+        self.marquee_running
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut Vec<Cell> {
+        while self.rows.len() <= y {
+            let blank = Cell { text: " ".to_string(), ..Cell::default() };
+            self.rows.push(vec![blank; self.width]);
+        }
+        &mut self.rows[y]
+    }
+
+    /// Writes `text` from `pos` like a terminal would, wrapping past the last column and
+    /// moving a wide character that doesn't fit to the next row, and returns where it
+    /// stops. A position past the last column is only wrapped by the next character.
+    fn write(&mut self, pos: (usize, usize), text: &str, style: Style, role: Role) -> (usize, usize) {
+        self.write_link(pos, text, style, role, None)
+    }
+
+    fn write_link(&mut self, pos: (usize, usize), text: &str, style: Style, role: Role, link: Option<&str>) -> (usize, usize) {
+        text.chars().fold(pos, |pos, c| self.put(pos, c, style, role, link))
+    }
+
+    fn put(&mut self, (mut x, mut y): (usize, usize), c: char, style: Style, role: Role, link: Option<&str>) -> (usize, usize) {
+        if c == '\n' {
+            return (0, y + 1);
+        }
+        let width = char_width(c);
+        if width == 0 {
+            // combines with the character before it
+            if let Some(cell) = x.checked_sub(1).and_then(|x| self.row_mut(y).get_mut(x)) {
+                cell.text.push(c);
+            }
+            return (x, y);
+        }
+        if x + width > self.width {
+            (x, y) = (0, y + 1);
+        }
+        let row = self.row_mut(y);
+        for (i, cell) in row.iter_mut().enumerate().skip(x).take(width) {
+            let text = if i == x { c.to_string() } else { String::new() };
+            *cell = Cell { text, style, role, link: link.map(str::to_string) };
+        }
+        (x + width, y)
+    }
+
+    fn write_styled(&mut self, mut pos: (usize, usize), text: &StyledText, base: Style, role: Role) -> (usize, usize) {
+        for span in text.spans() {
+            pos = self.write_link(pos, &span.text, span.style.apply(base), role, span.link.as_deref());
+        }
+        pos
+    }
+}
+
+/// What the renderer shows besides the input, see [Renderer::layout](crate::Renderer::layout).
+pub(crate) struct Scene<'a> {
+    pub prefix: &'a StyledText,
+    pub placeholder: &'a StyledText,
+    /// The input after every transformation, see [Renderer::display_text](crate::Renderer::display_text).
+    pub input: &'a DisplayText,
+    pub preview_pane: &'a [StyledText],
+    pub toolbar: &'a StyledText,
+    pub key_hints: &'a StyledText,
+    pub popup_border: Option<Border>,
+    pub number_badges: bool,
+    pub spinner_frame: usize,
+    /// How far the description of the selected suggestion is scrolled, if it scrolls.
+    pub marquee_step: Option<usize>,
+}
+
+impl Scene<'_> {
+    fn border_size(&self) -> usize {
+        if self.popup_border.is_some() { 2 } else { 0 }
+    }
+
+    fn badge_width(&self) -> usize {
+        usize::from(self.number_badges)
+    }
+}
+
+/// Lays out the prompt on a terminal of `size` (columns, rows): the prefix, the input,
+/// the completion popup and the toolbar, without writing anything.
+pub(crate) fn layout<C>(
+    size: (u16, u16),
+    scene: &Scene,
+    document: &Document,
+    completion: &CompletionManager<C>,
+    theme: &Theme,
+) -> Frame {
+    let (col, row) = (size.0 as usize, size.1 as usize);
+    let mut frame = Frame::new(col);
+    if col == 0 {
+        return frame;
+    }
+    let cramped = cramped(row, col, scene.prefix);
+    let prefix = fit_prefix(scene.prefix, col);
+    let mut show_popup = !cramped;
+    let mut popup_height = if cramped { 0 } else { popup_height(completion, scene.border_size()) };
+    let bars = [(scene.toolbar, theme.toolbar, Role::Toolbar), (scene.key_hints, theme.key_hints, Role::KeyHints)];
+    let mut bars = scene.preview_pane.iter().map(|line| (line, Style::default(), Role::PreviewPane))
+        .chain(bars.into_iter().filter(|(text, ..)| !text.is_empty()))
+        .filter(|_| !cramped)
+        .collect::<Vec<_>>();
+
+    // the bars go first, then the popup, so that the input fits
+    let (_, last_row) = to_pos(prefix.width() + scene.input.text.width(), col);
+    while last_row + 1 + popup_height + bars.len() > row && !bars.is_empty() {
+        bars.pop();
+    }
+    if last_row + 1 + popup_height > row {
+        show_popup = false;
+        popup_height = 0;
+    }
+    if last_row + 1 > row {
+        // cut to the screen, which is drawn over rather than cleared
+        let warning = &WINDOW_TOO_SMALL[..WINDOW_TOO_SMALL.len().min(col * row)];
+        frame.write((0, 0), warning, Style::new(Color::White, Color::Red), Role::Warning);
+        return frame;
+    }
+
+    let mut pos = frame.write_styled((0, 0), &prefix, theme.prefix, Role::Prefix);
+    let mut cursor = pos;
+    let mut chars = 0;
+    for span in scene.input.text.spans() {
+        for c in span.text.chars() {
+            if chars == scene.input.cursor {
+                cursor = pos;
+            }
+            pos = frame.put(pos, c, span.style.apply(theme.input), Role::Input, span.link.as_deref());
+            chars += 1;
+        }
+    }
+    if chars <= scene.input.cursor {
+        cursor = pos;
+    }
+    let wrap = |(x, y): (usize, usize)| if x >= col { (0, y + 1) } else { (x, y) };
+    let (end, mut cursor) = (wrap(pos), wrap(cursor));
+    frame.row_mut(end.1);
+
+    let placeholder_width = scene.placeholder.width();
+    if document.text.is_empty() && placeholder_width > 0 && cursor.0 + placeholder_width < col {
+        frame.write_styled(cursor, scene.placeholder, theme.placeholder, Role::Placeholder);
+    }
+
+    if show_popup {
+        let x = to_pos(prefix.width() + display_width(document.text_before_cursor_str()), col).0;
+        layout_completion(&mut frame, (x, cursor.1 + 1), col, scene, completion, theme);
+    }
+    for (i, (text, style, role)) in bars.into_iter().enumerate() {
+        let y = end.1 + popup_height + 1 + i;
+        let (x, y) = frame.write_styled((0, y), &text.truncate(col), style, role);
+        frame.write((x, y), &" ".repeat(col.saturating_sub(x)), style, role);
+    }
+
+    if let Some(suggestion) = completion.get_selected_suggestion() {
+        let word = document.get_word_before_cursor_until_separator(completion.word_separator());
+        let start = (cursor.1 * col + cursor.0).saturating_sub(display_width(&word));
+        let after = frame.write(to_pos(start, col), suggestion.text(), theme.preview_suggestion, Role::Preview);
+        cursor = wrap(after);
+        frame.write(after, document.text_after_cursor_str(), Style::default(), Role::Input);
+    }
+    frame.row_mut(cursor.1);
+    frame.cursor = cursor;
+    frame
+}
+
+/// Lays out the completion popup, or the loading row, from `(x, y)`, below the cursor.
+fn layout_completion<C>(
+    frame: &mut Frame,
+    (x, y): (usize, usize),
+    col: usize,
+    scene: &Scene,
+    completion: &CompletionManager<C>,
+    theme: &Theme,
+) {
+    let suggestions = completion.get_suggestions();
+    let window_height = completion.window_height();
+    if suggestions.is_empty() {
+        if completion.loading() && window_height > 0 {
+            let text = format!(" {} loading… ", SPINNER_FRAMES[scene.spinner_frame % SPINNER_FRAMES.len()]);
+            let x = x - (x + display_width(&text)).saturating_sub(col).min(x);
+            frame.write((x, y), &text, theme.suggestion, Role::Loading);
+        }
+        return;
+    }
+    let chrome = scene.badge_width() + SCROLLBAR_WIDTH + scene.border_size();
+    let prefix_width = fit_prefix(scene.prefix, col).width();
+    let (formatted, width) = format_suggestions(suggestions, col.saturating_sub(prefix_width + chrome), completion.column_layout());
+    if formatted.is_empty() || window_height == 0 {
+        return;
+    }
+    let width = width + chrome;
+    let x = popup_x(x, width, col);
+    let scroll = completion.vertical_scroll();
+    let formatted = &formatted[scroll..(scroll + window_height).min(formatted.len())];
+    let thumb = scrollbar_thumb(window_height, suggestions.len(), scroll);
+    let selected = completion.selected().and_then(|i| i.checked_sub(scroll));
+    let border = scene.popup_border.map(Border::chars);
+
+    let mut y = y;
+    let border_row = |frame: &mut Frame, y: usize, left: char, line: char, right: char| {
+        let text = format!("{left}{}{right}", line.to_string().repeat(width.saturating_sub(2)));
+        frame.write((x, y), &text, theme.popup_border, Role::Border);
+    };
+    if let Some([left, right, _, _, line, _]) = border {
+        border_row(frame, y, left, line, right);
+        y += 1;
+    }
+    for (i, s) in formatted.iter().enumerate() {
+        let suggestion = &suggestions[scroll + i];
+        let is_selected = Some(i) == selected;
+        let (style, role) = match is_selected {
+            true => (theme.selected_suggestion, Role::SelectedSuggestion),
+            false if suggestion.is_disabled() => (theme.disabled_suggestion, Role::DisabledSuggestion),
+            false if suggestion.is_correction() => (theme.correction, Role::Correction),
+            false => (theme.suggestion, Role::Suggestion),
+        };
+        let (description, description_role) = match is_selected {
+            true => (theme.selected_description, Role::SelectedDescription),
+            false if suggestion.is_disabled() => (theme.disabled_suggestion, Role::Description),
+            false => (theme.description, Role::Description),
+        };
+        let mut pos = (x, y + i);
+        if let Some([.., side]) = border {
+            pos = frame.put(pos, side, theme.popup_border, Role::Border, None);
+        }
+        if scene.number_badges {
+            let badge = if i < NUMBER_BADGES { (i + 1).to_string() } else { " ".to_string() };
+            pos = frame.write(pos, &badge, style, Role::Badge);
+        }
+        pos = frame.write(pos, s.text(), style, role);
+        let url = suggestion.url().filter(|_| !s.description().trim().is_empty());
+        let scrolled = match is_selected {
+            true => scene.marquee_step.and_then(|step| marquee(suggestion.description(), s.description(), step)),
+            false => None,
+        };
+        frame.marquee_running |= scrolled.is_some();
+        pos = frame.write_link(pos, scrolled.as_deref().unwrap_or(s.description()), description, description_role, url);
+        if let Some(hint) = s.hint() {
+            pos = frame.write(pos, hint, description, Role::Hint);
+        }
+        pos = match thumb.contains(&i) {
+            true => frame.write(pos, " ", theme.scrollbar_thumb, Role::ScrollbarThumb),
+            false => frame.write(pos, " ", theme.scrollbar, Role::Scrollbar),
+        };
+        if let Some([.., side]) = border {
+            frame.put(pos, side, theme.popup_border, Role::Border, None);
+        }
+    }
+    if let Some([_, _, left, right, line, _]) = border {
+        border_row(frame, y + formatted.len(), left, line, right);
+    }
+}
+
+/// Returns whether the terminal is too small for anything but the prefix and the
+/// input: shorter than 3 rows or narrower than the prefix. The completion popup, the
+/// preview pane, the toolbar and the key hints are hidden then.
+pub(crate) fn cramped(row: usize, col: usize, prefix: &StyledText) -> bool {
+    row < 3 || prefix.width() >= col
+}
+
+/// Returns the rows taken by the completion popup, with its border if it has one.
+pub(crate) fn popup_height<C>(completion: &CompletionManager<C>, border_size: usize) -> usize {
+    match completion.window_height() {
+        // the loading row has no border
+        n if n > 0 && !completion.get_suggestions().is_empty() => n + border_size,
+        n => n,
+    }
+}
+
+/// Returns the column of a popup `width` wide opened below column `x`, moved left so
+/// that it ends before the last column.
+pub(crate) fn popup_x(x: usize, width: usize, col: usize) -> usize {
+    if x + width >= col { x.saturating_sub(x + width - col) } else { x }
+}
+
+/// Returns the rows of the popup on which the scrollbar thumb is drawn.
+pub(crate) fn scrollbar_thumb(window_height: usize, content_height: usize, scroll: usize) -> RangeInclusive<usize> {
+    let fraction_visible = window_height as f64 / content_height as f64;
+    let fraction_above = scroll as f64 / content_height as f64;
+    let height = (window_height as f64 * fraction_visible).clamp(1.0, window_height as f64) as usize;
+    let top = (window_height as f64 * fraction_above) as usize;
+    top..=top + height
+}
+
+/// Returns the (column, row) of a display position.
+pub(crate) fn to_pos(cursor: usize, col: usize) -> (usize, usize) {
+    (cursor % col, cursor / col)
+}
+
+/// Returns `prefix` as rendered `col` columns wide: in a terminal narrower than the
+/// prefix, it is cut to half the width, ending with an ellipsis, so that there is room
+/// left for the input. A width of 0, unknown, leaves the prefix as it is.
+pub(crate) fn fit_prefix(prefix: &StyledText, col: usize) -> Cow<'_, StyledText> {
+    if col == 0 || prefix.width() < col {
+        return Cow::Borrowed(prefix);
+    }
+    let mut fitted = prefix.truncate((col / 2).saturating_sub(1));
+    if col / 2 > 0 {
+        fitted.push("…", SpanStyle::default());
+    }
+    Cow::Owned(fitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::Suggestion;
+    use crate::render::Renderer;
+    use crate::tests::TestCompleter;
+
+    fn suggestions(items: &[(&str, &str)]) -> Vec<Suggestion> {
+        items.iter().map(|(text, description)| Suggestion::new(text.to_string(), description.to_string())).collect()
+    }
+
+    fn role_letter(role: Role) -> char {
+        match role {
+            Role::Blank => '.',
+            Role::Prefix => 'p',
+            Role::Input => 'i',
+            Role::Placeholder => 'h',
+            Role::Preview => 'v',
+            Role::Badge => 'n',
+            Role::Suggestion => 's',
+            Role::SelectedSuggestion => 'S',
+            Role::Correction => 'c',
+            Role::DisabledSuggestion => 'x',
+            Role::Description => 'd',
+            Role::SelectedDescription => 'D',
+            Role::Hint => 't',
+            Role::Scrollbar => 'b',
+            Role::ScrollbarThumb => 'B',
+            Role::Border => '#',
+            Role::Loading => 'l',
+            Role::PreviewPane => 'w',
+            Role::Toolbar => 'T',
+            Role::KeyHints => 'k',
+            Role::Warning => '!',
+        }
+    }
+
+    /// Lays out `document` and compares the frame with `testdata/layout/<name>.txt`: its
+    /// text, the role of every cell and the cursor. Set UPDATE_GOLDEN to rewrite the file.
+    fn assert_golden(name: &str, r: &Renderer<Vec<u8>>, document: &Document, completion: &mut CompletionManager<TestCompleter>) {
+        completion.set_available_height(r.completion_height(document));
+        let frame = r.layout(document, completion);
+        let mut out = String::new();
+        for y in 0..frame.height() {
+            out += &format!("|{}|\n", frame.row_text(y));
+        }
+        out.push('\n');
+        for y in 0..frame.height() {
+            let roles = (0..frame.width()).map(|x| role_letter(frame.cell(x, y).unwrap().role)).collect::<String>();
+            out += &format!("|{roles}|\n");
+        }
+        out += &format!("\ncursor {},{}\n", frame.cursor().0, frame.cursor().1);
+
+        let path = format!("{}/testdata/layout/{name}.txt", env!("CARGO_MANIFEST_DIR"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(std::path::Path::new(&path).parent().unwrap()).unwrap();
+            std::fs::write(&path, &out).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("{path} is missing, run the tests with UPDATE_GOLDEN=1"));
+        assert_eq!(expected, out, "{name}");
+    }
+
+    fn renderer(prefix: &str, col: u16, row: u16) -> Renderer<Vec<u8>> {
+        let mut r = Renderer::new(Vec::new(), prefix);
+        r.update_win_size(col, row);
+        r
+    }
+
+    fn manager(items: Vec<Suggestion>, max: usize, document: &Document) -> CompletionManager<'_, TestCompleter> {
+        let mut completion = CompletionManager::new(TestCompleter::from(items), max);
+        completion.update_suggestions(document);
+        completion
+    }
+
+    #[test]
+    fn test_layout_ascii_popup() {
+        let r = renderer("> ", 30, 6);
+        let document = Document::with_text("git ch".to_string(), 6);
+        let items = suggestions(&[("checkout", "Switch branches"), ("cherry-pick", "Apply commits")]);
+        let mut completion = manager(items, 6, &document);
+        assert_golden("ascii_popup", &r, &document, &mut completion);
+        completion.next();
+        assert_golden("ascii_popup_selected", &r, &document, &mut completion);
+    }
+
+    #[test]
+    fn test_layout_placeholder() {
+        let mut r = renderer("> ", 20, 3);
+        r.set_placeholder("type a command");
+        let document = Document::new();
+        let mut completion = manager(Vec::new(), 6, &document);
+        assert_golden("placeholder", &r, &document, &mut completion);
+    }
+
+    #[test]
+    fn test_layout_cjk() {
+        let r = renderer("日本 > ", 24, 6);
+        let document = Document::with_text("検索 漢".to_string(), 4);
+        let items = vec![
+            Suggestion::new("漢字".to_string(), "かんじ".to_string()).with_hint("名"),
+            Suggestion::new("漢語".to_string(), "かんご".to_string()),
+        ];
+        let mut completion = manager(items, 6, &document);
+        assert_golden("cjk", &r, &document, &mut completion);
+    }
+
+    #[test]
+    fn test_layout_emoji() {
+        let r = renderer("$ ", 24, 5);
+        let document = Document::with_text("say 👍🏽 cafe\u{301} 🎉".to_string(), 13);
+        let items = suggestions(&[("🎉 party", "🥳"), ("🎂", "cake")]);
+        let mut completion = manager(items, 6, &document);
+        assert_golden("emoji", &r, &document, &mut completion);
+    }
+
+    #[test]
+    fn test_layout_wrapped_line() {
+        let r = renderer("> ", 12, 8);
+        // the wide char doesn't fit in the last column and moves to the next row
+        let document = Document::with_text("abcdefghi漢x echo on".to_string(), 19);
+        let items = suggestions(&[("one", ""), ("only", "")]);
+        let mut completion = manager(items, 6, &document);
+        assert_golden("wrapped_line", &r, &document, &mut completion);
+    }
+
+    #[test]
+    fn test_layout_narrow_terminal() {
+        let mut r = renderer("user@box ~/src > ", 10, 6);
+        r.set_toolbar("status");
+        let document = Document::with_text("ls".to_string(), 2);
+        let mut completion = manager(suggestions(&[("lsblk", "")]), 6, &document);
+        // the prefix is cut to half the width, and there is no room for the popup
+        assert_golden("narrow_terminal", &r, &document, &mut completion);
+
+        let r = renderer("> ", 6, 3);
+        let document = Document::with_text("a long line of input".to_string(), 20);
+        let mut completion = manager(Vec::new(), 6, &document);
+        assert_golden("too_small", &r, &document, &mut completion);
+
+        // the key hints and then the toolbar are hidden for the input to fit
+        let mut r = renderer("> ", 12, 3);
+        r.set_toolbar("status");
+        r.set_key_hints(" Tab complete");
+        let document = Document::with_text("two rows of input".to_string(), 17);
+        let mut completion = manager(Vec::new(), 6, &document);
+        assert_golden("crowded_bars", &r, &document, &mut completion);
+        let document = Document::with_text("three rows of the input".to_string(), 23);
+        assert_golden("crowded_input", &r, &document, &mut completion);
+    }
+
+    #[test]
+    fn test_layout_border_toolbar() {
+        let mut r = renderer("> ", 32, 10);
+        r.set_popup_border(Some(Border::Ascii));
+        r.set_number_badges(true);
+        r.set_toolbar("main");
+        r.set_key_hints(" Tab complete");
+        let document = Document::with_text("git ".to_string(), 4);
+        let items = vec![
+            Suggestion::new("add".to_string(), "Stage".to_string()),
+            Suggestion::new("bisect".to_string(), "Search".to_string()).disable("no repo"),
+            Suggestion::new("commit".to_string(), "Record".to_string()).with_hint("c"),
+            Suggestion::new("diff".to_string(), "Compare".to_string()),
+            Suggestion::new("fetch".to_string(), "Download".to_string()),
+        ];
+        let mut completion = manager(items, 3, &document);
+        completion.next();
+        completion.next();
+        assert_golden("border_toolbar", &r, &document, &mut completion);
+    }
+
+    #[test]
+    fn test_layout_loading() {
+        let r = renderer("> ", 16, 4);
+        let document = Document::with_text("fetch".to_string(), 5);
+        let mut completion = CompletionManager::new(TestCompleter::default().pending(), 6);
+        completion.update_suggestions(&document);
+        assert_golden("loading", &r, &document, &mut completion);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::buffer::TextChange;
+use crate::style::{SpanStyle, StyledText};
+
+/// Styles the input line by line, see `Prompt::with_lexer`. What a line looks like may
+/// depend on the lines before it, e.g. inside a string spanning lines, which is what
+/// [State](Lexer::State) carries from the end of a line to the start of the next.
+pub trait Lexer {
+    /// State at a line break. A line is only lexed again when its text or the state at
+    /// its start changed, so it should hold no more than what the next line needs.
+    type State: Clone + PartialEq;
+
+    /// Returns the state at the start of the input.
+    fn start(&self) -> Self::State;
+
+    /// Returns `line`, without its line break, styled, and updates `state` to the state
+    /// at its end. The text of the returned spans must be `line`.
+    fn lex_line(&self, line: &str, state: &mut Self::State) -> StyledText;
+}
+
+/// A line as it was lexed.
+#[derive(Debug, Clone)]
+struct Line<S> {
+    /// Number of chars, with the line break.
+    len: usize,
+    /// Lexed with the state at its start, None if the text changed since.
+    lexed: Option<(S, StyledText, S)>,
+}
+
+impl<S> Line<S> {
+    fn dirty(len: usize) -> Self {
+        Self { len, lexed: None }
+    }
+}
+
+/// Highlights the input with a [Lexer], lexing again only the lines that changed since
+/// the last time and the lines after them whose start state changed as a result.
+#[derive(Debug, Clone)]
+pub struct Highlighter<L: Lexer> {
+    lexer: L,
+    /// One for each line of the last text, an empty text having one empty line.
+    lines: Vec<Line<L::State>>,
+}
+
+impl<L: Lexer> Highlighter<L> {
+    pub fn new(lexer: L) -> Self {
+        Self { lexer, lines: vec![Line::dirty(0)] }
+    }
+
+    pub fn lexer(&self) -> &L {
+        &self.lexer
+    }
+
+    /// Forgets how the lines touched by `change` were lexed, keeping the other lines
+    /// whether they moved or not.
+    pub fn apply(&mut self, change: &TextChange) {
+        let (first, first_start) = self.line_at(change.range.start);
+        let (last, last_start) = self.line_at(change.range.end);
+        let before = change.range.start - first_start;
+        let after = (last_start + self.lines[last].len).saturating_sub(change.range.end);
+        let mut lens = change.text.split('\n').map(|piece| piece.chars().count() + 1).collect::<Vec<_>>();
+        lens[0] += before;
+        // the last piece goes on with the rest of the last line, which has its line break
+        if let Some(last) = lens.last_mut() {
+            *last = *last - 1 + after;
+        }
+        self.lines.splice(first..=last, lens.into_iter().map(Line::dirty));
+    }
+
+    /// Returns the line `offset` is on, with the offset the line starts at.
+    fn line_at(&self, offset: usize) -> (usize, usize) {
+        let mut start = 0;
+        for (i, line) in self.lines.iter().enumerate() {
+            if offset < start + line.len || i + 1 == self.lines.len() {
+                return (i, start);
+            }
+            start += line.len;
+        }
+        (0, 0)
+    }
+
+    /// Returns `text` styled, lexing the lines that changed since the last call. `text`
+    /// is expected to be the last one with the [applied](Highlighter::apply) changes;
+    /// if it doesn't fit, every line is lexed again.
+    pub fn highlight(&mut self, text: &str) -> StyledText {
+        let lines = text.split('\n').collect::<Vec<_>>();
+        let fits = lines.len() == self.lines.len()
+            && lines.iter().zip(&self.lines).enumerate()
+                .all(|(i, (line, cached))| line.chars().count() + usize::from(i + 1 < lines.len()) == cached.len);
+        if !fits {
+            self.lines = lines.iter().map(|line| Line::dirty(line.chars().count() + 1)).collect();
+            if let Some(last) = self.lines.last_mut() {
+                last.len -= 1;
+            }
+        }
+
+        let mut styled = StyledText::new();
+        let mut state = self.lexer.start();
+        for (i, (line, cached)) in lines.iter().zip(&mut self.lines).enumerate() {
+            let (_, text, end) = match cached.lexed.take() {
+                Some(lexed) if lexed.0 == state => cached.lexed.insert(lexed),
+                _ => {
+                    let start = state.clone();
+                    let text = self.lexer.lex_line(line, &mut state);
+                    cached.lexed.insert((start, text, state.clone()))
+                }
+            };
+            for span in text.spans() {
+                styled.push(span.text.as_str(), span.style);
+            }
+            if i + 1 < lines.len() {
+                styled.push("\n", SpanStyle::default());
+            }
+            state = end.clone();
+        }
+        styled
+    }
+}
+
+/// Object safe part of [Highlighter], for the prompt to hold one whatever its lexer.
+pub(crate) trait Highlight {
+    fn apply(&mut self, change: &TextChange);
+    fn highlight(&mut self, text: &str) -> StyledText;
+}
+
+impl<L: Lexer> Highlight for Highlighter<L> {
+    fn apply(&mut self, change: &TextChange) {
+        Highlighter::apply(self, change);
+    }
+
+    fn highlight(&mut self, text: &str) -> StyledText {
+        Highlighter::highlight(self, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crossterm::style::Color;
+    use std::cell::RefCell;
+
+    /// Styles what is between double quotes, which may span lines, and records the
+    /// lines it lexes.
+    #[derive(Default)]
+    struct QuoteLexer {
+        lexed: RefCell<Vec<String>>,
+    }
+
+    impl Lexer for QuoteLexer {
+        type State = bool;
+
+        fn start(&self) -> bool {
+            false
+        }
+
+        fn lex_line(&self, line: &str, in_string: &mut bool) -> StyledText {
+            self.lexed.borrow_mut().push(line.to_string());
+            let mut text = StyledText::new();
+            for c in line.chars() {
+                let quoted = *in_string || c == '"';
+                if c == '"' {
+                    *in_string = !*in_string;
+                }
+                let style = if quoted { SpanStyle::default().fg(Color::Green) } else { SpanStyle::default() };
+                text.push(c.to_string(), style);
+            }
+            text
+        }
+    }
+
+    /// Highlights the text of `b` after its changes, returning the lines lexed.
+    fn highlight(h: &mut Highlighter<QuoteLexer>, b: &mut Buffer) -> (StyledText, Vec<String>) {
+        for change in b.take_changes() {
+            h.apply(&change);
+        }
+        let styled = h.highlight(&b.text());
+        assert_eq!(b.text(), styled.text());
+        (styled, h.lexer().lexed.take())
+    }
+
+    #[test]
+    fn test_highlighter() {
+        let mut b = Buffer::new();
+        b.set_track_changes(true);
+        let mut h = Highlighter::new(QuoteLexer::default());
+        b.insert_text("a\nb\nc", false, true);
+        assert_eq!(vec!["a", "b", "c"], highlight(&mut h, &mut b).1);
+        // typing on a line lexes that line only
+        b.set_cursor_position(3);
+        b.insert_text("x", false, true);
+        assert_eq!(vec!["bx"], highlight(&mut h, &mut b).1);
+        // the lines after a new one are only moved
+        b.set_cursor_position(0);
+        b.insert_text("0\n", false, true);
+        assert_eq!(vec!["0", "a"], highlight(&mut h, &mut b).1);
+
+        // an open quote changes the state the lines after it start with
+        b.set_cursor_position(2);
+        b.insert_text("\"", false, true);
+        let (styled, lexed) = highlight(&mut h, &mut b);
+        assert_eq!(vec!["\"a", "bx", "c"], lexed);
+        assert_eq!("0\n", styled.spans()[0].text);
+        assert_eq!("\"a", styled.spans()[1].text);
+        assert_eq!(Some(Color::Green), styled.spans()[1].style.fg);
+        b.delete_before_cursor(1);
+        assert_eq!(vec!["a", "bx", "c"], highlight(&mut h, &mut b).1);
+
+        b.set_cursor_position(3);
+        b.delete(1);
+        assert_eq!("0\nabx\nc", b.text());
+        assert_eq!(vec!["abx"], highlight(&mut h, &mut b).1);
+        // a text it wasn't told about is lexed as a new one
+        assert_eq!("zz", h.highlight("zz").text());
+        assert_eq!(vec!["zz"], h.lexer().lexed.take());
+    }
+}
@@ -1,5 +1,134 @@
+mod buffer;
+mod cancel;
+mod commands;
 mod completion;
+mod continuation;
+mod correction;
+mod diagnostics;
+mod digraph;
 mod document;
+mod elapsed;
+mod emacs;
+mod esc;
+#[cfg(feature = "encryption")]
+mod encrypted_history;
+mod expand;
+#[cfg(feature = "external-completer")]
+mod external_completer;
+mod external_finder;
+mod file_completer;
+mod form;
+mod frames;
+mod frecency;
+#[cfg(any(test, feature = "internals"))]
+mod fuzz;
+mod fuzzy;
+mod history;
+mod history_store;
+mod help;
+mod hyperlink;
+mod inputrc;
+mod jobs;
+mod key_bind;
+mod key_bind_func;
+mod keymap;
+mod layout;
+mod lexer;
+mod links;
+mod markup;
+#[cfg(feature = "normalization")]
+mod normalization;
+mod notifier;
+mod output;
+mod pager;
+mod path_rules;
+mod picker;
+mod prompt;
+#[cfg(feature = "regex")]
+mod regex_lexer;
+mod render;
+mod repl_screen;
+mod segments;
+mod shell_completer;
+mod status;
+mod style;
+mod theme;
+mod threaded_completer;
+mod transform;
+mod typed;
+mod widgets;
+mod width;
+
+pub use buffer::{Buffer, TextChange};
+pub use cancel::CancelToken;
+pub use commands::{Command, CommandCategory};
+pub use completion::{ColumnLayout, Completer, CompletionManager, CompletionTrigger, ContextCompleter, Suggestion};
+pub use correction::{corrections, damerau_levenshtein, levenshtein, DidYouMean};
+pub use document::{CharClass, Document, WordModel, DEFAULT_TAB_WIDTH};
+pub use elapsed::{format_duration, ElapsedPlacement, ElapsedTime};
+#[cfg(feature = "encryption")]
+pub use encrypted_history::EncryptedFileHistory;
+#[cfg(feature = "external-completer")]
+pub use external_completer::ExternalCompleter;
+pub use external_finder::ExternalFinder;
+pub use expand::{expand_vars, expansion_preview, glob, is_glob};
+pub use file_completer::FilePathCompleter;
+pub use continuation::Continuation;
+pub use diagnostics::{Diagnostic, Severity};
+pub use digraph::digraph;
+pub use form::{Field, Form};
+pub use frecency::Frecency;
+pub use fuzzy::fuzzy_score;
+pub use history::{History, HistoryEntry};
+pub use history_store::{FileHistory, HistoryStore};
+pub use hyperlink::supports_hyperlinks;
+pub use inputrc::Inputrc;
+pub use jobs::{Job, Jobs};
+pub use key_bind::{ContextKeyBindFunc, Key, KeyBind, KeyBindFunc, KeyBindMode, KeyContext, KeyHandlerFunc};
+pub use key_bind_func::{
+    delete_before_char, delete_char, delete_word, delete_word_after, go_left_char, go_left_word, go_line_beginning,
+    go_line_end, go_next_paragraph, go_previous_paragraph, go_right_char, go_right_word, kill_line, kill_line_before,
+    repeat_last_change, transpose_chars, undo,
+};
+pub use keymap::{Binding, Keymap, KeymapCondition, KeymapStack};
+pub use layout::{Cell, Frame, Role};
+pub use lexer::{Highlighter, Lexer};
+pub use links::{find_links, Link, LinkKind, Links};
+pub use crossterm::event::{KeyCode, KeyModifiers};
+pub use markup::{parse_markup, parse_color, MarkupError};
+#[cfg(feature = "normalization")]
+pub use normalization::Normalization;
+pub use notifier::{Feedback, Notice, Notifier};
+pub use output::Output;
+pub use pager::{page, PagerMode};
+pub use path_rules::PathRules;
+pub use prompt::Prompt;
+#[cfg(feature = "regex")]
+pub use regex_lexer::RegexLexer;
+pub use render::Renderer;
+pub use repl_screen::ReplScreen;
+pub use segments::{Segment, Segments};
+pub use shell_completer::{ShellCompleter, BASH_COMPLETION_SCRIPT};
+pub use crossterm::style::Color;
+pub use status::Status;
+pub use style::{Span, SpanStyle, Style, StyledText};
+pub use theme::{Border, Theme, THEME_NAMES};
+pub use threaded_completer::ThreadedCompleter;
+pub use transform::{
+    ControlCharacters, ControlNotation, DisplayText, PasswordMask, RainbowBrackets, TabExpansion, Transformation,
+    TrailingWhitespace,
+};
+pub use typed::{input_date, input_parsed, input_path, input_u64, Date};
+pub use widgets::{confirm, multi_select, select};
+pub use width::{display_width, strip_ansi, truncate_to_width};
+
+/// Hot paths of a keystroke that are not otherwise public, for the benchmarks, and the
+/// entry points of the fuzz targets.
+#[cfg(feature = "internals")]
+pub mod internals {
+    pub use crate::completion::format_suggestions;
+    pub use crate::fuzz::document_edits;
+}
 
 #[cfg(test)]
 mod tests {
@@ -9,6 +138,67 @@ mod tests {
         terminal, cursor, style::{self, Stylize}, Result,
     };
 
+    use crate::{Completer, Document, Suggestion};
+
+    /// The completer of the tests, suggesting a fixed list.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct TestCompleter {
+        suggestions: Vec<Suggestion>,
+        /// Only suggests those starting with the word before the cursor.
+        prefixed: bool,
+        /// Suggests nothing while the word before the cursor is empty.
+        needs_word: bool,
+        pending: bool,
+    }
+
+    impl TestCompleter {
+        /// Suggests `titles`, without descriptions.
+        pub(crate) fn new<S: ToString>(titles: impl IntoIterator<Item = S>) -> Self {
+            titles.into_iter().map(|t| Suggestion::with_title(t.to_string())).collect::<Vec<_>>().into()
+        }
+
+        /// Only suggests those starting with the word before the cursor.
+        pub(crate) fn prefixed(mut self) -> Self {
+            self.prefixed = true;
+            self
+        }
+
+        /// Suggests nothing until a word is typed.
+        pub(crate) fn needs_word(mut self) -> Self {
+            self.needs_word = true;
+            self
+        }
+
+        /// Is still loading, see [Completer::pending].
+        pub(crate) fn pending(mut self) -> Self {
+            self.pending = true;
+            self
+        }
+    }
+
+    impl From<Vec<Suggestion>> for TestCompleter {
+        fn from(suggestions: Vec<Suggestion>) -> Self {
+            Self { suggestions, ..Self::default() }
+        }
+    }
+
+    impl Completer for TestCompleter {
+        fn complete(&self, document: &Document) -> Vec<Suggestion> {
+            let word = document.get_word_before_cursor();
+            if self.needs_word && word.is_empty() {
+                return Vec::new();
+            }
+            self.suggestions.iter()
+                .filter(|s| !self.prefixed || s.text().starts_with(word.as_str()))
+                .cloned()
+                .collect()
+        }
+
+        fn pending(&self) -> bool {
+            self.pending
+        }
+    }
+
     #[test]
     fn it_works() -> Result<()> {
         let mut stdout = stdout();
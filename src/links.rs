@@ -0,0 +1,128 @@
+use std::env;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crossterm::style::Color;
+
+use crate::style::{SpanStyle, StyledText};
+use crate::transform::{DisplayText, Transformation};
+
+/// What a [Link] points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Url,
+    Path,
+}
+
+/// A URL or a file path found in the input by [find_links].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Char range of the link in the text.
+    pub range: Range<usize>,
+    pub kind: LinkKind,
+    /// What the link opens: the URL itself, or a `file://` URL of the absolute path.
+    pub url: String,
+}
+
+/// Returns the URLs and the file paths in `text`, e.g. `https://example.com` or
+/// `~/notes.txt`. They are found by their shape, whether they exist or not: a URL has a
+/// scheme followed by `://`, or starts with `www.`, and a path starts with `/`, `./`,
+/// `../` or `~/`. Quotes and brackets around them and punctuation after them are left
+/// out.
+pub fn find_links(text: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut start = 0;
+    for word in text.split(char::is_whitespace) {
+        let len = word.chars().count();
+        let trimmed = word.trim_start_matches(['"', '\'', '(', '[', '<', '`']);
+        let lead = len - trimmed.chars().count();
+        let trimmed = trimmed.trim_end_matches(['"', '\'', ')', ']', '>', '`', '.', ',', ';', ':', '!', '?']);
+        if let Some((kind, url)) = link_of(trimmed) {
+            let begin = start + lead;
+            links.push(Link { range: begin..begin + trimmed.chars().count(), kind, url });
+        }
+        start += len + 1;
+    }
+    links
+}
+
+fn link_of(word: &str) -> Option<(LinkKind, String)> {
+    if let Some((scheme, rest)) = word.split_once("://") {
+        let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c));
+        return (valid && !rest.is_empty()).then(|| (LinkKind::Url, word.to_string()));
+    }
+    if word.len() > 4 && word.starts_with("www.") {
+        return Some((LinkKind::Url, format!("https://{}", word)));
+    }
+    let path = if let Some(rest) = word.strip_prefix("~/") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(rest))?
+    } else if word.starts_with("./") || word.starts_with("../") {
+        env::current_dir().ok()?.join(word)
+    } else if word.len() > 1 && word.starts_with('/') {
+        PathBuf::from(word)
+    } else {
+        return None;
+    };
+    Some((LinkKind::Path, format!("file://{}", path.display())))
+}
+
+/// Underlines the URLs and the file paths in the input, see [find_links], and makes them
+/// hyperlinks in terminals supporting them, see `Renderer::set_hyperlinks`.
+#[derive(Debug, Clone, Default)]
+pub struct Links {
+    /// Color of the links, None to keep the color they have.
+    pub color: Option<Color>,
+}
+
+impl Transformation for Links {
+    fn transform(&self, display: DisplayText) -> DisplayText {
+        let links = find_links(&display.text.text());
+        if links.is_empty() {
+            return display;
+        }
+        display.map_chars(|i, c, style| match links.iter().find(|link| link.range.contains(&i)) {
+            Some(link) => {
+                let style = SpanStyle { fg: self.color.or(style.fg), underline: true, ..style };
+                StyledText::new().link(c.to_string(), link.url.as_str(), style)
+            }
+            None => StyledText::new().span(c.to_string(), style),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_links() {
+        let links = find_links("curl 'https://example.com/a?b=1', see www.rust-lang.org. ftp:// a://b");
+        assert_eq!(3, links.len());
+        assert_eq!(6..31, links[0].range);
+        assert_eq!("https://example.com/a?b=1", links[0].url);
+        assert_eq!(LinkKind::Url, links[0].kind);
+        assert_eq!("https://www.rust-lang.org", links[1].url);
+        assert_eq!("a://b", links[2].url);
+
+        let links = find_links("cat /etc/hosts (./ä.txt) / ~ x/y");
+        assert_eq!(2, links.len());
+        assert_eq!((4..14, "file:///etc/hosts"), (links[0].range.clone(), links[0].url.as_str()));
+        assert_eq!(16..23, links[1].range);
+        assert_eq!(LinkKind::Path, links[1].kind);
+        assert!(links[1].url.starts_with("file:///") && links[1].url.ends_with("/./ä.txt"));
+    }
+
+    #[test]
+    fn test_links() {
+        let d = Links::default().transform(DisplayText::new("open /tmp/a\thttp://x", 0));
+        let linked = d.text.spans().iter()
+            .filter(|s| s.style.underline)
+            .map(|s| (s.text.as_str(), s.link.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![("/tmp/a", true), ("http://x", true)], linked);
+        // the links are kept by the transformations after it
+        let d = crate::transform::TabExpansion::default().transform(d);
+        assert_eq!(Some("http://x"), d.text.spans().last().unwrap().link.as_deref());
+    }
+}
@@ -0,0 +1,187 @@
+use std::error::Error;
+use std::fmt;
+
+use crossterm::style::Color;
+
+use crate::style::{SpanStyle, StyledText};
+
+/// Error returned by [parse_markup].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkupError {
+    /// The tag is neither an attribute nor a known color.
+    UnknownTag(String),
+    /// A closing tag doesn't match the most recently opened tag.
+    MismatchedTag { expected: String, found: String },
+    /// A closing tag was found without any opened tag.
+    UnexpectedClosingTag(String),
+    /// The input ended while a tag was still open.
+    UnclosedTag(String),
+    /// A `<` was never followed by a `>`.
+    UnterminatedTag,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkupError::UnknownTag(tag) => write!(f, "unknown tag <{}>", tag),
+            MarkupError::MismatchedTag { expected, found } => {
+                write!(f, "expected </{}>, found </{}>", expected, found)
+            }
+            MarkupError::UnexpectedClosingTag(tag) => write!(f, "unexpected closing tag </{}>", tag),
+            MarkupError::UnclosedTag(tag) => write!(f, "tag <{}> is never closed", tag),
+            MarkupError::UnterminatedTag => write!(f, "'<' without a matching '>'"),
+        }
+    }
+}
+
+impl Error for MarkupError {}
+
+/// Parses a small inline markup into [StyledText].
+///
+/// Supported tags are `<bold>`/`<b>`, `<italic>`/`<i>`, `<underline>`/`<u>`, foreground
+/// colors (`<blue>`, `<dark-red>`, `<#ff8800>`) and background colors (`<bg-blue>`,
+/// `<bg-#002b36>`). Tags nest and must be closed in order, e.g.
+/// `"<bold><blue>host</blue></bold> $ "`. Use `&lt;`, `&gt;` and `&amp;` for literal
+/// `<`, `>` and `&`.
+pub fn parse_markup(markup: &str) -> Result<StyledText, MarkupError> {
+    let mut text = StyledText::new();
+    let mut stack: Vec<(String, SpanStyle)> = Vec::new();
+    let mut rest = markup;
+
+    while !rest.is_empty() {
+        let current = stack.last().map(|(_, s)| *s).unwrap_or_default();
+        match rest.find('<') {
+            Some(0) => {
+                let end = rest.find('>').ok_or(MarkupError::UnterminatedTag)?;
+                let tag = &rest[1..end];
+                rest = &rest[end + 1..];
+
+                if let Some(name) = tag.strip_prefix('/') {
+                    match stack.pop() {
+                        None => return Err(MarkupError::UnexpectedClosingTag(name.to_string())),
+                        Some((open, _)) if open != name => {
+                            return Err(MarkupError::MismatchedTag {
+                                expected: open,
+                                found: name.to_string(),
+                            })
+                        }
+                        Some(_) => {}
+                    }
+                } else {
+                    let style = apply_tag(current, tag)
+                        .ok_or_else(|| MarkupError::UnknownTag(tag.to_string()))?;
+                    stack.push((tag.to_string(), style));
+                }
+            }
+            Some(start) => {
+                text.push(unescape(&rest[..start]), current);
+                rest = &rest[start..];
+            }
+            None => {
+                text.push(unescape(rest), current);
+                rest = "";
+            }
+        }
+    }
+
+    match stack.pop() {
+        Some((tag, _)) => Err(MarkupError::UnclosedTag(tag)),
+        None => Ok(text),
+    }
+}
+
+fn apply_tag(style: SpanStyle, tag: &str) -> Option<SpanStyle> {
+    match tag {
+        "b" | "bold" => Some(style.bold()),
+        "i" | "italic" => Some(style.italic()),
+        "u" | "underline" => Some(style.underline()),
+        _ => match tag.strip_prefix("bg-") {
+            Some(color) => parse_color(color).map(|c| style.bg(c)),
+            None => parse_color(tag).map(|c| style.fg(c)),
+        },
+    }
+}
+
+/// Parses a color name as used in markup, e.g. `blue`, `dark-grey` or `#268bd2`.
+pub fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    let color = match name {
+        "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        "dark-grey" | "dark-gray" => Color::DarkGrey,
+        "dark-red" => Color::DarkRed,
+        "dark-green" => Color::DarkGreen,
+        "dark-yellow" => Color::DarkYellow,
+        "dark-blue" => Color::DarkBlue,
+        "dark-magenta" => Color::DarkMagenta,
+        "dark-cyan" => Color::DarkCyan,
+        _ => return None,
+    };
+    Some(color)
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markup() {
+        let got = parse_markup("<bold><blue>host</blue></bold> $ ").unwrap();
+        let want = StyledText::new()
+            .span("host", SpanStyle::default().bold().fg(Color::Blue))
+            .plain(" $ ");
+        assert_eq!(want, got);
+        assert_eq!(7, got.width());
+    }
+
+    #[test]
+    fn test_parse_markup_colors_and_escapes() {
+        let got = parse_markup("<bg-#002b36><u>a&lt;b</u></bg-#002b36>&amp;").unwrap();
+        let want = StyledText::new()
+            .span("a<b", SpanStyle::default().bg(Color::Rgb { r: 0, g: 0x2b, b: 0x36 }).underline())
+            .plain("&");
+        assert_eq!(want, got);
+    }
+
+    #[test]
+    fn test_parse_markup_width_ignores_tags() {
+        let got = parse_markup("<green>日本</green>語 ").unwrap();
+        assert_eq!(7, got.width());
+        assert_eq!("日本語 ", got.text());
+    }
+
+    #[test]
+    fn test_parse_markup_errors() {
+        assert_eq!(Err(MarkupError::UnknownTag("blink".to_string())), parse_markup("<blink>x</blink>"));
+        assert_eq!(
+            Err(MarkupError::MismatchedTag { expected: "i".to_string(), found: "b".to_string() }),
+            parse_markup("<b><i>x</b></i>"),
+        );
+        assert_eq!(Err(MarkupError::UnexpectedClosingTag("b".to_string())), parse_markup("x</b>"));
+        assert_eq!(Err(MarkupError::UnclosedTag("red".to_string())), parse_markup("<red>x"));
+        assert_eq!(Err(MarkupError::UnterminatedTag), parse_markup("a <b"));
+        assert_eq!(None, parse_color("#12345"));
+    }
+}
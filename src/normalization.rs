@@ -0,0 +1,45 @@
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization form applied to typed text, see `Prompt::with_normalization`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Canonical composition: `e` followed by a combining acute accent becomes `é`, as
+    /// typed on most systems.
+    Nfc,
+    /// Compatibility composition: is like Nfc, and also folds compatibility characters,
+    /// e.g. `ﬁ` into `fi` and full-width `Ａ` into `A`.
+    Nfkc,
+}
+
+impl Normalization {
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfkc => text.nfkc().collect(),
+        }
+    }
+
+    /// Returns the byte index of the last char of `text` that chars typed after it may
+    /// combine with, so that only the text from there needs normalizing again.
+    pub(crate) fn combining_start(text: &str) -> usize {
+        text.char_indices().rev()
+            .find(|&(_, c)| canonical_combining_class(c) == 0)
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!("caf\u{e9}", Normalization::Nfc.apply("cafe\u{301}"));
+        assert_eq!("\u{fb01}le", Normalization::Nfc.apply("\u{fb01}le"));
+        assert_eq!("file", Normalization::Nfkc.apply("\u{fb01}le"));
+        assert_eq!("A1", Normalization::Nfkc.apply("\u{ff21}\u{ff11}"));
+        assert_eq!(3, Normalization::combining_start("cafe\u{301}"));
+        assert_eq!(0, Normalization::combining_start(""));
+    }
+}
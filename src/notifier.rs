@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+/// Something that did not work out after a key press, see [Notifier].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Notice {
+    /// Tab was pressed, but there is nothing to complete.
+    NoCompletions,
+    /// Up or Down was pressed at the oldest or newest history entry.
+    HistoryEnd,
+    /// A key combination that nothing is bound to was pressed, e.g. Ctrl-Q.
+    UnboundKey,
+    /// A typed character was rejected by the character filter or the maximum length of
+    /// the input.
+    RejectedInput,
+}
+
+impl Notice {
+    /// Returns the message shown for [Feedback::Message].
+    pub fn message(self) -> &'static str {
+        match self {
+            Notice::NoCompletions => "No completions.",
+            Notice::HistoryEnd => "No more history.",
+            Notice::UnboundKey => "Nothing is bound to this key.",
+            Notice::RejectedInput => "This character is not accepted.",
+        }
+    }
+}
+
+/// How a [Notice] is brought to the user's attention.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    #[default]
+    Silent,
+    /// Rings the terminal bell.
+    Bell,
+    /// Briefly flashes the screen in reverse video.
+    Flash,
+    /// Shows the notice's message in place of the toolbar until the next key press.
+    Message,
+}
+
+/// Tells what feedback to give for each [Notice], see `Prompt::with_notifier`. Rejected
+/// input shows a message by default, other notices are silent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notifier {
+    policies: HashMap<Notice, Feedback>,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self { policies: HashMap::from([(Notice::RejectedInput, Feedback::Message)]) }
+    }
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a notifier giving `feedback` for every notice.
+    pub fn all(feedback: Feedback) -> Self {
+        Self::new()
+            .with(Notice::NoCompletions, feedback)
+            .with(Notice::HistoryEnd, feedback)
+            .with(Notice::UnboundKey, feedback)
+            .with(Notice::RejectedInput, feedback)
+    }
+
+    pub fn with(mut self, notice: Notice, feedback: Feedback) -> Self {
+        self.set(notice, feedback);
+        self
+    }
+
+    pub fn set(&mut self, notice: Notice, feedback: Feedback) {
+        self.policies.insert(notice, feedback);
+    }
+
+    pub fn feedback(&self, notice: Notice) -> Feedback {
+        self.policies.get(&notice).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifier() {
+        let n = Notifier::all(Feedback::Bell).with(Notice::HistoryEnd, Feedback::Message);
+        assert_eq!(Feedback::Bell, n.feedback(Notice::NoCompletions));
+        assert_eq!(Feedback::Message, n.feedback(Notice::HistoryEnd));
+        assert_eq!(Feedback::Silent, Notifier::new().feedback(Notice::UnboundKey));
+        assert_eq!(Feedback::Message, Notifier::new().feedback(Notice::RejectedInput));
+    }
+}
@@ -0,0 +1,311 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crossterm::style::Print;
+use crossterm::terminal::{self, ClearType};
+use crossterm::queue;
+
+use crate::pager::{self, PagerMode};
+use crate::render::write_styled;
+use crate::repl_screen::ReplScreen;
+use crate::style::{Style, StyledText};
+
+/// Columns of the bar drawn by [Output::progress].
+const PROGRESS_WIDTH: usize = 20;
+
+/// Handle for the executor to write its output with, see `Prompt::with_output`, so that
+/// the output goes where the prompt's own output goes: above the input of a
+/// [ReplScreen], or to the terminal, with links written as hyperlinks where the terminal
+/// supports them.
+///
+/// Clones share the same output, so the executor keeps one to print with while the
+/// prompt holds another.
+#[derive(Clone)]
+pub struct Output {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    out: Box<dyn Write + Send>,
+    /// Where the prompt runs, if it runs in the alternate screen.
+    screen: Option<ReplScreen>,
+    hyperlinks: bool,
+    /// The progress bar shown on the last lines, and the rows it takes.
+    progress: Option<(StyledText, usize)>,
+    /// How output taller than the terminal is paged, see [Output::set_pager].
+    pager: Option<PagerMode>,
+    /// Rows written since the command began.
+    rows: usize,
+    /// Lines held back for the pager once a screenful was written.
+    held: Vec<String>,
+    /// Size of the terminal, asked for on every line unless set.
+    size: Option<(u16, u16)>,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::with_writer(io::stdout())
+    }
+}
+
+impl Output {
+    /// Returns an output writing to stdout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an output writing to `out` rather than stdout, unless the prompt runs in a
+    /// [ReplScreen].
+    pub fn with_writer<W: Write + Send + 'static>(out: W) -> Self {
+        let inner = Inner {
+            out: Box::new(out),
+            screen: None,
+            hyperlinks: false,
+            progress: None,
+            pager: None,
+            rows: 0,
+            held: Vec::new(),
+            size: None,
+        };
+        Self { inner: Arc::new(Mutex::new(inner)) }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pages the output of a command once it fills the terminal: the lines after the
+    /// first screenful are held back and shown through [page](crate::page) in `mode`
+    /// when the command is done. Output shown in a [ReplScreen] is never paged, it is
+    /// scrolled back instead.
+    pub fn set_pager(&self, pager: Option<PagerMode>) {
+        self.inner().pager = pager;
+    }
+
+    pub fn pager(&self) -> Option<PagerMode> {
+        self.inner().pager
+    }
+
+    /// Writes `text` as plain text on a line of its own, above the progress bar if there
+    /// is one.
+    pub fn print<S: AsRef<str>>(&self, text: S) {
+        self.print_styled(&StyledText::from(text.as_ref()));
+    }
+
+    /// Is [print](Output::print), keeping the styles and the links of the spans of `text`.
+    pub fn print_styled(&self, text: &StyledText) {
+        let mut inner = self.inner();
+        let bar = inner.remove_progress();
+        inner.print_line(text);
+        if let Some(bar) = bar {
+            inner.show_progress(bar);
+        }
+        inner.flush();
+    }
+
+    /// Shows a progress bar for `fraction`, from 0 to 1, on the last line, replacing the
+    /// one shown before. It is removed once the command is done.
+    pub fn progress(&self, fraction: f32) {
+        let fraction = if fraction.is_nan() { 0.0 } else { fraction.clamp(0.0, 1.0) };
+        let done = (fraction * PROGRESS_WIDTH as f32).round() as usize;
+        let bar = format!("[{}{}] {:>3}%", "#".repeat(done), " ".repeat(PROGRESS_WIDTH - done), (fraction * 100.0).round());
+        let mut inner = self.inner();
+        inner.remove_progress();
+        inner.show_progress(StyledText::from(bar));
+        inner.flush();
+    }
+
+    /// Prepares the output for a command of a prompt showing `screen`, if any.
+    pub(crate) fn begin(&self, screen: Option<ReplScreen>, hyperlinks: bool) {
+        let mut inner = self.inner();
+        inner.screen = screen;
+        inner.hyperlinks = hyperlinks;
+        inner.rows = 0;
+        inner.held.clear();
+    }
+
+    /// Removes the progress bar once the command is done, and pages the lines held back.
+    pub(crate) fn finish(&self) {
+        let (held, pager) = {
+            let mut inner = self.inner();
+            if inner.remove_progress().is_some() {
+                inner.flush();
+            }
+            (std::mem::take(&mut inner.held), inner.pager)
+        };
+        // the lock is released, so that the pager doesn't block other threads printing
+        if let (false, Some(mode)) = (held.is_empty(), pager) {
+            // output that can't be paged has nowhere else to go
+            let _ = pager::page_rest(&held.join("\n"), mode);
+        }
+    }
+
+    /// Sets the size of the terminal instead of asking it, e.g. for a writer that isn't one.
+    #[cfg(test)]
+    pub(crate) fn set_size(&self, size: (u16, u16)) {
+        self.inner().size = Some(size);
+    }
+}
+
+impl Inner {
+    fn size(&self) -> Option<(u16, u16)> {
+        self.size.or_else(|| terminal::size().ok())
+    }
+
+    /// Writes `text` on a line of its own, or holds it back for the pager once the lines
+    /// written fill the terminal, leaving a row for the pager's prompt.
+    fn print_line(&mut self, text: &StyledText) {
+        let size = self.size().filter(|_| self.pager.is_some() && self.screen.is_none());
+        if let Some((col, row)) = size {
+            let rows = pager::rows(&text.text(), col);
+            if !self.held.is_empty() || self.rows + rows >= row as usize {
+                let line = self.styled(text);
+                self.held.push(String::from_utf8_lossy(&line).into_owned());
+                return;
+            }
+        }
+        self.rows += self.write_line(text);
+    }
+
+    fn show_progress(&mut self, bar: StyledText) {
+        let rows = self.write_line(&bar);
+        self.progress = Some((bar, rows));
+    }
+
+    fn styled(&self, text: &StyledText) -> Vec<u8> {
+        let mut line = Vec::new();
+        // output that can't be written has nowhere else to go
+        let _ = write_styled(&mut line, text, Style::default(), self.hyperlinks);
+        line
+    }
+
+    /// Writes `text` on a line of its own, returning the rows it takes.
+    fn write_line(&mut self, text: &StyledText) -> usize {
+        let line = self.styled(text);
+        match &self.screen {
+            Some(screen) => {
+                screen.println(String::from_utf8_lossy(&line));
+                1
+            }
+            None => {
+                let _ = self.out.write_all(&line).and_then(|_| self.out.write_all(b"\n"));
+                self.size().map_or(1, |(col, _)| pager::rows(&text.text(), col))
+            }
+        }
+    }
+
+    /// Removes the progress bar, if there is one, returning it.
+    fn remove_progress(&mut self) -> Option<StyledText> {
+        let (bar, rows) = self.progress.take()?;
+        match &self.screen {
+            Some(screen) => screen.pop(),
+            // the bar is on the rows above the cursor
+            None => {
+                let _ = queue!(self.out, Print(format!("\x1b[{}A\r", rows)), terminal::Clear(ClearType::FromCursorDown));
+            }
+        }
+        Some(bar)
+    }
+
+    fn flush(&mut self) {
+        match &self.screen {
+            Some(screen) => {
+                if let Ok((col, row)) = terminal::size() {
+                    let mut stdout = io::stdout();
+                    let _ = screen.draw(&mut stdout, col, row).and_then(|_| stdout.flush());
+                }
+            }
+            None => {
+                let _ = self.out.flush();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::width::strip_ansi;
+
+    /// Writer whose output the test can read while the [Output] owns it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        /// Returns what was written since the last call, without the style resets.
+        fn take(&self) -> String {
+            let written = String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap();
+            written.replace("\x1b[0m", "").replace("\x1b[39m", "").replace("\x1b[49m", "")
+        }
+    }
+
+    #[test]
+    fn test_output() {
+        let out = Shared::default();
+        let output = Output::with_writer(out.clone());
+        output.set_size((80, 24));
+        output.begin(None, false);
+        output.print("a");
+        assert_eq!("a\n", out.take());
+        output.clone().progress(0.5);
+        assert_eq!("[##########          ]  50%\n", out.take());
+        // the bar stays below what is printed
+        output.print("b");
+        let written = out.take();
+        assert!(written.starts_with("\x1b[1A\r"));
+        assert!(written.ends_with("b\n[##########          ]  50%\n"));
+        output.finish();
+        assert!(out.take().starts_with("\x1b[1A\r"));
+        output.finish();
+        assert_eq!("", out.take());
+
+        let screen = ReplScreen::new(2);
+        output.begin(Some(screen.clone()), false);
+        output.progress(2.0);
+        output.print("c");
+        assert_eq!(2, screen.len());
+        output.finish();
+        assert_eq!(1, screen.len());
+        assert_eq!("", out.take());
+    }
+
+    #[test]
+    fn test_output_erases_wrapped_progress() {
+        let out = Shared::default();
+        let output = Output::with_writer(out.clone());
+        // the bar wraps over 3 rows
+        output.set_size((10, 24));
+        output.progress(0.5);
+        out.take();
+        output.print("a");
+        assert!(out.take().starts_with("\x1b[3A\r"));
+    }
+
+    #[test]
+    fn test_output_holds_lines_for_the_pager() {
+        let out = Shared::default();
+        let output = Output::with_writer(out.clone());
+        output.set_size((20, 4));
+        output.set_pager(Some(PagerMode::Internal));
+        output.begin(None, false);
+        for line in ["1", "a line wrapping over two rows", "3", "4"] {
+            output.print(line);
+        }
+        // a row is left for the pager's prompt
+        assert_eq!("1\na line wrapping over two rows\n", out.take());
+        let held = output.inner().held.iter().map(|line| strip_ansi(line).into_owned()).collect::<Vec<_>>();
+        assert_eq!(vec!["3", "4"], held);
+        output.begin(None, false);
+        assert!(output.inner().held.is_empty());
+    }
+}
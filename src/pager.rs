@@ -0,0 +1,191 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{cursor, queue};
+
+use crate::key_bind::Key;
+use crate::width::display_width;
+
+/// How [page] shows text taller than the terminal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PagerMode {
+    /// Built-in pager in the style of `more`: Space shows the next page, Enter the next
+    /// line and q quits.
+    #[default]
+    Internal,
+    /// The program in `$PAGER`, e.g. `less -R`. Falls back to the built-in pager when
+    /// `$PAGER` is not set.
+    External,
+}
+
+/// Prints `text`, e.g. the output of an executor, through a pager if it has more lines
+/// than the terminal, so that its beginning does not scroll away before it is read.
+///
+/// Must be called outside of raw mode, as executors are. The terminal is left as it
+/// was found, with the cursor on a new line.
+pub fn page(text: &str, mode: PagerMode) -> io::Result<()> {
+    let (col, row) = terminal::size()?;
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut out = io::stdout();
+    if height(&lines, col) < row as usize {
+        for line in lines {
+            writeln!(out, "{}", line)?;
+        }
+        return out.flush();
+    }
+    match (mode, env::var_os("PAGER").filter(|p| !p.is_empty())) {
+        (PagerMode::External, Some(pager)) => external(text, &pager),
+        _ => More::new(lines, col).run(&mut out, row, true),
+    }
+}
+
+/// Is the same as [page] for the lines following a screenful that was already printed:
+/// the built-in pager asks for a key before showing anything.
+pub(crate) fn page_rest(text: &str, mode: PagerMode) -> io::Result<()> {
+    let (col, row) = terminal::size()?;
+    match (mode, env::var_os("PAGER").filter(|p| !p.is_empty())) {
+        (PagerMode::External, Some(pager)) => external(text, &pager),
+        _ => More::new(text.lines().collect(), col).run(&mut io::stdout(), row, false),
+    }
+}
+
+/// Returns the number of rows `lines` take on a terminal `col` columns wide.
+fn height(lines: &[&str], col: u16) -> usize {
+    lines.iter().map(|l| rows(l, col)).sum()
+}
+
+/// Returns the number of rows `line` takes on a terminal `col` columns wide.
+pub(crate) fn rows(line: &str, col: u16) -> usize {
+    display_width(line).div_ceil(col.max(1) as usize).max(1)
+}
+
+fn external(text: &str, pager: &std::ffi::OsStr) -> io::Result<()> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell).arg(flag).arg(pager).stdin(Stdio::piped()).spawn()?;
+    let mut stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+    match stdin.write_all(text.as_bytes()) {
+        // the pager was quit before reading everything
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        result => result?,
+    }
+    drop(stdin);
+    child.wait()?;
+    Ok(())
+}
+
+/// State of the built-in pager.
+struct More<'a> {
+    lines: Vec<&'a str>,
+    col: u16,
+    /// Number of lines printed so far.
+    shown: usize,
+}
+
+impl<'a> More<'a> {
+    fn new(lines: Vec<&'a str>, col: u16) -> Self {
+        Self { lines, col, shown: 0 }
+    }
+
+    /// Returns the number of lines after the shown ones that fit in `rows` rows, at
+    /// least one.
+    fn fit(&self, rows: usize) -> usize {
+        let mut used = 0;
+        let count = self.lines[self.shown..].iter()
+            .take_while(|line| {
+                used += self::rows(line, self.col);
+                used <= rows
+            })
+            .count();
+        count.max(1)
+    }
+
+    /// Returns the number of lines to show next after `key` is pressed on a terminal
+    /// with `rows` rows, or None to quit.
+    fn feed(&self, key: Key, rows: usize) -> Option<usize> {
+        let n = match key.code {
+            _ if key == Key::ctrl('c') => return None,
+            KeyCode::Char('q' | 'Q') | KeyCode::Esc => return None,
+            KeyCode::Char(' ' | 'f') | KeyCode::PageDown => self.fit(rows - 1),
+            KeyCode::Enter | KeyCode::Down | KeyCode::Char('j') => 1,
+            _ => 0,
+        };
+        Some(n.min(self.lines.len() - self.shown))
+    }
+
+    fn print<W: Write>(&mut self, out: &mut W, n: usize) -> io::Result<()> {
+        for line in &self.lines[self.shown..self.shown + n] {
+            queue!(out, Print(line), Print("\r\n"))?;
+        }
+        self.shown += n;
+        Ok(())
+    }
+
+    fn status(&self) -> String {
+        format!("--More-- ({}%)", self.shown * 100 / self.lines.len())
+    }
+
+    fn run<W: Write>(&mut self, out: &mut W, row: u16, first_page: bool) -> io::Result<()> {
+        let rows = row.max(2) as usize;
+        if first_page {
+            self.print(out, self.fit(rows - 1))?;
+        }
+        while self.shown < self.lines.len() {
+            queue!(out, SetAttribute(Attribute::Reverse), Print(self.status()), SetAttribute(Attribute::Reset))?;
+            out.flush()?;
+            terminal::enable_raw_mode()?;
+            let key = read_key();
+            terminal::disable_raw_mode()?;
+            queue!(out, cursor::MoveToColumn(0), Clear(ClearType::CurrentLine))?;
+            match self.feed(key?, rows) {
+                Some(n) => self.print(out, n)?,
+                None => break,
+            }
+        }
+        out.flush()
+    }
+}
+
+fn read_key() -> io::Result<Key> {
+    loop {
+        if let Event::Key(event) = event::read()? {
+            if event.kind != KeyEventKind::Release {
+                return Ok(event.into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_height() {
+        assert_eq!(3, height(&["a", "", "b"], 80));
+        assert_eq!(3, height(&["日本語", "abcd"], 4));
+    }
+
+    #[test]
+    fn test_more() {
+        let lines = (0..10).map(|i| if i == 3 { "a long line wrapping over three rows" } else { "line" }).collect();
+        let mut m = More::new(lines, 16);
+        assert_eq!(3, m.fit(5));
+        m.print(&mut Vec::new(), 4).unwrap();
+        assert_eq!("--More-- (40%)", m.status());
+        assert_eq!(Some(1), m.feed(Key::plain(KeyCode::Enter), 6));
+        assert_eq!(Some(5), m.feed(Key::plain(KeyCode::Char(' ')), 6));
+        assert_eq!(Some(0), m.feed(Key::plain(KeyCode::Char('x')), 6));
+        assert_eq!(None, m.feed(Key::plain(KeyCode::Char('q')), 6));
+        assert_eq!(None, m.feed(Key::ctrl('c'), 6));
+        // never past the end
+        assert_eq!(Some(6), m.feed(Key::plain(KeyCode::Char(' ')), 50));
+
+        let mut out = Vec::new();
+        m.print(&mut out, 2).unwrap();
+        assert_eq!("line\r\nline\r\n", String::from_utf8(out).unwrap());
+    }
+}
@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+use std::env;
+use std::path::PathBuf;
+
+use crate::document::WordModel;
+
+/// How paths are written on a platform: which characters separate their components and
+/// what `~` stands for. Used by [FilePathCompleter](crate::FilePathCompleter), and for
+/// the word separators of completion and word-wise motion.
+///
+/// [native](PathRules::native), the default, follows the platform the program runs on;
+/// the rules of the other platform, or a different home directory, can be set instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathRules {
+    /// Characters separating path components, the first one being the preferred one.
+    pub separators: Vec<char>,
+    /// Directory a leading `~` expands to, or None to leave `~` alone.
+    pub home: Option<PathBuf>,
+}
+
+impl Default for PathRules {
+    fn default() -> Self {
+        Self::native()
+    }
+}
+
+impl PathRules {
+    /// The rules of the platform the program runs on.
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Self::windows()
+        } else {
+            Self::unix()
+        }
+    }
+
+    /// Components separated by `/`, and `~` standing for `$HOME`.
+    pub fn unix() -> Self {
+        Self { separators: vec!['/'], home: env::var_os("HOME").map(PathBuf::from) }
+    }
+
+    /// Components separated by `\` or `/`, and `~` standing for `%USERPROFILE%`, as
+    /// PowerShell has it.
+    pub fn windows() -> Self {
+        let home = env::var_os("USERPROFILE").or_else(|| env::var_os("HOME"));
+        Self { separators: vec!['\\', '/'], home: home.map(PathBuf::from) }
+    }
+
+    pub fn with_home(mut self, home: Option<PathBuf>) -> Self {
+        self.home = home;
+        self
+    }
+
+    pub fn is_separator(&self, c: char) -> bool {
+        self.separators.contains(&c)
+    }
+
+    /// Returns the completion word separator for completing one path component at a
+    /// time, see `Prompt::with_completion_word_separator`.
+    pub fn word_separator(&self) -> String {
+        self.separators.iter().collect()
+    }
+
+    /// Returns a word model stopping word-wise motion and deletion at spaces and path
+    /// separators, see `Prompt::with_word_model`.
+    pub fn word_model(&self) -> WordModel {
+        WordModel::Separator(format!(" {}", self.word_separator()))
+    }
+
+    /// Expands a leading `~`, alone or followed by a separator, to the home directory.
+    pub fn expand_tilde<'w>(&self, word: &'w str) -> Cow<'w, str> {
+        let Some(rest) = word.strip_prefix('~') else {
+            return Cow::Borrowed(word);
+        };
+        match &self.home {
+            Some(home) if rest.is_empty() || rest.starts_with(|c| self.is_separator(c)) => {
+                Cow::Owned(format!("{}{}", home.display(), rest))
+            }
+            _ => Cow::Borrowed(word),
+        }
+    }
+
+    /// Splits `path` after its last separator into the directory and the start of the
+    /// name in it, e.g. `src/ma` into `src/` and `ma`. The directory is empty if there is
+    /// no separator.
+    pub fn split<'p>(&self, path: &'p str) -> (&'p str, &'p str) {
+        match path.rfind(|c| self.is_separator(c)) {
+            Some(i) => {
+                let end = i + path[i..].chars().next().map_or(0, char::len_utf8);
+                path.split_at(end)
+            }
+            None => ("", path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_rules() {
+        let unix = PathRules::unix().with_home(Some(PathBuf::from("/home/me")));
+        assert_eq!("/home/me/src", unix.expand_tilde("~/src"));
+        assert_eq!("/home/me", unix.expand_tilde("~"));
+        assert_eq!("~other/src", unix.expand_tilde("~other/src"));
+        assert_eq!("~\\src", unix.expand_tilde("~\\src"));
+        assert_eq!(("/usr/", "lo"), unix.split("/usr/lo"));
+        assert_eq!(("", "usr"), unix.split("usr"));
+        assert_eq!(WordModel::Separator(" /".to_string()), unix.word_model());
+
+        let windows = PathRules::windows().with_home(Some(PathBuf::from("C:\\Users\\me")));
+        assert_eq!("C:\\Users\\me\\src", windows.expand_tilde("~\\src"));
+        assert_eq!(("C:\\Users\\", "Do"), windows.split("C:\\Users\\Do"));
+        assert_eq!(("C:/Users/", "Do"), windows.split("C:/Users/Do"));
+        assert_eq!("\\/", windows.word_separator());
+
+        assert_eq!("~/src", unix.with_home(None).expand_tilde("~/src"));
+    }
+}
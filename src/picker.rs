@@ -0,0 +1,261 @@
+use std::io::{self, Write};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+
+use crate::fuzzy::fuzzy_score;
+use crate::key_bind::Key;
+use crate::width::{display_width, truncate_to_width};
+
+/// Rows above the results: the query and the match count.
+const HEADER_HEIGHT: u16 = 2;
+/// Terminals with fewer rows show no preview.
+const MIN_PREVIEW_ROWS: u16 = 10;
+
+/// What the picker does after a key press.
+#[derive(Debug, PartialEq, Eq)]
+enum Pick {
+    Continue,
+    /// The entry at this index was chosen.
+    Done(usize),
+    Cancel,
+}
+
+/// Full-screen fuzzy finder, e.g. over the history, see `Prompt::with_history_picker`: a
+/// query line, the matching entries best first, and a preview of the whole selected
+/// entry, which helps with multi-line ones.
+struct Picker<'h> {
+    entries: &'h [&'h str],
+    query: String,
+    /// Indexes of the entries matching the query, best first.
+    matches: Vec<usize>,
+    selected: usize,
+    /// Index in `matches` of the first result shown.
+    scroll: usize,
+}
+
+impl<'h> Picker<'h> {
+    fn new(entries: &'h [&'h str]) -> Self {
+        let mut picker = Self { entries, query: String::new(), matches: Vec::new(), selected: 0, scroll: 0 };
+        picker.update_matches();
+        picker
+    }
+
+    /// Ranks the entries by score, in their order among equal scores.
+    fn update_matches(&mut self) {
+        let mut scored = self.entries.iter()
+            .enumerate()
+            .filter_map(|(i, entry)| fuzzy_score(&self.query, entry).map(|score| (score, i)))
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    fn selected_entry(&self) -> Option<&'h str> {
+        self.matches.get(self.selected).map(|&i| self.entries[i])
+    }
+
+    fn done(&self) -> Pick {
+        self.matches.get(self.selected).map_or(Pick::Cancel, |&i| Pick::Done(i))
+    }
+
+    fn feed(&mut self, key: Key) -> Pick {
+        let last = self.matches.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Enter => return self.done(),
+            KeyCode::Esc => return Pick::Cancel,
+            _ if key == Key::ctrl('c') || key == Key::ctrl('g') => return Pick::Cancel,
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            _ if key == Key::ctrl('n') || key == Key::ctrl('r') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            _ if key == Key::ctrl('p') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::PageDown => self.selected = (self.selected + 10).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(10),
+            KeyCode::Backspace if self.query.pop().is_some() => self.update_matches(),
+            _ if key == Key::ctrl('u') => {
+                self.query.clear();
+                self.update_matches();
+            }
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        Pick::Continue
+    }
+
+    /// Returns the number of rows for the results and for the preview on a `row` rows
+    /// terminal. The preview includes its separator line.
+    fn layout(&self, row: u16) -> (usize, usize) {
+        let rest = row.saturating_sub(HEADER_HEIGHT) as usize;
+        let preview = if row >= MIN_PREVIEW_ROWS { rest / 3 } else { 0 };
+        (rest - preview, preview)
+    }
+
+    /// Keeps the selected result within the `height` rows of the results.
+    fn scroll_to_selected(&mut self, height: usize) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if height > 0 && self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W, col: u16, row: u16) -> io::Result<()> {
+        let width = col as usize;
+        let (results, preview) = self.layout(row);
+        self.scroll_to_selected(results);
+        queue!(out, cursor::MoveTo(0, 0), terminal::Clear(ClearType::All))?;
+        queue!(out, Print(truncate_to_width(&format!("> {}", self.query), width)))?;
+        let count = format!("  {}/{}", self.matches.len(), self.entries.len());
+        queue!(out, cursor::MoveTo(0, 1), SetAttribute(Attribute::Dim), Print(truncate_to_width(&count, width)))?;
+        queue!(out, SetAttribute(Attribute::Reset))?;
+
+        let rows = self.matches.iter().enumerate().skip(self.scroll).take(results);
+        for (y, (i, &entry)) in rows.enumerate() {
+            let line = format!("{} {}", if i == self.selected { '>' } else { ' ' }, one_line(self.entries[entry]));
+            queue!(out, cursor::MoveTo(0, HEADER_HEIGHT + y as u16))?;
+            if i == self.selected {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(out, Print(truncate_to_width(&line, width)), SetAttribute(Attribute::Reset))?;
+        }
+
+        if let (Some(entry), true) = (self.selected_entry(), preview > 0) {
+            let top = HEADER_HEIGHT + results as u16;
+            queue!(out, cursor::MoveTo(0, top), SetAttribute(Attribute::Dim))?;
+            queue!(out, Print("─".repeat(width)), SetAttribute(Attribute::Reset))?;
+            for (y, line) in entry.lines().take(preview - 1).enumerate() {
+                queue!(out, cursor::MoveTo(0, top + 1 + y as u16), Print(truncate_to_width(line, width)))?;
+            }
+        }
+        let query_width = display_width(&self.query) + 2;
+        queue!(out, cursor::MoveTo(query_width.min(width.saturating_sub(1)) as u16, 0))?;
+        out.flush()
+    }
+}
+
+/// Shows the lines of a multi-line entry on a single row.
+pub(crate) fn one_line(entry: &str) -> String {
+    entry.replace('\n', " ↵ ")
+}
+
+/// Returns the history entries newest first, without duplicates.
+pub(crate) fn recent_first<'h>(histories: &[&'h str]) -> Vec<&'h str> {
+    let mut entries: Vec<&str> = Vec::new();
+    for &entry in histories.iter().rev() {
+        if !entry.is_empty() && !entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/// Lets the user fuzzy-search `entries` in raw mode. Returns the index of the chosen
+/// entry, or None if the search is cancelled. The picker runs in the alternate screen,
+/// unless the terminal is already showing it, in which case the caller has to redraw it.
+pub(crate) fn pick(entries: &[&str], alternate: bool) -> io::Result<Option<usize>> {
+    let mut out = io::stdout();
+    if alternate {
+        queue!(out, terminal::EnterAlternateScreen)?;
+    }
+    let result = run(&mut out, &mut Picker::new(entries));
+    if alternate {
+        queue!(out, terminal::LeaveAlternateScreen)?;
+    }
+    out.flush()?;
+    result
+}
+
+fn run<W: Write>(out: &mut W, picker: &mut Picker) -> io::Result<Option<usize>> {
+    let (mut col, mut row) = terminal::size()?;
+    loop {
+        picker.draw(out, col, row)?;
+        match event::read()? {
+            Event::Key(event) if event.kind != KeyEventKind::Release => match picker.feed(event.into()) {
+                Pick::Continue => {}
+                Pick::Done(i) => return Ok(Some(i)),
+                Pick::Cancel => return Ok(None),
+            },
+            Event::Resize(c, r) => (col, row) = (c, r),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::width::strip_ansi;
+
+    fn histories() -> Vec<&'static str> {
+        vec!["git status", "ls", "git checkout main", "for f in *\ndo wc -l $f\ndone", "ls"]
+    }
+
+    fn type_query(picker: &mut Picker, query: &str) {
+        for c in query.chars() {
+            picker.feed(Key::plain(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn test_picker_matches() {
+        let histories = histories();
+        let entries = recent_first(&histories);
+        // newest first, without duplicates
+        assert_eq!(vec!["ls", "for f in *\ndo wc -l $f\ndone", "git checkout main", "git status"], entries);
+        let mut p = Picker::new(&entries);
+        assert_eq!(Some("ls"), p.selected_entry());
+
+        type_query(&mut p, "gch");
+        assert_eq!(Some("git checkout main"), p.selected_entry());
+        assert_eq!(1, p.matches.len());
+        p.feed(Key::plain(KeyCode::Backspace));
+        p.feed(Key::plain(KeyCode::Backspace));
+        assert_eq!(2, p.matches.len());
+        p.feed(Key::plain(KeyCode::Down));
+        p.feed(Key::plain(KeyCode::Down));
+        assert_eq!(1, p.selected);
+        assert_eq!(Pick::Done(3), p.feed(Key::plain(KeyCode::Enter)));
+
+        p.feed(Key::ctrl('u'));
+        type_query(&mut p, "wc");
+        assert_eq!(Pick::Done(1), p.feed(Key::plain(KeyCode::Enter)));
+        type_query(&mut p, "zzz");
+        assert_eq!(Pick::Cancel, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!(Pick::Cancel, p.feed(Key::ctrl('g')));
+    }
+
+    #[test]
+    fn test_picker_draw() {
+        let echoes = (0..20).map(|i| format!("echo {}", i)).collect::<Vec<_>>();
+        let histories = echoes.iter().map(String::as_str).chain(histories()).collect::<Vec<_>>();
+        let entries = recent_first(&histories);
+        let mut p = Picker::new(&entries);
+        assert_eq!((10, 5), p.layout(17));
+        assert_eq!((4, 0), p.layout(6));
+
+        type_query(&mut p, "do");
+        let mut out = Vec::new();
+        p.draw(&mut out, 40, 17).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let out = strip_ansi(&out);
+        assert!(out.contains("> do"));
+        assert!(out.contains("1/24"));
+        assert!(out.contains("> for f in * ↵ do wc -l $f ↵ done"));
+        // the preview shows the lines of the entry
+        assert!(out.contains("do wc -l $f"));
+
+        p.feed(Key::ctrl('u'));
+        for _ in 0..15 {
+            p.feed(Key::plain(KeyCode::Down));
+        }
+        p.draw(&mut Vec::new(), 40, 17).unwrap();
+        assert_eq!(6, p.scroll);
+    }
+}
@@ -0,0 +1,3608 @@
+use std::cell::OnceCell;
+use std::collections::VecDeque;
+use std::io::{self, Stdout, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Color;
+use crossterm::terminal;
+use ropey::Rope;
+
+use crate::buffer::{Buffer, TextChange};
+use crate::commands::Command;
+use crate::completion::{ColumnLayout, Completer, CompletionManager, CompletionTrigger, ContextCompleter, Suggestion};
+use crate::diagnostics::{Diagnostic, LintWorker};
+use crate::digraph::{SpecialInsert, Step};
+use crate::document::{Document, WordModel};
+use crate::emacs::EMACS_KEY_BINDINGS;
+use crate::esc::{self, Sequence};
+use crate::elapsed::{ElapsedPlacement, ElapsedTime};
+use crate::external_finder::ExternalFinder;
+use crate::frames::{Frames, DEFAULT_FRAME_INTERVAL};
+use crate::frecency::Frecency;
+use crate::help;
+use crate::history::{History, HistoryEntry};
+use crate::history_store::HistoryStore;
+use crate::inputrc::Inputrc;
+use crate::key_bind::{
+    ContextKeyBindFunc, Key, KeyBind, KeyBindMode, KeyContext, KeyHandlerFunc, COMMON_KEY_BINDINGS,
+};
+use crate::keymap::{Binding, Keymap, KeymapCondition, KeymapStack};
+use crate::lexer::{Highlight, Highlighter, Lexer};
+#[cfg(feature = "normalization")]
+use crate::normalization::Normalization;
+use crate::notifier::{Feedback, Notice, Notifier};
+use crate::picker::{self, recent_first};
+use crate::render::Renderer;
+use crate::cancel::{CancelToken, CtrlCListener};
+use crate::jobs::Jobs;
+use crate::output::Output;
+use crate::pager::PagerMode;
+use crate::repl_screen::ReplScreen;
+use crate::segments::Segments;
+use crate::status::Status;
+use crate::style::{SpanStyle, StyledText};
+use crate::theme::{Border, Theme};
+use crate::transform::PasswordMask;
+
+/// Number of suggestions shown at once unless configured otherwise.
+const DEFAULT_MAX_SUGGESTION: usize = 6;
+/// Number of history entries suggested after a `!`, see
+/// [Prompt::with_history_expansion].
+const MAX_HISTORY_SUGGESTIONS: usize = 50;
+/// Time between two frames of the spinner shown while suggestions are loading.
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+/// Time between two steps of a scrolling description, see
+/// [Prompt::with_completion_marquee].
+const MARQUEE_INTERVAL: Duration = Duration::from_millis(200);
+/// Commands shown in the key hints while editing, in this order, see
+/// [Prompt::with_key_hints].
+const EDITING_HINTS: &[&str] = &["complete", "reverse-search-history", "fuzzy-complete", "toggle-history-scope", "command-palette", "help", "previous-field"];
+/// Commands shown in the key hints while a suggestion is selected.
+const COMPLETING_HINTS: &[&str] = &["menu-complete", "menu-complete-backward", "menu-page-down"];
+
+/// Returns the keymap used while a suggestion is selected, on top of the built-in ones:
+/// the arrows, Tab and the pages move through the menu, Enter accepts the suggestion and
+/// Esc closes the menu until the next word. Other keys accept the suggestion and then do what they do.
+fn completion_keymap() -> Keymap {
+    let bindings = [
+        (KeyCode::Tab, "menu-complete"),
+        (KeyCode::Down, "menu-complete"),
+        (KeyCode::BackTab, "menu-complete-backward"),
+        (KeyCode::Up, "menu-complete-backward"),
+        (KeyCode::PageDown, "menu-page-down"),
+        (KeyCode::PageUp, "menu-page-up"),
+        (KeyCode::Enter, "accept-suggestion"),
+        (KeyCode::Esc, "dismiss-completion"),
+    ];
+    bindings.into_iter()
+        .filter_map(|(code, name)| Some((Key::plain(code), Binding::named(name)?)))
+        .fold(Keymap::new("completion"), |keymap, (key, binding)| keymap.with_binding(key, binding))
+}
+
+/// Returns the keymap used while the suggestions are shown but none is selected: Esc
+/// closes them until the next word.
+fn popup_keymap() -> Keymap {
+    let mut keymap = Keymap::new("popup");
+    if let Some(binding) = Binding::named("dismiss-completion") {
+        keymap.bind(Key::plain(KeyCode::Esc), binding);
+    }
+    keymap
+}
+
+/// Binds Alt-1 to Alt-9 in `keymap` to accept the suggestion shown with that number, see
+/// [Prompt::with_completion_number_keys].
+fn with_number_keys(keymap: Keymap) -> Keymap {
+    (1..=9).fold(keymap, |keymap, n| {
+        let key = char::from_digit(n, 10).map(Key::alt);
+        match (key, Binding::named(&format!("accept-suggestion-{}", n))) {
+            (Some(key), Some(binding)) => keymap.with_binding(key, binding),
+            _ => keymap,
+        }
+    })
+}
+
+/// Returns the toolbar text for the current input, see [Prompt::with_preview].
+type Preview<'a> = Box<dyn Fn(&Document) -> Option<StyledText> + 'a>;
+
+/// Returns what the preview pane shows for the input, see [Prompt::with_preview_pane].
+type PreviewPane<'a> = Box<dyn Fn(&Document) -> StyledText + 'a>;
+
+/// Runs the accepted input, see [Prompt::with_context].
+type Executor<'a, Ctx> = Box<dyn FnMut(&str, &mut Ctx) -> Status + 'a>;
+
+/// Returns whether to stop after running the input, see [Prompt::with_exit_checker].
+type ExitChecker<'a> = Box<dyn Fn(&str, Status) -> bool + 'a>;
+
+/// Returns why the input needs to be confirmed before it is run, see
+/// [Prompt::with_danger_check].
+type DangerCheck<'a> = Box<dyn Fn(&str) -> Option<String> + 'a>;
+
+/// Returns why the input can't be accepted, see [Prompt::with_validator].
+type Validator<'a> = Box<dyn Fn(&str) -> Result<(), String> + 'a>;
+
+/// Returns whether the input is complete, see [Prompt::with_input_complete].
+type InputComplete<'a> = Box<dyn Fn(&str) -> bool + 'a>;
+
+/// Returns whether a typed character is accepted, see [Prompt::with_char_filter].
+type CharFilter<'a> = Box<dyn Fn(char) -> bool + 'a>;
+
+/// Called while no key is pressed, see [Prompt::with_idle_callback].
+type IdleCallback<'a, Ctx> = Box<dyn FnMut(&mut Renderer<Stdout>, &mut Ctx) + 'a>;
+
+/// Returns the scope of the history for the current state, see
+/// [Prompt::with_history_scope].
+type HistoryScope<'a, Ctx> = Box<dyn Fn(&Ctx) -> Option<String> + 'a>;
+
+/// Updates the prefix segments for the context, see [Prompt::with_segment_update].
+type SegmentUpdate<'a, Ctx> = Box<dyn Fn(&Ctx, &mut Segments) + 'a>;
+
+/// Called with the suggestion inserted into the input, see [Prompt::with_on_accepted].
+type OnAccepted<'a, Ctx> = Box<dyn FnMut(&Suggestion, &mut Ctx) + 'a>;
+
+/// Called with each change of the input text, see [Prompt::with_on_change].
+type OnChange<'a, Ctx> = Box<dyn FnMut(&TextChange, &mut Ctx) + 'a>;
+
+/// Highlights the input, see [Prompt::with_lexer].
+type InputHighlighter<'a> = Box<dyn Highlight + 'a>;
+
+/// Something the [command palette](Prompt::with_command_palette) runs.
+#[derive(Debug, Clone, Copy)]
+enum PaletteItem {
+    Command(&'static Command),
+    /// The action at this index, see [Prompt::with_action].
+    Action(usize),
+}
+
+/// What the prompt loop waits for besides events.
+#[derive(Debug, PartialEq, Eq)]
+enum Timer {
+    /// Next frame, see [Frames].
+    Frame,
+    /// Next frame of the loading spinner.
+    Spinner,
+    /// Next step of the scrolling description.
+    Marquee,
+    /// Next check whether the linter is done.
+    Lint,
+    /// The input stayed the same long enough to update the preview pane.
+    PreviewPane,
+    Idle,
+    Timeout,
+}
+
+/// What the keymaps on top of the [stack](Prompt::keymap_stack) depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Editing,
+    /// Suggestions are shown, none of them selected.
+    Popup,
+    /// A suggestion is selected.
+    Completing,
+}
+
+/// What the prompt loop has to do after a key press.
+#[derive(Debug, PartialEq, Eq)]
+enum Feed {
+    Continue,
+    /// The input was accepted with Enter.
+    Accept,
+    /// The input was thrown away with Ctrl-C.
+    Cancel,
+    /// The input was commented out with Alt-#, to be kept in the history but not run.
+    Comment,
+    /// Ctrl-D was pressed on an empty input.
+    Exit,
+    /// The key going back to the previous field of a form was pressed.
+    Back,
+    /// The key opening the history picker was pressed.
+    PickHistory,
+    /// The key picking a suggestion with the finder was pressed.
+    PickSuggestion,
+    /// A key showing the help was pressed.
+    Help,
+    /// The key opening the command palette was pressed.
+    Palette,
+}
+
+/// Where the prompt reads events from, the terminal unless a test replaces it.
+trait Terminal {
+    /// Returns the (columns, rows) of the terminal.
+    fn size(&self) -> io::Result<(u16, u16)>;
+    /// Returns whether an event can be read within `timeout`.
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+    fn read(&mut self) -> io::Result<Event>;
+}
+
+/// The terminal, through crossterm.
+struct Crossterm;
+
+impl Terminal for Crossterm {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+}
+
+/// How reading a line ended.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Ending {
+    Accept(String),
+    Exit,
+    Back,
+}
+
+/// Prompt reads lines from the terminal, with completion and key bindings, and passes
+/// them to the executor.
+///
+/// `Ctx` is state of the application, e.g. a database connection, owned by the prompt
+/// and passed to the completer, the executor and the context key bindings, see
+/// [with_context](Prompt::with_context).
+pub struct Prompt<'a, C, Ctx = ()> {
+    buffer: Buffer,
+    renderer: Renderer<Stdout>,
+    completion: CompletionManager<'a, C>,
+    context: Ctx,
+    executor: Executor<'a, Ctx>,
+    exit_checker: Option<ExitChecker<'a>>,
+    /// What the executor returned for the last command.
+    last_status: Option<Status>,
+    /// Prefix segment showing the last status, see [Prompt::with_status_segment].
+    status_segment: Option<String>,
+    /// Prefix segment showing the number of the next history entry, see
+    /// [Prompt::with_history_index_segment].
+    history_index_segment: Option<String>,
+    rerun_key: Option<Key>,
+    history: History,
+    history_store: Option<Box<dyn HistoryStore + 'a>>,
+    history_store_error: Option<io::Error>,
+    history_scope: Option<HistoryScope<'a, Ctx>>,
+    all_scopes_key: Option<Key>,
+    history_expansion: bool,
+    /// Whether Alt-1 to Alt-9 accept the suggestions shown with those numbers.
+    number_keys: bool,
+    key_bind_mode: KeyBindMode,
+    key_bindings: Vec<KeyBind>,
+    /// Key bindings replacing the built-in ones for their keys, see [Prompt::with_inputrc].
+    rebound_keys: Vec<KeyBind>,
+    context_key_bindings: Vec<(Key, ContextKeyBindFunc<Ctx>)>,
+    /// Keymaps on top of the built-in ones, see [Prompt::with_keymap].
+    keymaps: Vec<Keymap>,
+    key_handlers: Vec<(Key, KeyHandlerFunc<C, Ctx>)>,
+    preview: Option<Preview<'a>>,
+    /// Fills the preview pane once the input stays the same for the duration.
+    preview_pane: Option<(Duration, PreviewPane<'a>)>,
+    /// The text and the cursor position the preview pane was filled for.
+    pane_input: Option<(String, usize)>,
+    segment_update: Option<SegmentUpdate<'a, Ctx>>,
+    elapsed_time: Option<ElapsedTime>,
+    /// How long the executor took to run the last command.
+    last_elapsed: Option<Duration>,
+    on_accepted: Option<OnAccepted<'a, Ctx>>,
+    on_change: Option<OnChange<'a, Ctx>>,
+    highlighter: Option<InputHighlighter<'a>>,
+    linter: Option<LintWorker>,
+    /// What the linter found in the input, moved along with the text until it is linted
+    /// again.
+    diagnostics: Vec<Diagnostic>,
+    /// The suggestions were only filtered and the completer has yet to be asked.
+    completion_stale: bool,
+    validators: Vec<Validator<'a>>,
+    danger_checks: Vec<DangerCheck<'a>>,
+    awaiting_confirmation: bool,
+    input_complete: Option<InputComplete<'a>>,
+    comment_prefix: String,
+    /// Inputs put aside with push-line, with their cursor positions, the last one to be
+    /// restored first.
+    stash: Vec<(String, usize)>,
+    accept_at_end: bool,
+    /// The toolbar to restore once the message shown in its place is dismissed.
+    saved_toolbar: Option<StyledText>,
+    timeout: Option<Duration>,
+    idle: Option<(Duration, IdleCallback<'a, Ctx>)>,
+    screen: Option<ReplScreen>,
+    output: Option<Output>,
+    cancel: Option<CancelToken>,
+    pager: Option<PagerMode>,
+    jobs: Option<Jobs>,
+    /// Whether the alternate screen of `screen` is shown.
+    screen_entered: bool,
+    /// Size of the whole terminal, the renderer only gets the input area of `screen`.
+    win_size: (u16, u16),
+    /// More keys are already queued, e.g. text committed by an input method or pasted,
+    /// so the suggestions are only updated after the last one.
+    input_pending: bool,
+    /// The keymap stack of each [Layer], built the first time it is needed while reading a
+    /// line.
+    keymap_stacks: [OnceCell<Rc<KeymapStack>>; 3],
+    terminal: Box<dyn Terminal>,
+    esc_timeout: Duration,
+    /// Events read ahead while waiting for the keys following an Esc.
+    queued_events: VecDeque<Event>,
+    frames: Frames,
+    notifier: Notifier,
+    /// Bell or flash to give after the next render.
+    alert: Option<Feedback>,
+    max_length: Option<usize>,
+    char_filter: Option<CharFilter<'a>>,
+    /// Read-only text each input starts with.
+    protected_text: Option<String>,
+    back_key: Option<Key>,
+    history_picker_key: Option<Key>,
+    completion_finder_key: Option<Key>,
+    help_keys: Vec<Key>,
+    palette_key: Option<Key>,
+    digraph_key: Option<Key>,
+    literal_key: Option<Key>,
+    /// Width of the tab stops Tab inserts spaces up to while no suggestions are shown.
+    expand_tab: Option<usize>,
+    /// The character being entered over several keys, if any.
+    special_insert: Option<SpecialInsert>,
+    /// Named functions of the application for the command palette.
+    actions: Vec<(String, ContextKeyBindFunc<Ctx>)>,
+    external_finder: Option<ExternalFinder>,
+    secret: bool,
+    /// Whether the bar of key hints is shown, see [Prompt::with_key_hints].
+    show_key_hints: bool,
+    #[cfg(feature = "normalization")]
+    normalization: Option<Normalization>,
+}
+
+impl<'a, C: Completer> Prompt<'a, C> {
+    pub fn new<E: FnMut(&str) -> R + 'a, R: Into<Status>>(mut executor: E, completer: C) -> Self {
+        Prompt::with_context((), move |input, _| executor(input), completer)
+    }
+}
+
+impl<'a, C: ContextCompleter<Ctx>, Ctx> Prompt<'a, C, Ctx> {
+    /// Returns a prompt owning `context`, which the executor can change and the
+    /// completer can read.
+    ///
+    /// The executor returns how the command went, anything converting into a [Status],
+    /// e.g. `()` or an exit code.
+    pub fn with_context<E, R>(context: Ctx, mut executor: E, completer: C) -> Self
+    where
+        E: FnMut(&str, &mut Ctx) -> R + 'a,
+        R: Into<Status>,
+    {
+        Self {
+            buffer: Buffer::new(),
+            renderer: Renderer::new(io::stdout(), "> "),
+            completion: CompletionManager::new(completer, DEFAULT_MAX_SUGGESTION),
+            context,
+            executor: Box::new(move |input, context| executor(input, context).into()),
+            exit_checker: None,
+            last_status: None,
+            status_segment: None,
+            history_index_segment: None,
+            rerun_key: None,
+            history: History::new(),
+            history_store: None,
+            history_store_error: None,
+            history_scope: None,
+            all_scopes_key: None,
+            history_expansion: false,
+            number_keys: false,
+            key_bind_mode: KeyBindMode::default(),
+            key_bindings: Vec::new(),
+            rebound_keys: Vec::new(),
+            context_key_bindings: Vec::new(),
+            keymaps: Vec::new(),
+            key_handlers: Vec::new(),
+            preview: None,
+            preview_pane: None,
+            pane_input: None,
+            segment_update: None,
+            elapsed_time: None,
+            last_elapsed: None,
+            on_accepted: None,
+            on_change: None,
+            highlighter: None,
+            linter: None,
+            diagnostics: Vec::new(),
+            completion_stale: false,
+            validators: Vec::new(),
+            danger_checks: Vec::new(),
+            awaiting_confirmation: false,
+            input_complete: None,
+            comment_prefix: "#".to_string(),
+            stash: Vec::new(),
+            accept_at_end: false,
+            saved_toolbar: None,
+            timeout: None,
+            idle: None,
+            screen: None,
+            output: None,
+            cancel: None,
+            pager: None,
+            jobs: None,
+            screen_entered: false,
+            win_size: (0, 0),
+            input_pending: false,
+            keymap_stacks: Default::default(),
+            terminal: Box::new(Crossterm),
+            esc_timeout: Duration::ZERO,
+            queued_events: VecDeque::new(),
+            frames: Frames::new(DEFAULT_FRAME_INTERVAL),
+            notifier: Notifier::new(),
+            alert: None,
+            max_length: None,
+            char_filter: None,
+            protected_text: None,
+            back_key: None,
+            history_picker_key: None,
+            completion_finder_key: None,
+            help_keys: vec![Key::plain(KeyCode::F(1)), Key::ctrl('?')],
+            palette_key: None,
+            digraph_key: None,
+            literal_key: Some(Key::ctrl('v')),
+            expand_tab: None,
+            special_insert: None,
+            actions: Vec::new(),
+            external_finder: None,
+            secret: false,
+            show_key_hints: false,
+            #[cfg(feature = "normalization")]
+            normalization: None,
+        }
+    }
+
+    /// Sets the prefix shown in front of the input.
+    pub fn with_prefix<P: Into<StyledText>>(mut self, prefix: P) -> Self {
+        self.renderer.set_prefix(prefix);
+        self
+    }
+
+    /// Sets the prefix to `segments`, see [Renderer::set_prefix_segments].
+    pub fn with_prefix_segments(mut self, segments: Segments) -> Self {
+        self.renderer.set_prefix_segments(segments);
+        self
+    }
+
+    /// Calls `update` before each input to update the prefix segments from the context,
+    /// e.g. the working directory after the executor ran `cd`.
+    pub fn with_segment_update<F: Fn(&Ctx, &mut Segments) + 'a>(mut self, update: F) -> Self {
+        self.segment_update = Some(Box::new(update));
+        self
+    }
+
+    /// Sets the compact prefix written in front of accepted input, see
+    /// [Renderer::set_transient_prefix].
+    pub fn with_transient_prefix<P: Into<StyledText>>(mut self, prefix: P) -> Self {
+        self.renderer.set_transient_prefix(Some(prefix.into()));
+        self
+    }
+
+    /// Shows how long each command took to run, see [ElapsedTime].
+    pub fn with_elapsed_time(mut self, elapsed_time: ElapsedTime) -> Self {
+        self.elapsed_time = Some(elapsed_time);
+        self
+    }
+
+    /// Shows the status of the last command, e.g. `✗ 2`, in the prefix segment called
+    /// `name`, see [Prompt::with_prefix_segments]. The segment is green after a success
+    /// and red after a failure.
+    pub fn with_status_segment<N: Into<String>>(mut self, name: N) -> Self {
+        self.status_segment = Some(name.into());
+        self
+    }
+
+    /// Shows the number the input will have in the history, e.g. `42`, in the prefix
+    /// segment called `name`, see [Prompt::with_prefix_segments]. With
+    /// [history expansion](Prompt::with_history_expansion), `!42` then stands for it.
+    pub fn with_history_index_segment<N: Into<String>>(mut self, name: N) -> Self {
+        self.history_index_segment = Some(name.into());
+        self
+    }
+
+    /// Runs the last entry of the history again when `key` is pressed, in place of the
+    /// input, as if it had been typed and accepted.
+    pub fn with_rerun_key(mut self, key: Key) -> Self {
+        self.rerun_key = Some(key);
+        self
+    }
+
+    /// Makes [run](Prompt::run) stop after a command for which `exit` returns true, given
+    /// the input and the status the executor returned, e.g. for `exit` or when a
+    /// connection was lost.
+    pub fn with_exit_checker<F: Fn(&str, Status) -> bool + 'a>(mut self, exit: F) -> Self {
+        self.exit_checker = Some(Box::new(exit));
+        self
+    }
+
+    /// Draws `border` around the completion popup, see [Renderer::set_popup_border].
+    pub fn with_completion_border(mut self, border: Border) -> Self {
+        self.renderer.set_popup_border(Some(border));
+        self
+    }
+
+    /// Numbers the first nine suggestions shown and makes Alt-1 to Alt-9 accept them
+    /// while the suggestions are open, see [Renderer::set_number_badges].
+    pub fn with_completion_number_keys(mut self) -> Self {
+        self.number_keys = true;
+        self.renderer.set_number_badges(true);
+        self
+    }
+
+    /// Scrolls the description of the selected suggestion when it is too long for its
+    /// column, see [Renderer::set_marquee].
+    pub fn with_completion_marquee(mut self) -> Self {
+        self.renderer.set_marquee(true);
+        self
+    }
+
+    /// Sets the shortest time between two renders, 1/60 s by default. Changes made in
+    /// between, e.g. by repeated keys or pasted text, are rendered together.
+    pub fn with_frame_interval(mut self, interval: Duration) -> Self {
+        self.frames.set_interval(interval);
+        self
+    }
+
+    /// Sets the text shown in place of the input while it is empty.
+    pub fn with_placeholder<P: Into<StyledText>>(mut self, placeholder: P) -> Self {
+        self.renderer.set_placeholder(placeholder);
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.renderer.set_theme(theme);
+        self
+    }
+
+    /// Sets the entries browsed with Up and Down, oldest first.
+    pub fn with_history(mut self, history: Vec<String>) -> Self {
+        self.history = history.into();
+        self
+    }
+
+    /// Loads the history from `store` and appends each accepted input to it. If the store
+    /// fails, the error is kept in [history_store_error](Prompt::history_store_error) and
+    /// the prompt goes on with the history in memory.
+    pub fn with_history_store<S: HistoryStore + 'a>(mut self, store: S) -> Self {
+        match store.load() {
+            Ok(entries) => self.history = entries.into(),
+            Err(e) => self.history_store_error = Some(e),
+        }
+        self.history_store = Some(Box::new(store));
+        self
+    }
+
+    /// Partitions the history by the scope `scope` returns for the context, e.g. the
+    /// working directory or the connected database, checked before each input. Up and
+    /// Down and the history picker only go through the entries of the current scope, see
+    /// [History::set_scope].
+    pub fn with_history_scope<F: Fn(&Ctx) -> Option<String> + 'a>(mut self, scope: F) -> Self {
+        self.history_scope = Some(Box::new(scope));
+        self
+    }
+
+    /// Switches between the history of the current scope and that of all scopes when
+    /// `key` is pressed, see [with_history_scope](Prompt::with_history_scope).
+    pub fn with_all_scopes_key(mut self, key: Key) -> Self {
+        self.all_scopes_key = Some(key);
+        self
+    }
+
+    /// Expands history designators such as `!!` or `!12` when the input is accepted, see
+    /// [History::expand], and suggests the recent entries with their numbers once a word
+    /// starts with `!`.
+    pub fn with_history_expansion(mut self) -> Self {
+        self.history_expansion = true;
+        self
+    }
+
+    /// Sets the characters delimiting the word replaced by the selected suggestion,
+    /// e.g. `"/"` for [FilePathCompleter](crate::FilePathCompleter).
+    pub fn with_completion_word_separator(mut self, separator: &'a str) -> Self {
+        self.completion.set_word_separator(separator);
+        self
+    }
+
+    /// Keeps `scroll_off` suggestions visible above and below the selected one, see
+    /// [CompletionManager::set_scroll_off].
+    pub fn with_completion_scroll_off(mut self, scroll_off: usize) -> Self {
+        self.completion.set_scroll_off(scroll_off);
+        self
+    }
+
+    /// Opens the suggestions for the empty input as soon as the prompt is shown, and again
+    /// after each accepted line, see [CompletionManager::set_show_at_start].
+    pub fn with_completion_show_at_start(mut self, show_at_start: bool) -> Self {
+        self.completion.set_show_at_start(show_at_start);
+        self
+    }
+
+    /// Sets whether the selection wraps around at the ends of the suggestions, see
+    /// [CompletionManager::set_wrap_around].
+    pub fn with_completion_wrap_around(mut self, wrap_around: bool) -> Self {
+        self.completion.set_wrap_around(wrap_around);
+        self
+    }
+
+    /// Sets whether the selected suggestion stays selected when the suggestions are
+    /// updated, see [CompletionManager::set_keep_selection].
+    pub fn with_completion_keep_selection(mut self, keep_selection: bool) -> Self {
+        self.completion.set_keep_selection(keep_selection);
+        self
+    }
+
+    /// Sets when the description column and then the padding of the suggestions are left
+    /// out on narrow terminals, see [ColumnLayout].
+    pub fn with_completion_column_layout(mut self, column_layout: ColumnLayout) -> Self {
+        self.completion.set_column_layout(column_layout);
+        self
+    }
+
+    /// Narrows down the shown suggestions right away as you type, and asks the completer
+    /// once no more keys are waiting. See [CompletionManager::set_filter_while_typing].
+    pub fn with_completion_filter_while_typing(mut self, filter_while_typing: bool) -> Self {
+        self.completion.set_filter_while_typing(filter_while_typing);
+        self
+    }
+
+    /// Sets whether the suggestions open as you type, the default, or only on Tab, see
+    /// [CompletionTrigger].
+    pub fn with_completion_trigger(mut self, trigger: CompletionTrigger) -> Self {
+        self.completion.set_trigger(trigger);
+        self
+    }
+
+    /// Waits for the word before the cursor to be `min_prefix_len` chars long before
+    /// opening the suggestions as you type, see [CompletionManager::set_min_prefix_len].
+    pub fn with_completion_min_prefix_len(mut self, min_prefix_len: usize) -> Self {
+        self.completion.set_min_prefix_len(min_prefix_len);
+        self
+    }
+
+    /// Shows what `preview` returns for the current input in the toolbar, e.g.
+    /// [expansion_preview](crate::expansion_preview). The toolbar is hidden when it
+    /// returns None.
+    pub fn with_preview<F: Fn(&Document) -> Option<StyledText> + 'a>(mut self, preview: F) -> Self {
+        self.preview = Some(Box::new(preview));
+        self
+    }
+
+    /// Shows what `pane` returns for the input in a pane between the completion popup and
+    /// the toolbar, e.g. the parsed input, a dry run of the command or the matching files.
+    /// `pane` is called once the input and the cursor stay the same for `delay`, so that
+    /// a slow one doesn't run for every key typed. See `Renderer::set_preview_pane_height`
+    /// for the height of the pane. The pane stays empty for a secret input.
+    pub fn with_preview_pane<F: Fn(&Document) -> StyledText + 'a>(mut self, delay: Duration, pane: F) -> Self {
+        self.preview_pane = Some((delay, Box::new(pane)));
+        self
+    }
+
+    /// Calls `on_accepted` whenever the selected suggestion is inserted into the input,
+    /// e.g. to load more data for the next completion into the context. Use
+    /// [Suggestion::with_id] to know which entry was accepted.
+    pub fn with_on_accepted<F: FnMut(&Suggestion, &mut Ctx) + 'a>(mut self, on_accepted: F) -> Self {
+        self.on_accepted = Some(Box::new(on_accepted));
+        self
+    }
+
+    /// Calls `on_change` with each change of the input text before the input is drawn
+    /// again, oldest first, so that e.g. a highlighter or a language server only has to
+    /// look at the changed part rather than the whole input. It isn't called for a
+    /// [secret](Prompt::with_secret) input.
+    pub fn with_on_change<F: FnMut(&TextChange, &mut Ctx) + 'a>(mut self, on_change: F) -> Self {
+        self.buffer.set_track_changes(true);
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
+    /// Styles the input with `lexer`, lexing again only the lines that changed and the
+    /// lines after them whose state changed. A secret input isn't styled.
+    pub fn with_lexer<L: Lexer + 'a>(mut self, lexer: L) -> Self {
+        self.buffer.set_track_changes(true);
+        self.highlighter = Some(Box::new(Highlighter::new(lexer)));
+        self
+    }
+
+    /// Lints the input with `linter` on a background thread as it is typed, e.g. to flag
+    /// unknown table names before running a query. The text of each [Diagnostic] is
+    /// underlined in the color of its severity, and its message replaces the toolbar
+    /// while the cursor is on it. A linter that panics is reported in the toolbar, and a
+    /// secret input isn't linted.
+    pub fn with_linter<F: Fn(&str) -> Vec<Diagnostic> + Send + 'static>(mut self, linter: F) -> Self {
+        self.buffer.set_track_changes(true);
+        self.linter = Some(LintWorker::new(linter));
+        self
+    }
+
+    /// Returns what the linter found in the input, see [with_linter](Prompt::with_linter).
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Ranks the suggestions accepted often and lately first, and records every accepted
+    /// suggestion in `frecency`. Save it with [frecency](Prompt::frecency) to keep the
+    /// ranking across sessions.
+    pub fn with_frecency(mut self, frecency: Frecency) -> Self {
+        self.completion.set_frecency(Some(frecency));
+        self
+    }
+
+    /// Adds a validator run when the input is accepted. If it returns an error, the
+    /// error is shown in the toolbar until the next key press and the input is not
+    /// accepted.
+    pub fn with_validator<F: Fn(&str) -> Result<(), String> + 'a>(mut self, validator: F) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    /// Adds a check run when the input is accepted. If it returns a reason, e.g. for
+    /// `rm -rf /`, the reason is shown in the toolbar and the input only runs once `y` is
+    /// pressed. Any other key goes back to editing.
+    pub fn with_danger_check<F: Fn(&str) -> Option<String> + 'a>(mut self, check: F) -> Self {
+        self.danger_checks.push(Box::new(check));
+        self
+    }
+
+    /// Makes Enter start a new line rather than accept the input while `complete` returns
+    /// false for it, e.g. while a quote is open, as [Continuation] tells for shell-like
+    /// input. Alt-Enter accepts the input anyway.
+    pub fn with_input_complete<F: Fn(&str) -> bool + 'a>(mut self, complete: F) -> Self {
+        self.input_complete = Some(Box::new(complete));
+        self
+    }
+
+    /// Sets what Alt-# puts before each line of the input before adding it to the
+    /// history, without running it, and starting over with an empty input, so that a
+    /// half-typed command is kept for later. `#` by default.
+    pub fn with_comment_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.comment_prefix = prefix.into();
+        self
+    }
+
+    /// Makes Enter accept the input only with the cursor at its end, and start a new line
+    /// anywhere else, for applications editing several lines at once. Alt-Enter accepts
+    /// the input wherever the cursor is.
+    pub fn with_accept_at_end(mut self) -> Self {
+        self.accept_at_end = true;
+        self
+    }
+
+    /// Asks for confirmation before running inputs matching `pattern`, see
+    /// [with_danger_check](Prompt::with_danger_check).
+    #[cfg(feature = "regex")]
+    pub fn with_danger_pattern<R: Into<String>>(self, pattern: regex::Regex, reason: R) -> Self {
+        let reason = reason.into();
+        self.with_danger_check(move |input| pattern.is_match(input).then(|| reason.clone()))
+    }
+
+    /// Makes [input](Prompt::input) fail with [io::ErrorKind::TimedOut] if no key is
+    /// pressed for `timeout`. The text typed so far is kept for the next call.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Calls `callback` every `interval` while no key is pressed, e.g. to update a clock
+    /// in the prefix or the toolbar through the renderer. The prompt is rendered again
+    /// after each call.
+    pub fn with_idle_callback<F>(mut self, interval: Duration, callback: F) -> Self
+    where
+        F: FnMut(&mut Renderer<Stdout>, &mut Ctx) + 'a,
+    {
+        self.idle = Some((interval, Box::new(callback)));
+        self
+    }
+
+    /// Runs the prompt in the alternate screen, with the input at the bottom and the
+    /// output of `screen` scrolling above it. Accepted inputs are added to the output,
+    /// and Shift-PageUp and Shift-PageDown scroll it. The normal screen is restored once
+    /// [run](Prompt::run) returns or the prompt is dropped.
+    pub fn with_repl_screen(mut self, screen: ReplScreen) -> Self {
+        self.screen = Some(screen);
+        self
+    }
+
+    /// Routes the output the executor writes to a clone of `output` where the prompt
+    /// writes its own, e.g. above the input of a [ReplScreen], and removes its progress
+    /// bar once the command is done.
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Pages what the executor writes to its [Output](Prompt::with_output) once it fills
+    /// the terminal, see [Output::set_pager].
+    pub fn with_pager(mut self, mode: PagerMode) -> Self {
+        self.pager = Some(mode);
+        self
+    }
+
+    /// Keeps listening for Ctrl-C while the executor runs, cancelling a clone of `token`
+    /// and showing a "cancelling…" notice when it is pressed. The executor keeps the
+    /// terminal in cooked mode and stdin to itself, and child processes still get the
+    /// interrupt.
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Prints the completion messages of the background jobs registered with a clone of
+    /// `jobs` just before the next prompt, so that they never interleave with the input
+    /// being typed.
+    pub fn with_jobs(mut self, jobs: Jobs) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Normalizes the input as it is typed or pasted, so that e.g. an `é` typed as `e`
+    /// followed by a combining accent, as on macOS, matches an `é` in suggestions and
+    /// history.
+    #[cfg(feature = "normalization")]
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = Some(normalization);
+        self
+    }
+
+    /// Rejects typed characters once the input has `max_length` characters, e.g. for a
+    /// one-time code. Any other change making the input longer than that, e.g. by a
+    /// suggestion, the history or a key binding, is taken back.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Rejects typed characters for which `filter` returns false, e.g.
+    /// `char::is_ascii_digit`. Any other change inserting such a character is taken back.
+    pub fn with_char_filter<F: Fn(char) -> bool + 'a>(mut self, filter: F) -> Self {
+        self.char_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Starts each input with `text`, e.g. a command name, which can't be deleted or
+    /// entered by the cursor. It is part of the returned input.
+    pub fn with_protected_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.protected_text = Some(text.into());
+        self.reset_buffer();
+        self
+    }
+
+    /// Marks the input as a secret, e.g. a password: it is masked on the screen, also
+    /// once accepted, it is neither completed nor saved in the history, and only the
+    /// executor gets its text.
+    pub fn with_secret(mut self) -> Self {
+        self.secret = true;
+        self.renderer.add_transformation(PasswordMask::default());
+        self
+    }
+
+    pub fn is_secret(&self) -> bool {
+        self.secret
+    }
+
+    /// Opens a full-screen fuzzy finder over the history when `key` is pressed, e.g.
+    /// Ctrl-R. The chosen entry replaces the input, to be edited or accepted.
+    pub fn with_history_picker(mut self, key: Key) -> Self {
+        self.history_picker_key = Some(key);
+        self
+    }
+
+    /// Lets the user pick one of the suggestions with a fuzzy finder when `key` is
+    /// pressed, see [with_external_finder](Prompt::with_external_finder).
+    pub fn with_completion_finder(mut self, key: Key) -> Self {
+        self.completion_finder_key = Some(key);
+        self
+    }
+
+    /// Opens a fuzzy finder over the named commands and the [actions](Prompt::with_action)
+    /// when `key` is pressed, e.g. Ctrl-Shift-P or Alt-X, and runs the chosen one.
+    /// Commands are listed with their keys, so that features without a well-known key
+    /// can be found.
+    pub fn with_command_palette(mut self, key: Key) -> Self {
+        self.palette_key = Some(key);
+        self
+    }
+
+    /// Enters a digraph when `key` is pressed, as Ctrl-K does in vim: the next two
+    /// characters typed are replaced by the one they stand for, e.g. `e'` by `é`, see
+    /// [digraph](crate::digraph). None by default, since emacs mode binds Ctrl-K.
+    pub fn with_digraph_key(mut self, key: Key) -> Self {
+        self.digraph_key = Some(key);
+        self
+    }
+
+    /// Makes Tab insert spaces up to the next multiple of `width` while no suggestions are
+    /// shown, rather than opening them, e.g. to indent code. None, the default, leaves Tab
+    /// to completion.
+    pub fn with_expand_tab(mut self, width: Option<usize>) -> Self {
+        self.expand_tab = width;
+        self
+    }
+
+    /// Sets the key inserting the next key literally, e.g. Tab, or, followed by `u` and
+    /// up to four hex digits, the character of that code point, as in `u+00e9`; `U`
+    /// takes up to eight digits. Ctrl-V by default, None to disable.
+    pub fn with_literal_key(mut self, key: Option<Key>) -> Self {
+        self.literal_key = key;
+        self
+    }
+
+    /// Adds an action of the application to the [command palette](Prompt::with_command_palette),
+    /// listed as `label`.
+    pub fn with_action<L: Into<String>>(mut self, label: L, func: ContextKeyBindFunc<Ctx>) -> Self {
+        self.actions.push((label.into(), func));
+        self
+    }
+
+    /// Runs `finder`, e.g. fzf, instead of the built-in fuzzy finder of
+    /// [with_history_picker](Prompt::with_history_picker) and
+    /// [with_completion_finder](Prompt::with_completion_finder).
+    pub fn with_external_finder(mut self, finder: ExternalFinder) -> Self {
+        self.external_finder = Some(finder);
+        self
+    }
+
+    /// Sets the feedback given when e.g. Tab finds nothing to complete, see [Notifier].
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Renders for screen readers, see [Renderer::set_screen_reader].
+    pub fn with_screen_reader_mode(mut self) -> Self {
+        self.renderer.set_screen_reader(true);
+        self
+    }
+
+    /// Sets how words are delimited for word-wise movement and deletion.
+    pub fn with_word_model(mut self, word_model: WordModel) -> Self {
+        self.buffer.set_word_model(word_model);
+        self
+    }
+
+    pub fn with_key_bind_mode(mut self, mode: KeyBindMode) -> Self {
+        self.key_bind_mode = mode;
+        self
+    }
+
+    /// Shows a bar on the last line listing the most useful key bindings for what is
+    /// being done, e.g. `Tab complete · Ctrl-R history` while editing, and the keys moving
+    /// through the suggestions once one is selected. See [Prompt::key_map].
+    pub fn with_key_hints(mut self) -> Self {
+        self.show_key_hints = true;
+        self
+    }
+
+    /// Sets the keys showing the help, a full-screen list of the [key map](Prompt::key_map)
+    /// grouped by category, closed with Esc. F1 and Ctrl-? by default, the latter only
+    /// reaching terminals that report it. No keys disable the help.
+    pub fn with_help_keys(mut self, keys: Vec<Key>) -> Self {
+        self.help_keys = keys;
+        self
+    }
+
+    /// Adds a key binding, run after the built-in ones for the same key.
+    pub fn with_key_bind(mut self, key_bind: KeyBind) -> Self {
+        self.key_bindings.push(key_bind);
+        self
+    }
+
+    /// Waits up to `timeout` for more keys after an Esc, so that an escape sequence the
+    /// terminal sent in several parts, e.g. that of an arrow over a slow connection, is
+    /// read as its key rather than as Esc followed by characters, and that Esc followed
+    /// by a character is read as Alt and the character. A bare Esc, e.g. closing the
+    /// completion menu, is then only handled once the timeout is over. Zero by default,
+    /// leaving escape sequences to the terminal backend.
+    pub fn with_esc_timeout(mut self, timeout: Duration) -> Self {
+        self.esc_timeout = timeout;
+        self
+    }
+
+    /// Adds a keymap on top of the built-in ones and those added before, used while its
+    /// [condition](Keymap::with_condition) holds. The keys it binds shadow their other
+    /// bindings, unless it [falls through](Keymap::with_fallthrough), so that e.g. a
+    /// keymap binding `j` and `k` to `menu-complete` and `menu-complete-backward` while
+    /// completing moves through the menu with them rather than typing them.
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymaps.push(keymap);
+        self
+    }
+
+    /// Applies the key bindings and settings of a readline init file, see [Inputrc]. Its
+    /// key bindings replace the built-in ones for the same keys, and
+    /// `completion-ignore-case` sets [CompletionManager::set_ignore_case].
+    pub fn with_inputrc(mut self, inputrc: &Inputrc) -> Self {
+        if let Some(mode) = inputrc.editing_mode {
+            self.key_bind_mode = mode;
+        }
+        if let Some(ignore_case) = inputrc.completion_ignore_case {
+            self.completion.set_ignore_case(ignore_case);
+        }
+        self.rebound_keys.extend(&inputrc.bindings);
+        self
+    }
+
+    /// Adds a key binding whose function also gets the context, run after the other
+    /// ones for the same key.
+    pub fn with_context_key_bind(mut self, key: Key, func: ContextKeyBindFunc<Ctx>) -> Self {
+        self.context_key_bindings.push((key, func));
+        self
+    }
+
+    /// Adds a handler run before the built-in handling of `key`, with access to the
+    /// completion and the history, see [KeyContext]. E.g. a handler for Tab can accept the
+    /// selected suggestion while the menu is open and indent otherwise. Handlers for the
+    /// same key run in the order they were added, until one returns true.
+    pub fn with_key_handler(mut self, key: Key, func: KeyHandlerFunc<C, Ctx>) -> Self {
+        self.key_handlers.push((key, func));
+        self
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut Ctx {
+        &mut self.context
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Returns the store set with [with_history_store](Prompt::with_history_store), e.g.
+    /// to [prune](HistoryStore::prune) it.
+    pub fn history_store_mut(&mut self) -> Option<&mut (dyn HistoryStore + 'a)> {
+        self.history_store.as_deref_mut()
+    }
+
+    /// Returns the last error of the history store, if it failed.
+    pub fn history_store_error(&self) -> Option<&io::Error> {
+        self.history_store_error.as_ref()
+    }
+
+    pub fn frecency(&self) -> Option<&Frecency> {
+        self.completion.frecency()
+    }
+
+    pub fn renderer_mut(&mut self) -> &mut Renderer<Stdout> {
+        &mut self.renderer
+    }
+
+    /// Returns what the executor returned for the last command, or None before the first
+    /// one.
+    pub fn last_status(&self) -> Option<Status> {
+        self.last_status
+    }
+
+    /// Returns how long the executor took to run the last command, or None before the
+    /// first one.
+    pub fn last_elapsed(&self) -> Option<Duration> {
+        self.last_elapsed
+    }
+
+    /// Returns the keys bound in the current state of the prompt, e.g. whether a
+    /// suggestion is selected, with the commands they run, in the order they are tried.
+    /// Bindings to functions that are not named commands, and key handlers, are left out.
+    pub fn key_map(&self) -> Vec<(Key, &'static Command)> {
+        let mut map = Vec::new();
+        let mut bind = |key: Option<Key>, name| {
+            if let (Some(key), Some(command)) = (key, Command::named(name)) {
+                map.push((key, command));
+            }
+        };
+        // while completing, the menu keymap binds these
+        if !self.completion.completing() {
+            bind(Some(Key::plain(KeyCode::Tab)), "complete");
+            bind(Some(Key::plain(KeyCode::Up)), "previous-history");
+            bind(Some(Key::plain(KeyCode::Down)), "next-history");
+            bind(Some(Key::ctrl('p')), "previous-history");
+            bind(Some(Key::ctrl('n')), "next-history");
+            bind(Some(Key::plain(KeyCode::Enter)), "accept-line");
+        }
+        bind(Some(Key::new(KeyCode::Enter, KeyModifiers::ALT)), "accept-line");
+        bind(Some(Key::ctrl('c')), "abort");
+        if self.buffer.len_chars() == 0 {
+            bind(Some(Key::ctrl('d')), "end-of-file");
+        }
+
+        for (key, binding) in self.keymap_stack().effective_bindings() {
+            if let Some(command) = binding.command() {
+                map.push((key, command));
+            }
+        }
+        map
+    }
+
+    /// Returns the keymaps in use. They are only built again once the [Layer] changes or
+    /// the next line is read.
+    fn keymap_stack(&self) -> Rc<KeymapStack> {
+        let layer = match self.completion.completing() {
+            true => Layer::Completing,
+            false if !self.completion.get_suggestions().is_empty() => Layer::Popup,
+            false => Layer::Editing,
+        };
+        let stack = &self.keymap_stacks[layer as usize];
+        Rc::clone(stack.get_or_init(|| Rc::new(self.build_keymap_stack(layer))))
+    }
+
+    /// Forgets the keymap stacks built, once the key bindings may have changed.
+    fn forget_keymap_stacks(&mut self) {
+        self.keymap_stacks = Default::default();
+    }
+
+    /// Returns the keymaps of `layer`, from the lowest one: the common key bindings, those
+    /// of the [mode](Prompt::with_key_bind_mode), those of the [inputrc](Prompt::with_inputrc),
+    /// those [added](Prompt::with_key_bind) running after the others, the keys given to
+    /// the builders, e.g. [Prompt::with_help_keys], and then the
+    /// [keymaps](Prompt::with_keymap) whose condition holds.
+    fn build_keymap_stack(&self, layer: Layer) -> KeymapStack {
+        let funcs = |name, bindings: &[KeyBind]| {
+            bindings.iter().fold(Keymap::new(name), |keymap, b| keymap.with_binding(b.key, Binding::Func(b.func)))
+        };
+        let mut stack = KeymapStack::new();
+        stack.push(funcs("common", COMMON_KEY_BINDINGS));
+        if self.key_bind_mode == KeyBindMode::EmacsKeyBind {
+            stack.push(funcs("emacs", EMACS_KEY_BINDINGS));
+        }
+        stack.push(funcs("inputrc", &self.rebound_keys));
+        stack.push(funcs("user", &self.key_bindings).with_fallthrough());
+
+        let mut keys = Keymap::new("prompt");
+        let mut bind = |key: Option<Key>, name| {
+            if let (Some(key), Some(binding)) = (key, Binding::named(name)) {
+                keys.bind(key, binding);
+            }
+        };
+        bind(self.back_key, "previous-field");
+        if !self.secret {
+            bind(self.history_picker_key, "reverse-search-history");
+            bind(self.completion_finder_key, "fuzzy-complete");
+        }
+        for key in &self.help_keys {
+            bind(Some(*key), "help");
+        }
+        bind(self.palette_key, "command-palette");
+        bind(self.digraph_key, "insert-digraph");
+        bind(self.literal_key, "quoted-insert");
+        bind(self.all_scopes_key, "toggle-history-scope");
+        bind(Some(Key::alt('#')), "insert-comment");
+        if !self.secret {
+            bind(Some(Key::alt('q')), "push-line");
+            bind(self.rerun_key, "rerun-last");
+        }
+        stack.push(keys);
+
+        let completing = layer == Layer::Completing;
+        let popup = match layer {
+            Layer::Completing => Some(completion_keymap()),
+            Layer::Popup => Some(popup_keymap()),
+            Layer::Editing => None,
+        };
+        if let Some(popup) = popup {
+            stack.push(if self.number_keys { with_number_keys(popup) } else { popup });
+        }
+        for keymap in &self.keymaps {
+            match keymap.condition() {
+                KeymapCondition::Always => stack.push(keymap.clone()),
+                KeymapCondition::Completing if completing => stack.push(keymap.clone()),
+                KeymapCondition::Completing => {}
+            }
+        }
+        stack
+    }
+
+    /// Returns the bar of [key hints](Prompt::with_key_hints) for the current state.
+    fn key_hints(&self) -> StyledText {
+        let names = if self.completion.completing() { COMPLETING_HINTS } else { EDITING_HINTS };
+        let map = self.key_map();
+        let mut hints = StyledText::new();
+        for name in names {
+            let Some((key, command)) = map.iter().find(|(_, command)| command.name == *name) else {
+                continue;
+            };
+            hints.push(if hints.is_empty() { " " } else { " · " }, SpanStyle::default());
+            hints.push(key.to_string(), SpanStyle::default().bold());
+            hints.push(format!(" {}", command.label), SpanStyle::default());
+        }
+        hints
+    }
+
+    /// Reads lines and passes them to the executor until Ctrl-D is pressed on an
+    /// empty input, or the [exit checker](Prompt::with_exit_checker) stops it.
+    pub fn run(&mut self) -> io::Result<()> {
+        while let Some(input) = self.input()? {
+            let start = Instant::now();
+            let status = self.execute(&input)?;
+            self.finish_command(start.elapsed(), status)?;
+            if self.exit_checker.as_ref().is_some_and(|exit| exit(&input, status)) {
+                break;
+            }
+        }
+        self.leave_screen()
+    }
+
+    /// Reads a single line. Returns None when Ctrl-D is pressed on an empty input.
+    /// Fails with [io::ErrorKind::TimedOut] after the [timeout](Prompt::with_timeout).
+    pub fn input(&mut self) -> io::Result<Option<String>> {
+        match self.read()? {
+            Ending::Accept(input) => Ok(Some(input)),
+            Ending::Exit | Ending::Back => Ok(None),
+        }
+    }
+
+    pub(crate) fn read(&mut self) -> io::Result<Ending> {
+        self.print_job_notices()?;
+        terminal::enable_raw_mode()?;
+        let result = self.enter_screen().and_then(|_| self.read_line());
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    /// Prints the completion messages of the background jobs, see [Prompt::with_jobs].
+    fn print_job_notices(&mut self) -> io::Result<()> {
+        let Some(jobs) = &self.jobs else {
+            return Ok(());
+        };
+        for notice in jobs.take_notices() {
+            match &self.screen {
+                Some(screen) => screen.println(notice.text()),
+                None => self.renderer.print_line(&notice)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows the number of the next history entry, see
+    /// [Prompt::with_history_index_segment].
+    fn update_history_index(&mut self) {
+        if let Some(name) = &self.history_index_segment {
+            let index = self.history.in_scope().len() + 1;
+            self.renderer.update_segments(|segments| segments.set(name, index.to_string()));
+        }
+    }
+
+    /// Runs the executor on `input`, listening for Ctrl-C meanwhile if there is a
+    /// [cancel token](Prompt::with_cancel_token).
+    fn execute(&mut self, input: &str) -> io::Result<Status> {
+        let output = self.output.clone().or_else(|| self.cancel.is_some().then(Output::new));
+        if let Some(output) = &output {
+            if self.pager.is_some() {
+                output.set_pager(self.pager);
+            }
+            output.begin(self.screen.clone(), self.renderer.hyperlinks());
+        }
+        let listener = match (&self.cancel, &output) {
+            (Some(token), Some(output)) => Some(CtrlCListener::start(token.clone(), output.clone())?),
+            _ => None,
+        };
+        let status = (self.executor)(input, &mut self.context);
+        drop(listener);
+        if let Some(output) = &output {
+            output.finish();
+        }
+        Ok(status)
+    }
+
+    /// Records how the last command went and how long it took, and shows it, see
+    /// [ElapsedTime] and [Prompt::with_status_segment].
+    fn finish_command(&mut self, elapsed: Duration, status: Status) -> io::Result<()> {
+        self.last_elapsed = Some(elapsed);
+        self.last_status = Some(status);
+        if let Some(name) = &self.status_segment {
+            self.renderer.update_segments(|segments| {
+                segments.set(name, status.to_string());
+                segments.set_style(name, status.style());
+            });
+        }
+        let Some(elapsed_time) = &self.elapsed_time else {
+            return Ok(());
+        };
+        let text = elapsed_time.text(elapsed);
+        match (elapsed_time.placement(), text) {
+            (ElapsedPlacement::Segment(name), text) => {
+                self.renderer.set_segment(name, text.map(|t| t.text()).unwrap_or_default());
+            }
+            (ElapsedPlacement::Line, Some(text)) => match &self.screen {
+                Some(screen) => screen.println(text.text()),
+                None => self.renderer.print_line(&text)?,
+            },
+            (ElapsedPlacement::Line, None) => {}
+        }
+        Ok(())
+    }
+
+    /// Makes `key` end the input with [Ending::Back].
+    pub(crate) fn set_back_key(&mut self, key: Key) {
+        self.back_key = Some(key);
+        self.forget_keymap_stacks();
+    }
+
+    /// Replaces the input, with the cursor at its end.
+    pub(crate) fn set_input(&mut self, text: String) {
+        let len = text.chars().count();
+        self.buffer.set_text(text);
+        self.buffer.set_cursor_position(len);
+        self.buffer.clear_undo();
+    }
+
+    fn enter_screen(&mut self) -> io::Result<()> {
+        if let (Some(screen), false) = (&self.screen, self.screen_entered) {
+            screen.enter(&mut io::stdout())?;
+            self.screen_entered = true;
+        }
+        Ok(())
+    }
+
+    fn leave_screen(&mut self) -> io::Result<()> {
+        if let (Some(screen), true) = (&self.screen, self.screen_entered) {
+            screen.leave(&mut io::stdout())?;
+            self.screen_entered = false;
+        }
+        Ok(())
+    }
+
+    /// Gives the renderer the size of the terminal, or of the input area of the screen.
+    fn update_win_size(&mut self, col: u16, row: u16) {
+        self.win_size = (col, row);
+        let height = self.screen.as_ref().map_or(row, |screen| screen.area_height(row));
+        self.renderer.update_win_size(col, height);
+    }
+
+    /// Moves the rendered input out of the way, onto the next line or into the output of
+    /// the screen.
+    fn break_line(&mut self) -> io::Result<()> {
+        let document = self.buffer.document();
+        let Some(screen) = &self.screen else {
+            return self.renderer.break_line(document);
+        };
+        // as displayed, so that secrets stay masked
+        let display = self.renderer.display_text(document);
+        screen.println(format!("{}{}", self.renderer.final_prefix().text(), display.text.text()));
+        screen.scroll_to_bottom();
+        self.renderer.break_line(&Document::new())?;
+        let mut out = io::stdout();
+        screen.reset_area(&mut out, self.win_size.1)?;
+        screen.draw(&mut out, self.win_size.0, self.win_size.1)?;
+        out.flush()
+    }
+
+    fn read_line(&mut self) -> io::Result<Ending> {
+        self.forget_keymap_stacks();
+        self.update_history_scope();
+        self.update_history_index();
+        if let Some(update) = &self.segment_update {
+            self.renderer.update_segments(|segments| update(&self.context, segments));
+        }
+        let (col, row) = self.terminal.size()?;
+        self.update_win_size(col, row);
+        if let Some(screen) = &self.screen {
+            screen.reset_area(&mut io::stdout(), row)?;
+        }
+        self.show_completion_at_start();
+        self.update_preview_pane();
+        self.frame()?;
+
+        let mut last_key = Instant::now();
+        let mut last_idle = last_key;
+        loop {
+            // the keys already queued are read first, the frame shows them all at once
+            if self.frames.is_due(Instant::now()) && !self.input_queued()? {
+                self.frame()?;
+            }
+            let timer = self.next_timer(Instant::now(), last_key, last_idle);
+            let frame = self.frames.due().map(|due| (due, Timer::Frame));
+            let next = [timer, frame].into_iter().flatten().min_by_key(|(deadline, _)| *deadline);
+            if let Some((deadline, timer)) = next.filter(|_| self.queued_events.is_empty()) {
+                if !self.terminal.poll(deadline.saturating_duration_since(Instant::now()))? {
+                    match timer {
+                        Timer::Frame => {}
+                        Timer::Marquee => self.renderer.marquee_tick(),
+                        Timer::Lint => {}
+                        Timer::PreviewPane => self.update_preview_pane(),
+                        Timer::Spinner => {
+                            self.renderer.tick();
+                            self.update_completion();
+                        }
+                        Timer::Idle => {
+                            if let Some((_, callback)) = &mut self.idle {
+                                callback(&mut self.renderer, &mut self.context);
+                            }
+                            last_idle = Instant::now();
+                        }
+                        Timer::Timeout => {
+                            self.break_line()?;
+                            return Err(io::Error::new(io::ErrorKind::TimedOut, "no key was pressed before the timeout"));
+                        }
+                    }
+                    self.frames.request();
+                    continue;
+                }
+            }
+            let event = self.read_event()?;
+            if let Event::Key(_) = event {
+                last_key = Instant::now();
+                last_idle = last_key;
+            }
+            match event {
+                Event::Key(event) if event.kind != KeyEventKind::Release => match self.feed_event(event.into())? {
+                    // feed_event runs the palette itself
+                    Feed::Continue | Feed::Palette => self.frames.request(),
+                    Feed::Accept => {
+                        self.break_line()?;
+                        return Ok(Ending::Accept(self.accept()));
+                    }
+                    Feed::Cancel => {
+                        self.break_line()?;
+                        self.reset_buffer();
+                        self.restore_stash();
+                        self.history.clear();
+                        self.show_completion_at_start();
+                        self.frames.request();
+                    }
+                    Feed::Comment => {
+                        self.break_line()?;
+                        self.accept();
+                        self.show_completion_at_start();
+                        self.frames.request();
+                    }
+                    Feed::Exit => {
+                        self.break_line()?;
+                        return Ok(Ending::Exit);
+                    }
+                    Feed::Back => {
+                        self.break_line()?;
+                        return Ok(Ending::Back);
+                    }
+                    Feed::PickHistory => {
+                        let entries = recent_first(&self.history.in_scope()).into_iter()
+                            .map(str::to_string)
+                            .collect::<Vec<_>>();
+                        if let Some(i) = self.find(&entries)? {
+                            let before = self.buffer.text();
+                            self.buffer.set_text(entries[i].clone());
+                            self.restore_protected();
+                            self.buffer.set_cursor_position(self.buffer.len_chars());
+                            self.enforce_limits(&before);
+                            self.completion.reset();
+                        }
+                        self.frames.request();
+                    }
+                    Feed::PickSuggestion => {
+                        self.update_completion();
+                        let suggestions = self.completion.get_suggestions().iter()
+                            .filter(|s| !s.is_disabled())
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        let entries = suggestions.iter()
+                            .map(|s| match s.description() {
+                                "" => s.text().to_string(),
+                                description => format!("{}  {}", s.text(), description),
+                            })
+                            .collect::<Vec<_>>();
+                        if entries.is_empty() {
+                            self.notify(Notice::NoCompletions);
+                        } else if let Some(i) = self.find(&entries)? {
+                            let before = self.buffer.text();
+                            self.insert_suggestion(&suggestions[i]);
+                            self.enforce_limits(&before);
+                            self.completion.reset();
+                        }
+                        self.frames.request();
+                    }
+                    Feed::Help => {
+                        help::show(&self.key_map(), !self.screen_entered)?;
+                        if let Some(screen) = &self.screen {
+                            // the help drew over the whole screen
+                            self.renderer.break_line(&Document::new())?;
+                            screen.reset_area(&mut io::stdout(), self.win_size.1)?;
+                        }
+                        self.frames.request();
+                    }
+                },
+                Event::Resize(col, row) => {
+                    self.update_win_size(col, row);
+                    if let Some(screen) = &self.screen {
+                        // the input area moved with the bottom of the screen
+                        self.renderer.break_line(&Document::new())?;
+                        screen.reset_area(&mut io::stdout(), row)?;
+                    }
+                    self.frames.request();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Lets the user pick one of `entries` with the external finder or the built-in one.
+    /// Returns the index of the chosen entry.
+    fn find(&mut self, entries: &[String]) -> io::Result<Option<usize>> {
+        let chosen = match &self.external_finder {
+            Some(finder) => {
+                self.renderer.erase()?;
+                let chosen = finder.pick(entries);
+                // a full-screen finder leaves the alternate screen on exit
+                if let (Some(screen), true) = (&self.screen, self.screen_entered) {
+                    screen.enter(&mut io::stdout())?;
+                }
+                chosen?
+            }
+            None => picker::pick(&entries.iter().map(String::as_str).collect::<Vec<_>>(), !self.screen_entered)?,
+        };
+        if let Some(screen) = &self.screen {
+            // the finder drew over the whole screen
+            self.renderer.break_line(&Document::new())?;
+            screen.reset_area(&mut io::stdout(), self.win_size.1)?;
+        }
+        Ok(chosen)
+    }
+
+    fn update_history_scope(&mut self) {
+        if let Some(scope) = &self.history_scope {
+            let scope = scope(&self.context);
+            if scope.as_deref() != self.history.scope() {
+                self.history.set_scope(scope);
+            }
+        }
+    }
+
+    /// Empties the buffer, leaving only the protected text.
+    fn reset_buffer(&mut self) {
+        let text = self.protected_text.clone().unwrap_or_default();
+        let len = text.chars().count();
+        self.buffer.set_text(text);
+        self.buffer.set_protected(len);
+        self.buffer.set_cursor_position(len);
+    }
+
+    /// Puts the protected text back in front of the input once something else replaced
+    /// the input, e.g. an entry of the history, and protects it again.
+    fn restore_protected(&mut self) {
+        let Some(text) = &self.protected_text else {
+            return;
+        };
+        let len = text.chars().count();
+        if !self.buffer.text().starts_with(text.as_str()) {
+            self.buffer.set_protected(0);
+            self.buffer.replace_range(0..0, text);
+        }
+        self.buffer.set_protected(len);
+    }
+
+    /// Replaces the input with the previous entry of the history, or the next one if
+    /// `newer`, after the protected text.
+    fn browse_history(&mut self, newer: bool) {
+        let moved = match newer {
+            true => self.history.newer(&mut self.buffer),
+            false => self.history.older(&mut self.buffer),
+        };
+        match moved {
+            true => self.restore_protected(),
+            false => self.notify(Notice::HistoryEnd),
+        }
+    }
+
+    /// Returns the first timer to expire and when, given when the last key was pressed and
+    /// when the idle callback was last called.
+    fn next_timer(&self, now: Instant, last_key: Instant, last_idle: Instant) -> Option<(Instant, Timer)> {
+        let spinner = self.completion.loading().then(|| (now + SPINNER_INTERVAL, Timer::Spinner));
+        let marquee = self.renderer.marquee_running().then(|| (now + MARQUEE_INTERVAL, Timer::Marquee));
+        let lint = self.linter.as_ref().filter(|linter| linter.pending()).map(|_| (now + SPINNER_INTERVAL, Timer::Lint));
+        let idle = self.idle.as_ref().map(|(interval, _)| (last_idle + *interval, Timer::Idle));
+        let timeout = self.timeout.map(|timeout| (last_key + timeout, Timer::Timeout));
+        let pane = self.preview_pane.as_ref()
+            .filter(|_| self.pane_input != Some((self.buffer.text(), self.buffer.cursor_position())))
+            .map(|(delay, _)| (last_key + *delay, Timer::PreviewPane));
+        [timeout, idle, spinner, marquee, lint, pane].into_iter().flatten().min_by_key(|(deadline, _)| *deadline)
+    }
+
+    /// Fills the preview pane for the current input.
+    fn update_preview_pane(&mut self) {
+        if let Some((_, pane)) = &self.preview_pane {
+            // a secret input leaves the pane empty
+            let text = self.observed().map(pane).unwrap_or_default();
+            self.renderer.set_preview_pane(text);
+            let document = self.buffer.document();
+            self.pane_input = Some((document.text.clone(), document.cursor_position()));
+        }
+    }
+
+    /// Takes the input out of the buffer and saves it in the history.
+    fn accept(&mut self) -> String {
+        let input = self.buffer.text();
+        self.reset_buffer();
+        self.restore_stash();
+        if !input.is_empty() && !self.secret {
+            self.history.add(input.clone());
+            if let Some(store) = &mut self.history_store {
+                let entry = HistoryEntry { text: input.clone(), scope: self.history.scope().map(str::to_string) };
+                if let Err(e) = store.append(&entry) {
+                    self.history_store_error = Some(e);
+                }
+            }
+        }
+        input
+    }
+
+    /// Renders a frame. Suggestions left stale while keys were queued are updated once
+    /// no more are, after rendering the input, so that a slow completer doesn't hold the
+    /// typed text back.
+    fn frame(&mut self) -> io::Result<()> {
+        self.render()?;
+        if self.completion_stale && !self.input_queued()? {
+            self.update_completion();
+            self.render()?;
+        }
+        self.frames.rendered(Instant::now());
+        Ok(())
+    }
+
+    fn render(&mut self) -> io::Result<()> {
+        self.notify_changes();
+        if let (Some(preview), None) = (&self.preview, &self.saved_toolbar) {
+            let toolbar = self.observed().and_then(preview);
+            self.renderer.set_toolbar(toolbar.unwrap_or_default());
+        }
+        let document = self.buffer.document();
+        if self.show_key_hints {
+            self.renderer.set_key_hints(self.key_hints());
+        }
+        self.completion.set_available_height(self.renderer.completion_height(document));
+        if let Some(screen) = &self.screen {
+            screen.draw(&mut io::stdout(), self.win_size.0, self.win_size.1)?;
+        }
+        self.renderer.render(document, &self.completion)?;
+        match self.alert.take() {
+            Some(Feedback::Bell) => self.renderer.bell(),
+            Some(Feedback::Flash) => self.renderer.flash(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Passes the changes of the input text since the last call to the highlighter, to
+    /// `on_change` and to the diagnostics.
+    fn notify_changes(&mut self) {
+        let mut changes = self.buffer.take_changes();
+        if self.observed().is_none() {
+            changes.clear();
+        }
+        for change in changes {
+            if let Some(highlighter) = &mut self.highlighter {
+                highlighter.apply(&change);
+            }
+            if let Some(on_change) = &mut self.on_change {
+                on_change(&change, &mut self.context);
+            }
+            self.diagnostics.retain_mut(|diagnostic| diagnostic.follow(&change));
+        }
+        if let (Some(text), Some(linter)) = (self.observed().map(|d| d.text.clone()), &self.linter) {
+            if let Some(diagnostics) = linter.lint(&text) {
+                self.diagnostics = diagnostics;
+            }
+            if let Some(error) = linter.take_error() {
+                let message = format!("The linter failed: {}", error);
+                self.show_message(StyledText::new().span(message, SpanStyle::default().fg(Color::Red)));
+            }
+            self.renderer.set_diagnostics(self.diagnostics.clone());
+            let cursor = self.buffer.cursor_position();
+            let under_cursor = self.diagnostics.iter()
+                .filter(|d| d.range.start <= cursor && cursor <= d.range.end)
+                .max_by_key(|d| d.severity);
+            if let (Some(diagnostic), None) = (under_cursor, &self.saved_toolbar) {
+                let message = StyledText::new().span(diagnostic.message.clone(), diagnostic.severity.style());
+                self.show_message(message);
+            }
+        }
+        if let (Some(text), Some(highlighter)) = (self.observed().map(|d| d.text.clone()), &mut self.highlighter) {
+            let highlighted = highlighter.highlight(&text);
+            self.renderer.set_highlighted(Some(highlighted));
+        }
+    }
+
+    /// Returns the input for what looks at its text besides the executor: the completer,
+    /// the highlighter, the linter, `on_change`, the previews and history expansion. None
+    /// if the input is [secret](Prompt::with_secret), which is kept from all of them.
+    fn observed(&self) -> Option<&Document> {
+        (!self.secret).then(|| self.buffer.document())
+    }
+
+    /// Returns whether more events can be read right away.
+    fn input_queued(&mut self) -> io::Result<bool> {
+        Ok(!self.queued_events.is_empty() || self.terminal.poll(Duration::ZERO)?)
+    }
+
+    /// Is [feed](Prompt::feed) for a key read from the terminal.
+    fn feed_event(&mut self, key: Key) -> io::Result<Feed> {
+        self.input_pending = self.input_queued()?;
+        match self.feed(key) {
+            Feed::Palette => self.palette(),
+            feed => Ok(feed),
+        }
+    }
+
+    /// Lets the user pick one of the [palette items](Prompt::palette_items) and runs it.
+    fn palette(&mut self) -> io::Result<Feed> {
+        let items = self.palette_items();
+        let entries = items.iter().map(|(entry, _)| entry.clone()).collect::<Vec<_>>();
+        match self.find(&entries)? {
+            Some(i) => Ok(self.run_palette_item(items[i].1)),
+            None => Ok(Feed::Continue),
+        }
+    }
+
+    /// Returns the commands, each with its keys, and then the actions, as listed by the
+    /// command palette. Aliases of a command and the palette itself are left out.
+    fn palette_items(&self) -> Vec<(String, PaletteItem)> {
+        let map = self.key_map();
+        let mut items: Vec<(String, PaletteItem)> = Vec::new();
+        for command in Command::all() {
+            let alias = items.iter().any(|(_, item)| matches!(item, PaletteItem::Command(c) if c.label == command.label));
+            if alias || command.name == "command-palette" {
+                continue;
+            }
+            let keys = map.iter()
+                .filter(|(_, c)| c.name == command.name)
+                .map(|(key, _)| key.to_string())
+                .collect::<Vec<_>>();
+            let entry = match keys.is_empty() {
+                true => format!("{}  {}", command.label, command.name),
+                false => format!("{}  {}  {}", command.label, command.name, keys.join(", ")),
+            };
+            items.push((entry, PaletteItem::Command(command)));
+        }
+        items.extend(self.actions.iter().enumerate().map(|(i, (label, _))| (label.clone(), PaletteItem::Action(i))));
+        items
+    }
+
+    /// Runs a command or an action chosen in the command palette, as its key would.
+    fn run_palette_item(&mut self, item: PaletteItem) -> Feed {
+        let command = match item {
+            PaletteItem::Action(i) => {
+                (self.actions[i].1)(&mut self.buffer, &mut self.context);
+                self.update_completion();
+                return Feed::Continue;
+            }
+            PaletteItem::Command(command) => command,
+        };
+        self.run_command(command)
+    }
+
+    /// Runs a named command, as its key does.
+    fn run_command(&mut self, command: &Command) -> Feed {
+        if let Some(func) = command.func {
+            self.buffer.end_change();
+            self.buffer.apply(func);
+            if self.completion.triggered_by(self.buffer.document()) {
+                self.update_completion();
+            }
+            return Feed::Continue;
+        }
+        match command.name {
+            "complete" | "menu-complete" => {
+                // left closed while typing, see with_completion_trigger
+                if self.completion.get_suggestions().is_empty() {
+                    self.update_completion();
+                }
+                self.completion.next();
+            }
+            "menu-complete-backward" => self.completion.previous(),
+            "menu-page-down" => self.completion.page_down(),
+            "menu-page-up" => self.completion.page_up(),
+            "fuzzy-complete" if !self.secret => return Feed::PickSuggestion,
+            "accept-suggestion" => {
+                self.accept_suggestion();
+                self.update_completion();
+            }
+            "dismiss-completion" => self.completion.dismiss(self.buffer.document()),
+            name if name.starts_with("accept-suggestion-") => {
+                if let Ok(n) = name["accept-suggestion-".len()..].parse() {
+                    self.accept_numbered(n);
+                    self.update_completion();
+                }
+            }
+            "previous-history" => self.browse_history(false),
+            "next-history" => self.browse_history(true),
+            "reverse-search-history" if !self.secret => return Feed::PickHistory,
+            "toggle-history-scope" => self.toggle_history_scope(),
+            "rerun-last" if !self.secret => match self.history.in_scope().last().map(|e| e.to_string()) {
+                Some(entry) => {
+                    self.buffer.set_text(entry);
+                    self.restore_protected();
+                    self.buffer.set_cursor_position(self.buffer.len_chars());
+                    self.completion.reset();
+                    return self.check_accept();
+                }
+                None => self.notify(Notice::HistoryEnd),
+            },
+            "accept-line" => return self.check_accept(),
+            "push-line" if !self.secret => {
+                self.stash.push((self.buffer.text(), self.buffer.cursor_position()));
+                self.reset_buffer();
+                self.completion.reset();
+            }
+            "insert-comment" => {
+                self.comment_out();
+                return Feed::Comment;
+            }
+            "abort" => return Feed::Cancel,
+            "end-of-file" => return Feed::Exit,
+            "previous-field" => return Feed::Back,
+            "help" => return Feed::Help,
+            "command-palette" => return Feed::Palette,
+            "insert-digraph" => self.special_insert = Some(SpecialInsert::Digraph(None)),
+            "quoted-insert" => self.special_insert = Some(SpecialInsert::Literal),
+            _ => {}
+        }
+        Feed::Continue
+    }
+
+    /// Reads the next event. An Esc is read with the keys following it within the
+    /// [esc timeout](Prompt::with_esc_timeout), and those parsed as one key.
+    fn read_event(&mut self) -> io::Result<Event> {
+        if let Some(event) = self.queued_events.pop_front() {
+            return Ok(event);
+        }
+        let event = self.terminal.read()?;
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release && Key::from(key) == Key::plain(KeyCode::Esc) => {}
+            _ => return Ok(event),
+        }
+        if self.esc_timeout.is_zero() {
+            return Ok(event);
+        }
+        let deadline = Instant::now() + self.esc_timeout;
+        let mut keys = Vec::new();
+        let mut other = None;
+        let keys = loop {
+            match esc::parse(&keys) {
+                Sequence::Incomplete => {}
+                Sequence::Key(key) => break vec![key],
+                Sequence::Keys(keys) => break keys,
+            }
+            if !self.terminal.poll(deadline.saturating_duration_since(Instant::now()))? {
+                break esc::finish(&keys);
+            }
+            match self.terminal.read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Release => {}
+                Event::Key(key) => keys.push(key.into()),
+                event => {
+                    other = Some(event);
+                    break esc::finish(&keys);
+                }
+            }
+        };
+        let mut events = keys.into_iter().map(|key| Event::Key(KeyEvent::new(key.code, key.modifiers)));
+        let first = events.next().unwrap_or(event);
+        self.queued_events.extend(events.chain(other));
+        Ok(first)
+    }
+
+    /// Applies a key press to the buffer and the completion.
+    fn feed(&mut self, key: Key) -> Feed {
+        let before = self.limited().then(|| self.buffer.text());
+        let feed = self.feed_key(key);
+        if let Some(before) = before {
+            self.enforce_limits(&before);
+        }
+        feed
+    }
+
+    fn feed_key(&mut self, key: Key) -> Feed {
+        if let Some(toolbar) = self.saved_toolbar.take() {
+            self.renderer.set_toolbar(toolbar);
+        }
+        if std::mem::take(&mut self.awaiting_confirmation) {
+            return match key.code {
+                KeyCode::Char('y' | 'Y') if key.modifiers.is_empty() => Feed::Accept,
+                _ => Feed::Continue,
+            };
+        }
+        if let Some(insert) = self.special_insert.take() {
+            match insert.feed(key) {
+                Step::Wait(insert) => self.special_insert = Some(insert),
+                Step::Insert(c) => self.insert_special(c),
+                Step::InsertThen(c, key) => {
+                    if let Some(c) = c {
+                        self.insert_special(c);
+                    }
+                    return self.feed(key);
+                }
+                Step::Cancel => {}
+            }
+            return Feed::Continue;
+        }
+        if let Some(screen) = &self.screen {
+            let page = (self.win_size.1 - screen.area_height(self.win_size.1)).max(1) as usize;
+            if key == Key::new(KeyCode::PageUp, KeyModifiers::SHIFT) {
+                screen.scroll_up(page);
+                return Feed::Continue;
+            }
+            if key == Key::new(KeyCode::PageDown, KeyModifiers::SHIFT) {
+                screen.scroll_down(page);
+                return Feed::Continue;
+            }
+        }
+        self.buffer.set_last_key_stroke(Some(key.code));
+        if !matches!(key.code, KeyCode::Char(_)) || key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            self.buffer.end_change();
+        }
+        if self.handle_key_handlers(key) {
+            return Feed::Continue;
+        }
+        if let Some(width) = self.expand_tab.filter(|_| key == Key::plain(KeyCode::Tab)) {
+            if self.completion.get_suggestions().is_empty() {
+                self.buffer.insert_tab(Some(width));
+                return Feed::Continue;
+            }
+        }
+        let keymaps = self.keymap_stack();
+        let bindings = keymaps.resolve(key);
+        // commands the prompt carries out itself take the key over
+        let command = bindings.iter().find_map(|binding| match binding {
+            Binding::Command(command) if command.func.is_none() => Some(*command),
+            _ => None,
+        });
+        if let Some(command) = command {
+            return self.run_command(command);
+        }
+        let completing = self.completion.completing();
+        // typing closes the suggestions before they are updated
+        let triggered = key.code == KeyCode::Tab || self.completion.triggered_by(self.buffer.document());
+        let filtering = self.filters_completion(key);
+        if !filtering {
+            self.handle_completion_key_binding(key);
+        }
+
+        let is_ctrl = |c| key == Key::ctrl(c);
+        match key.code {
+            // multi-line entries are browsed line by line before moving through history
+            KeyCode::Up if !completing && key.modifiers.is_empty() && self.buffer.cursor_row() > 0 => {
+                self.buffer.cursor_up(1);
+                return Feed::Continue;
+            }
+            KeyCode::Down if !completing && key.modifiers.is_empty()
+                && self.buffer.cursor_row() + 1 < self.buffer.line_count() => {
+                self.buffer.cursor_down(1);
+                return Feed::Continue;
+            }
+            KeyCode::Up if !completing && key.modifiers.is_empty() => {
+                self.browse_history(false);
+                return Feed::Continue;
+            }
+            KeyCode::Down if !completing && key.modifiers.is_empty() => {
+                self.browse_history(true);
+                return Feed::Continue;
+            }
+            _ if is_ctrl('p') && !completing => {
+                self.browse_history(false);
+                return Feed::Continue;
+            }
+            _ if is_ctrl('n') && !completing => {
+                self.browse_history(true);
+                return Feed::Continue;
+            }
+            KeyCode::Enter if key.modifiers.is_empty() => return self.enter(),
+            _ if is_ctrl('j') || is_ctrl('m') => return self.enter(),
+            KeyCode::Enter if key.modifiers == KeyModifiers::ALT => return self.check_accept(),
+            _ if is_ctrl('c') => return Feed::Cancel,
+            _ if is_ctrl('d') && self.buffer.len_chars() == 0 => return Feed::Exit,
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                && !keymaps.shadows(key) => {
+                match self.rejection(c) {
+                    None => self.insert_typed(c),
+                    Some(message) => {
+                        self.notify_with(Notice::RejectedInput, message);
+                        return Feed::Continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let bound = self.handle_key_binding(key, &bindings);
+        let chord = match key.code {
+            KeyCode::Char(_) => key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT),
+            KeyCode::F(_) => true,
+            _ => false,
+        };
+        if chord && !bound {
+            self.notify(Notice::UnboundKey);
+        }
+        if filtering {
+            let document = self.buffer.document();
+            self.completion.filter(&document.get_word_before_cursor_until_separator(self.completion.word_separator()));
+            self.completion_stale = true;
+        } else if !triggered && !self.completion.triggered_by(self.buffer.document()) {
+            // left for Tab to open
+        } else if self.input_pending {
+            self.completion_stale = true;
+        } else {
+            self.update_completion();
+            if key.code == KeyCode::Tab && self.completion.get_suggestions().is_empty() && !self.completion.loading() {
+                self.notify(Notice::NoCompletions);
+            }
+        }
+        Feed::Continue
+    }
+
+    /// Switches between the history of the current scope and that of all scopes.
+    fn toggle_history_scope(&mut self) {
+        let all_scopes = !self.history.all_scopes();
+        self.history.set_all_scopes(all_scopes);
+        let message = if all_scopes { "History of all scopes." } else { "History of this scope." };
+        self.show_message(message.into());
+    }
+
+    /// Returns why `c` can't be typed, if it can't.
+    fn rejection(&self, c: char) -> Option<String> {
+        if self.char_filter.as_ref().is_some_and(|accepts| !accepts(c)) {
+            return Some(format!("{:?} is not accepted.", c));
+        }
+        match self.max_length {
+            Some(max) if self.buffer.len_chars() >= max => Some(format!("At most {} characters.", max)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the input has a [max length](Prompt::with_max_length) or a
+    /// [char filter](Prompt::with_char_filter).
+    fn limited(&self) -> bool {
+        self.max_length.is_some() || self.char_filter.is_some()
+    }
+
+    /// Takes back the last change of the input, which was `before` it, if it inserted
+    /// characters the char filter rejects or made the input longer than the max length.
+    fn enforce_limits(&mut self, before: &str) {
+        let text = self.buffer.text();
+        if text == before {
+            return;
+        }
+        let inserted = TextChange::between(&Rope::from_str(before), &text).text;
+        let rejected = self.char_filter.as_ref().and_then(|accepts| inserted.chars().find(|&c| !accepts(c)));
+        let len = text.chars().count();
+        let message = match (rejected, self.max_length) {
+            (Some(c), _) => format!("{:?} is not accepted.", c),
+            (None, Some(max)) if len > max && len > before.chars().count() => format!("At most {} characters.", max),
+            _ => return,
+        };
+        self.buffer.undo();
+        self.notify_with(Notice::RejectedInput, message);
+    }
+
+    /// Gives the feedback the notifier asks for. Sounds and flashes are written once the
+    /// prompt is rendered.
+    fn notify(&mut self, notice: Notice) {
+        self.notify_with(notice, notice.message().to_string());
+    }
+
+    /// Is [notify](Prompt::notify) with a more specific message than the notice's.
+    fn notify_with(&mut self, notice: Notice, message: String) {
+        match self.notifier.feedback(notice) {
+            Feedback::Silent => {}
+            Feedback::Message => self.show_message(StyledText::new().span(message, SpanStyle::default().fg(Color::Yellow))),
+            alert => self.alert = Some(alert),
+        }
+    }
+
+    /// Types `c` at the cursor. With [normalization](Prompt::with_normalization), it is
+    /// normalized with the chars before the cursor it combines with, and only those are
+    /// replaced.
+    fn insert_typed(&mut self, c: char) {
+        #[cfg(feature = "normalization")]
+        if let Some(normalization) = self.normalization {
+            let before = self.buffer.document().text_before_cursor_str();
+            let tail = &before[Normalization::combining_start(before)..];
+            let typed = format!("{}{}", tail, c);
+            let normalized = normalization.apply(&typed);
+            if normalized != typed {
+                let cursor = self.buffer.cursor_position();
+                let start = cursor - tail.chars().count();
+                self.buffer.replace_range(start..cursor, &normalized);
+                return;
+            }
+        }
+        self.buffer.insert_typed(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Types a character entered over several keys, as if its key was pressed.
+    fn insert_special(&mut self, c: char) {
+        match self.rejection(c) {
+            None => {
+                self.buffer.end_change();
+                self.insert_typed(c);
+                self.buffer.end_change();
+                self.update_completion();
+            }
+            Some(message) => self.notify_with(Notice::RejectedInput, message),
+        }
+    }
+
+    /// Gives back the input last put aside with push-line (Alt-q), once the input typed
+    /// meanwhile is accepted or thrown away.
+    fn restore_stash(&mut self) {
+        if let Some((text, cursor)) = self.stash.pop() {
+            self.buffer.set_text(text);
+            self.buffer.set_cursor_position(cursor);
+            self.buffer.clear_undo();
+        }
+    }
+
+    /// Puts the [comment prefix](Prompt::with_comment_prefix) before each line of the
+    /// input.
+    fn comment_out(&mut self) {
+        let text = self.buffer.text()
+            .split('\n')
+            .map(|line| format!("{}{}", self.comment_prefix, line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.buffer.set_text(text);
+    }
+
+    /// Accepts the input, or starts a new line if the cursor isn't at the end of the input
+    /// and [it has to be](Prompt::with_accept_at_end), or if the input is
+    /// [incomplete](Prompt::with_input_complete).
+    fn enter(&mut self) -> Feed {
+        let at_end = self.buffer.cursor_position() == self.buffer.len_chars();
+        let complete = self.input_complete.as_ref().is_none_or(|complete| complete(&self.buffer.text()));
+        if (self.accept_at_end && !at_end) || !complete {
+            self.buffer.new_line(false);
+            self.update_completion();
+            return Feed::Continue;
+        }
+        self.check_accept()
+    }
+
+    /// Accepts the input, unless a validator rejects it or a danger check flags it and it
+    /// has to be confirmed first.
+    fn check_accept(&mut self) -> Feed {
+        let mut input = self.buffer.text();
+        let error_style = SpanStyle::default().fg(Color::Red).bold();
+        if self.history_expansion && self.observed().is_some() {
+            match self.history.expand(&input) {
+                Ok(expanded) if expanded != input => {
+                    self.buffer.set_text(expanded.clone());
+                    self.buffer.set_cursor_position(self.buffer.len_chars());
+                    input = expanded;
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    self.show_message(StyledText::new().span(error, error_style));
+                    return Feed::Continue;
+                }
+            }
+        }
+        if let Some(error) = self.validators.iter().find_map(|validate| validate(&input).err()) {
+            self.show_message(StyledText::new().span(error, error_style));
+            return Feed::Continue;
+        }
+        let Some(reason) = self.danger_checks.iter().find_map(|check| check(&input)) else {
+            return Feed::Accept;
+        };
+        self.show_message(StyledText::new()
+            .span(reason, error_style)
+            .span(" Press y to run it, any other key to edit.", SpanStyle::default()));
+        self.awaiting_confirmation = true;
+        Feed::Continue
+    }
+
+    /// Shows `message` in place of the toolbar until the next key press.
+    fn show_message(&mut self, message: StyledText) {
+        if self.saved_toolbar.is_none() {
+            self.saved_toolbar = Some(self.renderer.toolbar().clone());
+        }
+        self.renderer.set_toolbar(message);
+    }
+
+    /// Returns true if `key` edits the word being completed while suggestions are shown
+    /// and filtered as you type. Ending the word inserts the selected suggestion.
+    fn filters_completion(&self, key: Key) -> bool {
+        let typing = match key.code {
+            KeyCode::Char(c) => {
+                !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                    && !c.is_whitespace()
+                    && !self.completion.word_separator().contains(c)
+            }
+            KeyCode::Backspace => key.modifiers.is_empty(),
+            _ => false,
+        };
+        typing
+            && self.completion.filter_while_typing()
+            && !self.completion.get_suggestions().is_empty()
+            && self.history_designator().is_none()
+    }
+
+    /// Opens the completion menu on the keys that do, or, for keys the
+    /// [menu keymap](completion_keymap) doesn't bind, accepts the selected suggestion and
+    /// closes the menu.
+    fn handle_completion_key_binding(&mut self, key: Key) {
+        match key.code {
+            KeyCode::Tab if !self.completion.completing() => self.completion.next(),
+            KeyCode::BackTab if !self.completion.completing() => self.completion.previous(),
+            KeyCode::PageDown if !self.completion.completing() => self.completion.page_down(),
+            KeyCode::PageUp if !self.completion.completing() => self.completion.page_up(),
+            _ => self.accept_suggestion(),
+        }
+    }
+
+    /// Inserts the selected suggestion, if any, and closes the completion menu.
+    fn accept_suggestion(&mut self) {
+        if let Some(s) = self.completion.get_selected_suggestion().cloned() {
+            self.insert_suggestion(&s);
+        }
+        self.completion.reset();
+    }
+
+    /// Inserts the suggestion shown with the number `n`, counting from 1 at the top of the
+    /// window, and closes the completion menu.
+    fn accept_numbered(&mut self, n: usize) {
+        if n == 0 || n > self.completion.window_height() {
+            return;
+        }
+        let index = self.completion.vertical_scroll() + n - 1;
+        if let Some(s) = self.completion.get_suggestions().get(index).filter(|s| !s.is_disabled()).cloned() {
+            self.insert_suggestion(&s);
+            self.completion.reset();
+        }
+    }
+
+    /// Replaces the word being completed with `s`.
+    fn insert_suggestion(&mut self, s: &Suggestion) {
+        let document = self.buffer.document();
+        let start = document.find_start_of_previous_word_until_separator(self.completion.word_separator());
+        let cursor = document.cursor_position();
+        self.buffer.replace_range(start..cursor, s.text());
+        if let Some(frecency) = self.completion.frecency_mut() {
+            frecency.record(s);
+        }
+        if let Some(on_accepted) = &mut self.on_accepted {
+            on_accepted(s, &mut self.context);
+        }
+    }
+
+    /// Runs the [key handlers](Prompt::with_key_handler) of `key` until one handles it,
+    /// returns whether one did.
+    fn handle_key_handlers(&mut self, key: Key) -> bool {
+        if !self.key_handlers.iter().any(|(k, _)| *k == key) {
+            return false;
+        }
+        let text = self.buffer.text();
+        let mut cx = KeyContext {
+            buffer: &mut self.buffer,
+            completion: &mut self.completion,
+            history: &self.history,
+            context: &mut self.context,
+            accept_selected: false,
+        };
+        let handled = self.key_handlers.iter().filter(|(k, _)| *k == key).any(|(_, func)| func(&mut cx));
+        if !handled {
+            return false;
+        }
+        if cx.accept_selected {
+            if let Some(s) = self.completion.get_selected_suggestion().cloned() {
+                self.insert_suggestion(&s);
+            }
+            self.completion.reset();
+        }
+        // the handler may have moved through the suggestions, which updating them would undo
+        if self.buffer.text() != text && self.completion.triggered_by(self.buffer.document()) {
+            if self.input_pending {
+                self.completion_stale = true;
+            } else {
+                self.update_completion();
+            }
+        }
+        true
+    }
+
+    /// Runs the functions `key` is bound to, as resolved by the [keymaps](Prompt::keymap_stack),
+    /// then the context key bindings. Returns false if there is none.
+    fn handle_key_binding(&mut self, key: Key, bindings: &[Binding]) -> bool {
+        let mut bound = !bindings.is_empty();
+        for binding in bindings {
+            match *binding {
+                Binding::Func(func) => self.buffer.apply(func),
+                Binding::Command(Command { func: Some(func), .. }) => self.buffer.apply(*func),
+                Binding::Command(_) => {}
+            }
+        }
+        for (_, func) in self.context_key_bindings.iter().filter(|(k, _)| *k == key) {
+            func(&mut self.buffer, &mut self.context);
+            bound = true;
+        }
+        bound
+    }
+
+    /// Returns what follows the `!` of the word before the cursor, if it is a history
+    /// designator to complete.
+    fn history_designator(&self) -> Option<String> {
+        if !self.history_expansion {
+            return None;
+        }
+        self.buffer.document().get_word_before_cursor().strip_prefix('!').map(str::to_string)
+    }
+
+    /// Opens the suggestions for a new line, if they are
+    /// [shown at start](Prompt::with_completion_show_at_start).
+    fn show_completion_at_start(&mut self) {
+        if self.completion.show_at_start() {
+            self.completion.reset();
+            self.update_completion();
+        }
+    }
+
+    fn update_completion(&mut self) {
+        self.completion_stale = false;
+        if self.observed().is_none() {
+            return;
+        }
+        if let Some(designator) = self.history_designator() {
+            self.completion.set_suggestions(history_suggestions(&self.history, &designator));
+            return;
+        }
+        self.completion.update_suggestions_with_context(self.buffer.document(), &self.context);
+    }
+}
+
+/// Returns the entries in scope matching the history `designator`, newest first, as
+/// `!n` suggestions described by the entry.
+fn history_suggestions(history: &History, designator: &str) -> Vec<Suggestion> {
+    let entries = history.in_scope();
+    let suggestion = |text: String, entry: &str| Suggestion::new(text, picker::one_line(entry));
+    if designator == "!" {
+        return entries.last().map(|entry| suggestion("!!".to_string(), entry)).into_iter().collect();
+    }
+    let numeric = designator.chars().all(|c| c.is_ascii_digit());
+    entries.iter()
+        .enumerate()
+        .rev()
+        .filter(|(i, entry)| match numeric {
+            true => (i + 1).to_string().starts_with(designator),
+            false => entry.starts_with(designator),
+        })
+        .take(MAX_HISTORY_SUGGESTIONS)
+        .map(|(i, entry)| suggestion(format!("!{}", i + 1), entry))
+        .collect()
+}
+
+impl<'a, C, Ctx> Drop for Prompt<'a, C, Ctx> {
+    /// Restores the normal screen if [input](Prompt::input) was called without
+    /// [run](Prompt::run).
+    fn drop(&mut self) {
+        if let (Some(screen), true) = (&self.screen, self.screen_entered) {
+            let _ = screen.leave(&mut io::stdout());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::completion::Suggestion;
+    use crate::tests::TestCompleter;
+    use crossterm::event::KeyEvent;
+
+    fn fruits() -> TestCompleter {
+        TestCompleter::new(["apple", "apricot", "banana"]).prefixed().needs_word()
+    }
+
+    fn kanji() -> TestCompleter {
+        TestCompleter::new(["日本", "日本語"]).prefixed().needs_word()
+    }
+
+    fn type_text<C: ContextCompleter<Ctx>, Ctx>(p: &mut Prompt<C, Ctx>, text: &str) {
+        for c in text.chars() {
+            assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Char(c))));
+        }
+    }
+
+    #[test]
+    fn test_feed_insert_and_accept() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        type_text(&mut p, "eat ap");
+        assert_eq!(2, p.completion.get_suggestions().len());
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Tab));
+        // Enter accepts the suggestion, then the input
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("eat apricot", p.buffer.text());
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Enter)));
+    }
+
+    #[test]
+    fn test_feed_completion_keymap() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_history(vec!["ls".to_string()]);
+        type_text(&mut p, "a");
+        p.feed(Key::plain(KeyCode::BackTab));
+        assert_eq!(Some("apricot"), p.completion.get_selected_suggestion().map(Suggestion::text));
+        p.feed(Key::plain(KeyCode::Up));
+        p.feed(Key::plain(KeyCode::Down));
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!(Some("apple"), p.completion.get_selected_suggestion().map(Suggestion::text));
+        p.feed(Key::plain(KeyCode::Esc));
+        assert!(!p.completion.completing());
+        assert_eq!("a", p.buffer.text());
+        // with the menu closed the arrows browse the history again
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("ls", p.buffer.text());
+        assert_eq!(Some("accept-line"), p.key_map().iter().find(|(k, _)| k.code == KeyCode::Enter).map(|(_, c)| c.name));
+    }
+
+    #[test]
+    fn test_keymap_stack_is_cached() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        let editing = p.keymap_stack();
+        type_text(&mut p, "x");
+        assert!(Rc::ptr_eq(&editing, &p.keymap_stack()));
+        p.feed(Key::plain(KeyCode::Backspace));
+        type_text(&mut p, "a");
+        let popup = p.keymap_stack();
+        assert!(!Rc::ptr_eq(&editing, &popup));
+        p.feed(Key::plain(KeyCode::Tab));
+        let completing = p.keymap_stack();
+        assert!(!Rc::ptr_eq(&popup, &completing));
+        p.set_back_key(Key::plain(KeyCode::Esc));
+        assert!(!Rc::ptr_eq(&completing, &p.keymap_stack()));
+    }
+
+    #[test]
+    fn test_feed_history() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_history(vec!["ls".to_string()]);
+        type_text(&mut p, "pwd");
+        assert_eq!("pwd", p.accept());
+        assert_eq!(["ls", "pwd"], p.history().histories());
+        assert_eq!("", p.buffer.text());
+
+        type_text(&mut p, "cd");
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("pwd", p.buffer.text());
+        p.feed(Key::ctrl('p'));
+        assert_eq!("ls", p.buffer.text());
+        p.feed(Key::ctrl('n'));
+        p.feed(Key::plain(KeyCode::Down));
+        assert_eq!("cd", p.buffer.text());
+
+        p.buffer.set_text(String::new());
+        assert_eq!("", p.accept());
+        assert_eq!(2, p.history().histories().len());
+    }
+
+    #[test]
+    fn test_history_store() {
+        struct Memory(Rc<RefCell<Vec<String>>>);
+
+        impl HistoryStore for Memory {
+            fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+                Ok(self.0.borrow().iter().map(HistoryEntry::new).collect())
+            }
+
+            fn append(&mut self, entry: &HistoryEntry) -> io::Result<()> {
+                if entry.text == "fail" {
+                    return Err(io::Error::other("store is read-only"));
+                }
+                self.0.borrow_mut().push(entry.text.clone());
+                Ok(())
+            }
+
+            fn prune(&mut self, max: usize) -> io::Result<()> {
+                let mut entries = self.0.borrow_mut();
+                let len = entries.len();
+                entries.drain(..len.saturating_sub(max));
+                Ok(())
+            }
+        }
+
+        let entries = Rc::new(RefCell::new(vec!["ls".to_string()]));
+        let mut p = Prompt::new(|_| {}, fruits()).with_history_store(Memory(entries.clone()));
+        assert_eq!(["ls"], p.history().histories());
+        type_text(&mut p, "pwd");
+        p.accept();
+        assert_eq!(vec!["ls", "pwd"], *entries.borrow());
+        assert!(p.history_store_error().is_none());
+        type_text(&mut p, "fail");
+        p.accept();
+        assert_eq!("store is read-only", p.history_store_error().unwrap().to_string());
+        // the input is still in the history in memory
+        assert_eq!(3, p.history().histories().len());
+
+        p.history_store_mut().unwrap().prune(1).unwrap();
+        assert_eq!(vec!["pwd"], *entries.borrow());
+    }
+
+    #[test]
+    fn test_history_scope() {
+        let history = vec![HistoryEntry::new("ls").with_scope("/tmp"), HistoryEntry::new("pwd").with_scope("/home")];
+        let mut p = Prompt::with_context("/home".to_string(), |_, _| {}, fruits())
+            .with_history_scope(|cwd: &String| Some(cwd.clone()))
+            .with_all_scopes_key(Key::alt('a'));
+        p.history = history.into();
+        p.update_history_scope();
+        assert_eq!(vec!["pwd"], p.history().in_scope());
+        type_text(&mut p, "make");
+        p.accept();
+        assert_eq!(Some("/home"), p.history().entries()[2].scope.as_deref());
+
+        p.feed(Key::alt('a'));
+        assert_eq!("History of all scopes.", p.renderer.toolbar().text());
+        p.feed(Key::plain(KeyCode::Up));
+        p.feed(Key::plain(KeyCode::Up));
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("ls", p.buffer.text());
+        p.feed(Key::alt('a'));
+        assert_eq!(vec!["pwd", "make"], p.history().in_scope());
+    }
+
+    #[test]
+    fn test_elapsed_time_segment() {
+        let segments = Segments::new().segment("took", "", SpanStyle::default()).with_suffix("> ");
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_prefix_segments(segments)
+            .with_elapsed_time(ElapsedTime::new(Duration::from_secs(1)).in_segment("took"));
+        assert_eq!(None, p.last_elapsed());
+        p.finish_command(Duration::from_millis(2500), Status::Success).unwrap();
+        assert_eq!(Some(Duration::from_millis(2500)), p.last_elapsed());
+        assert_eq!("2.5s> ", p.renderer.prefix().text());
+        p.finish_command(Duration::from_millis(10), Status::Success).unwrap();
+        assert_eq!("> ", p.renderer.prefix().text());
+    }
+
+    #[test]
+    fn test_history_index() {
+        let segments = Segments::new().segment("index", "", SpanStyle::default()).with_suffix("> ");
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_history(vec!["ls".to_string(), "pwd".to_string()])
+            .with_prefix_segments(segments)
+            .with_history_index_segment("index")
+            .with_history_expansion()
+            .with_rerun_key(Key::ctrl('o'));
+        p.update_history_index();
+        assert_eq!("3> ", p.renderer.prefix().text());
+        type_text(&mut p, "echo !1");
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("echo ls", p.accept());
+        p.update_history_index();
+        assert_eq!("4> ", p.renderer.prefix().text());
+
+        type_text(&mut p, "abc");
+        assert_eq!(Feed::Accept, p.feed(Key::ctrl('o')));
+        assert_eq!("echo ls", p.buffer.text());
+        let mut p = Prompt::new(|_| {}, fruits()).with_rerun_key(Key::ctrl('o'));
+        assert_eq!(Feed::Continue, p.feed(Key::ctrl('o')));
+    }
+
+    #[test]
+    fn test_status_segment() {
+        let segments = Segments::new().segment("status", "", SpanStyle::default()).with_suffix("> ");
+        let mut p = Prompt::new(|input: &str| if input == "false" { 1 } else { 0 }, fruits())
+            .with_prefix_segments(segments)
+            .with_status_segment("status")
+            .with_exit_checker(|input, status| input == "exit" || status.code() == 255);
+        assert_eq!(None, p.last_status());
+        let status = (p.executor)("false", &mut p.context);
+        p.finish_command(Duration::ZERO, status).unwrap();
+        assert_eq!(Some(Status::Failure(1)), p.last_status());
+        assert_eq!("✗ 1> ", p.renderer.prefix().text());
+        assert_eq!(Some(Color::Red), p.renderer.prefix().spans()[0].style.fg);
+        let status = (p.executor)("true", &mut p.context);
+        p.finish_command(Duration::ZERO, status).unwrap();
+        assert_eq!("✔> ", p.renderer.prefix().text());
+
+        let exit = p.exit_checker.as_ref().unwrap();
+        assert!(exit("exit", Status::Success));
+        assert!(exit("ssh", Status::Failure(255)));
+        assert!(!exit("ls", Status::Failure(2)));
+    }
+
+    #[test]
+    fn test_history_expansion() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_history(vec!["ls".to_string(), "git status".to_string(), "git log\n--oneline".to_string()])
+            .with_history_expansion();
+        type_text(&mut p, "echo !");
+        p.update_completion();
+        let texts = |p: &Prompt<TestCompleter>| {
+            let suggestions = p.completion.get_suggestions().iter();
+            suggestions.map(|s| (s.text().to_string(), s.description().to_string())).collect::<Vec<_>>()
+        };
+        let pair = |t: &str, d: &str| (t.to_string(), d.to_string());
+        assert_eq!(
+            vec![pair("!3", "git log ↵ --oneline"), pair("!2", "git status"), pair("!1", "ls")],
+            texts(&p)
+        );
+        type_text(&mut p, "g");
+        p.update_completion();
+        assert_eq!(2, p.completion.get_suggestions().len());
+        p.feed(Key::plain(KeyCode::Backspace));
+        type_text(&mut p, "!");
+        p.update_completion();
+        assert_eq!(vec![pair("!!", "git log ↵ --oneline")], texts(&p));
+
+        p.buffer.set_text("echo !2".to_string());
+        p.update_completion();
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Char(' ')));
+        assert_eq!("echo !2 ", p.buffer.text());
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("echo git status ", p.buffer.text());
+
+        p.buffer.set_text("!9".to_string());
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("!9: event not found", p.renderer.toolbar().text());
+    }
+
+    #[test]
+    fn test_feed_history_picker() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_history_picker(Key::ctrl('r'));
+        assert_eq!(Feed::PickHistory, p.feed(Key::ctrl('r')));
+        let mut p = Prompt::new(|_| {}, fruits()).with_history_picker(Key::ctrl('r')).with_secret();
+        assert_eq!(Feed::Continue, p.feed(Key::ctrl('r')));
+        let mut p = Prompt::new(|_| {}, fruits()).with_completion_finder(Key::ctrl('t'));
+        assert_eq!(Feed::PickSuggestion, p.feed(Key::ctrl('t')));
+    }
+
+    #[test]
+    fn test_feed_undo() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        type_text(&mut p, "ab");
+        p.feed(Key::plain(KeyCode::Char(' ')));
+        type_text(&mut p, "cd");
+        p.feed(Key::alt('b'));
+        type_text(&mut p, "x");
+        assert_eq!("ab xcd", p.buffer.text());
+        p.feed(Key::ctrl('7'));
+        assert_eq!("ab cd", p.buffer.text());
+        p.feed(Key::ctrl('_'));
+        assert_eq!("", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_special_insert() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_digraph_key(Key::ctrl('k'));
+        type_text(&mut p, "caf");
+        p.feed(Key::ctrl('k'));
+        type_text(&mut p, "e'");
+        p.feed(Key::ctrl('v'));
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::ctrl('v'));
+        type_text(&mut p, "u+3042");
+        p.feed(Key::ctrl('v'));
+        type_text(&mut p, "u301 x");
+        assert_eq!("café\tあ\u{301} x", p.buffer.text());
+        assert_eq!(9, p.buffer.cursor_position());
+        // the tab reaches column 8, the wide character takes two and the accent none
+        assert_eq!(12, p.buffer.document().display_cursor_position_with_tab_width(4));
+        // the digraph key takes over Ctrl-K from kill-line
+        assert!(p.key_map().iter().any(|(k, c)| *k == Key::ctrl('k') && c.name == "insert-digraph"));
+    }
+
+    #[test]
+    fn test_feed_expand_tab() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_expand_tab(Some(4));
+        type_text(&mut p, "if");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!("if  ", p.buffer.text());
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!("if      ", p.buffer.text());
+        // Tab still completes while suggestions are shown
+        type_text(&mut p, "ap");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!(Some("apple"), p.completion.get_selected_suggestion().map(Suggestion::text));
+        assert_eq!("if      ap", p.buffer.text());
+
+        let mut p = Prompt::new(|_| {}, fruits());
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!("", p.buffer.text());
+    }
+
+    #[test]
+    fn test_command_palette() {
+        let mut p = Prompt::with_context(0, |_, _| {}, fruits())
+            .with_command_palette(Key::alt('x'))
+            .with_action("clear the screen", |_, cleared: &mut i32| *cleared += 1);
+        assert_eq!(Feed::Palette, p.feed(Key::alt('x')));
+        let items = p.palette_items();
+        let entry = |label: &str| items.iter().find(|(entry, _)| entry.starts_with(label)).unwrap();
+        assert_eq!("line start  beginning-of-line  Home, Ctrl-A", entry("line start").0);
+        assert_eq!("swap chars  transpose-chars  Ctrl-T", entry("swap chars").0);
+        // aliases and the palette itself are left out
+        assert_eq!(1, items.iter().filter(|(entry, _)| entry.starts_with("cut to line start")).count());
+        assert!(!items.iter().any(|(entry, _)| entry.contains("command-palette")));
+
+        type_text(&mut p, "ls ap");
+        p.run_palette_item(entry("line start").1);
+        assert_eq!(0, p.buffer.cursor_position());
+        p.run_palette_item(entry("line end").1);
+        p.run_palette_item(entry("complete").1);
+        assert_eq!(Some("apple"), p.completion.get_selected_suggestion().map(Suggestion::text));
+        assert_eq!(Feed::Help, p.run_palette_item(entry("help").1));
+        p.run_palette_item(entry("clear the screen").1);
+        assert_eq!(1, p.context);
+    }
+
+    #[test]
+    fn test_feed_help() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        assert_eq!(Feed::Help, p.feed(Key::plain(KeyCode::F(1))));
+        let mut p = Prompt::new(|_| {}, fruits()).with_help_keys(vec![Key::alt('h')]);
+        assert_eq!(Feed::Help, p.feed(Key::alt('h')));
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::F(1))));
+        assert_eq!(Some("help"), p.key_map().iter().find(|(k, _)| *k == Key::alt('h')).map(|(_, c)| c.name));
+    }
+
+    #[test]
+    fn test_feed_accept_at_end() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_accept_at_end()
+            .with_input_complete(|input| !input.ends_with('\\'));
+        type_text(&mut p, "ls");
+        p.feed(Key::plain(KeyCode::Left));
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("l\ns", p.buffer.text());
+        p.feed(Key::plain(KeyCode::End));
+        type_text(&mut p, " \\");
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("l\ns \\\n", p.buffer.text());
+        type_text(&mut p, "-a");
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Enter)));
+
+        p.feed(Key::plain(KeyCode::Home));
+        assert_eq!(Feed::Accept, p.feed(Key::new(KeyCode::Enter, KeyModifiers::ALT)));
+    }
+
+    #[test]
+    fn test_feed_insert_comment() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_history(vec![]);
+        type_text(&mut p, "rm -rf");
+        assert_eq!(Feed::Comment, p.feed(Key::alt('#')));
+        assert_eq!("#rm -rf", p.accept());
+        assert_eq!(["#rm -rf"], p.history().histories());
+
+        let mut p = Prompt::new(|_| {}, fruits()).with_comment_prefix("-- ").with_accept_at_end();
+        type_text(&mut p, "a");
+        p.feed(Key::plain(KeyCode::Home));
+        p.feed(Key::plain(KeyCode::Enter));
+        assert_eq!(Feed::Comment, p.feed(Key::alt('#')));
+        assert_eq!("-- \n-- a", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_push_line() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        type_text(&mut p, "git commit -m");
+        p.feed(Key::plain(KeyCode::Left));
+        p.feed(Key::alt('q'));
+        assert_eq!("", p.buffer.text());
+        type_text(&mut p, "git status");
+        p.feed(Key::alt('q'));
+        type_text(&mut p, "ls");
+        assert_eq!("ls", p.accept());
+        assert_eq!("git status", p.buffer.text());
+        assert_eq!(Feed::Cancel, p.feed(Key::ctrl('c')));
+        p.reset_buffer();
+        p.restore_stash();
+        assert_eq!("git commit -m", p.buffer.text());
+        assert_eq!(12, p.buffer.cursor_position());
+        assert_eq!("git commit -m", p.accept());
+        assert_eq!("", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_multi_line_history() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_history(vec!["ls".to_string(), "if true\nthen ls\nfi".to_string()]);
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("if true\nthen ls\nfi", p.buffer.text());
+        // Up walks the lines of the entry before going to the older one
+        p.feed(Key::plain(KeyCode::Up));
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!(0, p.buffer.cursor_row());
+        assert_eq!("if true\nthen ls\nfi", p.buffer.text());
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("ls", p.buffer.text());
+        p.feed(Key::plain(KeyCode::Down));
+        p.feed(Key::plain(KeyCode::Down));
+        assert_eq!(1, p.buffer.cursor_row());
+        p.feed(Key::plain(KeyCode::Down));
+        p.feed(Key::plain(KeyCode::Down));
+        assert_eq!("", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_ctrl_d_and_ctrl_c() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        type_text(&mut p, "ab");
+        p.feed(Key::plain(KeyCode::Left));
+        assert_eq!(Feed::Continue, p.feed(Key::ctrl('d')));
+        assert_eq!("a", p.buffer.text());
+        assert_eq!(Feed::Cancel, p.feed(Key::ctrl('c')));
+        p.buffer.set_text(String::new());
+        assert_eq!(Feed::Exit, p.feed(Key::ctrl('d')));
+    }
+
+    #[test]
+    fn test_feed_word_model() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        type_text(&mut p, "foo.bar(baz)");
+        p.feed(Key::alt('b'));
+        assert_eq!(0, p.buffer.cursor_position());
+
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_word_model(WordModel::CharClass);
+        type_text(&mut p, "foo.bar(baz)");
+        p.feed(Key::alt('b'));
+        p.feed(Key::alt('b'));
+        assert_eq!(8, p.buffer.cursor_position());
+        p.feed(Key::ctrl('w'));
+        assert_eq!("foo.barbaz)", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_paragraph_navigation() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        p.buffer.insert_text("a\nb\n\nc\nd", false, true);
+        // terminals may report Shift along with the brace
+        p.feed(KeyEvent::new(KeyCode::Char('{'), KeyModifiers::ALT | KeyModifiers::SHIFT).into());
+        assert_eq!(4, p.buffer.cursor_position());
+        p.feed(Key::alt('{'));
+        assert_eq!(0, p.buffer.cursor_position());
+        p.feed(Key::alt('}'));
+        assert_eq!(4, p.buffer.cursor_position());
+    }
+
+    #[test]
+    fn test_feed_key_bind_mode() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_key_bind_mode(KeyBindMode::CommonKeyBind)
+            .with_key_bind(KeyBind { key: Key::ctrl('x'), func: |b| b.set_text("x".to_string()) });
+        type_text(&mut p, "ab");
+        p.feed(Key::ctrl('a'));
+        assert_eq!(2, p.buffer.cursor_position());
+        p.feed(Key::plain(KeyCode::Home));
+        assert_eq!(0, p.buffer.cursor_position());
+        p.feed(Key::ctrl('x'));
+        assert_eq!("x", p.buffer.text());
+    }
+
+    #[test]
+    fn test_completion_show_at_start() {
+        let commands = TestCompleter::new(["build", "test"]).prefixed();
+        let mut p = Prompt::new(|_| {}, commands.clone());
+        p.show_completion_at_start();
+        assert!(p.completion.get_suggestions().is_empty());
+
+        let mut p = Prompt::new(|_| {}, commands).with_completion_show_at_start(true);
+        p.show_completion_at_start();
+        assert_eq!(2, p.completion.get_suggestions().len());
+        type_text(&mut p, "te");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!(1, p.completion.get_suggestions().len());
+        p.accept();
+        // the next line starts with all the suggestions again, none selected
+        p.show_completion_at_start();
+        assert_eq!(2, p.completion.get_suggestions().len());
+        assert_eq!(None, p.completion.selected());
+    }
+
+    #[test]
+    fn test_completion_trigger() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_completion_min_prefix_len(2);
+        type_text(&mut p, "a");
+        assert!(p.completion.get_suggestions().is_empty());
+        type_text(&mut p, "p");
+        assert_eq!(2, p.completion.get_suggestions().len());
+
+        let mut p = Prompt::new(|_| {}, fruits()).with_completion_trigger(CompletionTrigger::Tab);
+        type_text(&mut p, "a");
+        assert!(p.completion.get_suggestions().is_empty());
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!(2, p.completion.get_suggestions().len());
+        // once open, typing narrows them down
+        type_text(&mut p, "pp");
+        assert_eq!(vec!["apple"], p.completion.get_suggestions().iter().map(Suggestion::text).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_dismiss_completion_until_next_word() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        type_text(&mut p, "a");
+        assert_eq!(2, p.completion.get_suggestions().len());
+        p.feed(Key::plain(KeyCode::Esc));
+        assert!(p.completion.get_suggestions().is_empty());
+        type_text(&mut p, "p");
+        assert!(p.completion.get_suggestions().is_empty());
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!(2, p.completion.get_suggestions().len());
+
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Esc));
+        assert_eq!(("ap", None), (p.buffer.text().as_str(), p.completion.selected()));
+        type_text(&mut p, "r");
+        assert!(p.completion.get_suggestions().is_empty());
+        type_text(&mut p, " b");
+        assert_eq!(vec!["banana"], p.completion.get_suggestions().iter().map(Suggestion::text).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_completion_number_keys() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        type_text(&mut p, "a");
+        p.feed(Key::alt('2'));
+        assert_eq!("a", p.buffer.text());
+
+        let mut p = Prompt::new(|_| {}, fruits()).with_completion_number_keys();
+        p.feed(Key::alt('1'));
+        assert_eq!("", p.buffer.text());
+        type_text(&mut p, "a");
+        p.feed(Key::alt('3'));
+        assert_eq!("a", p.buffer.text());
+        p.feed(Key::alt('2'));
+        assert_eq!("apricot", p.buffer.text());
+        // while a suggestion is selected too
+        p.buffer.set_text("a".to_string());
+        p.buffer.set_cursor_position(1);
+        p.update_completion();
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::alt('1'));
+        assert_eq!("apple", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_completion_paging() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_completion_wrap_around(false);
+        type_text(&mut p, "a");
+        p.feed(Key::plain(KeyCode::PageDown));
+        assert_eq!(Some(1), p.completion.selected());
+        p.feed(Key::plain(KeyCode::Down));
+        assert_eq!(Some(1), p.completion.selected());
+        p.feed(Key::plain(KeyCode::PageUp));
+        assert_eq!(Some(0), p.completion.selected());
+        p.feed(Key::plain(KeyCode::Enter));
+        assert_eq!("apple", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_keymap() {
+        let menu = Keymap::new("vi-menu")
+            .with_condition(KeymapCondition::Completing)
+            .with_binding(Key::plain(KeyCode::Char('j')), Binding::named("menu-complete").unwrap())
+            .with_binding(Key::plain(KeyCode::Char('k')), Binding::named("menu-complete-backward").unwrap());
+        let mut p = Prompt::new(|_| {}, fruits()).with_keymap(menu);
+        type_text(&mut p, "a");
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Char('j')));
+        assert_eq!(Some(1), p.completion.selected());
+        p.feed(Key::plain(KeyCode::Char('k')));
+        assert_eq!(Some(0), p.completion.selected());
+        assert_eq!("a", p.buffer.text());
+        p.feed(Key::plain(KeyCode::Enter));
+        // once the menu is closed the keys type again
+        type_text(&mut p, "jk");
+        assert_eq!("applejk", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_key_handler() {
+        fn tab(cx: &mut KeyContext<TestCompleter, ()>) -> bool {
+            if cx.completion.get_suggestions().is_empty() {
+                cx.buffer.insert_text("    ", false, true);
+            } else {
+                if !cx.completion.completing() {
+                    cx.completion.next();
+                }
+                cx.accept_selected();
+            }
+            true
+        }
+        fn last_entry(cx: &mut KeyContext<TestCompleter, ()>) -> bool {
+            let Some(last) = cx.history.histories().last().cloned() else {
+                return false;
+            };
+            cx.buffer.insert_text(&last, false, true);
+            true
+        }
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_history(vec!["banana".to_string()])
+            .with_key_handler(Key::plain(KeyCode::Tab), tab)
+            .with_key_handler(Key::alt('.'), last_entry);
+        type_text(&mut p, "ap");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!("apple", p.buffer.text());
+        assert!(!p.completion.completing());
+        type_text(&mut p, " ");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!("apple     ", p.buffer.text());
+        p.feed(Key::alt('.'));
+        assert_eq!("apple     banana", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_inputrc() {
+        let inputrc = Inputrc::parse("\"\\C-w\": unix-line-discard\nset completion-ignore-case off\n");
+        let mut p = Prompt::new(|_| {}, fruits()).with_inputrc(&inputrc).with_completion_filter_while_typing(true);
+        type_text(&mut p, "ls -l apple");
+        p.feed(Key::ctrl('w'));
+        assert_eq!("", p.buffer.text());
+
+        type_text(&mut p, "a");
+        assert_eq!(2, p.completion.get_suggestions().len());
+        type_text(&mut p, "P");
+        assert!(p.completion.get_suggestions().is_empty());
+    }
+
+    #[test]
+    fn test_key_hints() {
+        let inputrc = Inputrc::parse("\"\\C-a\": end-of-line\n");
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_history_picker(Key::ctrl('r'))
+            .with_inputrc(&inputrc)
+            .with_key_hints();
+        assert_eq!(" Tab complete · Ctrl-R history · F1 help", p.key_hints().text());
+        let map = p.key_map();
+        let command = |key| map.iter().find(|(k, _)| *k == key).map(|(_, c)| c.name);
+        assert_eq!(Some("end-of-line"), command(Key::ctrl('a')));
+        assert_eq!(Some("forward-word"), command(Key::alt('f')));
+        assert_eq!(Some("end-of-file"), command(Key::ctrl('d')));
+
+        type_text(&mut p, "a");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!(" Tab next · Shift-Tab previous · PgDn page down", p.key_hints().text());
+        assert_eq!(Some("delete-char"), p.key_map().iter().find(|(k, _)| *k == Key::ctrl('d')).map(|(_, c)| c.name));
+        assert_eq!("Alt-Backspace", Key::new(KeyCode::Backspace, KeyModifiers::ALT).to_string());
+
+        let p = Prompt::new(|_| {}, fruits()).with_history_picker(Key::ctrl('r')).with_secret();
+        assert_eq!(" Tab complete · F1 help", p.key_hints().text());
+    }
+
+    #[test]
+    fn test_feed_on_accepted() {
+        let mut p = Prompt::with_context(Vec::new(), |_, _| {}, TestCompleter::from(vec![
+            Suggestion::with_title("users".to_string()).with_id("t1"),
+            Suggestion::with_title("users".to_string()).with_id("t2"),
+        ]))
+            .with_on_accepted(|s, accepted: &mut Vec<String>| accepted.push(s.id().unwrap().to_string()));
+        type_text(&mut p, "u");
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Char(' ')));
+        assert_eq!("users ", p.buffer.text());
+        assert_eq!(["t2"], p.context().as_slice());
+        // nothing is accepted without a selection
+        p.feed(Key::plain(KeyCode::Enter));
+        assert_eq!(1, p.context().len());
+    }
+
+    #[test]
+    fn test_on_change() {
+        let mut p = Prompt::with_context(Vec::new(), |_, _| {}, fruits())
+            .with_on_change(|change, changes: &mut Vec<TextChange>| changes.push(change.clone()));
+        type_text(&mut p, "ab");
+        p.feed(Key::plain(KeyCode::Backspace));
+        p.notify_changes();
+        let change = |range, text: &str| TextChange { range, text: text.to_string() };
+        assert_eq!(vec![change(0..0, "a"), change(1..1, "b"), change(1..2, "")], *p.context());
+        p.notify_changes();
+        assert_eq!(3, p.context().len());
+
+        let mut p = Prompt::with_context(Vec::new(), |_, _| {}, fruits())
+            .with_on_change(|change, changes: &mut Vec<TextChange>| changes.push(change.clone()))
+            .with_secret();
+        type_text(&mut p, "ab");
+        p.notify_changes();
+        assert!(p.context().is_empty());
+    }
+
+    #[test]
+    fn test_linter() {
+        fn wait_for_lint(p: &mut Prompt<TestCompleter>) {
+            for _ in 0..500 {
+                p.notify_changes();
+                if !p.linter.as_ref().unwrap().pending() {
+                    p.notify_changes();
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            panic!("the linter did not finish");
+        }
+
+        let mut p = Prompt::new(|_| {}, fruits()).with_linter(|text: &str| {
+            text.match_indices("bad").map(|(i, _)| Diagnostic::error(i..i + 3, "no bad words")).collect()
+        });
+        type_text(&mut p, "a bad b");
+        wait_for_lint(&mut p);
+        assert_eq!(vec![Diagnostic::error(2..5, "no bad words")], p.diagnostics());
+        let marked = p.renderer.display_text(p.buffer.document()).text.spans().iter()
+            .filter(|s| s.style.underline)
+            .map(|s| s.text.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["bad"], marked);
+        assert_eq!("", p.renderer.toolbar().text());
+
+        // the message is shown while the cursor is on the text
+        p.feed(Key::plain(KeyCode::Left));
+        p.feed(Key::plain(KeyCode::Left));
+        p.notify_changes();
+        assert_eq!("no bad words", p.renderer.toolbar().text());
+        p.feed(Key::plain(KeyCode::Left));
+        p.notify_changes();
+        assert_eq!("no bad words", p.renderer.toolbar().text());
+
+        // the diagnostics follow the text until it is linted again
+        p.feed(Key::plain(KeyCode::Home));
+        type_text(&mut p, "so ");
+        p.notify_changes();
+        assert_eq!(5..8, p.diagnostics()[0].range);
+        assert_eq!("", p.renderer.toolbar().text());
+        p.feed(Key::plain(KeyCode::End));
+        p.feed(Key::plain(KeyCode::Backspace));
+        type_text(&mut p, "bad");
+        p.notify_changes();
+        assert_eq!(1, p.diagnostics().len());
+        wait_for_lint(&mut p);
+        assert_eq!(vec![5..8, 9..12], p.diagnostics().iter().map(|d| d.range.clone()).collect::<Vec<_>>());
+
+        // a secret is never linted
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_linter(|text: &str| vec![Diagnostic::error(0..text.chars().count(), "seen")])
+            .with_secret();
+        type_text(&mut p, "bad");
+        wait_for_lint(&mut p);
+        assert!(p.diagnostics().is_empty());
+
+        // a linter that panics is reported and linting goes on
+        let mut p = Prompt::new(|_| {}, fruits()).with_linter(|text: &str| match text {
+            "boom" => panic!("out of tables"),
+            _ => Vec::new(),
+        });
+        type_text(&mut p, "boom");
+        wait_for_lint(&mut p);
+        assert_eq!("The linter failed: out of tables", p.renderer.toolbar().text());
+    }
+
+    #[test]
+    fn test_lexer() {
+        /// Styles digits, with no state across lines.
+        struct DigitLexer;
+
+        impl Lexer for DigitLexer {
+            type State = ();
+
+            fn start(&self) {}
+
+            fn lex_line(&self, line: &str, _: &mut ()) -> StyledText {
+                line.chars().fold(StyledText::new(), |text, c| match c.is_ascii_digit() {
+                    true => text.span(c.to_string(), SpanStyle::default().bold()),
+                    false => text.plain(c.to_string()),
+                })
+            }
+        }
+
+        let bold = |p: &Prompt<TestCompleter>| {
+            let display = p.renderer.display_text(p.buffer.document());
+            display.text.spans().iter().filter(|s| s.style.bold).map(|s| s.text.clone()).collect::<Vec<_>>()
+        };
+        let mut p = Prompt::new(|_| {}, fruits()).with_lexer(DigitLexer);
+        type_text(&mut p, "a1b23");
+        p.notify_changes();
+        assert_eq!(vec!["1", "23"], bold(&p));
+        // a stale highlight isn't shown
+        p.feed(Key::plain(KeyCode::Backspace));
+        assert!(bold(&p).is_empty());
+        p.notify_changes();
+        assert_eq!(vec!["1", "2"], bold(&p));
+
+        let mut p = Prompt::new(|_| {}, fruits()).with_lexer(DigitLexer).with_secret();
+        type_text(&mut p, "a1");
+        p.notify_changes();
+        assert!(bold(&p).is_empty());
+    }
+
+    #[test]
+    fn test_execute_with_pager() {
+        let output = Output::with_writer(io::sink());
+        output.set_size((80, 24));
+        let printer = output.clone();
+        let mut p = Prompt::new(move |input: &str| printer.print(input), fruits())
+            .with_output(output.clone())
+            .with_pager(PagerMode::External);
+        assert_eq!(None, output.pager());
+        p.execute("ls").unwrap();
+        assert_eq!(Some(PagerMode::External), output.pager());
+    }
+
+    #[test]
+    fn test_feed_frecency() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_frecency(Frecency::new());
+        type_text(&mut p, "ap");
+        assert_eq!("apple", p.completion.get_suggestions()[0].text());
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Char(' ')));
+        assert_eq!(1, p.frecency().unwrap().count(&Suggestion::with_title("apricot".to_string())));
+
+        type_text(&mut p, "ap");
+        assert_eq!("apricot", p.completion.get_suggestions()[0].text());
+    }
+
+    #[test]
+    fn test_feed_filter_while_typing() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_completion_filter_while_typing(true);
+        type_text(&mut p, "a");
+        assert!(!p.completion_stale);
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Tab));
+        type_text(&mut p, "p");
+        // the selection is kept and not inserted
+        assert_eq!("ap", p.buffer.text());
+        assert_eq!("apricot", p.completion.get_selected_suggestion().unwrap().text());
+        assert!(p.completion_stale);
+        type_text(&mut p, "r");
+        assert_eq!(1, p.completion.get_suggestions().len());
+        p.feed(Key::plain(KeyCode::Backspace));
+        p.feed(Key::plain(KeyCode::Backspace));
+        assert_eq!(1, p.completion.get_suggestions().len());
+        p.update_completion();
+        assert!(!p.completion_stale);
+        assert_eq!(2, p.completion.get_suggestions().len());
+        assert_eq!("apricot", p.completion.get_selected_suggestion().unwrap().text());
+
+        // ending the word inserts the selected suggestion
+        p.feed(Key::plain(KeyCode::Char(' ')));
+        assert_eq!("apricot ", p.buffer.text());
+    }
+
+    #[test]
+    fn test_feed_danger_check() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_danger_check(|input| input.starts_with("rm -rf").then(|| "Deletes files recursively.".to_string()));
+        p.renderer.set_toolbar("status");
+        type_text(&mut p, "rm -rf /");
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert!(p.renderer.toolbar().text().starts_with("Deletes files recursively."));
+        // the key is swallowed and editing goes on
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Char('n'))));
+        assert_eq!("rm -rf /", p.buffer.text());
+        assert_eq!("status", p.renderer.toolbar().text());
+
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Char('y'))));
+        assert_eq!("status", p.renderer.toolbar().text());
+
+        p.buffer.set_text("ls".to_string());
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Enter)));
+    }
+
+    #[test]
+    fn test_feed_validator() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_validator(|input| input.parse::<u8>().map(|_| ()).map_err(|e| e.to_string()));
+        type_text(&mut p, "300");
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("number too large to fit in target type", p.renderer.toolbar().text());
+        // the key is not swallowed
+        p.feed(Key::plain(KeyCode::Backspace));
+        assert_eq!("30", p.buffer.text());
+        assert_eq!("", p.renderer.toolbar().text());
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Enter)));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_feed_danger_pattern() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_danger_pattern(regex::Regex::new(r"(?i)^\s*drop\s+table").unwrap(), "Drops a table.");
+        type_text(&mut p, "DROP TABLE users");
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Enter)));
+        assert!(p.renderer.toolbar().text().starts_with("Drops a table."));
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Char('Y'))));
+    }
+
+    #[test]
+    fn test_next_timer() {
+        let now = Instant::now();
+        let p = Prompt::new(|_| {}, fruits());
+        assert_eq!(None, p.next_timer(now, now, now));
+
+        let p = Prompt::new(|_| {}, fruits())
+            .with_timeout(Duration::from_secs(30))
+            .with_idle_callback(Duration::from_secs(1), |r, _| r.set_toolbar("tick"));
+        assert_eq!(Some((now + Duration::from_secs(1), Timer::Idle)), p.next_timer(now, now, now));
+        let last_key = now - Duration::from_millis(29_500);
+        assert_eq!(Some((now + Duration::from_millis(500), Timer::Timeout)), p.next_timer(now, last_key, now));
+    }
+
+    #[test]
+    fn test_preview_pane() {
+        let now = Instant::now();
+        let delay = Duration::from_millis(300);
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_preview_pane(delay, |d| StyledText::new().plain(format!("{} chars\nat {}", d.char_count(), d.cursor_position())));
+        p.update_preview_pane();
+        assert!(p.next_timer(now, now, now).is_none());
+        let pane = |p: &Prompt<TestCompleter>| p.renderer.preview_pane().iter().map(|l| l.text()).collect::<Vec<_>>();
+        assert_eq!(vec!["0 chars", "at 0"], pane(&p));
+
+        // the pane waits for the typing to stop
+        type_text(&mut p, "ab");
+        assert_eq!(Some((now + delay, Timer::PreviewPane)), p.next_timer(now, now, now));
+        assert_eq!(vec!["0 chars", "at 0"], pane(&p));
+        p.update_preview_pane();
+        assert_eq!(vec!["2 chars", "at 2"], pane(&p));
+        p.feed(Key::plain(KeyCode::Left));
+        assert_eq!(Some((now + delay, Timer::PreviewPane)), p.next_timer(now, now, now));
+
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_preview_pane(delay, |d| StyledText::new().plain(d.text.clone()))
+            .with_secret();
+        type_text(&mut p, "hunter2");
+        p.update_preview_pane();
+        assert!(p.renderer.preview_pane().is_empty());
+        assert!(p.next_timer(now, now, now).is_none());
+    }
+
+    #[test]
+    fn test_repl_screen_scroll_keys() {
+        let screen = ReplScreen::new(4);
+        for i in 0..20 {
+            screen.println(i.to_string());
+        }
+        let mut p = Prompt::new(|_| {}, fruits()).with_repl_screen(screen.clone());
+        p.update_win_size(80, 10);
+        assert_eq!(4, p.renderer.completion_height(&Document::new()) + 1);
+        p.feed(Key::new(KeyCode::PageUp, KeyModifiers::SHIFT));
+        p.feed(Key::new(KeyCode::PageUp, KeyModifiers::SHIFT));
+        assert_eq!(12, screen.scroll());
+        p.feed(Key::new(KeyCode::PageDown, KeyModifiers::SHIFT));
+        assert_eq!(6, screen.scroll());
+        assert_eq!("", p.buffer.text());
+    }
+
+    #[cfg(feature = "normalization")]
+    #[test]
+    fn test_normalization() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_normalization(Normalization::Nfc);
+        type_text(&mut p, "cafe x");
+        p.buffer.cursor_left(2);
+        p.feed(Key::plain(KeyCode::Char('\u{301}')));
+        assert_eq!("caf\u{e9} x", p.buffer.text());
+        assert_eq!(4, p.buffer.document().cursor_position());
+        // only the accented letter is replaced
+        p.buffer.set_track_changes(true);
+        p.feed(Key::plain(KeyCode::Char('s')));
+        p.feed(Key::plain(KeyCode::Char('\u{301}')));
+        assert_eq!("caf\u{e9}\u{15b} x", p.buffer.text());
+        assert_eq!(
+            vec![TextChange { range: 4..4, text: "s".to_string() }, TextChange { range: 4..5, text: "\u{15b}".to_string() }],
+            p.buffer.take_changes(),
+        );
+    }
+
+    #[test]
+    fn test_notifier() {
+        let notifier = Notifier::new()
+            .with(Notice::NoCompletions, Feedback::Message)
+            .with(Notice::HistoryEnd, Feedback::Bell)
+            .with(Notice::UnboundKey, Feedback::Flash);
+        let mut p = Prompt::new(|_| {}, fruits()).with_notifier(notifier);
+        type_text(&mut p, "x");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!("No completions.", p.renderer.toolbar().text());
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("", p.renderer.toolbar().text());
+        assert_eq!(Some(Feedback::Bell), p.alert.take());
+        p.feed(Key::ctrl('q'));
+        assert_eq!(Some(Feedback::Flash), p.alert.take());
+        // bound keys are quiet
+        p.feed(Key::ctrl('a'));
+        type_text(&mut p, "ap");
+        p.feed(Key::plain(KeyCode::Tab));
+        assert_eq!(None, p.alert);
+        assert_eq!("", p.renderer.toolbar().text());
+    }
+
+    #[test]
+    fn test_max_length_and_char_filter() {
+        let mut p = Prompt::new(|_| {}, fruits())
+            .with_max_length(4)
+            .with_char_filter(|c| c.is_ascii_digit())
+            .with_context_key_bind(Key::ctrl('x'), |b, _| b.insert_text("99", false, true));
+        type_text(&mut p, "12a");
+        assert_eq!("12", p.buffer.text());
+        assert_eq!("'a' is not accepted.", p.renderer.toolbar().text());
+        type_text(&mut p, "345");
+        assert_eq!("1234", p.buffer.text());
+        assert_eq!("At most 4 characters.", p.renderer.toolbar().text());
+        p.feed(Key::plain(KeyCode::Backspace));
+        assert_eq!("", p.renderer.toolbar().text());
+        type_text(&mut p, "9");
+        assert_eq!("1239", p.buffer.text());
+
+        // whatever else changes the input is held to the same limits
+        p.buffer.set_text("12".to_string());
+        p.buffer.set_cursor_position(2);
+        p.buffer.end_change();
+        p.history.add("12345".to_string());
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("12", p.buffer.text());
+        assert_eq!("At most 4 characters.", p.renderer.toolbar().text());
+        p.history.add("1x".to_string());
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("12", p.buffer.text());
+        assert_eq!("'x' is not accepted.", p.renderer.toolbar().text());
+        p.feed(Key::ctrl('x'));
+        assert_eq!("1299", p.buffer.text());
+        p.feed(Key::ctrl('x'));
+        assert_eq!("1299", p.buffer.text());
+    }
+
+    #[test]
+    fn test_protected_text() {
+        let mut p = Prompt::new(|_| {}, fruits()).with_protected_text("eat ");
+        assert_eq!(4, p.buffer.cursor_position());
+        p.feed(Key::plain(KeyCode::Backspace));
+        p.feed(Key::ctrl('u'));
+        p.feed(Key::plain(KeyCode::Home));
+        type_text(&mut p, "ap");
+        p.feed(Key::plain(KeyCode::Tab));
+        p.feed(Key::plain(KeyCode::Enter));
+        assert_eq!("eat apple", p.accept());
+        assert_eq!("eat ", p.buffer.text());
+        assert_eq!(4, p.buffer.protected());
+
+        // entries typed without the protected text get it back
+        p.history.add("banana".to_string());
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("eat banana", p.buffer.text());
+        p.feed(Key::plain(KeyCode::Up));
+        assert_eq!("eat apple", p.buffer.text());
+        p.feed(Key::plain(KeyCode::Down));
+        p.feed(Key::plain(KeyCode::Down));
+        assert_eq!("eat ", p.buffer.text());
+        assert_eq!(4, p.buffer.protected());
+        p.feed(Key::ctrl('u'));
+        assert_eq!("eat ", p.buffer.text());
+    }
+
+    #[test]
+    fn test_back_key() {
+        let mut p = Prompt::new(|_| {}, fruits());
+        assert_eq!(Feed::Continue, p.feed(Key::plain(KeyCode::Esc)));
+        p.set_back_key(Key::plain(KeyCode::Esc));
+        p.set_input("apple".to_string());
+        assert_eq!(5, p.buffer.cursor_position());
+        assert_eq!(Feed::Back, p.feed(Key::plain(KeyCode::Esc)));
+    }
+
+    #[test]
+    fn test_secret() {
+        let screen = ReplScreen::new(2);
+        let mut p = Prompt::new(|_| {}, fruits()).with_secret().with_repl_screen(screen.clone());
+        type_text(&mut p, "apple");
+        assert!(p.completion.get_suggestions().is_empty());
+        assert_eq!("*****", p.renderer.display_text(p.buffer.document()).text.text());
+        assert_eq!("apple", p.accept());
+        assert!(p.history.histories().is_empty());
+
+        // history designators in a secret are left as typed
+        let mut p = Prompt::new(|_| {}, fruits()).with_secret().with_history_expansion();
+        p.history.add("apple".to_string());
+        type_text(&mut p, "!!");
+        assert_eq!(Feed::Accept, p.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("!!", p.accept());
+    }
+
+    #[test]
+    fn test_input_method_commit() {
+        let mut p = Prompt::new(|_| {}, kanji());
+        // an input method commits 日本 as two keys read at once
+        p.input_pending = true;
+        p.feed(Key::plain(KeyCode::Char('日')));
+        assert!(p.completion.get_suggestions().is_empty());
+        assert!(p.completion_stale);
+        p.input_pending = false;
+        p.feed(Key::plain(KeyCode::Char('本')));
+        assert!(!p.completion_stale);
+        assert_eq!(2, p.completion.get_suggestions().len());
+        assert_eq!(2, p.buffer.document().cursor_position());
+    }
+
+    /// Events read by the prompt loop, each one arriving its delay after the one before.
+    struct Script {
+        events: VecDeque<(Duration, Event)>,
+        last: Instant,
+    }
+
+    impl Script {
+        fn new<I: IntoIterator<Item = (Duration, Event)>>(events: I) -> Self {
+            Self { events: events.into_iter().collect(), last: Instant::now() }
+        }
+
+        /// Returns when the next event arrives.
+        fn next_at(&self) -> Option<Instant> {
+            self.events.front().map(|(delay, _)| self.last + *delay)
+        }
+    }
+
+    impl Terminal for Script {
+        fn size(&self) -> io::Result<(u16, u16)> {
+            // nothing is drawn on a terminal without a size
+            Ok((0, 0))
+        }
+
+        fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+            let deadline = Instant::now() + timeout;
+            let at = self.next_at().unwrap_or(deadline);
+            std::thread::sleep(at.min(deadline).saturating_duration_since(Instant::now()));
+            Ok(self.next_at().is_some_and(|at| at <= deadline))
+        }
+
+        fn read(&mut self) -> io::Result<Event> {
+            let at = self.next_at().ok_or(io::ErrorKind::UnexpectedEof)?;
+            std::thread::sleep(at.saturating_duration_since(Instant::now()));
+            self.last = Instant::now();
+            Ok(self.events.pop_front().unwrap().1)
+        }
+    }
+
+    #[test]
+    fn test_input_method_commit_renders_once() {
+        use std::cell::RefCell;
+
+        let rendered = RefCell::new(Vec::new());
+        let mut p = Prompt::new(|_| {}, kanji())
+            .with_preview(|document| {
+                rendered.borrow_mut().push(document.text.clone());
+                None
+            });
+        let key = |code, delay| (Duration::from_millis(delay), Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+        // an input method commits 日本語 as three keys read at once, Enter comes later
+        let keys = [key(KeyCode::Char('日'), 0), key(KeyCode::Char('本'), 0), key(KeyCode::Char('語'), 0), key(KeyCode::Enter, 100)];
+        p.terminal = Box::new(Script::new(keys));
+        assert_eq!(Ending::Accept("日本語".to_string()), p.read_line().unwrap());
+        // the empty input, then the committed text at once
+        assert_eq!(vec!["".to_string(), "日本語".to_string()], *rendered.borrow());
+    }
+
+    struct TableCompleter;
+
+    impl ContextCompleter<Vec<&str>> for TableCompleter {
+        fn complete_with_context(&self, document: &Document, tables: &Vec<&str>) -> Vec<Suggestion> {
+            let word = document.get_word_before_cursor();
+            tables.iter()
+                .filter(|t| t.starts_with(word.as_str()))
+                .map(|t| Suggestion::with_title(t.to_string()))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_feed_context() {
+        let mut p = Prompt::with_context(vec!["users"], |input, tables: &mut Vec<&str>| {
+            if input == "create orders" {
+                tables.push("orders");
+            }
+        }, TableCompleter)
+            .with_context_key_bind(Key::ctrl('x'), |b, tables| b.insert_text(tables[0], false, true));
+        type_text(&mut p, "select o");
+        assert!(p.completion.get_suggestions().is_empty());
+
+        (p.executor)("create orders", &mut p.context);
+        assert_eq!(["users", "orders"], p.context().as_slice());
+        p.feed(Key::plain(KeyCode::Backspace));
+        type_text(&mut p, "o");
+        assert_eq!(1, p.completion.get_suggestions().len());
+
+        p.context_mut().clear();
+        p.context_mut().push("items");
+        p.buffer.set_text(String::new());
+        p.feed(Key::ctrl('x'));
+        assert_eq!("items", p.buffer.text());
+    }
+}
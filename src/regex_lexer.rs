@@ -0,0 +1,206 @@
+use regex::Regex;
+
+use crate::lexer::Lexer;
+use crate::style::{SpanStyle, StyledText};
+
+/// How a class of tokens is found.
+#[derive(Debug, Clone)]
+enum Rule {
+    /// Within a line.
+    Token(Regex),
+    /// From `start` to the end of the first match of `end` after it, e.g. a block comment,
+    /// which may span lines.
+    Block { start: Regex, end: Regex },
+}
+
+/// [Lexer] styling token classes defined by regular expressions, e.g. keywords, strings,
+/// numbers and comments, for applications that don't need a lexer of their own.
+///
+/// At each position the rule matching first is used, the one added first if several
+/// start at the same char, and the text no rule matches is left plain.
+///
+/// ```
+/// use rusty_prompt::{Color, RegexLexer, SpanStyle};
+///
+/// let lexer = RegexLexer::new()
+///     .with_keywords(["select", "from", "where"], SpanStyle::default().fg(Color::Blue).bold())
+///     .with_strings(SpanStyle::default().fg(Color::Green))
+///     .with_numbers(SpanStyle::default().fg(Color::Magenta))
+///     .with_line_comments("--", SpanStyle::default().fg(Color::DarkGrey));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RegexLexer {
+    rules: Vec<(Rule, SpanStyle)>,
+}
+
+impl RegexLexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Styles the matches of `pattern`. Empty matches are ignored.
+    pub fn with_rule(mut self, pattern: Regex, style: SpanStyle) -> Self {
+        self.rules.push((Rule::Token(pattern), style));
+        self
+    }
+
+    /// Styles what runs from a match of `start` to the end of the first match of `end`
+    /// after it, over as many lines as it takes.
+    pub fn with_block(mut self, start: Regex, end: Regex, style: SpanStyle) -> Self {
+        self.rules.push((Rule::Block { start, end }, style));
+        self
+    }
+
+    /// Styles `keywords` as whole words, case sensitively.
+    pub fn with_keywords<I, S>(self, keywords: I, style: SpanStyle) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let keywords = keywords.into_iter().map(|k| regex::escape(k.as_ref())).collect::<Vec<_>>();
+        match keywords.is_empty() {
+            true => self,
+            false => self.with_rule(Regex::new(&format!(r"\b(?:{})\b", keywords.join("|"))).unwrap(), style),
+        }
+    }
+
+    /// Styles strings between double or single quotes, in which a backslash escapes the
+    /// next char. A string left open goes on to the next line.
+    pub fn with_strings(self, style: SpanStyle) -> Self {
+        self.with_block(Regex::new("\"").unwrap(), Regex::new(r#"(?:[^"\\]|\\.)*""#).unwrap(), style)
+            .with_block(Regex::new("'").unwrap(), Regex::new(r"(?:[^'\\]|\\.)*'").unwrap(), style)
+    }
+
+    /// Styles decimal numbers, with a fraction or not, and hexadecimal ones starting with
+    /// `0x`.
+    pub fn with_numbers(self, style: SpanStyle) -> Self {
+        self.with_rule(Regex::new(r"\b(?:0x[0-9a-fA-F]+|\d+(?:\.\d+)?)\b").unwrap(), style)
+    }
+
+    /// Styles comments running from `prefix`, e.g. `#` or `--`, to the end of the line.
+    pub fn with_line_comments(self, prefix: &str, style: SpanStyle) -> Self {
+        self.with_rule(Regex::new(&format!("{}.*", regex::escape(prefix))).unwrap(), style)
+    }
+
+    /// Returns where the block of the rule at `index` ends in `line`, searching from
+    /// `from`, if it does.
+    fn block_end(&self, index: usize, line: &str, from: usize) -> Option<usize> {
+        match &self.rules[index].0 {
+            Rule::Block { end, .. } => end.find(&line[from..]).map(|m| from + m.end()),
+            Rule::Token(_) => None,
+        }
+    }
+
+    /// Returns the rule matching first from `from`, with where its match starts and
+    /// ends.
+    fn first_match(&self, line: &str, from: usize) -> Option<(usize, usize, usize)> {
+        self.rules.iter().enumerate()
+            .filter_map(|(i, (rule, _))| {
+                let pattern = match rule {
+                    Rule::Token(pattern) => pattern,
+                    Rule::Block { start, .. } => start,
+                };
+                pattern.find_at(line, from).filter(|m| !m.is_empty()).map(|m| (i, m.start(), m.end()))
+            })
+            .min_by_key(|&(i, start, _)| (start, i))
+    }
+}
+
+impl Lexer for RegexLexer {
+    /// The block rule the line starts inside of, if any.
+    type State = Option<usize>;
+
+    fn start(&self) -> Option<usize> {
+        None
+    }
+
+    fn lex_line(&self, line: &str, state: &mut Option<usize>) -> StyledText {
+        let mut text = StyledText::new();
+        let mut pos = 0;
+        if let Some(index) = *state {
+            let Some(end) = self.block_end(index, line, 0) else {
+                return text.span(line, self.rules[index].1);
+            };
+            text.push(&line[..end], self.rules[index].1);
+            pos = end;
+            *state = None;
+        }
+        while let Some((index, start, end)) = self.first_match(line, pos) {
+            text.push(&line[pos..start], SpanStyle::default());
+            let style = self.rules[index].1;
+            let end = match &self.rules[index].0 {
+                Rule::Token(_) => end,
+                Rule::Block { .. } => match self.block_end(index, line, end) {
+                    Some(end) => end,
+                    None => {
+                        *state = Some(index);
+                        line.len()
+                    }
+                },
+            };
+            text.push(&line[start..end], style);
+            pos = end;
+        }
+        text.push(&line[pos..], SpanStyle::default());
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Highlighter;
+    use crossterm::style::Color;
+
+    fn lexer() -> RegexLexer {
+        RegexLexer::new()
+            .with_line_comments("#", SpanStyle::default().fg(Color::DarkGrey))
+            .with_keywords(["if", "then", "fi"], SpanStyle::default().bold())
+            .with_strings(SpanStyle::default().fg(Color::Green))
+            .with_numbers(SpanStyle::default().fg(Color::Magenta))
+            .with_block(Regex::new(r"/\*").unwrap(), Regex::new(r"\*/").unwrap(), SpanStyle::default().italic())
+    }
+
+    fn spans(text: &StyledText) -> Vec<(&str, SpanStyle)> {
+        text.spans().iter().map(|s| (s.text.as_str(), s.style)).collect()
+    }
+
+    #[test]
+    fn test_regex_lexer() {
+        let plain = SpanStyle::default();
+        let keyword = SpanStyle::default().bold();
+        let string = SpanStyle::default().fg(Color::Green);
+        let number = SpanStyle::default().fg(Color::Magenta);
+        let comment = SpanStyle::default().fg(Color::DarkGrey);
+        let l = lexer();
+        let mut state = l.start();
+        let text = l.lex_line(r##"if echo "a\"#b" 0x1f; then  # 2 "##, &mut state);
+        assert_eq!(vec![
+            ("if", keyword),
+            (" echo ", plain),
+            (r##""a\"#b""##, string),
+            (" ", plain),
+            ("0x1f", number),
+            ("; ", plain),
+            ("then", keyword),
+            ("  ", plain),
+            ("# 2 ", comment),
+        ], spans(&text));
+        assert_eq!(None, state);
+        // not within words
+        assert_eq!(vec![("iffy x2", plain)], spans(&l.lex_line("iffy x2", &mut state)));
+
+        // strings and blocks go on to the next lines
+        let text = l.lex_line("echo 'it", &mut state);
+        assert_eq!(vec![("echo ", plain), ("'it", string)], spans(&text));
+        assert_eq!(vec![("is", string)], spans(&l.lex_line("is", &mut state)));
+        let text = l.lex_line(r"\'s' fi", &mut state);
+        assert_eq!(vec![(r"\'s'", string), (" ", plain), ("fi", keyword)], spans(&text));
+        assert_eq!(None, state);
+
+        let mut h = Highlighter::new(lexer());
+        let text = h.highlight("1 /* if\n2 */ 3");
+        let italic = SpanStyle::default().italic();
+        assert_eq!(vec![("1", number), (" ", plain), ("/* if", italic), ("\n", plain), ("2 */", italic), (" ", plain), ("3", number)], spans(&text));
+    }
+}
@@ -0,0 +1,1328 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use crossterm::{
+    cursor,
+    queue,
+    style::{Attribute, Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+
+use crate::completion::CompletionManager;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::document::{Document, DEFAULT_TAB_WIDTH};
+use crate::hyperlink::{self, supports_hyperlinks};
+use crate::layout::{self, fit_prefix, Cell, Frame, Role, Scene, SPINNER_FRAMES};
+use crate::segments::Segments;
+use crate::style::{SpanStyle, Style, StyledText};
+use crate::theme::{Border, Theme};
+use crate::transform::{ControlCharacters, DisplayText, TabExpansion, Transformation};
+
+/// Most lines of the preview pane shown unless configured otherwise.
+const DEFAULT_PREVIEW_PANE_HEIGHT: usize = 5;
+/// How long the screen stays in reverse video for [Renderer::flash].
+const FLASH_DURATION: Duration = Duration::from_millis(100);
+
+/// Renders the prompt, the completion popup and the toolbar to the terminal.
+pub struct Renderer<W: Write> {
+    out: W,
+    /// What is written to `out` at the next flush, so that each frame is written at once
+    /// rather than piece by piece, which terminals may show half drawn.
+    frame: Vec<u8>,
+    prefix: StyledText,
+    /// What `prefix` is made of, if it was set from segments.
+    segments: Option<Segments>,
+    /// Replaces the prefix once the input is accepted, see [Renderer::set_transient_prefix].
+    transient_prefix: Option<StyledText>,
+    placeholder: StyledText,
+    toolbar: StyledText,
+    /// Lines shown between the completion popup and the toolbar, see
+    /// [Renderer::set_preview_pane].
+    preview_pane: Vec<StyledText>,
+    preview_pane_height: usize,
+    key_hints: StyledText,
+    theme: Theme,
+    popup_border: Option<Border>,
+    transformations: Vec<Box<dyn Transformation>>,
+    /// The input styled by a lexer, see [Renderer::set_highlighted].
+    highlighted: Option<StyledText>,
+    diagnostics: Vec<Diagnostic>,
+    control_characters: ControlCharacters,
+    tab_width: usize,
+    row: u16,
+    col: u16,
+    previous_cursor: usize,
+    /// The frame on the screen, which the next one is drawn over, or None to draw every row.
+    drawn: Option<Frame>,
+    spinner_frame: usize,
+    /// Whether the description of the selected suggestion scrolls when it is truncated.
+    marquee: bool,
+    marquee_step: usize,
+    /// The suggestion the marquee scrolls the description of.
+    marquee_selected: Option<usize>,
+    /// The description of the selected suggestion was scrolling at the last render.
+    marquee_running: bool,
+    number_badges: bool,
+    hyperlinks: bool,
+    screen_reader: bool,
+    /// What was last announced in screen reader mode.
+    announced: Announced,
+}
+
+/// State of the prompt as last announced to a screen reader.
+#[derive(Debug, Default)]
+struct Announced {
+    suggestions: Vec<String>,
+    selected: Option<usize>,
+    toolbar: String,
+    loading: bool,
+}
+
+impl<W: Write> Renderer<W> {
+    pub fn new<P: Into<StyledText>>(out: W, prefix: P) -> Self {
+        Self {
+            out,
+            frame: Vec::new(),
+            prefix: prefix.into(),
+            segments: None,
+            transient_prefix: None,
+            placeholder: StyledText::new(),
+            toolbar: StyledText::new(),
+            preview_pane: Vec::new(),
+            preview_pane_height: DEFAULT_PREVIEW_PANE_HEIGHT,
+            key_hints: StyledText::new(),
+            theme: Theme::default(),
+            popup_border: None,
+            transformations: Vec::new(),
+            highlighted: None,
+            diagnostics: Vec::new(),
+            control_characters: ControlCharacters::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            row: 0,
+            col: 0,
+            previous_cursor: 0,
+            drawn: None,
+            spinner_frame: 0,
+            marquee: false,
+            marquee_step: 0,
+            marquee_selected: None,
+            marquee_running: false,
+            number_badges: false,
+            hyperlinks: supports_hyperlinks(),
+            screen_reader: false,
+            announced: Announced::default(),
+        }
+    }
+
+    pub fn prefix(&self) -> &StyledText {
+        &self.prefix
+    }
+
+    /// Sets the prefix. Spans without colors of their own use the theme's prefix style.
+    pub fn set_prefix<P: Into<StyledText>>(&mut self, prefix: P) {
+        self.prefix = prefix.into();
+        self.segments = None;
+    }
+
+    pub fn prefix_segments(&self) -> Option<&Segments> {
+        self.segments.as_ref()
+    }
+
+    /// Sets the prefix to `segments`, which can then be updated one at a time with
+    /// [set_segment](Renderer::set_segment).
+    pub fn set_prefix_segments(&mut self, segments: Segments) {
+        self.prefix = segments.styled_text();
+        self.segments = Some(segments);
+    }
+
+    /// Sets the text of the prefix segment called `name`. Returns false if there is no
+    /// such segment.
+    pub fn set_segment<T: Into<String>>(&mut self, name: &str, text: T) -> bool {
+        self.update_segments(|segments| segments.set(name, text)).unwrap_or(false)
+    }
+
+    /// Updates the prefix segments with `update`, e.g. several at once. Returns None if
+    /// the prefix is not made of segments.
+    pub fn update_segments<F: FnOnce(&mut Segments) -> R, R>(&mut self, update: F) -> Option<R> {
+        let segments = self.segments.as_mut()?;
+        let result = update(segments);
+        self.prefix = segments.styled_text();
+        Some(result)
+    }
+
+    pub fn transient_prefix(&self) -> Option<&StyledText> {
+        self.transient_prefix.as_ref()
+    }
+
+    /// Sets a compact prefix, e.g. `"$ "`, that replaces the prefix in front of accepted
+    /// input, so that the scrollback holds the commands without the full prompt. None
+    /// keeps the prefix.
+    pub fn set_transient_prefix(&mut self, prefix: Option<StyledText>) {
+        self.transient_prefix = prefix;
+    }
+
+    /// Returns the prefix accepted input is written after by [break_line](Renderer::break_line).
+    pub fn final_prefix(&self) -> &StyledText {
+        self.transient_prefix.as_ref().unwrap_or(&self.prefix)
+    }
+
+    pub fn placeholder(&self) -> &StyledText {
+        &self.placeholder
+    }
+
+    /// Sets the text shown in place of the input while it is empty.
+    pub fn set_placeholder<P: Into<StyledText>>(&mut self, placeholder: P) {
+        self.placeholder = placeholder.into();
+    }
+
+    pub fn toolbar(&self) -> &StyledText {
+        &self.toolbar
+    }
+
+    /// Sets the text of the toolbar shown below the completion popup.
+    /// An empty text hides the toolbar.
+    pub fn set_toolbar<T: Into<StyledText>>(&mut self, toolbar: T) {
+        self.toolbar = toolbar.into();
+    }
+
+    /// Returns the lines of the preview pane, as they are shown.
+    pub fn preview_pane(&self) -> &[StyledText] {
+        &self.preview_pane
+    }
+
+    /// Sets what the pane between the completion popup and the toolbar shows, e.g. a dry
+    /// run of the input. Only its first [preview_pane_height](Renderer::set_preview_pane_height)
+    /// lines are shown, and the pane is hidden when `text` is empty.
+    pub fn set_preview_pane<T: Into<StyledText>>(&mut self, text: T) {
+        let text = text.into();
+        self.preview_pane = match text.is_empty() {
+            true => Vec::new(),
+            false => text.lines().into_iter().take(self.preview_pane_height).collect(),
+        };
+    }
+
+    /// Sets the most lines the preview pane takes, 5 by default.
+    pub fn set_preview_pane_height(&mut self, height: usize) {
+        self.preview_pane_height = height;
+        self.preview_pane.truncate(height);
+    }
+
+    pub fn key_hints(&self) -> &StyledText {
+        &self.key_hints
+    }
+
+    /// Sets the bar listing key bindings, e.g. `Tab complete · Ctrl-R history`, shown on
+    /// the last line, below the toolbar. An empty text hides the bar.
+    pub fn set_key_hints<T: Into<StyledText>>(&mut self, key_hints: T) {
+        self.key_hints = key_hints.into();
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Replaces the palette. It is used from the next call to [render](Renderer::render),
+    /// use [switch_theme](Renderer::switch_theme) to redraw right away.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Replaces the palette and immediately redraws the prompt with it.
+    pub fn switch_theme<C>(
+        &mut self,
+        theme: Theme,
+        document: &Document,
+        completion: &CompletionManager<C>,
+    ) -> io::Result<()> {
+        self.set_theme(theme);
+        self.render(document, completion)
+    }
+
+    pub fn popup_border(&self) -> Option<Border> {
+        self.popup_border
+    }
+
+    /// Sets the border drawn around the completion popup, in the theme's `popup_border`
+    /// style, or None for no border. The border takes two rows and two columns from the
+    /// room left for the suggestions.
+    pub fn set_popup_border(&mut self, border: Option<Border>) {
+        self.popup_border = border;
+    }
+
+    /// Appends a transformation applied to the input text at render time.
+    pub fn add_transformation<T: Transformation + 'static>(&mut self, transformation: T) {
+        self.transformations.push(Box::new(transformation));
+    }
+
+    pub fn clear_transformations(&mut self) {
+        self.transformations.clear();
+    }
+
+    /// Sets the input as styled by a lexer, which the transformations start from. It
+    /// is ignored once the text of the input is no longer the same.
+    pub fn set_highlighted(&mut self, highlighted: Option<StyledText>) {
+        self.highlighted = highlighted;
+    }
+
+    /// Sets the problems found in the input, whose text is shown in the style of their
+    /// [severity](crate::Severity::style), the worst one winning where they overlap.
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn control_characters(&self) -> &ControlCharacters {
+        &self.control_characters
+    }
+
+    /// Sets how non-printable characters in the input are displayed.
+    pub fn set_control_characters(&mut self, control_characters: ControlCharacters) {
+        self.control_characters = control_characters;
+    }
+
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// Sets the number of columns between tab stops, counted from the start of the input.
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width.max(1);
+    }
+
+    /// Returns the input text as it is displayed, after every transformation.
+    /// Non-printable characters are made visible and tabs are expanded after the added
+    /// transformations, so that nothing is left for the terminal to interpret.
+    pub fn display_text(&self, document: &Document) -> DisplayText {
+        let mut display = DisplayText::new(&document.text, document.cursor_position());
+        if let Some(highlighted) = self.highlighted.as_ref().filter(|h| h.text() == document.text) {
+            display.text = highlighted.clone();
+        }
+        if !self.diagnostics.is_empty() {
+            display = display.map_chars(|i, c, style| {
+                let severity = self.diagnostics.iter().filter(|d| d.range.contains(&i)).map(|d| d.severity).max();
+                let style = match severity.map(Severity::style) {
+                    Some(marked) => SpanStyle { fg: marked.fg, underline: true, ..style },
+                    None => style,
+                };
+                StyledText::new().span(c.to_string(), style)
+            });
+        }
+        let display = self.transformations.iter()
+            .fold(display, |display, t| t.transform(display));
+        let display = self.control_characters.transform(display);
+        TabExpansion { width: self.tab_width, start_column: 0 }.transform(display)
+    }
+
+    pub fn hyperlinks(&self) -> bool {
+        self.hyperlinks
+    }
+
+    /// Sets whether links in styled text and suggestions are written as OSC 8
+    /// hyperlinks. It is detected from the environment by default, see
+    /// [supports_hyperlinks].
+    pub fn set_hyperlinks(&mut self, hyperlinks: bool) {
+        self.hyperlinks = hyperlinks;
+        self.drawn = None;
+    }
+
+    pub fn screen_reader(&self) -> bool {
+        self.screen_reader
+    }
+
+    /// Sets whether to render for screen readers: instead of drawing the popup and the
+    /// toolbar below the input and jumping back, changes to the suggestions, the
+    /// selected suggestion and the toolbar are announced as plain lines printed above
+    /// the input, and the input line is only redrawn in place.
+    pub fn set_screen_reader(&mut self, screen_reader: bool) {
+        self.screen_reader = screen_reader;
+        self.drawn = None;
+    }
+
+    /// Advances the spinner shown while the suggestions are loading.
+    pub fn tick(&mut self) {
+        self.spinner_frame = (self.spinner_frame + 1) % SPINNER_FRAMES.len();
+    }
+
+    /// Sets whether the description of the selected suggestion scrolls horizontally,
+    /// one char per [marquee_tick](Renderer::marquee_tick), when it is too long for its
+    /// column, instead of staying truncated.
+    pub fn set_marquee(&mut self, marquee: bool) {
+        self.marquee = marquee;
+    }
+
+    /// Sets whether the first nine suggestions shown are numbered from 1, in a column left
+    /// of their text, for the keys accepting them by number.
+    pub fn set_number_badges(&mut self, number_badges: bool) {
+        self.number_badges = number_badges;
+    }
+
+    /// Returns whether the description of the selected suggestion was scrolling at the
+    /// last render, so that [marquee_tick](Renderer::marquee_tick) needs to be called.
+    pub fn marquee_running(&self) -> bool {
+        self.marquee_running
+    }
+
+    /// Scrolls the description of the selected suggestion by one char.
+    pub fn marquee_tick(&mut self) {
+        self.marquee_step += 1;
+    }
+
+    /// Called when the window size is changed.
+    pub fn update_win_size(&mut self, col: u16, row: u16) {
+        self.col = col;
+        self.row = row;
+        self.drawn = None;
+    }
+
+    /// Returns the number of rows left for the completion popup below the input, once
+    /// the toolbar is shown, so that the popup never pushes the input off the screen.
+    pub fn completion_height(&self, document: &Document) -> usize {
+        if self.col == 0 || self.cramped() {
+            return 0;
+        }
+        let display = self.display_text(document);
+        let (_, y) = self.to_pos(self.prefix_width() + display.text.width());
+        (self.row as usize).saturating_sub(y + 1 + self.border_size() + self.toolbar_height())
+    }
+
+    /// Returns the rows [render](Renderer::render) would draw, cell by cell, without
+    /// writing anything.
+    pub fn layout<C>(&self, document: &Document, completion: &CompletionManager<C>) -> Frame {
+        let input = self.display_text(document);
+        let scene = Scene {
+            prefix: &self.prefix,
+            placeholder: &self.placeholder,
+            input: &input,
+            preview_pane: &self.preview_pane,
+            toolbar: &self.toolbar,
+            key_hints: &self.key_hints,
+            popup_border: self.popup_border,
+            number_badges: self.number_badges,
+            spinner_frame: self.spinner_frame,
+            marquee_step: Some(self.marquee_step).filter(|_| self.marquee),
+        };
+        layout::layout((self.col, self.row), &scene, document, completion, &self.theme)
+    }
+
+    /// Renders the prefix, the input text, the completion popup and the toolbar, as laid
+    /// out by [layout](Renderer::layout).
+    pub fn render<C>(
+        &mut self,
+        document: &Document,
+        completion: &CompletionManager<C>,
+    ) -> io::Result<()> {
+        // In situations where a pseudo tty is allocated (e.g. within a docker container),
+        // window size is not immediately available and will result in 0,0 dimensions.
+        if self.col == 0 {
+            return Ok(());
+        }
+        if self.screen_reader {
+            return self.render_for_screen_reader(document, completion);
+        }
+        if completion.selected() != self.marquee_selected {
+            self.marquee_selected = completion.selected();
+            self.marquee_step = 0;
+        }
+        let frame = self.layout(document, completion);
+        self.marquee_running = frame.marquee_running();
+        self.draw(frame)?;
+        self.flush()
+    }
+
+    /// Draws `frame` over the one drawn last: only the rows that changed are written,
+    /// from their first changed cell, and the rows left below it are cleared.
+    fn draw(&mut self, frame: Frame) -> io::Result<()> {
+        let col = self.col as usize;
+        let previous = self.drawn.take().filter(|drawn| drawn.width() == frame.width());
+        let (_, y) = self.to_pos(self.previous_cursor);
+        queue!(self.frame, cursor::Hide)?;
+        self.up(y)?;
+        queue!(self.frame, Print("\r"))?;
+        if previous.is_none() {
+            queue!(self.frame, terminal::Clear(ClearType::FromCursorDown))?;
+        }
+
+        for y in 0..frame.height() {
+            if y > 0 {
+                queue!(self.frame, Print("\r\n"))?;
+            }
+            let row = frame.row(y).unwrap_or_default();
+            let Some(mut start) = first_change(previous.as_ref().and_then(|p| p.row(y)), row) else {
+                continue;
+            };
+            // starts at the first column of a wide character
+            while start > 0 && row[start].text.is_empty() {
+                start -= 1;
+            }
+            let end = row.iter().rposition(|cell| cell.role != Role::Blank).map_or(0, |end| end + 1).max(start);
+            if start > 0 {
+                queue!(self.frame, cursor::MoveToColumn(start as u16))?;
+            }
+            self.write_cells(&row[start..end])?;
+            if end < col {
+                queue!(self.frame, terminal::Clear(ClearType::UntilNewLine))?;
+            }
+        }
+        let last = frame.height().saturating_sub(1);
+        if previous.is_some_and(|p| p.height() > frame.height()) {
+            queue!(self.frame, Print("\r\n"), terminal::Clear(ClearType::FromCursorDown), cursor::MoveUp(1))?;
+        }
+
+        let (x, y) = frame.cursor();
+        self.up(last - y)?;
+        queue!(self.frame, cursor::MoveToColumn(x as u16), cursor::Show)?;
+        self.previous_cursor = y * col + x;
+        self.drawn = Some(frame);
+        Ok(())
+    }
+
+    /// Writes `cells`, a run of cells of a row, each style and link at once.
+    fn write_cells(&mut self, cells: &[Cell]) -> io::Result<()> {
+        let mut cells = cells.iter().filter(|cell| !cell.text.is_empty()).peekable();
+        while let Some(first) = cells.next() {
+            let mut text = first.text.clone();
+            while let Some(cell) = cells.next_if(|cell| cell.style == first.style && cell.link == first.link) {
+                text.push_str(&cell.text);
+            }
+            queue_style(&mut self.frame, first.style)?;
+            write_link(&mut self.frame, &text, first.link.as_deref().filter(|_| self.hyperlinks))?;
+        }
+        queue_style(&mut self.frame, Style::default())
+    }
+
+    /// Renders the input line, after printing what changed since the last render on
+    /// lines of their own, see [set_screen_reader](Renderer::set_screen_reader).
+    fn render_for_screen_reader<C>(&mut self, document: &Document, completion: &CompletionManager<C>) -> io::Result<()> {
+        let announcements = self.announcements(completion);
+        let display = self.display_text(document);
+        let line_width = display.text.width();
+        let end = self.prefix_width() + line_width;
+        if !announcements.is_empty() {
+            // keeps the input on the screen above the announcements
+            self.move_cursor(self.previous_cursor, end)?;
+            queue!(self.frame, Print("\r\n"))?;
+            for line in announcements {
+                queue!(self.frame, Print(line), Print("\r\n"))?;
+            }
+            self.previous_cursor = 0;
+        }
+
+        self.move_cursor(self.previous_cursor, 0)?;
+        self.render_prefix()?;
+        write_styled(&mut self.frame, &display.text, self.theme.input, self.hyperlinks)?;
+        self.line_wrap(end)?;
+        queue!(self.frame, terminal::Clear(ClearType::FromCursorDown))?;
+        self.previous_cursor = self.backward(end, line_width - display.width_before_cursor())?;
+        self.flush()
+    }
+
+    /// Returns the lines telling what changed since the last announcement.
+    fn announcements<C>(&mut self, completion: &CompletionManager<C>) -> Vec<String> {
+        let mut lines = Vec::new();
+        let suggestions = completion.get_suggestions();
+        let texts = suggestions.iter().map(|s| s.text().to_string()).collect::<Vec<_>>();
+        let describe = |i: usize| match suggestions[i].description() {
+            "" => suggestions[i].text().to_string(),
+            description => format!("{}, {}", suggestions[i].text(), description),
+        };
+
+        if completion.loading() && !self.announced.loading {
+            lines.push("Loading suggestions.".to_string());
+        }
+        self.announced.loading = completion.loading();
+        if texts != self.announced.suggestions {
+            match texts.len() {
+                0 if !self.announced.suggestions.is_empty() => lines.push("No suggestions.".to_string()),
+                0 => {}
+                n => {
+                    lines.push(format!("{} suggestion{}:", n, if n == 1 { "" } else { "s" }));
+                    let shown = n.min(completion.max().max(1));
+                    lines.extend((0..shown).map(|i| format!("{}. {}", i + 1, describe(i))));
+                    if n > shown {
+                        lines.push(format!("and {} more.", n - shown));
+                    }
+                }
+            }
+            self.announced.suggestions = texts;
+        }
+        let selected = completion.selected();
+        if let (Some(i), true) = (selected, selected != self.announced.selected) {
+            lines.push(format!("{}, {} of {}.", describe(i), i + 1, suggestions.len()));
+        }
+        self.announced.selected = selected;
+        let toolbar = self.toolbar.text();
+        if toolbar != self.announced.toolbar && !toolbar.is_empty() {
+            lines.push(toolbar.clone());
+        }
+        self.announced.toolbar = toolbar;
+        lines
+    }
+
+    /// Rings the terminal bell.
+    pub fn bell(&mut self) -> io::Result<()> {
+        queue!(self.frame, Print("\x07"))?;
+        self.flush()
+    }
+
+    /// Flashes the screen by briefly switching it to reverse video.
+    pub fn flash(&mut self) -> io::Result<()> {
+        // DECSCNM
+        queue!(self.frame, Print("\x1b[?5h"))?;
+        self.flush()?;
+        thread::sleep(FLASH_DURATION);
+        queue!(self.frame, Print("\x1b[?5l"))?;
+        self.flush()
+    }
+
+    /// Clears the rendered area, writes the accepted text after the
+    /// [final prefix](Renderer::final_prefix) and moves to the next line. `document` does
+    /// not need to be the one rendered last.
+    pub fn break_line(&mut self, document: &Document) -> io::Result<()> {
+        // a toolbar staying the same for the next input is not announced again
+        self.announced = Announced { toolbar: std::mem::take(&mut self.announced.toolbar), ..Announced::default() };
+        let display = self.display_text(document);
+        self.clear(self.previous_cursor)?;
+        let prefix = fit_prefix(self.transient_prefix.as_ref().unwrap_or(&self.prefix), self.col as usize);
+        write_styled(&mut self.frame, &prefix, self.theme.prefix, self.hyperlinks)?;
+        write_styled(&mut self.frame, &display.text, self.theme.input, self.hyperlinks)?;
+        queue!(self.frame, Print("\n"))?;
+        self.previous_cursor = 0;
+        self.drawn = None;
+        self.flush()
+    }
+
+    /// Writes `text` on a line of its own, outside of the rendered area, e.g. after the
+    /// output of a command.
+    pub fn print_line(&mut self, text: &StyledText) -> io::Result<()> {
+        write_styled(&mut self.frame, text, Style::default(), self.hyperlinks)?;
+        queue!(self.frame, Print("\n"))?;
+        self.drawn = None;
+        self.flush()
+    }
+
+    /// Clears the rendered area and leaves the cursor where the prefix started, e.g. for
+    /// another program to draw there. The next render starts from that position.
+    pub fn erase(&mut self) -> io::Result<()> {
+        self.clear(self.previous_cursor)?;
+        self.previous_cursor = 0;
+        self.drawn = None;
+        self.flush()
+    }
+
+    /// Writes the composed frame to the terminal in a single write. The frame is dropped
+    /// even if the write fails, so that it is not written again in front of the next one.
+    fn flush(&mut self) -> io::Result<()> {
+        let frame = std::mem::take(&mut self.frame);
+        let written = self.out.write_all(&frame).and_then(|()| self.out.flush());
+        if written.is_err() {
+            // what the screen shows is unknown, the next frame is drawn in full
+            self.drawn = None;
+        }
+        written
+    }
+
+    fn render_prefix(&mut self) -> io::Result<()> {
+        let prefix = fit_prefix(&self.prefix, self.col as usize);
+        write_styled(&mut self.frame, &prefix, self.theme.prefix, self.hyperlinks)
+    }
+
+    /// Returns the width of the prefix as rendered, see [fit_prefix].
+    fn prefix_width(&self) -> usize {
+        fit_prefix(&self.prefix, self.col as usize).width()
+    }
+
+    /// Returns whether the terminal is too small for anything but the prefix and the
+    /// input: shorter than 3 rows or narrower than the prefix. The completion popup, the
+    /// preview pane, the toolbar and the key hints are hidden then.
+    fn cramped(&self) -> bool {
+        layout::cramped(self.row as usize, self.col as usize, &self.prefix)
+    }
+
+    /// Returns the rows, and columns, taken by the border of the popup.
+    fn border_size(&self) -> usize {
+        if self.popup_border.is_some() { 2 } else { 0 }
+    }
+
+    /// Returns the rows taken by the preview pane, the toolbar and the key hints.
+    fn toolbar_height(&self) -> usize {
+        if self.cramped() {
+            return 0;
+        }
+        self.preview_pane.len() + [&self.toolbar, &self.key_hints].iter().filter(|text| !text.is_empty()).count()
+    }
+
+    /// Erases the rendered area, starting from the cursor.
+    fn clear(&mut self, cursor: usize) -> io::Result<()> {
+        self.move_cursor(cursor, 0)?;
+        queue!(self.frame, terminal::Clear(ClearType::FromCursorDown))
+    }
+
+    /// Moves the cursor `n` display columns back, returning the new position.
+    fn backward(&mut self, from: usize, n: usize) -> io::Result<usize> {
+        self.move_cursor(from, from.saturating_sub(n))
+    }
+
+    /// Moves the cursor from one display position to another, returning the new position.
+    fn move_cursor(&mut self, from: usize, to: usize) -> io::Result<usize> {
+        if self.col == 0 {
+            // nothing was drawn without a window size
+            return Ok(to);
+        }
+        let (from_x, from_y) = self.to_pos(from);
+        let (to_x, to_y) = self.to_pos(to);
+
+        if from_y > to_y {
+            self.up(from_y - to_y)?;
+        } else if to_y > from_y {
+            queue!(self.frame, cursor::MoveDown((to_y - from_y) as u16))?;
+        }
+        if from_x > to_x {
+            queue!(self.frame, cursor::MoveLeft((from_x - to_x) as u16))?;
+        } else if to_x > from_x {
+            self.forward(to_x - from_x)?;
+        }
+        Ok(to)
+    }
+
+    fn up(&mut self, n: usize) -> io::Result<()> {
+        if n > 0 {
+            queue!(self.frame, cursor::MoveUp(n as u16))?;
+        }
+        Ok(())
+    }
+
+    fn forward(&mut self, n: usize) -> io::Result<()> {
+        if n > 0 {
+            queue!(self.frame, cursor::MoveRight(n as u16))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the (column, row) of a display position.
+    fn to_pos(&self, cursor: usize) -> (usize, usize) {
+        layout::to_pos(cursor, self.col as usize)
+    }
+
+    /// Terminals don't wrap until a character is written past the last column,
+    /// so move to the next line by hand when the cursor sits exactly at the edge.
+    fn line_wrap(&mut self, cursor: usize) -> io::Result<()> {
+        if cursor > 0 && cursor.is_multiple_of(self.col as usize) {
+            queue!(self.frame, Print("\n"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the first column at which `row` differs from `previous`, the same row of the
+/// frame drawn before, or None if they are the same.
+fn first_change(previous: Option<&[Cell]>, row: &[Cell]) -> Option<usize> {
+    match previous {
+        Some(previous) if previous.len() == row.len() => row.iter().zip(previous).position(|(a, b)| a != b),
+        _ => Some(0),
+    }
+}
+
+fn queue_style<W: Write>(out: &mut W, style: Style) -> io::Result<()> {
+    queue!(
+        out,
+        SetAttribute(Attribute::Reset),
+        SetForegroundColor(style.fg),
+        SetBackgroundColor(style.bg),
+    )?;
+    if style.bold {
+        queue!(out, SetAttribute(Attribute::Bold))?;
+    }
+    if style.italic {
+        queue!(out, SetAttribute(Attribute::Italic))?;
+    }
+    if style.underline {
+        queue!(out, SetAttribute(Attribute::Underlined))?;
+    }
+    Ok(())
+}
+
+/// Writes every span of `text` with its style layered over `base`.
+/// Links are written as hyperlinks if `hyperlinks` is true.
+pub(crate) fn write_styled<W: Write>(out: &mut W, text: &StyledText, base: Style, hyperlinks: bool) -> io::Result<()> {
+    for span in text.spans() {
+        queue_style(out, span.style.apply(base))?;
+        write_link(out, &span.text, span.link.as_deref().filter(|_| hyperlinks))?;
+    }
+    queue_style(out, Style::default())
+}
+
+/// Writes `text`, as a hyperlink to `url` if there is one.
+fn write_link<W: Write>(out: &mut W, text: &str, url: Option<&str>) -> io::Result<()> {
+    match url {
+        Some(url) => queue!(out, Print(hyperlink::start(url)), Print(text), Print(hyperlink::END)),
+        None => queue!(out, Print(text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::style::Color;
+
+    use super::*;
+    use crate::completion::Suggestion;
+    use crate::tests::TestCompleter;
+    use crate::width::strip_ansi;
+
+    fn sgr_fg(color: Color) -> String {
+        let mut s = String::new();
+        crossterm::Command::write_ansi(&SetForegroundColor(color), &mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn test_switch_theme_redraws_with_new_palette() {
+        let mut r = Renderer::new(Vec::new(), "> ".to_string());
+        r.update_win_size(80, 24);
+        r.set_toolbar("status".to_string());
+        let document = Document::new();
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+
+        r.render(&document, &completion).unwrap();
+        let before = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(before.contains(&sgr_fg(Color::Blue)), "default prefix color should be used");
+
+        r.switch_theme(Theme::high_contrast(), &document, &completion).unwrap();
+        let after = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(after.contains("> "), "prompt should be redrawn");
+        assert!(after.contains("status"), "toolbar should be redrawn");
+        assert!(after.contains(&sgr_fg(Color::Yellow)), "new prefix color should be used");
+        assert!(!after.contains(&sgr_fg(Color::Blue)), "old prefix color should be gone");
+    }
+
+    #[test]
+    fn test_render_writes_each_frame_at_once() {
+        #[derive(Default)]
+        struct Writes(Vec<usize>);
+
+        impl Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut r = Renderer::new(Writes::default(), "> ");
+        r.update_win_size(80, 24);
+        r.set_toolbar("toolbar");
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        r.render(&Document::with_text("ls -l".to_string(), 5), &completion).unwrap();
+        assert_eq!(1, r.out.0.len());
+        r.break_line(&Document::with_text("ls -l".to_string(), 5)).unwrap();
+        assert_eq!(2, r.out.0.len());
+        assert!(r.frame.is_empty());
+    }
+
+    #[test]
+    fn test_render_drops_a_frame_that_failed_to_write() {
+        struct Flaky(Option<Vec<u8>>);
+
+        impl Write for Flaky {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                match self.0.as_mut() {
+                    Some(written) => written.write(buf),
+                    None => {
+                        self.0 = Some(Vec::new());
+                        Err(io::ErrorKind::BrokenPipe.into())
+                    }
+                }
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut r = Renderer::new(Flaky(None), "> ");
+        r.update_win_size(80, 24);
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        let document = Document::with_text("ls".to_string(), 2);
+        assert!(r.render(&document, &completion).is_err());
+        assert!(r.frame.is_empty());
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(r.out.0.take().unwrap()).unwrap();
+        // drawn again in full, once
+        assert_eq!("\r> ls", strip_ansi(&out));
+        assert!(out.contains("\x1b[J"));
+    }
+
+    #[test]
+    fn test_render_without_window_size() {
+        let mut r = Renderer::new(Vec::new(), "> ".to_string());
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        r.render(&Document::new(), &completion).unwrap();
+        assert!(r.out.is_empty());
+        r.break_line(&Document::with_text("ls".to_string(), 2)).unwrap();
+        assert!(strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).ends_with("> ls\n"));
+    }
+
+    #[test]
+    fn test_render_markup_prefix_and_placeholder() {
+        let mut r = Renderer::new(Vec::new(), crate::markup::parse_markup("<red>日本</red> $ ").unwrap());
+        r.update_win_size(80, 24);
+        r.set_placeholder("type a command");
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        r.render(&Document::new(), &completion).unwrap();
+
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains(&sgr_fg(Color::Red)));
+        assert!(out.contains("日本"));
+        assert!(!out.contains("<red>"));
+        assert!(out.contains("type a command"));
+        // the cursor is left in front of the placeholder
+        assert_eq!(" $ ".len() + 4, r.previous_cursor);
+    }
+
+    #[test]
+    fn test_render_prefix_segments() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        assert!(!r.set_segment("cwd", "~"));
+        r.set_prefix_segments(Segments::new().segment("cwd", "~", crate::style::SpanStyle::default()).with_suffix(" $ "));
+        assert_eq!("~ $ ", r.prefix().text());
+        assert!(r.set_segment("cwd", "~/src"));
+        assert_eq!("~/src $ ", r.prefix().text());
+        r.set_prefix("> ");
+        assert_eq!(None, r.prefix_segments());
+    }
+
+    #[test]
+    fn test_break_line_with_transient_prefix() {
+        let mut r = Renderer::new(Vec::new(), "user@box ~/src (main) > ");
+        r.update_win_size(80, 24);
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        let document = Document::with_text("ls".into(), 2);
+        r.render(&document, &completion).unwrap();
+        r.out.clear();
+        r.break_line(&document).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(strip_ansi(&out).ends_with("user@box ~/src (main) > ls\n"));
+
+        r.set_transient_prefix(Some("$ ".into()));
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("(main)"));
+        r.break_line(&document).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("(main)"));
+        assert!(strip_ansi(&out).ends_with("$ ls\n"));
+
+        // cut like the prefix of the prompt in a narrow terminal
+        r.set_transient_prefix(None);
+        r.update_win_size(10, 24);
+        r.break_line(&document).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(strip_ansi(&out).ends_with("user…ls\n"));
+    }
+
+    #[test]
+    fn test_render_applies_transformations() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        r.add_transformation(crate::transform::PasswordMask::default());
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        let document = Document::with_text("secret".to_string(), 0);
+        r.render(&document, &completion).unwrap();
+
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("******"));
+        assert!(!out.contains("secret"));
+        assert_eq!("> ".len(), r.previous_cursor);
+
+        r.break_line(&document).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("secret"), "accepted line must stay masked");
+    }
+
+    #[test]
+    fn test_render_control_characters() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        let document = Document::with_text("\x1b[2J\x07x".to_string(), 5);
+        r.render(&document, &completion).unwrap();
+
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("^[") && out.contains("[2J") && out.contains("^G"));
+        assert!(!out.contains("\x1b[2J"), "escape sequences from the input must not reach the terminal");
+        assert!(!out.contains('\x07'));
+        assert_eq!("> ^[[2J^G".len(), r.previous_cursor);
+    }
+
+    #[test]
+    fn test_render_expands_tabs() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        r.set_tab_width(4);
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        let document = Document::with_text("a\tb".to_string(), 2);
+        r.render(&document, &completion).unwrap();
+
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("a   b"));
+        assert!(!out.contains('\t'));
+        assert_eq!("> ".len() + document.display_cursor_position_with_tab_width(4), r.previous_cursor);
+    }
+
+    fn words() -> TestCompleter {
+        TestCompleter::new(["alpha", "beta", "gamma", "delta"])
+    }
+
+    #[test]
+    fn test_render_completion_fits_terminal_height() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(20, 4);
+        r.set_toolbar("status");
+        let document = Document::new();
+        let mut completion = CompletionManager::new(words(), 6);
+        completion.update_suggestions(&document);
+        // one row for the input and one for the toolbar
+        assert_eq!(2, r.completion_height(&document));
+        completion.set_available_height(r.completion_height(&document));
+        completion.previous();
+        r.render(&document, &completion).unwrap();
+
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("too small"));
+        assert!(out.contains("gamma") && out.contains("delta"));
+        assert!(!out.contains("alpha") && !out.contains("beta"));
+
+        // a single row only has room for the input
+        r.update_win_size(20, 1);
+        assert_eq!(0, r.completion_height(&document));
+        completion.set_available_height(0);
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("too small") && !out.contains("status"));
+    }
+
+    #[test]
+    fn test_render_crowded_never_clears_the_screen() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(12, 3);
+        r.set_toolbar("status");
+        r.set_key_hints(" Tab complete");
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        let document = Document::with_text("two rows of input".to_string(), 17);
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("\x1b[2J"));
+        assert!(out.contains("status") && !out.contains("Tab complete") && !out.contains("too small"));
+
+        let document = Document::with_text("far too many rows of input to fit in".to_string(), 36);
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("\x1b[2J"));
+        assert!(strip_ansi(&out).contains("Your console"));
+    }
+
+    #[test]
+    fn test_render_tiny_terminal() {
+        let mut r = Renderer::new(Vec::new(), "my-project> ");
+        r.set_toolbar("status");
+        let document = Document::new();
+        let mut completion = CompletionManager::new(words(), 6);
+        completion.update_suggestions(&document);
+        completion.set_available_height(4);
+        completion.next();
+        r.update_win_size(8, 10);
+        assert_eq!(0, r.completion_height(&document));
+        r.render(&document, &completion).unwrap();
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        // the prefix is cut to half the width, and the popup and toolbar are hidden
+        assert!(out.contains("my-…") && !out.contains("my-p"));
+        assert!(!out.contains("beta") && !out.contains("status"));
+
+        r.update_win_size(1, 1);
+        r.render(&document, &completion).unwrap();
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        assert!(!out.contains('m') && !out.contains('…'));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_render_any_size(col in 0u16..40, row in 0u16..8, text in "[a-zé日 \t\n]{0,30}", cursor in 0usize..40) {
+            let mut r = Renderer::new(Vec::new(), "project> ");
+            r.set_toolbar("status");
+            r.set_key_hints("Tab complete");
+            r.set_preview_pane("preview");
+            r.set_popup_border(Some(Border::Rounded));
+            r.update_win_size(col, row);
+            let len = text.chars().count();
+            let document = Document::with_text(text, cursor.min(len));
+            let mut completion = CompletionManager::new(words(), 6);
+            completion.update_suggestions(&document);
+            completion.set_available_height(r.completion_height(&document));
+            completion.next();
+            r.render(&document, &completion).unwrap();
+            r.render(&document, &completion).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_render_popup_border() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(20, 8);
+        r.set_popup_border(Some(Border::Rounded));
+        let document = Document::new();
+        let mut completion = CompletionManager::new(words(), 6);
+        completion.update_suggestions(&document);
+        // the border takes a row above and below the suggestions
+        assert_eq!(5, r.completion_height(&document));
+        completion.set_available_height(r.completion_height(&document));
+        r.render(&document, &completion).unwrap();
+
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        // the suggestions are 8 wide with the scrollbar, the border adds two columns
+        assert!(out.contains("╭────────╮"));
+        assert!(out.contains("│ alpha  │"));
+        assert!(out.contains("╰────────╯"));
+
+        r.set_popup_border(Some(Border::Ascii));
+        r.render(&document, &completion).unwrap();
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        assert!(out.contains("+--------+") && out.contains("| delta  |"));
+    }
+
+    #[test]
+    fn test_render_number_badges() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(20, 8);
+        r.set_popup_border(Some(Border::Ascii));
+        r.set_number_badges(true);
+        let document = Document::new();
+        let mut completion = CompletionManager::new(words(), 6);
+        completion.update_suggestions(&document);
+        completion.set_available_height(r.completion_height(&document));
+        r.render(&document, &completion).unwrap();
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        // the badges widen the popup by a column
+        assert!(out.contains("+---------+"));
+        assert!(out.contains("|1 alpha  |") && out.contains("|4 delta  |"));
+    }
+
+    #[test]
+    fn test_render_key_hints() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(40, 5);
+        r.set_toolbar("status");
+        r.set_key_hints(" Tab complete");
+        let document = Document::new();
+        // one row for the input, one for the toolbar and one for the key hints
+        assert_eq!(2, r.completion_height(&document));
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        r.render(&document, &completion).unwrap();
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        let toolbar = out.find("status").unwrap();
+        assert!(out[toolbar..].contains(" Tab complete"));
+
+        // cut at the width of the terminal rather than wrapped onto a row of their own
+        r.set_key_hints(" Tab complete · Ctrl-R history · F1 help · Ctrl-D exit");
+        r.render(&document, &completion).unwrap();
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        assert!(out.contains("F1 help") && !out.contains("Ctrl-D"));
+        let frame = r.layout(&document, &completion);
+        assert_eq!(3, frame.height());
+        assert_eq!(" Tab complete · Ctrl-R history · F1 help", frame.row_text(2));
+    }
+
+    #[test]
+    fn test_render_preview_pane() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(10, 8);
+        r.set_toolbar("status");
+        r.set_preview_pane_height(2);
+        r.set_preview_pane(StyledText::new().plain("first line is long\n").span("second", SpanStyle::default().bold()).plain("\nthird"));
+        assert_eq!(vec!["first line is long", "second"], r.preview_pane().iter().map(|l| l.text()).collect::<Vec<_>>());
+        let document = Document::new();
+        // the input, the pane and the toolbar
+        assert_eq!(4, r.completion_height(&document));
+        let mut completion = CompletionManager::new(words(), 6);
+        completion.update_suggestions(&document);
+        completion.set_available_height(r.completion_height(&document));
+        r.render(&document, &completion).unwrap();
+        let out = strip_ansi(&String::from_utf8(std::mem::take(&mut r.out)).unwrap()).to_string();
+        // below the popup, above the toolbar, cut at the width of the terminal
+        let (delta, first, status) = (out.find("delta").unwrap(), out.find("first line").unwrap(), out.find("status").unwrap());
+        assert!(delta < first && first < status);
+        assert!(!out.contains("first line is"));
+        assert!(out.contains("second") && !out.contains("third"));
+
+        r.set_preview_pane("");
+        assert_eq!(6, r.completion_height(&document));
+    }
+
+    #[test]
+    fn test_render_correction_style() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        r.set_theme(Theme { correction: Style::new(Color::Magenta, Color::Reset), ..Theme::default() });
+        let mut completion = CompletionManager::new(TestCompleter::from(vec![Suggestion::with_title("--namespace".to_string()).mark_correction()]), 6);
+        completion.update_suggestions(&Document::new());
+        r.render(&Document::new(), &completion).unwrap();
+
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        let (_, after) = out.split_once(&sgr_fg(Color::Magenta)).unwrap();
+        assert!(after.starts_with("\x1b[49m --namespace"));
+    }
+
+    #[test]
+    fn test_render_marquee() {
+        let force = Suggestion::new("--force".to_string(), "Overwrite the existing files without asking".to_string());
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(30, 24);
+        let mut completion = CompletionManager::new(TestCompleter::from(vec![force]), 6);
+        completion.update_suggestions(&Document::new());
+        completion.next();
+        r.render(&Document::new(), &completion).unwrap();
+        assert!(!r.marquee_running());
+
+        r.set_marquee(true);
+        r.render(&Document::new(), &completion).unwrap();
+        assert!(r.marquee_running());
+        for _ in 0..6 {
+            r.marquee_tick();
+        }
+        r.out.clear();
+        r.render(&Document::new(), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        // only the scrolled description is written again
+        assert!(strip_ansi(&out).contains("erwrite the exi"));
+        assert!(!strip_ansi(&out).contains("--force"));
+    }
+
+    #[test]
+    fn test_render_draws_layout() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(20, 10);
+        r.set_popup_border(Some(Border::Rounded));
+        r.set_toolbar("a toolbar longer than the terminal");
+        r.set_key_hints("Tab complete");
+        let document = Document::with_text("日本 a".to_string(), 4);
+        let mut completion = CompletionManager::new(words(), 6);
+        completion.update_suggestions(&document);
+        completion.set_available_height(r.completion_height(&document));
+        completion.next();
+        r.render(&document, &completion).unwrap();
+
+        let frame = r.layout(&document, &completion);
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        let rows = strip_ansi(&out).split("\r\n").map(|row| row.trim_start_matches('\r').trim_end().to_string()).collect::<Vec<_>>();
+        let expected = (0..frame.height()).map(|y| frame.row_text(y).trim_end().to_string()).collect::<Vec<_>>();
+        assert_eq!(expected, rows);
+        let (x, y) = frame.cursor();
+        assert_eq!(y * 20 + x, r.previous_cursor);
+    }
+
+    #[test]
+    fn test_render_redraws_changes_only() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        r.set_toolbar("status");
+        let completion = CompletionManager::new(TestCompleter::default(), 6);
+        r.render(&Document::with_text("ls -l".to_string(), 5), &completion).unwrap();
+        r.out.clear();
+        r.render(&Document::with_text("ls -la".to_string(), 6), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert_eq!("\ra\r\n", strip_ansi(&out));
+        assert!(out.contains("\x1b[8G"));
+
+        // a shorter frame clears the rows it no longer takes
+        r.set_toolbar("");
+        r.render(&Document::with_text("ls -la".to_string(), 6), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("\x1b[J"));
+        assert!(!strip_ansi(&out).contains("ls"));
+    }
+
+    #[test]
+    fn test_render_leaves_cursor_at_wide_input() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        let mut completion = CompletionManager::new(words(), 6);
+        let document = Document::with_text("日本語 ok".to_string(), 3);
+        completion.update_suggestions(&document);
+        r.render(&document, &completion).unwrap();
+        // input methods draw their candidate window at the cursor, after 日本語
+        assert_eq!(2 + 6, r.previous_cursor);
+    }
+
+    #[test]
+    fn test_render_for_screen_reader() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        r.set_screen_reader(true);
+        r.set_toolbar("Tab to complete");
+        let mut completion = CompletionManager::new(words(), 2);
+        let document = Document::with_text("a".to_string(), 1);
+        completion.update_suggestions(&document);
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert_eq!("\r\n4 suggestions:\r\n1. alpha\r\n2. beta\r\nand 2 more.\r\nTab to complete\r\n> a", strip_ansi(&out));
+        assert!(out.ends_with("\x1b[J"));
+        assert!(!out.contains("\x1b7") && !out.contains("\x1bD"));
+
+        // nothing changed, only the input is redrawn
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.starts_with("\x1b[3D"));
+        assert_eq!("> a", strip_ansi(&out));
+
+        completion.next();
+        completion.next();
+        r.render(&document, &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert_eq!("\r\nbeta, 2 of 4.\r\n> a", strip_ansi(&out));
+    }
+
+    #[test]
+    fn test_render_hyperlinks() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        r.set_toolbar(StyledText::new().link("help", "https://example.com/help", Default::default()));
+        let all = Suggestion::new("--all".to_string(), "all namespaces".to_string()).with_url("https://example.com/all");
+        let mut completion = CompletionManager::new(TestCompleter::from(vec![all]), 6);
+        completion.update_suggestions(&Document::new());
+
+        r.set_hyperlinks(true);
+        r.render(&Document::new(), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("\x1b]8;;https://example.com/all\x1b\\ all namespaces \x1b]8;;\x1b\\"));
+        assert!(out.contains("\x1b]8;;https://example.com/help\x1b\\help\x1b]8;;\x1b\\"));
+
+        r.set_hyperlinks(false);
+        r.render(&Document::new(), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("\x1b]8;;"));
+        assert!(out.contains("all namespaces") && out.contains("help"));
+    }
+
+    #[test]
+    fn test_render_loading() {
+        let mut r = Renderer::new(Vec::new(), "> ");
+        r.update_win_size(80, 24);
+        let mut completion = CompletionManager::new(TestCompleter::default().pending(), 6);
+        completion.update_suggestions(&Document::new());
+        assert_eq!(1, completion.window_height());
+        r.render(&Document::new(), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("⠋ loading…"));
+
+        r.tick();
+        r.render(&Document::new(), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(out.contains("⠙ loading…"));
+
+        completion.reset();
+        r.render(&Document::new(), &completion).unwrap();
+        let out = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!out.contains("loading"));
+    }
+}
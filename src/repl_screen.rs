@@ -0,0 +1,232 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+
+use crate::width::truncate_to_width;
+
+/// Output region of a prompt running in the alternate screen, like a chat client: the
+/// input stays in a fixed area at the bottom of the screen and the output scrolls above
+/// it, see `Prompt::with_repl_screen`.
+///
+/// Clones share the same output, so the executor, or a background thread, can keep a
+/// clone to print with [println](ReplScreen::println) instead of writing to stdout,
+/// which would draw over the screen. Lines appended from another thread show up the next
+/// time the prompt is rendered, e.g. from an idle callback.
+#[derive(Debug, Clone)]
+pub struct ReplScreen {
+    output: Arc<Mutex<Output>>,
+}
+
+#[derive(Debug)]
+struct Output {
+    lines: Vec<String>,
+    /// Number of lines hidden below the visible ones.
+    scroll: usize,
+    area_height: u16,
+}
+
+impl ReplScreen {
+    /// `area_height` is the number of rows at the bottom of the screen for the input,
+    /// its completion popup and the toolbar.
+    pub fn new(area_height: u16) -> Self {
+        Self {
+            output: Arc::new(Mutex::new(Output {
+                lines: Vec::new(),
+                scroll: 0,
+                area_height: area_height.max(1),
+            })),
+        }
+    }
+
+    fn output(&self) -> MutexGuard<'_, Output> {
+        self.output.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Appends each line of `text` to the output. If the output is scrolled up, the
+    /// visible lines stay in place.
+    pub fn println<S: AsRef<str>>(&self, text: S) {
+        let mut output = self.output();
+        let before = output.lines.len();
+        let text = text.as_ref();
+        if text.is_empty() {
+            output.lines.push(String::new());
+        } else {
+            output.lines.extend(text.lines().map(str::to_string));
+        }
+        if output.scroll > 0 {
+            output.scroll += output.lines.len() - before;
+        }
+    }
+
+    /// Removes the last line of the output.
+    pub(crate) fn pop(&self) {
+        let mut output = self.output();
+        if output.lines.pop().is_some() {
+            // the line was hidden below the visible ones while scrolled up
+            output.scroll = output.scroll.saturating_sub(1);
+        }
+    }
+
+    /// Removes all output.
+    pub fn clear(&self) {
+        let mut output = self.output();
+        output.lines.clear();
+        output.scroll = 0;
+    }
+
+    /// Returns the number of output lines.
+    pub fn len(&self) -> usize {
+        self.output().lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.output().lines.is_empty()
+    }
+
+    /// Returns the number of lines hidden below the visible ones.
+    pub fn scroll(&self) -> usize {
+        self.output().scroll
+    }
+
+    /// Shows `n` older lines.
+    pub fn scroll_up(&self, n: usize) {
+        let mut output = self.output();
+        output.scroll = (output.scroll + n).min(output.lines.len().saturating_sub(1));
+    }
+
+    /// Shows `n` newer lines.
+    pub fn scroll_down(&self, n: usize) {
+        let mut output = self.output();
+        output.scroll = output.scroll.saturating_sub(n);
+    }
+
+    /// Shows the latest lines.
+    pub fn scroll_to_bottom(&self) {
+        self.output().scroll = 0;
+    }
+
+    /// Returns the number of rows of the input area on a screen with `row` rows.
+    pub fn area_height(&self, row: u16) -> u16 {
+        self.output().area_height(row)
+    }
+
+    /// Switches to the alternate screen and keeps the renderer's scrolling inside the
+    /// input area.
+    pub(crate) fn enter<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        queue!(out, terminal::EnterAlternateScreen, terminal::Clear(ClearType::All))?;
+        out.flush()
+    }
+
+    pub(crate) fn leave<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        // DECSTBM without parameters resets the scroll region to the whole screen
+        queue!(out, Print("\x1b[r"), terminal::LeaveAlternateScreen)?;
+        out.flush()
+    }
+
+    /// Clears the input area of a `row` rows screen and moves the cursor to its start.
+    pub(crate) fn reset_area<W: Write>(&self, out: &mut W, row: u16) -> io::Result<()> {
+        let top = row - self.area_height(row);
+        // DECSTBM: scroll region from row top + 1 to the last row, 1-based
+        queue!(out, Print(format!("\x1b[{};{}r", top + 1, row)))?;
+        queue!(out, cursor::MoveTo(0, top), terminal::Clear(ClearType::FromCursorDown))
+    }
+
+    /// Draws the output region of a `col` by `row` screen, leaving the cursor where it
+    /// was.
+    pub(crate) fn draw<W: Write>(&self, out: &mut W, col: u16, row: u16) -> io::Result<()> {
+        let output = self.output();
+        let height = (row - output.area_height(row)) as usize;
+        let (lines, more) = output.visible(height);
+        queue!(out, cursor::SavePosition)?;
+        for y in 0..height {
+            queue!(out, cursor::MoveTo(0, y as u16), terminal::Clear(ClearType::CurrentLine))?;
+            if y + 1 == height && more > 0 {
+                let notice = format!(" {} more line{} below ", more, if more == 1 { "" } else { "s" });
+                queue!(out, SetAttribute(Attribute::Reverse), Print(notice), SetAttribute(Attribute::Reset))?;
+            } else if let Some(line) = lines.get(y) {
+                queue!(out, Print(truncate_to_width(line, col as usize)), SetAttribute(Attribute::Reset))?;
+            }
+        }
+        queue!(out, cursor::RestorePosition)
+    }
+}
+
+impl Output {
+    fn area_height(&self, row: u16) -> u16 {
+        self.area_height.min(row)
+    }
+
+    /// Returns the lines shown in `height` rows, and the number of lines below them.
+    /// The last row tells how many lines are hidden below while scrolled up.
+    fn visible(&self, height: usize) -> (&[String], usize) {
+        let end = self.lines.len() - self.scroll;
+        let shown = if self.scroll > 0 { height.saturating_sub(1) } else { height };
+        (&self.lines[end.saturating_sub(shown)..end], self.scroll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visible(screen: &ReplScreen, height: usize) -> (Vec<String>, usize) {
+        let output = screen.output();
+        let (lines, more) = output.visible(height);
+        (lines.to_vec(), more)
+    }
+
+    fn strings(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_repl_screen_scroll() {
+        let screen = ReplScreen::new(3);
+        screen.println("a\nb");
+        screen.println("");
+        screen.clone().println("c");
+        assert_eq!(4, screen.len());
+        assert_eq!((strings(&["b", "", "c"]), 0), visible(&screen, 3));
+        assert_eq!((strings(&["a", "b", "", "c"]), 0), visible(&screen, 10));
+
+        screen.scroll_up(1);
+        assert_eq!((strings(&["b", ""]), 1), visible(&screen, 3));
+        // the view stays in place while scrolled up
+        screen.println("d");
+        assert_eq!((strings(&["b", ""]), 2), visible(&screen, 3));
+        screen.scroll_up(10);
+        assert_eq!(4, screen.scroll());
+        assert_eq!((strings(&["a"]), 4), visible(&screen, 3));
+        screen.scroll_down(3);
+        assert_eq!((strings(&["", "c"]), 1), visible(&screen, 3));
+        screen.scroll_to_bottom();
+        assert_eq!((strings(&["", "c", "d"]), 0), visible(&screen, 3));
+        screen.clear();
+        assert!(screen.is_empty());
+    }
+
+    #[test]
+    fn test_repl_screen_draw() {
+        let screen = ReplScreen::new(2);
+        assert_eq!(2, screen.area_height(24));
+        assert_eq!(1, screen.area_height(1));
+        screen.println("hello world");
+        screen.println("bye");
+        let mut out = Vec::new();
+        screen.draw(&mut out, 5, 4).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("hello"));
+        assert!(!out.contains("hello "));
+        assert!(out.contains("bye"));
+
+        screen.scroll_up(1);
+        let mut out = Vec::new();
+        screen.draw(&mut out, 20, 4).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("hello"));
+        assert!(out.contains(" 1 more line below "));
+    }
+}
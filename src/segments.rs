@@ -0,0 +1,173 @@
+use crossterm::style::Color;
+
+use crate::style::{SpanStyle, StyledText};
+
+/// A named part of a [Segments] prefix, e.g. the host or the working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    name: String,
+    text: String,
+    style: SpanStyle,
+}
+
+impl Segment {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn style(&self) -> SpanStyle {
+        self.style
+    }
+}
+
+/// Prefix made of named, independently styled segments, e.g. an icon, the host, the
+/// working directory and the VCS branch, see `Renderer::set_prefix_segments`.
+///
+/// Segments are updated by name, without rebuilding the others. Empty segments are not
+/// shown. The prefix is separated from the input by the [suffix](Segments::with_suffix),
+/// `" "` by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segments {
+    segments: Vec<Segment>,
+    /// Separator drawn between powerline segments, or None to join them with a space.
+    powerline: Option<String>,
+    suffix: StyledText,
+}
+
+impl Default for Segments {
+    fn default() -> Self {
+        Self { segments: Vec::new(), powerline: None, suffix: " ".into() }
+    }
+}
+
+impl Segments {
+    /// Segments joined with a space.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Powerline-style segments: each one is padded with a space on its background color
+    /// and followed by `separator`, e.g. `"\u{e0b0}"`, drawn in that color on the
+    /// background of the next segment. Needs a font with the powerline glyphs.
+    pub fn powerline<S: Into<String>>(separator: S) -> Self {
+        Self { powerline: Some(separator.into()), ..Self::default() }
+    }
+
+    /// Appends a segment called `name`.
+    pub fn segment<N: Into<String>, T: Into<String>>(mut self, name: N, text: T, style: SpanStyle) -> Self {
+        self.segments.push(Segment { name: name.into(), text: text.into(), style });
+        self
+    }
+
+    /// Sets what follows the segments, e.g. `" $ "`.
+    pub fn with_suffix<S: Into<StyledText>>(mut self, suffix: S) -> Self {
+        self.suffix = suffix.into();
+        self
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.name == name)
+    }
+
+    /// Sets the text of the segment called `name`, an empty text hides it. Returns false
+    /// if there is no such segment.
+    pub fn set<T: Into<String>>(&mut self, name: &str, text: T) -> bool {
+        self.segments.iter_mut().find(|s| s.name == name).map(|s| s.text = text.into()).is_some()
+    }
+
+    /// Sets the style of the segment called `name`, e.g. to turn the branch red when the
+    /// working tree is dirty. Returns false if there is no such segment.
+    pub fn set_style(&mut self, name: &str, style: SpanStyle) -> bool {
+        self.segments.iter_mut().find(|s| s.name == name).map(|s| s.style = style).is_some()
+    }
+
+    /// Returns the prefix the segments make up.
+    pub fn styled_text(&self) -> StyledText {
+        let mut text = StyledText::new();
+        let shown = self.segments.iter().filter(|s| !s.text.is_empty()).collect::<Vec<_>>();
+        match &self.powerline {
+            None => {
+                for (i, segment) in shown.iter().enumerate() {
+                    if i > 0 {
+                        text.push(" ", SpanStyle::default());
+                    }
+                    text.push(segment.text.as_str(), segment.style);
+                }
+            }
+            Some(separator) => {
+                for (i, segment) in shown.iter().enumerate() {
+                    text.push(format!(" {} ", segment.text), segment.style);
+                    let mut arrow = SpanStyle::default().fg(segment.style.bg.unwrap_or(Color::Reset));
+                    arrow.bg = shown.get(i + 1).and_then(|next| next.style.bg);
+                    text.push(separator.as_str(), arrow);
+                }
+            }
+        }
+        for span in self.suffix.spans() {
+            text.push(span.text.as_str(), span.style);
+        }
+        text
+    }
+}
+
+impl From<&Segments> for StyledText {
+    fn from(segments: &Segments) -> Self {
+        segments.styled_text()
+    }
+}
+
+impl From<Segments> for StyledText {
+    fn from(segments: Segments) -> Self {
+        segments.styled_text()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments() -> Segments {
+        Segments::new()
+            .segment("host", "box", SpanStyle::default().fg(Color::Green))
+            .segment("cwd", "~/src", SpanStyle::default().fg(Color::Blue))
+            .segment("branch", "", SpanStyle::default().fg(Color::Magenta))
+            .with_suffix(" $ ")
+    }
+
+    #[test]
+    fn test_segments() {
+        let mut s = segments();
+        assert_eq!("box ~/src $ ", s.styled_text().text());
+        assert!(s.set("branch", "main"));
+        assert!(s.set("cwd", "~/日本"));
+        assert!(!s.set("clock", "12:00"));
+        let text = s.styled_text();
+        assert_eq!("box ~/日本 main $ ", text.text());
+        assert_eq!(18, text.width());
+        assert_eq!(Some(Color::Magenta), text.spans()[4].style.fg);
+
+        assert!(s.set_style("branch", SpanStyle::default().fg(Color::Red)));
+        assert_eq!(Some(Color::Red), s.get("branch").unwrap().style().fg);
+    }
+
+    #[test]
+    fn test_powerline_segments() {
+        let s = Segments::powerline(">")
+            .segment("host", "box", SpanStyle::default().bg(Color::Blue))
+            .segment("empty", "", SpanStyle::default().bg(Color::Red))
+            .segment("cwd", "~", SpanStyle::default().bg(Color::Green));
+        let text = s.styled_text();
+        assert_eq!(" box > ~ > ", text.text());
+        let arrows = text.spans().iter().filter(|s| s.text == ">").collect::<Vec<_>>();
+        assert_eq!((Some(Color::Blue), Some(Color::Green)), (arrows[0].style.fg, arrows[0].style.bg));
+        assert_eq!((Some(Color::Green), None), (arrows[1].style.fg, arrows[1].style.bg));
+    }
+}
@@ -0,0 +1,208 @@
+use std::ffi::OsString;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use crate::completion::{Completer, Suggestion};
+use crate::document::Document;
+
+/// Path of the bash-completion entry point on most Linux distributions.
+pub const BASH_COMPLETION_SCRIPT: &str = "/usr/share/bash-completion/bash_completion";
+
+/// Runs in bash with the text before the cursor and the scripts to source as arguments,
+/// and prints one candidate per line. The first word completes to commands, the others
+/// use the completion function registered with `complete -F` for the command, loading it
+/// on demand through bash-completion when available, and fall back to file names.
+const SCRIPT: &str = r#"
+line=$1
+shift
+for script in "$@"; do
+    source "$script" >/dev/null 2>&1
+done
+read -ra COMP_WORDS <<< "$line"
+if [[ -z $line || $line == *[[:space:]] ]]; then
+    COMP_WORDS+=("")
+fi
+COMP_CWORD=$(( ${#COMP_WORDS[@]} - 1 ))
+COMP_LINE=$line
+COMP_POINT=${#line}
+cur=${COMP_WORDS[COMP_CWORD]}
+if (( COMP_CWORD == 0 )); then
+    compgen -c -- "$cur"
+    exit
+fi
+cmd=${COMP_WORDS[0]}
+spec=$(complete -p "$cmd" 2>/dev/null)
+if [[ -z $spec ]] && declare -F _completion_loader >/dev/null; then
+    _completion_loader "$cmd" >/dev/null 2>&1
+    spec=$(complete -p "$cmd" 2>/dev/null)
+fi
+if [[ $spec =~ -F\ ([^ ]+) ]]; then
+    COMPREPLY=()
+    "${BASH_REMATCH[1]}" "$cmd" "$cur" "${COMP_WORDS[COMP_CWORD-1]}" >/dev/null 2>&1
+    printf '%s\n' "${COMPREPLY[@]}"
+else
+    compgen -f -- "$cur"
+fi
+"#;
+
+/// Completer that reuses the completions written for bash, by running bash on every
+/// request. If bash doesn't finish within the [timeout](ShellCompleter::with_timeout),
+/// e.g. because a completion function hangs, it is killed and there are no suggestions.
+///
+/// zsh completion functions are not supported: unlike bash's, they only run inside an
+/// interactive shell.
+pub struct ShellCompleter {
+    shell: OsString,
+    scripts: Vec<PathBuf>,
+    timeout: Duration,
+}
+
+/// Default time to wait for bash to print the candidates.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl Default for ShellCompleter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShellCompleter {
+    /// Returns a completer that runs `bash` and loads bash-completion when it is installed.
+    pub fn new() -> Self {
+        let bash_completion = PathBuf::from(BASH_COMPLETION_SCRIPT);
+        Self {
+            shell: "bash".into(),
+            scripts: if bash_completion.exists() { vec![bash_completion] } else { Vec::new() },
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the bash executable to run.
+    pub fn with_shell<S: Into<OsString>>(mut self, shell: S) -> Self {
+        self.shell = shell.into();
+        self
+    }
+
+    /// Adds a script to source before completing, e.g. a completion script that is not
+    /// installed system-wide.
+    pub fn with_script<P: Into<PathBuf>>(mut self, script: P) -> Self {
+        self.scripts.push(script.into());
+        self
+    }
+
+    /// Removes the scripts to source, including bash-completion.
+    pub fn without_scripts(mut self) -> Self {
+        self.scripts.clear();
+        self
+    }
+
+    /// Sets how long to wait for bash before giving up on the request and killing it,
+    /// 2 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs bash and returns what it prints, read on a thread of its own so that it can
+    /// be waited for with a deadline.
+    fn run(&self, document: &Document) -> io::Result<Vec<u8>> {
+        let mut child = Command::new(&self.shell)
+            .args(["--noprofile", "--norc", "-c", SCRIPT, "bash"])
+            .arg(document.text_before_cursor_str())
+            .args(&self.scripts)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut stdout = child.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let (tx, output) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = tx.send(stdout.read_to_end(&mut buf).map(|_| buf));
+        });
+        let result = match output.recv_timeout(self.timeout) {
+            Ok(output) => output,
+            Err(RecvTimeoutError::Timeout) => {
+                let _ = child.kill();
+                Err(io::Error::new(io::ErrorKind::TimedOut, "bash did not finish in time"))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        let _ = child.wait();
+        result
+    }
+}
+
+impl Completer for ShellCompleter {
+    fn complete(&self, document: &Document) -> Vec<Suggestion> {
+        let output = match self.run(document) {
+            Ok(output) => output,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut candidates = String::from_utf8_lossy(&output)
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+        candidates.into_iter().map(Suggestion::with_title).collect()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn titles(suggestions: Vec<Suggestion>) -> Vec<String> {
+        suggestions.iter().map(|s| s.text().to_string()).collect()
+    }
+
+    #[test]
+    fn test_shell_completer() {
+        let script = std::env::temp_dir().join(format!("rusty-prompt-greet-{}.bash", std::process::id()));
+        fs::write(
+            &script,
+            "_greet() { COMPREPLY=($(compgen -W 'hello help world' -- \"$2\")); }\ncomplete -F _greet greet\n",
+        ).unwrap();
+        let completer = ShellCompleter::new().without_scripts().with_script(&script);
+
+        let d = Document::with_text("greet he".to_string(), 8);
+        assert_eq!(vec!["hello", "help"], titles(completer.complete(&d)));
+        let d = Document::with_text("greet ".to_string(), 6);
+        assert_eq!(vec!["hello", "help", "world"], titles(completer.complete(&d)));
+        // only the text before the cursor is completed
+        let d = Document::with_text("greet w trailing".to_string(), 7);
+        assert_eq!(vec!["world"], titles(completer.complete(&d)));
+
+        let d = Document::with_text("ech".to_string(), 3);
+        assert!(titles(completer.complete(&d)).contains(&"echo".to_string()));
+        fs::remove_file(script).unwrap();
+
+        let completer = ShellCompleter::new().with_shell("/nonexistent/bash");
+        assert!(completer.complete(&d).is_empty());
+    }
+
+    #[test]
+    fn test_shell_completer_timeout() {
+        let script = std::env::temp_dir().join(format!("rusty-prompt-slow-{}.bash", std::process::id()));
+        fs::write(&script, "_slow() { sleep 5; COMPREPLY=(late); }\ncomplete -F _slow slow\n").unwrap();
+        let completer = ShellCompleter::new()
+            .without_scripts()
+            .with_script(&script)
+            .with_timeout(Duration::from_millis(200));
+
+        let start = std::time::Instant::now();
+        let d = Document::with_text("slow ".to_string(), 5);
+        assert!(completer.complete(&d).is_empty());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        fs::remove_file(script).unwrap();
+    }
+}
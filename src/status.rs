@@ -0,0 +1,104 @@
+use std::fmt;
+
+use crossterm::style::Color;
+
+use crate::style::SpanStyle;
+
+/// How the last command went, returned by the executor, see `Prompt::with_context`.
+///
+/// Executors returning `()` always succeed. Exit codes, booleans and results convert
+/// into a status too, so that an executor can e.g. return the code of the process it ran.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    #[default]
+    Success,
+    /// Failed with this exit code, never 0.
+    Failure(i32),
+}
+
+impl Status {
+    pub fn is_success(self) -> bool {
+        self == Status::Success
+    }
+
+    /// Returns the exit code of the command, 0 on success.
+    pub fn code(self) -> i32 {
+        match self {
+            Status::Success => 0,
+            Status::Failure(code) => code,
+        }
+    }
+
+    /// Returns the style of the status in a prefix segment: green on success, red on
+    /// failure.
+    pub fn style(self) -> SpanStyle {
+        SpanStyle::default().fg(if self.is_success() { Color::Green } else { Color::Red })
+    }
+}
+
+impl fmt::Display for Status {
+    /// `✔` on success, `✗` and the exit code on failure.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Success => write!(f, "✔"),
+            Status::Failure(code) => write!(f, "✗ {}", code),
+        }
+    }
+}
+
+impl From<()> for Status {
+    fn from(_: ()) -> Self {
+        Status::Success
+    }
+}
+
+impl From<i32> for Status {
+    fn from(code: i32) -> Self {
+        if code == 0 {
+            Status::Success
+        } else {
+            Status::Failure(code)
+        }
+    }
+}
+
+impl From<bool> for Status {
+    fn from(success: bool) -> Self {
+        if success {
+            Status::Success
+        } else {
+            Status::Failure(1)
+        }
+    }
+}
+
+impl<E> From<Result<(), E>> for Status {
+    fn from(result: Result<(), E>) -> Self {
+        result.is_ok().into()
+    }
+}
+
+impl From<std::process::ExitStatus> for Status {
+    /// A process killed by a signal fails with code 1.
+    fn from(status: std::process::ExitStatus) -> Self {
+        status.code().unwrap_or(1).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        assert_eq!(Status::Success, ().into());
+        assert_eq!(Status::Success, 0.into());
+        assert_eq!(Status::Failure(2), 2.into());
+        assert_eq!(Status::Failure(1), false.into());
+        assert_eq!(Status::Failure(1), Status::from(Err::<(), _>("no such table")));
+        assert_eq!(2, Status::Failure(2).code());
+        assert_eq!("✔", Status::Success.to_string());
+        assert_eq!("✗ 127", Status::Failure(127).to_string());
+        assert_eq!(Some(Color::Red), Status::Failure(1).style().fg);
+    }
+}
@@ -0,0 +1,264 @@
+use crossterm::style::Color;
+
+use crate::width::{display_width, truncate_to_width};
+
+/// Foreground/background colors plus attributes used when writing a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub const fn new(fg: Color, bg: Color) -> Self {
+        Self {
+            fg,
+            bg,
+            bold: false,
+            italic: false,
+            underline: false,
+        }
+    }
+
+    pub const fn bold(self) -> Self {
+        Self {
+            bold: true,
+            ..self
+        }
+    }
+
+    pub const fn italic(self) -> Self {
+        Self {
+            italic: true,
+            ..self
+        }
+    }
+
+    pub const fn underline(self) -> Self {
+        Self {
+            underline: true,
+            ..self
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::new(Color::Reset, Color::Reset)
+    }
+}
+
+/// Style of a [Span]. Colors that are not set are taken from the style the span is
+/// rendered with, e.g. the theme's prefix style, and attributes are added to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpanStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl SpanStyle {
+    pub fn fg(self, color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn bg(self, color: Color) -> Self {
+        Self {
+            bg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn bold(self) -> Self {
+        Self {
+            bold: true,
+            ..self
+        }
+    }
+
+    pub fn italic(self) -> Self {
+        Self {
+            italic: true,
+            ..self
+        }
+    }
+
+    pub fn underline(self) -> Self {
+        Self {
+            underline: true,
+            ..self
+        }
+    }
+
+    /// Returns `base` with this span style layered on top of it.
+    pub fn apply(&self, base: Style) -> Style {
+        Style {
+            fg: self.fg.unwrap_or(base.fg),
+            bg: self.bg.unwrap_or(base.bg),
+            bold: base.bold || self.bold,
+            italic: base.italic || self.italic,
+            underline: base.underline || self.underline,
+        }
+    }
+}
+
+/// A piece of text written with a single style.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+    /// URL the text links to, rendered as an OSC 8 hyperlink where supported.
+    pub link: Option<String>,
+}
+
+/// Text made of independently styled spans, used for the prefix, the placeholder and
+/// the toolbar. It can be built span by span or parsed from markup, see
+/// [parse_markup](crate::markup::parse_markup).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyledText {
+    spans: Vec<Span>,
+}
+
+impl StyledText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a span with the given style.
+    pub fn span<S: Into<String>>(mut self, text: S, style: SpanStyle) -> Self {
+        self.push(text, style);
+        self
+    }
+
+    /// Appends a span without any style of its own.
+    pub fn plain<S: Into<String>>(self, text: S) -> Self {
+        self.span(text, SpanStyle::default())
+    }
+
+    /// Appends a span linking to `url`, e.g. the documentation of a flag. It is shown
+    /// as plain text in terminals without hyperlinks.
+    pub fn link<S: Into<String>, U: Into<String>>(mut self, text: S, url: U, style: SpanStyle) -> Self {
+        self.push_span(text.into(), style, Some(url.into()));
+        self
+    }
+
+    pub fn push<S: Into<String>>(&mut self, text: S, style: SpanStyle) {
+        self.push_span(text.into(), style, None);
+    }
+
+    fn push_span(&mut self, text: String, style: SpanStyle, link: Option<String>) {
+        if text.is_empty() {
+            return;
+        }
+        match self.spans.last_mut() {
+            Some(last) if last.style == style && last.link == link => last.text.push_str(&text),
+            _ => self.spans.push(Span { text, style, link }),
+        }
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Returns the text without any styling.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// Returns the number of columns the text occupies on the terminal.
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(|s| display_width(&s.text)).sum()
+    }
+
+    /// Returns the text split at line breaks, each line keeping the styles of its spans.
+    pub(crate) fn lines(&self) -> Vec<StyledText> {
+        let mut lines = Vec::new();
+        let mut line = StyledText::new();
+        for span in &self.spans {
+            for (i, part) in span.text.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push_span(part.to_string(), span.style, span.link.clone());
+            }
+        }
+        lines.push(line);
+        lines
+    }
+
+    /// Returns the longest start of the text fitting in `max` columns.
+    pub(crate) fn truncate(&self, max: usize) -> StyledText {
+        let mut truncated = StyledText::new();
+        let mut width = 0;
+        for span in &self.spans {
+            let text = truncate_to_width(&span.text, max - width);
+            width += display_width(&text);
+            let whole = text.len() == span.text.len();
+            truncated.push_span(text, span.style, span.link.clone());
+            if !whole {
+                break;
+            }
+        }
+        truncated
+    }
+}
+
+impl From<&str> for StyledText {
+    fn from(s: &str) -> Self {
+        Self::new().plain(s)
+    }
+}
+
+impl From<String> for StyledText {
+    fn from(s: String) -> Self {
+        Self::new().plain(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_styled_text_width() {
+        let t = StyledText::new()
+            .span("日本", SpanStyle::default().bold())
+            .plain(" $ ");
+        assert_eq!(7, t.width());
+        assert_eq!("日本 $ ", t.text());
+        assert_eq!(2, t.spans().len());
+    }
+
+    #[test]
+    fn test_styled_text_merges_equal_styles() {
+        let t = StyledText::new().plain("a").plain("b").plain("");
+        assert_eq!(vec![Span { text: "ab".to_string(), style: SpanStyle::default(), link: None }], t.spans());
+    }
+
+    #[test]
+    fn test_styled_text_link() {
+        let t = StyledText::new().plain("see ").link("docs", "https://example.com", SpanStyle::default()).plain(".");
+        assert_eq!("see docs.", t.text());
+        assert_eq!(3, t.spans().len());
+        assert_eq!(Some("https://example.com"), t.spans()[1].link.as_deref());
+    }
+
+    #[test]
+    fn test_span_style_apply() {
+        let base = Style::new(Color::Blue, Color::Black).bold();
+        let s = SpanStyle::default().fg(Color::Red).underline().apply(base);
+        assert_eq!(Style::new(Color::Red, Color::Black).bold().underline(), s);
+    }
+}
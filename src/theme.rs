@@ -0,0 +1,176 @@
+use crossterm::style::Color;
+
+use crate::style::Style;
+
+/// Names of the built-in themes, accepted by [Theme::from_name].
+pub const THEME_NAMES: [&str; 4] = ["default", "solarized-dark", "monochrome", "high-contrast"];
+
+/// The palette used by the renderer for every part of the prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub prefix: Style,
+    pub input: Style,
+    pub placeholder: Style,
+    pub preview_suggestion: Style,
+    pub suggestion: Style,
+    pub selected_suggestion: Style,
+    /// Used instead of `suggestion` for "did you mean" corrections.
+    pub correction: Style,
+    /// Used for the text and the reason of suggestions that can't be selected.
+    pub disabled_suggestion: Style,
+    pub description: Style,
+    pub selected_description: Style,
+    pub scrollbar_thumb: Style,
+    pub scrollbar: Style,
+    pub toolbar: Style,
+    /// The border around the completion popup, if it has one, see [Border].
+    pub popup_border: Style,
+    /// The bar of key bindings on the last line, see `Prompt::with_key_hints`.
+    pub key_hints: Style,
+}
+
+/// Lines drawn around the completion popup so that it reads as a panel floating over the
+/// input, see `Renderer::set_popup_border`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Border {
+    /// `╭─╮`, for terminals and fonts with box drawing characters.
+    Rounded,
+    /// `┌─┐`
+    Square,
+    /// `+-+`, for any terminal.
+    Ascii,
+}
+
+impl Border {
+    /// Returns the top left, top right, bottom left and bottom right corners, and the
+    /// horizontal and vertical lines.
+    pub fn chars(self) -> [char; 6] {
+        match self {
+            Border::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            Border::Square => ['┌', '┐', '└', '┘', '─', '│'],
+            Border::Ascii => ['+', '+', '+', '+', '-', '|'],
+        }
+    }
+}
+
+impl Default for Theme {
+    /// Same colors as go-prompt's defaults.
+    fn default() -> Self {
+        Self {
+            prefix: Style::new(Color::Blue, Color::Reset),
+            input: Style::default(),
+            placeholder: Style::new(Color::DarkGrey, Color::Reset),
+            preview_suggestion: Style::new(Color::Green, Color::Reset),
+            suggestion: Style::new(Color::White, Color::Cyan),
+            selected_suggestion: Style::new(Color::Black, Color::DarkCyan).bold(),
+            correction: Style::new(Color::Black, Color::Cyan).italic(),
+            disabled_suggestion: Style::new(Color::DarkGrey, Color::Cyan),
+            description: Style::new(Color::Black, Color::DarkCyan),
+            selected_description: Style::new(Color::White, Color::Cyan).bold(),
+            scrollbar_thumb: Style::new(Color::Reset, Color::DarkGrey),
+            scrollbar: Style::new(Color::Reset, Color::Cyan),
+            toolbar: Style::new(Color::Black, Color::Grey),
+            popup_border: Style::new(Color::White, Color::DarkCyan),
+            key_hints: Style::new(Color::DarkGrey, Color::Reset),
+        }
+    }
+}
+
+impl Theme {
+    /// Based on Ethan Schoonover's solarized palette.
+    pub fn solarized_dark() -> Self {
+        let base03 = Color::Rgb { r: 0x00, g: 0x2b, b: 0x36 };
+        let base02 = Color::Rgb { r: 0x07, g: 0x36, b: 0x42 };
+        let base01 = Color::Rgb { r: 0x58, g: 0x6e, b: 0x75 };
+        let base1 = Color::Rgb { r: 0x93, g: 0xa1, b: 0xa1 };
+        let yellow = Color::Rgb { r: 0xb5, g: 0x89, b: 0x00 };
+        let blue = Color::Rgb { r: 0x26, g: 0x8b, b: 0xd2 };
+        let cyan = Color::Rgb { r: 0x2a, g: 0xa1, b: 0x98 };
+        let green = Color::Rgb { r: 0x85, g: 0x99, b: 0x00 };
+
+        Self {
+            prefix: Style::new(blue, Color::Reset).bold(),
+            input: Style::new(base1, Color::Reset),
+            placeholder: Style::new(base01, Color::Reset).italic(),
+            preview_suggestion: Style::new(green, Color::Reset),
+            suggestion: Style::new(base1, base02),
+            selected_suggestion: Style::new(base03, yellow).bold(),
+            correction: Style::new(yellow, base02).italic(),
+            disabled_suggestion: Style::new(base01, base02),
+            description: Style::new(base01, base02),
+            selected_description: Style::new(base03, cyan),
+            scrollbar_thumb: Style::new(Color::Reset, base01),
+            scrollbar: Style::new(Color::Reset, base02),
+            toolbar: Style::new(base1, base02),
+            popup_border: Style::new(base01, base02),
+            key_hints: Style::new(base01, Color::Reset),
+        }
+    }
+
+    /// No colors at all, the selection is shown only through the bold attribute.
+    pub fn monochrome() -> Self {
+        Self {
+            prefix: Style::default(),
+            input: Style::default(),
+            placeholder: Style::default().italic(),
+            preview_suggestion: Style::default(),
+            suggestion: Style::default(),
+            selected_suggestion: Style::default().bold(),
+            correction: Style::default().italic(),
+            disabled_suggestion: Style::default(),
+            description: Style::default(),
+            selected_description: Style::default().bold(),
+            scrollbar_thumb: Style::default(),
+            scrollbar: Style::default(),
+            toolbar: Style::default(),
+            popup_border: Style::default(),
+            key_hints: Style::default(),
+        }
+    }
+
+    /// Only uses black, white and yellow so that every pair has maximum contrast.
+    pub fn high_contrast() -> Self {
+        Self {
+            prefix: Style::new(Color::Yellow, Color::Black).bold(),
+            input: Style::new(Color::White, Color::Black),
+            placeholder: Style::new(Color::Grey, Color::Black).italic(),
+            preview_suggestion: Style::new(Color::Yellow, Color::Black),
+            suggestion: Style::new(Color::White, Color::Black),
+            selected_suggestion: Style::new(Color::Black, Color::Yellow).bold(),
+            correction: Style::new(Color::Yellow, Color::Black).italic(),
+            disabled_suggestion: Style::new(Color::Grey, Color::Black),
+            description: Style::new(Color::White, Color::Black),
+            selected_description: Style::new(Color::Black, Color::White).bold(),
+            scrollbar_thumb: Style::new(Color::Reset, Color::White),
+            scrollbar: Style::new(Color::Reset, Color::Black),
+            toolbar: Style::new(Color::Black, Color::White).bold(),
+            popup_border: Style::new(Color::White, Color::Black),
+            key_hints: Style::new(Color::White, Color::Black),
+        }
+    }
+
+    /// Returns the built-in theme with the given name. See [THEME_NAMES].
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "solarized-dark" => Some(Self::solarized_dark()),
+            "monochrome" => Some(Self::monochrome()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        for name in THEME_NAMES {
+            assert!(Theme::from_name(name).is_some(), "theme {} should exist", name);
+        }
+        assert_eq!(Some(Theme::monochrome()), Theme::from_name("monochrome"));
+        assert_eq!(None, Theme::from_name("unknown"));
+    }
+}
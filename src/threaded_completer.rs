@@ -0,0 +1,201 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+
+use crate::completion::{Completer, Suggestion};
+use crate::document::Document;
+
+/// Text and cursor position of a completion request.
+type Request = (String, usize);
+
+/// Completer running a slow completer, e.g. one querying a server, on a background
+/// thread so that typing is never blocked.
+///
+/// A request returns no suggestions until its results are ready, and the prompt shows a
+/// spinner in the meantime. Requests made while another one runs replace each other, so
+/// only the latest input is completed. A request whose completer panics returns no
+/// suggestions, and the panic is kept in [last_error](ThreadedCompleter::last_error).
+pub struct ThreadedCompleter {
+    shared: Arc<(Mutex<State>, Condvar)>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The request waiting for the worker.
+    queued: Option<Request>,
+    /// The request the worker is completing.
+    running: Option<Request>,
+    done: Option<(Request, Vec<Suggestion>)>,
+    last_error: Option<String>,
+    closed: bool,
+}
+
+impl ThreadedCompleter {
+    pub fn new<C: Completer + Send + 'static>(completer: C) -> Self {
+        let shared = Arc::new((Mutex::new(State::default()), Condvar::new()));
+        let worker = Arc::clone(&shared);
+        thread::spawn(move || {
+            let (state, ready) = &*worker;
+            loop {
+                let request = {
+                    let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+                    let request = loop {
+                        if state.closed {
+                            return;
+                        }
+                        if let Some(request) = state.queued.take() {
+                            break request;
+                        }
+                        state = ready.wait(state).unwrap_or_else(PoisonError::into_inner);
+                    };
+                    state.running = Some(request.clone());
+                    request
+                };
+                let document = Document::with_text(request.0.clone(), request.1);
+                let result = panic::catch_unwind(AssertUnwindSafe(|| completer.complete(&document)));
+                let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+                state.running = None;
+                let suggestions = match result {
+                    Ok(suggestions) => {
+                        state.last_error = None;
+                        suggestions
+                    }
+                    Err(payload) => {
+                        state.last_error = Some(panic_message(payload.as_ref()));
+                        Vec::new()
+                    }
+                };
+                state.done = Some((request, suggestions));
+            }
+        });
+        Self { shared }
+    }
+
+    /// Returns the panic message of the last request, if its completer panicked.
+    pub fn last_error(&self) -> Option<String> {
+        self.shared.0.lock().unwrap_or_else(PoisonError::into_inner).last_error.clone()
+    }
+}
+
+/// Returns the message a thread panicked with.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "panicked".to_string(),
+    }
+}
+
+impl Completer for ThreadedCompleter {
+    fn complete(&self, document: &Document) -> Vec<Suggestion> {
+        let request = (document.text().to_string(), document.cursor_position());
+        let (state, ready) = &*self.shared;
+        let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+        match &state.done {
+            Some((done, suggestions)) if *done == request => return suggestions.clone(),
+            _ => {}
+        }
+        if state.running.as_ref() != Some(&request) && state.queued.as_ref() != Some(&request) {
+            state.queued = Some(request);
+            ready.notify_one();
+        }
+        Vec::new()
+    }
+
+    fn pending(&self) -> bool {
+        let state = self.shared.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.queued.is_some() || state.running.is_some()
+    }
+}
+
+impl Drop for ThreadedCompleter {
+    /// Stops the worker once it is done with the current request, without waiting for it.
+    fn drop(&mut self) {
+        let (state, ready) = &*self.shared;
+        state.lock().unwrap_or_else(PoisonError::into_inner).closed = true;
+        ready.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Completes once it is told to, with the text it was asked for.
+    struct Gated(Mutex<mpsc::Receiver<()>>);
+
+    impl Completer for Gated {
+        fn complete(&self, document: &Document) -> Vec<Suggestion> {
+            self.0.lock().unwrap().recv().unwrap();
+            vec![Suggestion::with_title(document.text().to_string())]
+        }
+    }
+
+    fn wait_until_done(c: &ThreadedCompleter) {
+        for _ in 0..500 {
+            if !c.pending() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        panic!("the completer did not finish");
+    }
+
+    #[test]
+    fn test_threaded_completer() {
+        let (open, gate) = mpsc::channel();
+        let c = ThreadedCompleter::new(Gated(Mutex::new(gate)));
+        assert!(!c.pending());
+
+        let g = Document::with_text("g".to_string(), 1);
+        let gi = Document::with_text("gi".to_string(), 2);
+        assert!(c.complete(&g).is_empty());
+        assert!(c.pending());
+        // queued after "g", or in its place if the worker did not pick it up yet
+        assert!(c.complete(&gi).is_empty());
+        assert!(c.complete(&gi).is_empty());
+        open.send(()).unwrap();
+        open.send(()).unwrap();
+        wait_until_done(&c);
+        assert_eq!(vec![Suggestion::with_title("gi".to_string())], c.complete(&gi));
+        assert!(!c.pending());
+        // older results are not returned for a different input
+        assert!(c.complete(&g).is_empty());
+        assert!(c.pending());
+        open.send(()).unwrap();
+        wait_until_done(&c);
+        assert_eq!(vec![Suggestion::with_title("g".to_string())], c.complete(&g));
+    }
+
+    struct Panicking;
+
+    impl Completer for Panicking {
+        fn complete(&self, document: &Document) -> Vec<Suggestion> {
+            if document.text() == "boom" {
+                panic!("completer failed");
+            }
+            vec![Suggestion::with_title(document.text().to_string())]
+        }
+    }
+
+    #[test]
+    fn test_threaded_completer_panics() {
+        let c = ThreadedCompleter::new(Panicking);
+        let boom = Document::with_text("boom".to_string(), 4);
+        assert!(c.complete(&boom).is_empty());
+        wait_until_done(&c);
+        assert!(c.complete(&boom).is_empty());
+        assert_eq!(Some("completer failed".to_string()), c.last_error());
+
+        // the worker keeps completing
+        let ok = Document::with_text("ok".to_string(), 2);
+        c.complete(&ok);
+        wait_until_done(&c);
+        assert_eq!(vec![Suggestion::with_title("ok".to_string())], c.complete(&ok));
+        assert_eq!(None, c.last_error());
+    }
+}
@@ -0,0 +1,364 @@
+use crossterm::style::Color;
+
+use crate::style::{SpanStyle, StyledText};
+use crate::Continuation;
+
+/// The input as it is about to be displayed. `cursor` is a char offset into `text`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisplayText {
+    pub text: StyledText,
+    pub cursor: usize,
+}
+
+impl DisplayText {
+    pub fn new(text: &str, cursor: usize) -> Self {
+        Self {
+            text: text.into(),
+            cursor,
+        }
+    }
+
+    /// Returns the number of columns between the start of the text and the cursor.
+    pub fn width_before_cursor(&self) -> usize {
+        let before = self.text.text().chars().take(self.cursor).collect::<String>();
+        crate::width::display_width(&before)
+    }
+
+    /// Replaces every char with the text returned by `f`, which receives the char index,
+    /// the char and its style. The cursor is moved along with the char it was in front of,
+    /// and the replacement links where the char did unless it links elsewhere.
+    pub fn map_chars<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(usize, char, SpanStyle) -> StyledText,
+    {
+        let mut text = StyledText::new();
+        let mut cursor = 0;
+        let mut index = 0;
+        for span in self.text.spans() {
+            for c in span.text.chars() {
+                let replaced = f(index, c, span.style);
+                if index < self.cursor {
+                    cursor += replaced.text().chars().count();
+                }
+                for s in replaced.spans() {
+                    match s.link.as_ref().or(span.link.as_ref()) {
+                        Some(url) => text = std::mem::take(&mut text).link(s.text.as_str(), url.as_str(), s.style),
+                        None => text.push(s.text.as_str(), s.style),
+                    }
+                }
+                index += 1;
+            }
+        }
+        if self.cursor > index {
+            cursor += self.cursor - index;
+        }
+        Self { text, cursor }
+    }
+}
+
+/// Changes how the input is displayed without touching the buffer, e.g. to mask a
+/// password. Transformations are applied by the renderer in the order they were added.
+pub trait Transformation {
+    fn transform(&self, display: DisplayText) -> DisplayText;
+}
+
+/// Replaces every character, except line breaks, with `mask`.
+#[derive(Debug, Clone)]
+pub struct PasswordMask {
+    pub mask: char,
+}
+
+impl Default for PasswordMask {
+    fn default() -> Self {
+        Self { mask: '*' }
+    }
+}
+
+impl Transformation for PasswordMask {
+    fn transform(&self, display: DisplayText) -> DisplayText {
+        display.map_chars(|_, c, style| {
+            let c = if c == '\n' { c } else { self.mask };
+            StyledText::new().span(c.to_string(), style)
+        })
+    }
+}
+
+/// How [ControlCharacters] displays ASCII control characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlNotation {
+    /// `^C` for `\x03`, `^?` for DEL.
+    Caret,
+    /// The matching symbol of the Control Pictures block, e.g. `␃` for `\x03`.
+    Pictures,
+}
+
+/// Makes characters that the terminal would not print visible, so that they take the
+/// columns the cursor math expects. ASCII control characters other than line breaks and
+/// tabs use `notation`, C1 control characters are shown as `<9b>`, and with
+/// `show_zero_width` invisible format characters such as U+200B are shown as `<200b>`.
+#[derive(Debug, Clone)]
+pub struct ControlCharacters {
+    pub notation: ControlNotation,
+    pub show_zero_width: bool,
+    pub style: SpanStyle,
+}
+
+impl Default for ControlCharacters {
+    fn default() -> Self {
+        Self {
+            notation: ControlNotation::Caret,
+            show_zero_width: false,
+            style: SpanStyle::default().fg(Color::DarkGrey),
+        }
+    }
+}
+
+impl ControlCharacters {
+    /// Returns what to display instead of `c`, or None if it is printed as is.
+    pub fn visualize(&self, c: char) -> Option<String> {
+        match c {
+            '\n' | '\t' => None,
+            '\x00'..='\x1f' | '\x7f' => Some(match self.notation {
+                ControlNotation::Caret => format!("^{}", (c as u8 ^ 0x40) as char),
+                ControlNotation::Pictures if c == '\x7f' => '\u{2421}'.to_string(),
+                ControlNotation::Pictures => char::from_u32(0x2400 + c as u32)?.to_string(),
+            }),
+            '\u{80}'..='\u{9f}' => Some(format!("<{:x}>", c as u32)),
+            _ if self.show_zero_width && is_invisible_format(c) => Some(format!("<{:x}>", c as u32)),
+            _ => None,
+        }
+    }
+}
+
+impl Transformation for ControlCharacters {
+    fn transform(&self, display: DisplayText) -> DisplayText {
+        display.map_chars(|_, c, style| match self.visualize(c) {
+            Some(s) => StyledText::new().span(s, self.style),
+            None => StyledText::new().span(c.to_string(), style),
+        })
+    }
+}
+
+/// Zero-width characters that only affect layout or joining, not combining marks.
+fn is_invisible_format(c: char) -> bool {
+    matches!(c, '\u{ad}' | '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2064}' | '\u{feff}')
+}
+
+/// Highlights spaces and tabs at the end of each line.
+#[derive(Debug, Clone)]
+pub struct TrailingWhitespace {
+    pub style: SpanStyle,
+}
+
+impl Default for TrailingWhitespace {
+    fn default() -> Self {
+        Self {
+            style: SpanStyle::default().bg(Color::DarkRed),
+        }
+    }
+}
+
+impl Transformation for TrailingWhitespace {
+    fn transform(&self, display: DisplayText) -> DisplayText {
+        let chars = display.text.text().chars().collect::<Vec<_>>();
+        let mut trailing = vec![false; chars.len()];
+        let mut in_trailing = true;
+        for (i, c) in chars.iter().enumerate().rev() {
+            match c {
+                '\n' => in_trailing = true,
+                ' ' | '\t' => trailing[i] = in_trailing,
+                _ => in_trailing = false,
+            }
+        }
+        display.map_chars(|i, c, style| {
+            let style = if trailing[i] { self.style } else { style };
+            StyledText::new().span(c.to_string(), style)
+        })
+    }
+}
+
+/// Colors brackets by how deeply they are nested, cycling through `colors`, so that
+/// matching brackets have the same color. A closing bracket not matching the last open
+/// one uses `unmatched`. Brackets in quotes, comments or escaped are left alone, as
+/// `syntax` tells. Only the foreground color of the text is replaced, so that it goes on
+/// top of the styles of a lexer.
+#[derive(Debug, Clone)]
+pub struct RainbowBrackets {
+    /// The brackets, quotes, comments and escape character of the input.
+    pub syntax: Continuation,
+    pub colors: Vec<Color>,
+    pub unmatched: SpanStyle,
+}
+
+impl Default for RainbowBrackets {
+    fn default() -> Self {
+        Self {
+            syntax: Continuation::default(),
+            colors: vec![Color::Yellow, Color::Magenta, Color::Cyan],
+            unmatched: SpanStyle::default().fg(Color::Red).bold(),
+        }
+    }
+}
+
+impl Transformation for RainbowBrackets {
+    fn transform(&self, display: DisplayText) -> DisplayText {
+        if self.colors.is_empty() {
+            return display;
+        }
+        // the depth of each bracket, None if it is unmatched
+        let mut closing = Vec::new();
+        let mut depths = Vec::new();
+        for (i, c) in self.syntax.scan(&display.text.text()).brackets {
+            let depth = if let Some(close) = self.syntax.closing(c) {
+                closing.push(close);
+                Some(closing.len() - 1)
+            } else {
+                match closing.last() == Some(&c) {
+                    true => closing.pop().map(|_| closing.len()),
+                    false => None,
+                }
+            };
+            depths.push((i, depth));
+        }
+        let mut depths = depths.into_iter().peekable();
+        display.map_chars(|i, c, style| {
+            let style = match depths.next_if(|&(at, _)| at == i) {
+                Some((_, Some(depth))) => SpanStyle { fg: Some(self.colors[depth % self.colors.len()]), ..style },
+                Some((_, None)) => self.unmatched,
+                None => style,
+            };
+            StyledText::new().span(c.to_string(), style)
+        })
+    }
+}
+
+/// Expands tabs to spaces up to the next tab stop.
+#[derive(Debug, Clone)]
+pub struct TabExpansion {
+    pub width: usize,
+    /// Column of the first input character on the first line, usually the prefix width.
+    pub start_column: usize,
+}
+
+impl Default for TabExpansion {
+    fn default() -> Self {
+        Self {
+            width: 8,
+            start_column: 0,
+        }
+    }
+}
+
+impl Transformation for TabExpansion {
+    fn transform(&self, display: DisplayText) -> DisplayText {
+        let mut column = self.start_column;
+        display.map_chars(|_, c, style| {
+            let s = match c {
+                '\t' => {
+                    let n = self.width.max(1) - column % self.width.max(1);
+                    column += n;
+                    " ".repeat(n)
+                }
+                '\n' => {
+                    column = 0;
+                    c.to_string()
+                }
+                _ => {
+                    column += crate::width::char_width(c);
+                    c.to_string()
+                }
+            };
+            StyledText::new().span(s, style)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_mask() {
+        let d = PasswordMask::default().transform(DisplayText::new("pässwörd", 3));
+        assert_eq!("********", d.text.text());
+        assert_eq!(3, d.cursor);
+    }
+
+    #[test]
+    fn test_control_characters() {
+        let d = ControlCharacters::default().transform(DisplayText::new("a\x03b\x7f", 2));
+        assert_eq!("a^Cb^?", d.text.text());
+        assert_eq!(3, d.cursor);
+        assert_eq!(4, d.text.spans().len());
+    }
+
+    #[test]
+    fn test_control_pictures_and_invisible_characters() {
+        let t = ControlCharacters {
+            notation: ControlNotation::Pictures,
+            show_zero_width: true,
+            ..Default::default()
+        };
+        let d = t.transform(DisplayText::new("\x00\x1b\x7f\u{9b}a\u{200b}b\u{301}", 6));
+        assert_eq!("\u{2400}\u{241b}\u{2421}<9b>a<200b>b\u{301}", d.text.text());
+        assert_eq!("\u{2400}\u{241b}\u{2421}<9b>a<200b>".chars().count(), d.cursor);
+
+        let d = ControlCharacters::default().transform(DisplayText::new("a\u{200b}b", 0));
+        assert_eq!("a\u{200b}b", d.text.text());
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let d = TrailingWhitespace::default().transform(DisplayText::new("a b  \nc ", 0));
+        let highlighted = d.text.spans().iter()
+            .filter(|s| s.style.bg.is_some())
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["  ", " "], highlighted);
+    }
+
+    #[test]
+    fn test_rainbow_brackets() {
+        let d = DisplayText { text: StyledText::new().plain("f(a[").span("1", SpanStyle::default().bold()).plain("], {}))"), cursor: 0 };
+        let d = RainbowBrackets::default().transform(d);
+        let style = |i: usize| d.text.spans().iter().flat_map(|s| s.text.chars().map(|_| s.style)).nth(i).unwrap();
+        assert_eq!(None, style(0).fg);
+        assert_eq!(Some(Color::Yellow), style(1).fg);
+        assert_eq!(Some(Color::Magenta), style(3).fg);
+        assert_eq!(SpanStyle::default().bold(), style(4));
+        assert_eq!(Some(Color::Magenta), style(5).fg);
+        assert_eq!(Some(Color::Magenta), style(8).fg);
+        assert_eq!(Some(Color::Magenta), style(9).fg);
+        assert_eq!(Some(Color::Yellow), style(10).fg);
+        assert_eq!(RainbowBrackets::default().unmatched, style(11));
+        assert_eq!("f(a[1], {}))", d.text.text());
+
+        // brackets in quotes, comments and escapes are not counted
+        let d = RainbowBrackets::default().transform(DisplayText::new("echo \")\" '(' \\] (x) # ]", 0));
+        let style = |i: usize| d.text.spans().iter().flat_map(|s| s.text.chars().map(|_| s.style)).nth(i).unwrap();
+        assert!((0..d.text.text().chars().count()).all(|i| style(i) != RainbowBrackets::default().unmatched));
+        assert_eq!(None, style(6).fg);
+        assert_eq!(None, style(10).fg);
+        assert_eq!(Some(Color::Yellow), style(16).fg);
+        assert_eq!(Some(Color::Yellow), style(18).fg);
+    }
+
+    #[test]
+    fn test_tab_expansion() {
+        let t = TabExpansion { width: 4, start_column: 2 };
+        let d = t.transform(DisplayText::new("a\tb\n\tc", 3));
+        assert_eq!("a b\n    c", d.text.text());
+        assert_eq!(3, d.cursor);
+        let d = t.transform(DisplayText::new("\t", 1));
+        assert_eq!(2, d.cursor);
+    }
+
+    #[test]
+    fn test_chain() {
+        let d = DisplayText::new("x\t\x01", 3);
+        let d = ControlCharacters::default().transform(d);
+        let d = TabExpansion { width: 4, start_column: 0 }.transform(d);
+        assert_eq!("x   ^A", d.text.text());
+        assert_eq!(6, d.cursor);
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt::{self, Display};
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::completion::{Completer, ContextCompleter, Suggestion};
+use crate::document::Document;
+use crate::expand::expand_vars;
+use crate::file_completer::FilePathCompleter;
+use crate::prompt::Prompt;
+
+/// Completer for inputs that have nothing to complete.
+struct NoCompletion;
+
+impl Completer for NoCompletion {
+    fn complete(&self, _: &Document) -> Vec<Suggestion> {
+        Vec::new()
+    }
+}
+
+/// Reads a line that `parse` accepts and returns what it parsed. Errors are shown in the
+/// toolbar until the input is fixed. Returns None when Ctrl-D is pressed on an empty
+/// input.
+pub fn input_parsed<T, E, F>(message: &str, parse: F) -> io::Result<Option<T>>
+where
+    E: Display,
+    F: Fn(&str) -> Result<T, E>,
+{
+    read_parsed(message, NoCompletion, "", parse)
+}
+
+fn read_parsed<C, T, E, F>(message: &str, completer: C, separator: &str, parse: F) -> io::Result<Option<T>>
+where
+    C: ContextCompleter<()>,
+    E: Display,
+    F: Fn(&str) -> Result<T, E>,
+{
+    let mut prompt = Prompt::with_context((), |_, _| {}, completer)
+        .with_prefix(format!("{} ", message))
+        .with_completion_word_separator(separator)
+        .with_validator(|input| parse(input).map(|_| ()).map_err(|e| e.to_string()));
+    let input = prompt.input()?;
+    // the validator only lets parsable input through
+    input.map(|input| parse(&input)).transpose().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+}
+
+/// Reads a non-negative integer.
+pub fn input_u64(message: &str) -> io::Result<Option<u64>> {
+    input_parsed(message, |input| input.trim().parse::<u64>().map_err(|_| "Enter a non-negative integer."))
+}
+
+/// Reads a path, with file path completion. `~` and environment variables are expanded.
+/// If `must_exist` is true, only existing paths are accepted.
+pub fn input_path(message: &str, must_exist: bool) -> io::Result<Option<PathBuf>> {
+    read_parsed(message, FilePathCompleter::default(), "/", |input| parse_path(input, must_exist))
+}
+
+fn parse_path(input: &str, must_exist: bool) -> Result<PathBuf, String> {
+    if input.is_empty() {
+        return Err("Enter a path.".to_string());
+    }
+    let path = PathBuf::from(expand_vars(input).as_ref());
+    if must_exist && !path.exists() {
+        return Err(format!("{} does not exist.", path.display()));
+    }
+    Ok(path)
+}
+
+/// Reads a date in `format`, see [Date::parse].
+pub fn input_date(message: &str, format: &str) -> io::Result<Option<Date>> {
+    input_parsed(message, |input| Date::parse(input, format))
+}
+
+/// A calendar date, as returned by [input_date].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// Parses `input` according to `format`, where `%Y` stands for a 4 digit year, `%m`
+    /// for a 2 digit month, `%d` for a 2 digit day and `%%` for `%`. Other characters
+    /// must appear as is, e.g. `"%d/%m/%Y"` parses `"31/12/2024"`.
+    pub fn parse(input: &str, format: &str) -> Result<Self, String> {
+        let invalid = || format!("Enter a date as {}.", format);
+        let (mut year, mut month, mut day) = (None, None, None);
+        let mut rest = input;
+        let mut format_chars = format.chars();
+        while let Some(c) = format_chars.next() {
+            let (field, digits) = match (c, format_chars.clone().next()) {
+                ('%', Some('Y')) => (&mut year, 4),
+                ('%', Some('m')) => (&mut month, 2),
+                ('%', Some('d')) => (&mut day, 2),
+                ('%', Some('%')) => {
+                    format_chars.next();
+                    rest = rest.strip_prefix('%').ok_or_else(invalid)?;
+                    continue;
+                }
+                _ => {
+                    rest = rest.strip_prefix(c).ok_or_else(invalid)?;
+                    continue;
+                }
+            };
+            format_chars.next();
+            let number = rest.get(..digits).filter(|n| n.bytes().all(|b| b.is_ascii_digit())).ok_or_else(invalid)?;
+            *field = Some(number.parse::<u16>().map_err(|_| invalid())?);
+            rest = &rest[digits..];
+        }
+        let (Some(year), Some(month), Some(day), "") = (year, month, day, rest) else {
+            return Err(invalid());
+        };
+        if !(1..=12).contains(&month) {
+            return Err(format!("{} is not a month.", month));
+        }
+        let date = Self { year, month: month as u8, day: day as u8 };
+        if day == 0 || date.day > date.days_in_month() {
+            return Err(format!("{:04}-{:02} has no day {}.", year, month, day));
+        }
+        Ok(date)
+    }
+
+    fn days_in_month(&self) -> u8 {
+        match self.month {
+            2 if self.year.is_multiple_of(4) && (!self.year.is_multiple_of(100) || self.year.is_multiple_of(400)) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl Display for Date {
+    /// Formats the date as `%Y-%m-%d`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    /// Parses a `%Y-%m-%d` date.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, "%Y-%m-%d")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_date_parse() {
+        assert_eq!(Ok(Date { year: 2024, month: 2, day: 29 }), "2024-02-29".parse());
+        assert_eq!(Ok(Date { year: 2024, month: 12, day: 31 }), Date::parse("31/12/2024", "%d/%m/%Y"));
+        assert_eq!(Ok(Date { year: 2024, month: 1, day: 5 }), Date::parse("2024%01%05", "%Y%%%m%%%d"));
+        assert_eq!(Err("2023-02 has no day 29.".to_string()), "2023-02-29".parse::<Date>());
+        assert_eq!(Err("13 is not a month.".to_string()), "2023-13-01".parse::<Date>());
+        assert_eq!(Err("Enter a date as %Y-%m-%d.".to_string()), "2023-1-01".parse::<Date>());
+        assert!("2023-01-01x".parse::<Date>().is_err());
+        assert!("2023-01".parse::<Date>().is_err());
+        assert!(Date::parse("2023", "%Y").is_err());
+        assert_eq!("2024-03-07", Date { year: 2024, month: 3, day: 7 }.to_string());
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let dir = env::temp_dir();
+        assert_eq!(Ok(dir.clone()), parse_path(dir.to_str().unwrap(), true));
+        let missing = dir.join("rusty-prompt-missing");
+        assert!(parse_path(missing.to_str().unwrap(), true).is_err());
+        assert_eq!(Ok(missing.clone()), parse_path(missing.to_str().unwrap(), false));
+        assert!(parse_path("", false).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use std::io;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal;
+
+use crate::completion::{Completer, CompletionManager, Suggestion};
+use crate::document::Document;
+use crate::key_bind::Key;
+use crate::render::Renderer;
+
+/// Number of options shown at once by [select] and [multi_select].
+const MAX_OPTIONS: usize = 10;
+
+/// Asks a yes or no question, answered with `y` or `n`. Returns None if it is cancelled
+/// with Esc or Ctrl-C.
+pub fn confirm(message: &str) -> io::Result<Option<bool>> {
+    run(message, "y/n · Esc to cancel", &mut Confirm::new())
+}
+
+/// Lets the user pick one of `options` with the arrow keys and Enter. Returns the index
+/// of the picked option, or None if it is cancelled with Esc or Ctrl-C.
+pub fn select<S: AsRef<str>>(message: &str, options: &[S]) -> io::Result<Option<usize>> {
+    run(message, "↑/↓ to move · Enter to select · Esc to cancel", &mut Select::new(options))
+}
+
+/// Lets the user check any number of `options` with Space and confirm with Enter.
+/// Returns the indexes of the checked options, or None if it is cancelled with Esc or
+/// Ctrl-C.
+pub fn multi_select<S: AsRef<str>>(message: &str, options: &[S]) -> io::Result<Option<Vec<usize>>> {
+    run(message, "↑/↓ to move · Space to check · Enter to confirm · Esc to cancel", &mut MultiSelect::new(options))
+}
+
+/// The options shown in the popup.
+struct Choices(Vec<Suggestion>);
+
+impl Completer for Choices {
+    fn complete(&self, _: &Document) -> Vec<Suggestion> {
+        self.0.clone()
+    }
+}
+
+/// What a widget does after a key press.
+#[derive(Debug, PartialEq, Eq)]
+enum Step<T> {
+    Continue,
+    Done(T),
+    Cancel,
+}
+
+/// A mini prompt drawn with the completion popup of the [Renderer].
+trait Widget {
+    type Output;
+
+    fn feed(&mut self, key: Key) -> Step<Self::Output>;
+
+    fn completion(&mut self) -> &mut CompletionManager<'static, Choices>;
+
+    /// Returns the text left after the message once the widget is done.
+    fn answer(&self, output: &Self::Output) -> String;
+}
+
+fn is_cancel(key: Key) -> bool {
+    key == Key::plain(KeyCode::Esc) || key == Key::ctrl('c')
+}
+
+fn run<W: Widget>(message: &str, hint: &str, widget: &mut W) -> io::Result<Option<W::Output>> {
+    let mut renderer = Renderer::new(io::stdout(), format!("{} ", message));
+    renderer.set_toolbar(hint);
+    terminal::enable_raw_mode()?;
+    let result = read(&mut renderer, widget);
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn read<W: Widget>(renderer: &mut Renderer<io::Stdout>, widget: &mut W) -> io::Result<Option<W::Output>> {
+    let (col, row) = terminal::size()?;
+    renderer.update_win_size(col, row);
+    let document = Document::new();
+    loop {
+        widget.completion().set_available_height(renderer.completion_height(&document));
+        renderer.render(&document, widget.completion())?;
+        match event::read()? {
+            Event::Key(event) if event.kind != KeyEventKind::Release => match widget.feed(event.into()) {
+                Step::Continue => {}
+                Step::Done(output) => {
+                    renderer.set_toolbar("");
+                    renderer.break_line(&Document::with_text(widget.answer(&output), 0))?;
+                    return Ok(Some(output));
+                }
+                Step::Cancel => {
+                    renderer.set_toolbar("");
+                    renderer.break_line(&document)?;
+                    return Ok(None);
+                }
+            },
+            Event::Resize(col, row) => renderer.update_win_size(col, row),
+            _ => {}
+        }
+    }
+}
+
+struct Confirm {
+    completion: CompletionManager<'static, Choices>,
+}
+
+impl Confirm {
+    fn new() -> Self {
+        Self { completion: CompletionManager::new(Choices(Vec::new()), 0) }
+    }
+}
+
+impl Widget for Confirm {
+    type Output = bool;
+
+    fn feed(&mut self, key: Key) -> Step<bool> {
+        match key.code {
+            KeyCode::Char('y' | 'Y') if key.modifiers.is_empty() => Step::Done(true),
+            KeyCode::Char('n' | 'N') if key.modifiers.is_empty() => Step::Done(false),
+            _ if is_cancel(key) => Step::Cancel,
+            _ => Step::Continue,
+        }
+    }
+
+    fn completion(&mut self) -> &mut CompletionManager<'static, Choices> {
+        &mut self.completion
+    }
+
+    fn answer(&self, output: &bool) -> String {
+        if *output { "yes" } else { "no" }.to_string()
+    }
+}
+
+/// Returns a manager showing `options` with the first one selected.
+fn choices(options: Vec<Suggestion>) -> CompletionManager<'static, Choices> {
+    let mut completion = CompletionManager::new(Choices(options), MAX_OPTIONS);
+    completion.set_wrap_around(false);
+    completion.update_suggestions(&Document::new());
+    completion.next();
+    completion
+}
+
+/// Moves the selection for the navigation keys, returns false for other keys.
+fn navigate(completion: &mut CompletionManager<Choices>, key: Key) -> bool {
+    match key.code {
+        KeyCode::Up if key.modifiers.is_empty() => completion.previous(),
+        KeyCode::Down | KeyCode::Tab if key.modifiers.is_empty() => completion.next(),
+        KeyCode::PageUp => completion.page_up(),
+        KeyCode::PageDown => completion.page_down(),
+        _ if key == Key::ctrl('p') => completion.previous(),
+        _ if key == Key::ctrl('n') => completion.next(),
+        _ => return false,
+    }
+    true
+}
+
+struct Select {
+    options: Vec<String>,
+    completion: CompletionManager<'static, Choices>,
+}
+
+impl Select {
+    fn new<S: AsRef<str>>(options: &[S]) -> Self {
+        let options = options.iter().map(|o| o.as_ref().to_string()).collect::<Vec<_>>();
+        let completion = choices(options.iter().cloned().map(Suggestion::with_title).collect());
+        Self { options, completion }
+    }
+}
+
+impl Widget for Select {
+    type Output = usize;
+
+    fn feed(&mut self, key: Key) -> Step<usize> {
+        if navigate(&mut self.completion, key) {
+            return Step::Continue;
+        }
+        match (key.code, self.completion.selected()) {
+            (KeyCode::Enter, Some(i)) => Step::Done(i),
+            _ if is_cancel(key) => Step::Cancel,
+            _ => Step::Continue,
+        }
+    }
+
+    fn completion(&mut self) -> &mut CompletionManager<'static, Choices> {
+        &mut self.completion
+    }
+
+    fn answer(&self, output: &usize) -> String {
+        self.options[*output].clone()
+    }
+}
+
+struct MultiSelect {
+    options: Vec<String>,
+    checked: Vec<bool>,
+    completion: CompletionManager<'static, Choices>,
+}
+
+impl MultiSelect {
+    fn new<S: AsRef<str>>(options: &[S]) -> Self {
+        let options = options.iter().map(|o| o.as_ref().to_string()).collect::<Vec<_>>();
+        let checked = vec![false; options.len()];
+        let completion = choices(Self::suggestions(&options, &checked));
+        Self { options, checked, completion }
+    }
+
+    fn suggestions(options: &[String], checked: &[bool]) -> Vec<Suggestion> {
+        options.iter()
+            .zip(checked)
+            .map(|(option, &checked)| Suggestion::with_title(format!("[{}] {}", if checked { 'x' } else { ' ' }, option)))
+            .collect()
+    }
+
+    fn toggle(&mut self, i: usize) {
+        self.checked[i] = !self.checked[i];
+        let suggestions = Self::suggestions(&self.options, &self.checked);
+        self.completion.get_suggestions_mut().clone_from_slice(&suggestions);
+    }
+}
+
+impl Widget for MultiSelect {
+    type Output = Vec<usize>;
+
+    fn feed(&mut self, key: Key) -> Step<Vec<usize>> {
+        if navigate(&mut self.completion, key) {
+            return Step::Continue;
+        }
+        match (key.code, self.completion.selected()) {
+            (KeyCode::Char(' '), Some(i)) => {
+                self.toggle(i);
+                Step::Continue
+            }
+            (KeyCode::Enter, _) => Step::Done((0..self.options.len()).filter(|&i| self.checked[i]).collect()),
+            _ if is_cancel(key) => Step::Cancel,
+            _ => Step::Continue,
+        }
+    }
+
+    fn completion(&mut self) -> &mut CompletionManager<'static, Choices> {
+        &mut self.completion
+    }
+
+    fn answer(&self, output: &Vec<usize>) -> String {
+        output.iter().map(|&i| self.options[i].as_str()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm() {
+        let mut c = Confirm::new();
+        assert_eq!(Step::Continue, c.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!(Step::Done(true), c.feed(Key::plain(KeyCode::Char('Y'))));
+        assert_eq!(Step::Done(false), c.feed(Key::plain(KeyCode::Char('n'))));
+        assert_eq!(Step::Cancel, c.feed(Key::ctrl('c')));
+        assert_eq!("no", c.answer(&false));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut s = Select::new(&["us-east-1", "eu-west-1", "ap-south-1"]);
+        assert_eq!(Some(0), s.completion.selected());
+        s.feed(Key::plain(KeyCode::Down));
+        s.feed(Key::plain(KeyCode::Down));
+        s.feed(Key::plain(KeyCode::Down));
+        assert_eq!(Some(2), s.completion.selected());
+        s.feed(Key::ctrl('p'));
+        assert_eq!(Step::Done(1), s.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("eu-west-1", s.answer(&1));
+        assert_eq!(Step::Cancel, s.feed(Key::plain(KeyCode::Esc)));
+
+        let mut empty = Select::new::<&str>(&[]);
+        assert_eq!(Step::Continue, empty.feed(Key::plain(KeyCode::Enter)));
+    }
+
+    #[test]
+    fn test_multi_select() {
+        let mut m = MultiSelect::new(&["docs", "examples", "tests"]);
+        m.feed(Key::plain(KeyCode::Char(' ')));
+        m.feed(Key::plain(KeyCode::Down));
+        m.feed(Key::plain(KeyCode::Down));
+        m.feed(Key::plain(KeyCode::Char(' ')));
+        assert_eq!("[x] tests", m.completion.get_suggestions()[2].text());
+        assert_eq!("[ ] examples", m.completion.get_suggestions()[1].text());
+        m.feed(Key::plain(KeyCode::Up));
+        m.feed(Key::plain(KeyCode::Up));
+        m.feed(Key::plain(KeyCode::Char(' ')));
+        assert_eq!(Step::Done(vec![2]), m.feed(Key::plain(KeyCode::Enter)));
+        assert_eq!("docs, tests", m.answer(&vec![0, 2]));
+    }
+}
@@ -0,0 +1,150 @@
+use std::borrow::Cow;
+
+use unicode_width::UnicodeWidthChar;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// Returns the number of columns `s` occupies on a terminal.
+///
+/// ANSI escape sequences (CSI like `\x1b[31m`, OSC like hyperlinks, and two-character
+/// escapes) take no space, wide characters such as '日' take two columns, and
+/// zero-width or control characters take none.
+pub fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    for_each_part(s, |part, is_escape| {
+        if !is_escape {
+            width += part.chars().map(char_width).sum::<usize>();
+        }
+        true
+    });
+    width
+}
+
+/// Returns `s` without any ANSI escape sequence.
+pub fn strip_ansi(s: &str) -> Cow<'_, str> {
+    if !s.contains(ESC) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    for_each_part(s, |part, is_escape| {
+        if !is_escape {
+            out.push_str(part);
+        }
+        true
+    });
+    Cow::Owned(out)
+}
+
+/// Returns the longest prefix of `s` that fits in `max` columns. Escape sequences
+/// inside that prefix are kept, and a wide character that would only half fit is dropped.
+pub fn truncate_to_width(s: &str, max: usize) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut width = 0;
+    for_each_part(s, |part, is_escape| {
+        if is_escape {
+            out.push_str(part);
+            return true;
+        }
+        for c in part.chars() {
+            let w = char_width(c);
+            if width + w > max {
+                return false;
+            }
+            width += w;
+            out.push(c);
+        }
+        true
+    });
+    out
+}
+
+/// Display width of a single character. Control characters take no space.
+pub(crate) fn char_width(c: char) -> usize {
+    UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// Splits `s` into plain text and escape sequences, calling `f` with each part until it
+/// returns false.
+fn for_each_part<'a, F: FnMut(&'a str, bool) -> bool>(s: &'a str, mut f: F) {
+    let mut rest = s;
+    while !rest.is_empty() {
+        match rest.find(ESC) {
+            Some(0) => {
+                let len = escape_len(rest);
+                if !f(&rest[..len], true) {
+                    return;
+                }
+                rest = &rest[len..];
+            }
+            Some(i) => {
+                if !f(&rest[..i], false) {
+                    return;
+                }
+                rest = &rest[i..];
+            }
+            None => {
+                f(rest, false);
+                return;
+            }
+        }
+    }
+}
+
+/// Byte length of the escape sequence at the start of `s`, which must start with ESC.
+fn escape_len(s: &str) -> usize {
+    let mut chars = s.char_indices().skip(1);
+    match chars.next() {
+        None => 1,
+        // CSI: parameters and intermediates, then a final byte in '@'..='~'
+        Some((_, '[')) => chars
+            .find(|(_, c)| ('@'..='~').contains(c))
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(s.len()),
+        // OSC: terminated by BEL or ST (ESC \)
+        Some((_, ']')) => {
+            let mut prev_esc = false;
+            for (i, c) in chars {
+                if c == BEL || (prev_esc && c == '\\') {
+                    return i + c.len_utf8();
+                }
+                prev_esc = c == ESC;
+            }
+            s.len()
+        }
+        Some((i, c)) => i + c.len_utf8(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(5, display_width("hello"));
+        assert_eq!(6, display_width("日本語"));
+        assert_eq!(5, display_width("\x1b[31mhello\x1b[0m"));
+        assert_eq!(2, display_width("\x1b[1;38;2;255;0;0m日\x1b[m"));
+        // OSC 8 hyperlink, terminated by ST and BEL
+        assert_eq!(4, display_width("\x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x07"));
+        // combining acute accent and zero width joiner
+        assert_eq!(1, display_width("e\u{301}"));
+        assert_eq!(0, display_width("\u{200b}"));
+        assert_eq!(0, display_width("\x1b"));
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!("hello", strip_ansi("\x1b[31mhello\x1b[0m"));
+        assert!(matches!(strip_ansi("plain"), Cow::Borrowed("plain")));
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!("hel", truncate_to_width("hello", 3));
+        assert_eq!("日", truncate_to_width("日本語", 3));
+        assert_eq!("\x1b[31mhe", truncate_to_width("\x1b[31mhello\x1b[0m", 2));
+        assert_eq!("hello", truncate_to_width("hello", 10));
+    }
+}